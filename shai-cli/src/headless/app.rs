@@ -1,10 +1,11 @@
 use std::sync::Arc;
 
 use crate::headless::tools::ToolConfig;
+use crate::LoopLimits;
 
 use super::tools::{ToolName, list_all_tools, parse_tools_list};
 use shai_core::agent::{Agent, AgentBuilder, AgentError, AgentResult, Brain, LoggingConfig, StdoutEventManager};
-use shai_core::config::config::ShaiConfig;
+use shai_core::config::config::{ModelRole, ShaiConfig};
 use shai_core::config::agent::AgentConfig;
 use shai_core::runners::coder::coder::CoderBrain;
 use shai_core::runners::searcher::searcher::SearcherBrain;
@@ -32,7 +33,8 @@ impl AppHeadless {
         tools: Option<String>, 
         remove: Option<String>,
         trace: bool,
-        agent_name: Option<String>
+        agent_name: Option<String>,
+        limits: LoopLimits
     ) -> Result<(), Box<dyn std::error::Error>> {   
         // Configure internal debug logging to file
         /*
@@ -49,7 +51,7 @@ impl AppHeadless {
             return Ok(());
         }
 
-        let agent = if let Some(agent_name) = agent_name {
+        let mut agent = if let Some(agent_name) = agent_name {
             // Use custom agent from config
             AgentBuilder::create(Some(agent_name)).await
                 .map_err(|e| format!("Failed to create agent: {}", e))?
@@ -58,7 +60,11 @@ impl AppHeadless {
                 .build()
         } else {
             // Use default agent with provided tools
-            let (llm_client, model) = ShaiConfig::get_llm().await?;
+            let role = match self.kind {
+                AgentKind::Coder => ModelRole::Coder,
+                AgentKind::Searcher => ModelRole::Searcher,
+            };
+            let (llm_client, model) = ShaiConfig::get_llm_for(role).await?;
             eprintln!("\x1b[2m░ {} on {}\x1b[0m", model, llm_client.provider().name());
 
             // Handle tool selection if needed
@@ -97,6 +103,8 @@ impl AppHeadless {
             }
         };
 
+        agent.loop_guard = limits.apply(agent.loop_guard);
+
         let result = agent
             .with_event_handler(StdoutEventManager::new())
             .run().await;
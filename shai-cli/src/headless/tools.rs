@@ -1,6 +1,6 @@
 use std::sync::Arc;
-use shai_core::tools::{AnyTool, BashTool, EditTool, FetchTool, FindTool, LsTool, 
-                     MultiEditTool, ReadTool, TodoReadTool, TodoWriteTool, WriteTool,
+use shai_core::tools::{AnyTool, BashTool, BashOutputTool, BashInputTool, BashListTool, BashKillTool, ProcessRegistry, EditTool, FetchTool, FindTool, LsTool, 
+                     MultiEditTool, ReadTool, ShellTool, TodoReadTool, TodoWriteTool, WriteTool,
                      TodoStorage, FsOperationLog};
 
 /// Available tools for the coder agent
@@ -13,6 +13,7 @@ pub enum ToolName {
     Ls,
     MultiEdit,
     Read,
+    Shell,
     TodoRead,
     TodoWrite,
     Write,
@@ -28,6 +29,7 @@ impl ToolName {
             ToolName::Ls,
             ToolName::MultiEdit,
             ToolName::Read,
+            ToolName::Shell,
             ToolName::TodoRead,
             ToolName::TodoWrite,
             ToolName::Write,
@@ -43,6 +45,7 @@ impl ToolName {
             ToolName::Ls => "ls",
             ToolName::MultiEdit => "multiedit",
             ToolName::Read => "read",
+            ToolName::Shell => "shell",
             ToolName::TodoRead => "todoread",
             ToolName::TodoWrite => "todowrite",
             ToolName::Write => "write",
@@ -58,6 +61,7 @@ impl ToolName {
             "ls" => Some(ToolName::Ls),
             "multiedit" => Some(ToolName::MultiEdit),
             "read" => Some(ToolName::Read),
+            "shell" => Some(ToolName::Shell),
             "todoread" => Some(ToolName::TodoRead),
             "todowrite" => Some(ToolName::TodoWrite),
             "write" => Some(ToolName::Write),
@@ -115,16 +119,25 @@ impl ToolConfig {
     pub fn build_toolbox(&self) -> Vec<Box<dyn AnyTool>> {
         let todo_storage = Arc::new(TodoStorage::new());
         let fs_log = Arc::new(FsOperationLog::new());
+        let processes = Arc::new(ProcessRegistry::new());
         let mut toolbox: Vec<Box<dyn AnyTool>> = Vec::new();
         for tool_name in &self.tools {
             match tool_name {
-                ToolName::Bash => toolbox.push(Box::new(BashTool::new())),
+                ToolName::Bash => {
+                    // background processes started by bash are driven by its companion tools
+                    toolbox.push(Box::new(BashTool::new().with_processes(processes.clone())));
+                    toolbox.push(Box::new(BashOutputTool::new(processes.clone())));
+                    toolbox.push(Box::new(BashInputTool::new(processes.clone())));
+                    toolbox.push(Box::new(BashListTool::new(processes.clone())));
+                    toolbox.push(Box::new(BashKillTool::new(processes.clone())));
+                }
                 ToolName::Edit => toolbox.push(Box::new(EditTool::new(fs_log.clone()))),
                 ToolName::Fetch => toolbox.push(Box::new(FetchTool::new())),
                 ToolName::Find => toolbox.push(Box::new(FindTool::new())),
                 ToolName::Ls => toolbox.push(Box::new(LsTool::new())),
                 ToolName::MultiEdit => toolbox.push(Box::new(MultiEditTool::new(fs_log.clone()))),
                 ToolName::Read => toolbox.push(Box::new(ReadTool::new(fs_log.clone()))),
+                ToolName::Shell => toolbox.push(Box::new(ShellTool::new())),
                 ToolName::TodoRead => toolbox.push(Box::new(TodoReadTool::new(todo_storage.clone()))),
                 ToolName::TodoWrite => toolbox.push(Box::new(TodoWriteTool::new(todo_storage.clone()))),
                 ToolName::Write => toolbox.push(Box::new(WriteTool::new(fs_log.clone()))),
@@ -9,8 +9,8 @@ use crossterm::{
 
 use ringbuffer::RingBuffer;
 use console::strip_ansi_codes;
-use shai_core::agent::LoggingConfig;
-use shai_core::config::config::ShaiConfig;
+use shai_core::agent::{LoggingConfig, LoopGuard};
+use shai_core::config::config::{ModelRole, ShaiConfig};
 use shai_core::config::agent::AgentConfig;
 use shai_core::agent::builder::AgentBuilder;
 use shai_core::runners::clifixer::fix::clifix;
@@ -69,6 +69,12 @@ struct Cli {
     /// Remove specific tools from the default set (comma-separated)
     #[arg(long)]
     remove: Option<String>,
+    /// Maximum number of tool steps per turn before the agent pauses (0 = no limit)
+    #[arg(long, global = true)]
+    max_steps: Option<usize>,
+    /// Number of identical steps or errors in a row tolerated before the agent is warned then paused (0 = never)
+    #[arg(long, global = true)]
+    max_repeats: Option<usize>,
     /// Show version information
     #[arg(short, long)]
     version: bool,
@@ -77,6 +83,23 @@ struct Cli {
     args: Vec<String>,
 }
 
+/// Step limits given on the command line, they override the ones of the agent
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LoopLimits {
+    pub max_steps: Option<usize>,
+    pub max_repeats: Option<usize>,
+}
+
+impl LoopLimits {
+    pub fn is_set(&self) -> bool {
+        self.max_steps.is_some() || self.max_repeats.is_some()
+    }
+
+    pub fn apply(&self, guard: LoopGuard) -> LoopGuard {
+        guard.with_overrides(self.max_steps, self.max_repeats)
+    }
+}
+
 #[derive(Subcommand)]
 enum AgentAction {
     /// List all available agents
@@ -142,12 +165,22 @@ enum Commands {
         /// Use ephemeral mode (spawn new agent per request)
         #[arg(long)]
         ephemeral: bool,
+        /// Session workspace: shared, temp, template:<path>, worktree or worktree:<repo>[@ref]
+        #[arg(long, default_value = "shared")]
+        workspace: shai_http::WorkspaceMode,
+        /// Keep session workspaces on disk once the session ends
+        #[arg(long)]
+        keep_workspace: bool,
+        /// Persist sessions in this directory so they survive server restarts
+        #[arg(long)]
+        session_dir: Option<std::path::PathBuf>,
     }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
+    let limits = LoopLimits { max_steps: cli.max_steps, max_repeats: cli.max_repeats };
     default_config(cli.default_shai_config_url).await;
 
     match cli.command {
@@ -167,7 +200,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             handle_config().await?;
         },
         Some(Commands::Agent { action }) => {
-            handle_agent_command(action).await?;
+            handle_agent_command(action, limits).await?;
         },
         #[cfg(unix)]
         Some(Commands::Precmd { command }) => {
@@ -179,8 +212,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let command_str = command.join(" ");
             handle_postcmd(exit_code, command_str).await?;
         },
-        Some(Commands::Serve { host, port, agent, ephemeral }) => {
-            handle_serve(host, port, agent, ephemeral).await?;
+        Some(Commands::Serve { host, port, agent, ephemeral, workspace, keep_workspace, session_dir }) => {
+            handle_serve(host, port, agent, ephemeral, workspace, keep_workspace, session_dir).await?;
         },
         None => {
             // Check for stdin input or trailing arguments
@@ -218,10 +251,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             if !messages.is_empty() || cli.list_tools {
                 // Route to fix command with combined messages and global options
-                handle_fix(messages, cli.tools, cli.remove, cli.trace, None).await?;
+                handle_fix(messages, cli.tools, cli.remove, cli.trace, None, limits).await?;
             } else {
                 // No input, show TUI
-                handle_main(None).await?;
+                handle_main(None, limits).await?;
             }
         }
     }
@@ -248,10 +281,10 @@ async fn default_config(default_config_url: Option<String>) {
     let _ = config.save();
 }
 
-async fn handle_main(agent_name: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+async fn handle_main(agent_name: Option<String>, limits: LoopLimits) -> Result<(), Box<dyn std::error::Error>> {
     let logo = logo();
     println!("{}", apply_gradient(&logo, SHAI_YELLOW, SHAI_YELLOW));
-    let mut app = App::new();
+    let mut app = App::new().with_loop_limits(limits);
     match app.run(agent_name).await {
         Err(e) => eprintln!("error: {}",e),
         _ => {}
@@ -274,7 +307,8 @@ async fn handle_fix(
     tools: Option<String>, 
     remove: Option<String>,
     trace: bool,
-    agent_name: Option<String>
+    agent_name: Option<String>,
+    limits: LoopLimits
 ) -> Result<(), Box<dyn std::error::Error>> {
     let initial_trace: Vec<ChatMessage> = prompt.into_iter()
         .map(|p| ChatMessage::User { 
@@ -283,7 +317,7 @@ async fn handle_fix(
         })
         .collect();
     
-    AppHeadless::new().run(initial_trace, tools, remove, trace, agent_name).await
+    AppHeadless::new().run(initial_trace, tools, remove, trace, agent_name, limits).await
 }
 
 fn show_version() -> Result<(), Box<dyn std::error::Error>> {
@@ -368,7 +402,7 @@ pub async fn handle_postcmd(exit_code: i32, command: String) -> Result<(), Box<d
                     name: None 
                 }];
             
-                let (llm, model) = ShaiConfig::get_llm().await?;
+                let (llm, model) = ShaiConfig::get_llm_for(ModelRole::Clifix).await?;
                 
                 enable_raw_mode().unwrap();
                 let mut events = EventStream::new();
@@ -467,7 +501,7 @@ pub async fn handle_postcmd(exit_code: i32, command: String) -> Result<(), Box<d
     Ok(())
 }
 
-async fn handle_serve(host: String, port: u16, agent: Option<String>, ephemeral: bool) -> Result<(), Box<dyn std::error::Error>> {
+async fn handle_serve(host: String, port: u16, agent: Option<String>, ephemeral: bool, workspace: shai_http::WorkspaceMode, keep_workspace: bool, session_dir: Option<std::path::PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing for HTTP server logs
     tracing_subscriber::fmt()
         .with_target(false)
@@ -478,16 +512,25 @@ async fn handle_serve(host: String, port: u16, agent: Option<String>, ephemeral:
     println!("{}", logo_cyan());
 
     let addr = format!("{}:{}", host, port);
-    let config = shai_http::ServerConfig::new(addr)
+    let mut config = shai_http::ServerConfig::new(addr)
         .with_ephemeral(ephemeral)
-        .with_max_sessions(Some(1));
+        .with_max_sessions(Some(1))
+        .with_workspace(shai_http::WorkspaceConfig {
+            mode: workspace,
+            cleanup: if keep_workspace { shai_http::WorkspaceCleanup::Keep } else { shai_http::WorkspaceCleanup::Delete },
+            base_dir: None,
+        });
+
+    if let Some(dir) = session_dir {
+        config = config.with_store(std::sync::Arc::new(shai_http::JsonlSessionStore::new(dir)?));
+    }
 
     shai_http::start_server(config).await?;
 
     Ok(())
 }
 
-async fn handle_agent_command(action: AgentAction) -> Result<(), Box<dyn std::error::Error>> {
+async fn handle_agent_command(action: AgentAction, limits: LoopLimits) -> Result<(), Box<dyn std::error::Error>> {
     match action {
         AgentAction::List => {
             let agents = AgentConfig::list_agents()?;
@@ -531,11 +574,11 @@ async fn handle_agent_command(action: AgentAction) -> Result<(), Box<dyn std::er
             
             if prompt_args.is_empty() {
                 // No prompt provided, start TUI mode with the agent
-                handle_main(Some(agent_name.clone())).await?;
+                handle_main(Some(agent_name.clone()), limits).await?;
             } else {
                 // Prompt provided, run in headless mode
                 let prompt = prompt_args.join(" ");
-                handle_fix(vec![prompt], None, None, false, Some(agent_name.clone())).await?;
+                handle_fix(vec![prompt], None, None, false, Some(agent_name.clone()), limits).await?;
             }
         }
     }
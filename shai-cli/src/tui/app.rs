@@ -15,11 +15,12 @@ use ratatui::Terminal;
 use shai_core::agent::{Agent, AgentRequest, AgentEvent, AgentController, PublicAgentState};
 use shai_core::agent::events::{PermissionRequest, PermissionResponse};
 use shai_core::agent::output::PrettyFormatter;
-use shai_core::config::config::ShaiConfig;
+use shai_core::config::config::{ModelRole, ShaiConfig};
 use shai_core::config::agent::AgentConfig;
 use shai_core::agent::builder::AgentBuilder;
 use shai_core::logging::LoggingConfig;
-use shai_core::runners::coder::coder::coder;
+use shai_core::runners::coder::coder::coder_with_compactor;
+use shai_core::runners::compacter::Compactor;
 use shai_core::tools::{ToolCall, ToolResult};
 use shai_llm::{LlmClient, ToolCallMethod};
 use ratatui::{
@@ -36,6 +37,7 @@ use ansi_to_tui::IntoText;
 use std::collections::{HashMap, VecDeque};
 
 use crate::tui::input::InputArea;
+use crate::LoopLimits;
 use super::input::UserAction;
 use crate::tui::perm::PermissionWidget;
 use crate::tui::perm_alt_screen::AlternateScreenPermissionModal;
@@ -43,6 +45,11 @@ use super::perm::PermissionModalAction;
 use super::theme::Theme;
 
 
+/// Lines of live output shown under each running tool
+const TOOL_PROGRESS_LINES: usize = 3;
+/// Bytes of live output kept per running tool
+const TOOL_PROGRESS_BYTES: usize = 4096;
+
 pub enum AppModalState<'a> {
     InputShown,
     PermissionModal {
@@ -66,6 +73,8 @@ pub struct App<'a> {
     pub(crate) state: AppModalState<'a>,
     pub(crate) formatter: PrettyFormatter, // streaming log formatter
     pub(crate) running_tools: HashMap<String, ToolCall>, // (request_id, request)
+    pub(crate) tool_progress: HashMap<String, String>, // (request_id, tail of the live output)
+    pub(crate) queued_inputs: Vec<String>, // messages waiting for the next agent step
     pub(crate) input: InputArea<'a>,       // input text
     pub(crate) commands: HashMap<(String, String),Vec<String>>,
     pub(crate) exit: bool,
@@ -75,6 +84,7 @@ pub struct App<'a> {
     pub(crate) total_output_tokens: u32,
     
     pub(crate) theme: Theme, // UI theme (dark/light)
+    pub(crate) loop_limits: LoopLimits, // step limits given on the command line
 }
 
 
@@ -92,10 +102,16 @@ impl App<'_> {
             Box::new(agent_builder.build())
         } else {
             // Use default coder agent
-            let (llm, model) = ShaiConfig::get_llm().await?;
+            let (llm, model) = ShaiConfig::get_llm_for(ModelRole::Coder).await?;
             println!("\x1b[2m░ {} on {}\x1b[0m", model, llm.provider().name());
             
-            Box::new(coder(Arc::new(llm), model))
+            // summaries of the trace are written by the compaction model
+            let llm = Arc::new(llm);
+            let mut compactor = Compactor::new(llm.clone(), model.clone());
+            if let Ok((summarizer, summary_model)) = ShaiConfig::get_llm_for(ModelRole::Compaction).await {
+                compactor = compactor.with_summarizer(Arc::new(summarizer), summary_model);
+            }
+            Box::new(coder_with_compactor(llm, model, compactor))
         };
         
         // Get Agent I/O
@@ -110,6 +126,12 @@ impl App<'_> {
             }
         });
 
+        // command line limits take precedence over the agent ones
+        if self.loop_limits.is_set() {
+            let guard = controller.set_loop_guard(None).await?;
+            controller.set_loop_guard(Some(self.loop_limits.apply(guard))).await?;
+        }
+
         self.agent = Some(AppRunningAgent{
             handle,
             controller,
@@ -138,18 +160,37 @@ impl App<'_> {
         }
         if let AgentEvent::ToolCallCompleted { call, .. }= &event {
             self.running_tools.remove(&call.tool_call_id);
+            self.tool_progress.remove(&call.tool_call_id);
+        }
+
+        // track messages waiting for the next agent step
+        if let AgentEvent::UserInputQueued { input } = &event {
+            self.queued_inputs.push(input.clone());
+        }
+        if let AgentEvent::UserInput { input } = &event {
+            if let Some(pos) = self.queued_inputs.iter().position(|queued| queued == input) {
+                self.queued_inputs.remove(pos);
+            }
+        }
+
+        // keep the tail of the live output of running tools
+        if let AgentEvent::ToolCallProgress { call_id, chunk } = &event {
+            if self.running_tools.contains_key(call_id) {
+                let tail = self.tool_progress.entry(call_id.clone()).or_default();
+                tail.push_str(chunk);
+                if tail.len() > TOOL_PROGRESS_BYTES {
+                    let mut cut = tail.len() - TOOL_PROGRESS_BYTES;
+                    while !tail.is_char_boundary(cut) {
+                        cut += 1;
+                    }
+                    tail.drain(..cut);
+                }
+            }
         }
 
         // Format and display event
         if let Some(formatted) = self.formatter.format_event(&event) {
-            if let Some(ref mut terminal) = self.terminal {
-                let wrapped = formatted.into_text().unwrap();
-                let line_count = wrapped.lines.iter().len() as u16;
-                terminal.clear()?; // this is to avoid visual artifact
-                terminal.insert_before(line_count, |buf| {
-                    wrapped.render(buf.area, buf);
-                })?;
-            }
+            self.print_above(&formatted)?;
         }
 
         // Handle permission requests - just add to queue
@@ -162,6 +203,11 @@ impl App<'_> {
             self.total_input_tokens += input_tokens;
             self.total_output_tokens += output_tokens;
         }
+
+        // Context window indicator
+        if let AgentEvent::ContextUsage { used_tokens, context_window } = &event {
+            self.input.set_context_usage(*used_tokens, *context_window);
+        }
         
         Ok(())
     }
@@ -170,6 +216,19 @@ impl App<'_> {
 
 // UI-related Internals
 impl App<'_> {
+    /// Print text (may contain ansi colors) in the scrollback, above the input
+    pub(crate) fn print_above(&mut self, text: &str) -> io::Result<()> {
+        if let Some(ref mut terminal) = self.terminal {
+            let wrapped = text.into_text().unwrap();
+            let line_count = wrapped.lines.iter().len() as u16;
+            terminal.clear()?; // this is to avoid visual artifact
+            terminal.insert_before(line_count, |buf| {
+                wrapped.render(buf.area, buf);
+            })?;
+        }
+        Ok(())
+    }
+
     pub fn new() -> Self {
         let theme = Theme::from_env(); // Read from SHAI_TUI_THEME env var
         let palette = theme.palette();
@@ -185,13 +244,21 @@ impl App<'_> {
             commands: Self::list_command(),
             exit: false,
             running_tools: HashMap::new(),
+            tool_progress: HashMap::new(),
+            queued_inputs: Vec::new(),
             permission_queue: VecDeque::new(),
             total_input_tokens: 0,
             total_output_tokens: 0,
             theme,
+            loop_limits: LoopLimits::default(),
         }
     }
 
+    pub fn with_loop_limits(mut self, loop_limits: LoopLimits) -> Self {
+        self.loop_limits = loop_limits;
+        self
+    }
+
     pub async fn run(&mut self, agent_name: Option<String>) -> io::Result<()> {
         let x = self.try_run(agent_name).await;
         let _ = disable_raw_mode();
@@ -376,6 +443,21 @@ impl App<'_> {
                 if let Some(ref agent) = self.agent {
                     let _ = agent.controller.stop_current_task().await;
                     self.input.alert_msg("Task cancelled", Duration::from_secs(1));
+
+                    // messages that were not delivered go back to the input
+                    if let Ok(inputs) = agent.controller.take_queued_inputs().await {
+                        if !inputs.is_empty() {
+                            self.input.restore_input(&inputs.join("\n"));
+                        }
+                    }
+                    self.queued_inputs.clear();
+                }
+            }
+            UserAction::QueueInput { input } => {
+                if let Some(ref agent) = self.agent {
+                    if agent.controller.queue_user_input(input).await.is_err() {
+                        self.input.alert_msg("channel with agent closed. Please restart the app", Duration::from_secs(3));
+                    }
                 }
             }
             UserAction::UserInput { input } => {
@@ -396,14 +478,37 @@ impl App<'_> {
     }
 
 
+    /// Last lines of the live output of a running tool
+    fn tool_progress_tail(&self, call_id: &str) -> Vec<String> {
+        let Some(tail) = self.tool_progress.get(call_id) else {
+            return vec![];
+        };
+        let lines: Vec<String> = tail
+            .lines()
+            .map(|line| line.rsplit('\r').next().unwrap_or("").replace('\t', "    "))
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        lines[lines.len().saturating_sub(TOOL_PROGRESS_LINES)..].to_vec()
+    }
+
     fn draw_ui(&mut self) -> io::Result<()> {
         let modal_height = match &self.state {
             AppModalState::InputShown => self.input.height(),
             AppModalState::PermissionModal { widget } => widget.height(),
         }.max(5);
+        let running: Vec<(&ToolCall, Vec<String>)> = self.running_tools
+            .iter()
+            .map(|(id, tc)| (tc, self.tool_progress_tail(id)))
+            .collect();
+        let queued: Vec<String> = self.queued_inputs
+            .iter()
+            .map(|input| format!("  ⧗ queued: {}", input.lines().next().unwrap_or("")))
+            .collect();
+        let running_height = running.iter().map(|(_, tail)| 1 + tail.len() as u16).sum::<u16>()
+            + queued.len() as u16;
         let height = modal_height
         + 1 
-        + self.running_tools.len() as u16;
+        + running_height;
 
         if let Some(ref mut terminal) = self.terminal {  
             if height != self.terminal_height {
@@ -414,15 +519,33 @@ impl App<'_> {
             terminal.draw(|frame| {                    
                 let [_, inprogress, modal] = Layout::vertical([
                     Constraint::Length(1), // padding
-                    Constraint::Length(self.running_tools.len() as u16 + 1), // running tool (if any)
+                    Constraint::Length(running_height + 1), // running tool (if any) and their live output
                     Constraint::Length(modal_height)])                // input or modal
                     .areas(frame.area()); 
 
-                // draw running tool
-                if !self.running_tools.is_empty() {
-                    let layout: std::rc::Rc<[Rect]> = Layout::vertical(vec![Constraint::Length(1); self.running_tools.len()+1]).split(inprogress);
-                    for ((_,tc), &area) in self.running_tools.iter().zip(layout.into_iter()) {
-                        frame.render_widget(self.formatter.format_tool_running(tc).into_text().unwrap(), area);
+                // draw running tool and queued messages
+                if !running.is_empty() || !queued.is_empty() {
+                    let rows: Vec<Constraint> = running.iter()
+                        .flat_map(|(_, tail)| std::iter::repeat(Constraint::Length(1)).take(1 + tail.len()))
+                        .chain(queued.iter().map(|_| Constraint::Length(1)))
+                        .chain(std::iter::once(Constraint::Length(1)))
+                        .collect();
+                    let layout: std::rc::Rc<[Rect]> = Layout::vertical(rows).split(inprogress);
+                    let mut areas = layout.iter();
+                    for (tc, tail) in &running {
+                        if let Some(&area) = areas.next() {
+                            frame.render_widget(self.formatter.format_tool_running(tc).into_text().unwrap(), area);
+                        }
+                        for line in tail {
+                            if let Some(&area) = areas.next() {
+                                frame.render_widget(Line::from(format!("  │ {}", line)).style(Style::default().fg(Color::DarkGray)), area);
+                            }
+                        }
+                    }
+                    for line in &queued {
+                        if let Some(&area) = areas.next() {
+                            frame.render_widget(Line::from(line.as_str()).style(Style::default().fg(Color::DarkGray)), area);
+                        }
                     }
                 }
 
@@ -93,6 +93,12 @@ impl AppAuth {
                         }
                     }
                 }
+
+                _ = tokio::time::sleep(std::time::Duration::from_millis(100)), if matches!(&self.state, AuthState::ModelSelection(modal) if modal.is_pulling()) => {
+                    if let AuthState::ModelSelection(ref mut modal_model) = &mut self.state {
+                        modal_model.poll_pull().await;
+                    }
+                }
             }
         }
 
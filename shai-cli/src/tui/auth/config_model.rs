@@ -8,8 +8,12 @@ use ratatui::{
     widgets::{Block, Borders, Padding, Paragraph},
     Frame,
 };
+use std::sync::{Arc, Mutex};
+use futures::StreamExt;
 use shai_core::config::config::ShaiConfig;
 use shai_llm::provider::ProviderInfo;
+use shai_llm::providers::ollama::OllamaProvider;
+use tokio::task::JoinHandle;
 
 use super::auth::NavAction;
 
@@ -26,6 +30,10 @@ pub struct ModalModel {
     pub provider: ProviderInfo,
     pub env_values: HashMap<String, String>,
     pub error_message: Option<String>,
+    /// ollama model being downloaded and the last progress reported
+    pub pulling: Option<String>,
+    pub pull_status: Arc<Mutex<String>>,
+    pub pull_task: Option<JoinHandle<Result<(), String>>>,
 }
 
 const MAX_VISIBLE_MODELS: usize = 20;
@@ -46,6 +54,9 @@ impl ModalModel {
             provider,
             env_values,
             error_message: None,
+            pulling: None,
+            pull_status: Arc::new(Mutex::new(String::new())),
+            pull_task: None,
         }
     }
 
@@ -80,6 +91,72 @@ impl ModalModel {
         }
     }
 
+    /// Model the user searched for that ollama could download
+    fn pull_candidate(&self) -> Option<&str> {
+        let query = self.search_query.trim();
+        (self.provider.name == "ollama" && self.search_mode && !query.is_empty() && self.filtered_models.is_empty())
+            .then_some(query)
+    }
+
+    fn start_pull(&mut self, model: String) {
+        let provider = OllamaProvider::new(self.env_values.get("OLLAMA_BASE_URL").cloned().filter(|u| !u.is_empty()));
+        let status = self.pull_status.clone();
+        *status.lock().unwrap() = "starting".to_string();
+        self.pulling = Some(model.clone());
+        self.error_message = None;
+
+        self.pull_task = Some(tokio::spawn(async move {
+            let mut progress = provider.pull(&model).await.map_err(|e| e.to_string())?;
+            while let Some(update) = progress.next().await {
+                let update = update.map_err(|e| e.to_string())?;
+                *status.lock().unwrap() = match update.percent() {
+                    Some(percent) => format!("{} {}%", update.status, percent),
+                    None => update.status,
+                };
+            }
+            Ok(())
+        }));
+    }
+
+    pub fn is_pulling(&self) -> bool {
+        self.pull_task.is_some()
+    }
+
+    /// Once the download is over, select the model or show why it failed
+    pub async fn poll_pull(&mut self) {
+        if !self.pull_task.as_ref().is_some_and(|task| task.is_finished()) {
+            return;
+        }
+        let result = match self.pull_task.take() {
+            Some(task) => task.await.unwrap_or_else(|e| Err(e.to_string())),
+            None => return,
+        };
+        let Some(model) = self.pulling.take() else {
+            return;
+        };
+        match result {
+            Ok(()) => {
+                if !self.all_models.contains(&model) {
+                    self.all_models.push(model.clone());
+                }
+                self.search_mode = false;
+                self.search_query.clear();
+                self.filter_models();
+                self.selected_index = self.filtered_models.iter().position(|m| *m == model).unwrap_or(0);
+                self.update_scroll();
+            }
+            Err(e) => self.error_message = Some(format!("Failed to pull {}: {}", model, e)),
+        }
+    }
+
+    /// Download progress, or how to start one
+    fn pull_line(&self) -> Option<String> {
+        match &self.pulling {
+            Some(model) => Some(format!("⬇ pulling {}: {}", model, self.pull_status.lock().unwrap())),
+            None => self.pull_candidate().map(|model| format!("Enter to pull {} from the Ollama library", model)),
+        }
+    }
+
     fn update_scroll(&mut self) {
         if self.filtered_models.len() <= MAX_VISIBLE_MODELS {
             self.scroll_offset = 0;
@@ -106,6 +183,17 @@ impl ModalModel {
 
 impl ModalModel {
     pub async fn handle_event(&mut self, key_event: KeyEvent) -> NavAction {
+        // a download only listens to Esc, which cancels it
+        if self.is_pulling() {
+            if key_event.code == KeyCode::Esc {
+                if let Some(task) = self.pull_task.take() {
+                    task.abort();
+                }
+                self.pulling = None;
+            }
+            return NavAction::None;
+        }
+
         match key_event.code {
             KeyCode::Esc => {
                 if self.search_mode {
@@ -118,6 +206,10 @@ impl ModalModel {
                 }
             }
             KeyCode::Enter => {
+                if let Some(model) = self.pull_candidate().map(str::to_string) {
+                    self.start_pull(model);
+                    return NavAction::None;
+                }
                 if self.filtered_models.is_empty() {
                     return NavAction::None;
                 }
@@ -167,8 +259,9 @@ impl ModalModel {
         let search_bar_height = if self.search_mode { 2 } else { 0 };
         let visible_models = std::cmp::min(self.filtered_models.len(), MAX_VISIBLE_MODELS);
         let error_height = if self.error_message.is_some() { 2 } else { 0 };
+        let pull_height = if self.pull_line().is_some() { 2 } else { 0 };
         let help_height = 2;
-        visible_models + search_bar_height + error_height + help_height
+        visible_models + search_bar_height + error_height + pull_height + help_height
     }
 
     pub fn height(&self) -> usize {
@@ -271,9 +364,20 @@ impl ModalModel {
             area_index += 1;
         }
         
+        // Draw pull progress or hint
+        if let Some(pull) = self.pull_line() {
+            area_index += 1; // Skip empty line
+            let pull_paragraph = Paragraph::new(pull)
+                .style(Style::default().fg(Color::Yellow));
+            frame.render_widget(pull_paragraph, layout_areas[area_index]);
+            area_index += 1;
+        }
+
         // Draw help text
         area_index += 1; // Skip empty line
-        let help_text = if self.search_mode {
+        let help_text = if self.is_pulling() {
+            "Downloading... • Esc cancel"
+        } else if self.search_mode {
             "Type to search • ↑↓ navigate • Backspace clear • Esc clear search • Enter select"
         } else {
             "↑↓ navigate • Type to search • Enter select • Esc back"
@@ -1,4 +1,6 @@
 use std::{collections::HashMap, io, time::Duration};
+use shai_core::agent::PlanRequest;
+use shai_core::agent::branch::user_messages;
 use shai_llm::ToolCallMethod;
 
 use crate::tui::App;
@@ -12,6 +14,9 @@ impl App<'_> {
             (("/tc","set the tool call method: [fc | fc2 | so]"), vec!["method"]),
             (("/tokens","display token usage (input/output)"), vec![]),
             (("/theme","set theme: [dark | light | toggle]"), vec!["mode"]),
+            (("/plan","plan before acting: [<task> | approve | reject]"), vec!["action"]),
+            (("/fork","retry from an earlier message: [<n> [restore]]"), vec!["message"]),
+            (("/branch","list or switch conversation branches: [<id>]"), vec!["id"]),
         ])
         .into_iter()
         .map(|((cmd,desc),args)|((cmd.to_string(),desc.to_string()),args.into_iter().map(|s|s.to_string()).collect()))
@@ -58,13 +63,117 @@ impl App<'_> {
                     }
                 }
             }
+            "/plan" => {
+                if let Some(ref agent) = self.agent {
+                    let result = match args.first().copied() {
+                        Some("approve") => agent.controller.plan(PlanRequest::Approve).await,
+                        Some("reject") => agent.controller.plan(PlanRequest::Reject).await,
+                        _ => agent.controller.plan(PlanRequest::Enter).await,
+                    };
+                    match result {
+                        Ok(enabled) => {
+                            let msg = match (args.first().copied(), enabled) {
+                                (Some("approve"), _) => "plan approved, executing it",
+                                (Some("reject"), _) => "plan rejected",
+                                _ => "plan mode: the agent can only read until you approve its plan",
+                            };
+                            self.input.alert_msg(msg, Duration::from_secs(3));
+                            self.input.set_plan_mode(enabled);
+                        }
+                        Err(e) => {
+                            self.input.alert_msg(&e.to_string(), Duration::from_secs(3));
+                        }
+                    }
+
+                    // anything else is the task to plan
+                    if !args.is_empty() && !matches!(args[0], "approve" | "reject") {
+                        if agent.controller.send_user_input(args.join(" ")).await.is_err() {
+                            self.input.alert_msg("channel with agent closed. Please restart the app", Duration::from_secs(3));
+                        }
+                    }
+                }
+            }
+            "/fork" => {
+                if let Some(ref agent) = self.agent {
+                    match args.first().map(|n| n.parse::<usize>()) {
+                        None => {
+                            let trace = agent.controller.get_trace().await.unwrap_or_default();
+                            let lines: Vec<String> = user_messages(&trace).into_iter()
+                                .enumerate()
+                                .map(|(i, (_, text))| {
+                                    let first_line = text.lines().next().unwrap_or_default();
+                                    let short: String = first_line.chars().take(80).collect();
+                                    format!("\x1b[2m  {:>3}. {}\x1b[0m", i + 1, short)
+                                })
+                                .collect();
+                            if lines.is_empty() {
+                                self.input.alert_msg("no message to fork from yet", Duration::from_secs(2));
+                            } else {
+                                self.print_above(&format!("\x1b[2m░ /fork <n> [restore] to retry from one of your messages:\x1b[0m\n{}", lines.join("\n")))?;
+                            }
+                        }
+                        Some(Ok(n)) if n > 0 => {
+                            let restore = args.get(1) == Some(&"restore");
+                            match agent.controller.fork(n - 1, restore).await {
+                                Ok((branch, message, restored)) => {
+                                    self.queued_inputs.clear();
+                                    let mut msg = format!("\x1b[2m░ forked to {} before message {}", branch, n);
+                                    if !restored.is_empty() {
+                                        msg.push_str(&format!(", restored {}", restored.join(", ")));
+                                    }
+                                    msg.push_str("\x1b[0m");
+                                    self.print_above(&msg)?;
+                                    self.input.restore_input(&message);
+                                }
+                                Err(e) => {
+                                    self.input.alert_msg(&e.to_string(), Duration::from_secs(3));
+                                }
+                            }
+                        }
+                        _ => {
+                            self.input.alert_msg("Usage: /fork [<n> [restore]]", Duration::from_secs(3));
+                        }
+                    }
+                }
+            }
+            "/branch" => {
+                if let Some(ref agent) = self.agent {
+                    let result = match args.first() {
+                        Some(id) => agent.controller.switch_branch(id.to_string()).await,
+                        None => agent.controller.list_branches().await,
+                    };
+                    match result {
+                        Ok(branches) => {
+                            if args.first().is_some() {
+                                self.queued_inputs.clear();
+                            }
+                            let lines: Vec<String> = branches.iter()
+                                .map(|b| format!(
+                                    "\x1b[2m  {} {:<10} {} messages{}\x1b[0m",
+                                    if b.current { "*" } else { " " },
+                                    b.id,
+                                    b.messages,
+                                    b.parent.as_ref().map(|p| format!(", forked from {} after {} messages", p, b.forked_at)).unwrap_or_default()
+                                ))
+                                .collect();
+                            self.print_above(&lines.join("\n"))?;
+                        }
+                        Err(e) => {
+                            self.input.alert_msg(&e.to_string(), Duration::from_secs(3));
+                        }
+                    }
+                }
+            }
             "/tokens" => {
-                let msg = format!(
+                let mut msg = format!(
                     "Token Usage - Input: {}, Output: {}, Total: {}",
                     self.total_input_tokens,
                     self.total_output_tokens,
                     self.total_input_tokens + self.total_output_tokens
                 );
+                if let Some(ctx) = self.input.context_str() {
+                    msg += &format!(", {}", ctx);
+                }
                 self.input.alert_msg(&msg, Duration::from_secs(5));
             }
             "/theme" => {
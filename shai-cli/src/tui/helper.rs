@@ -10,19 +10,24 @@ impl HelpArea {
         [
             "  ? to print help      tap esc twice to clear input",
             "  / for commands       tap esc while agent is running to cancel",
+            "                       enter while agent is running to queue a message",
+            "                       ctrl^s while agent is running to interrupt and send",
             "                       ctrl^c to exit",
             "",
             "  Available Commands:",
             "  /exit                exit from the tui",
             "  /tc <method>         set tool call method: [auto | fc | fc2 | so]",
-            "  /tokens              display token usage"
+            "  /tokens              display token usage",
+            "  /plan [task]         plan first, the agent can only read until /plan approve or /plan reject",
+            "  /fork [n] [restore]  list your messages, or retry from message n (restore also undoes file changes)",
+            "  /branch [id]         list the conversation branches, or switch to one"
         ].join("\n").to_string()
     }
 }
 
 impl HelpArea {
     pub fn height(&self) -> u16 {
-        8 // content (3 general help lines + 1 blank + 1 header + 3 command lines)
+        13 // content (5 general help lines + 1 blank + 1 header + 6 command lines)
     }
 
     pub fn draw(&self, f: &mut Frame, area: Rect) {
@@ -28,6 +28,9 @@ pub enum UserAction {
     UserInput {
         input: String
     },
+    QueueInput {
+        input: String
+    },
     UserAppCommand {
         command: String
     }
@@ -58,6 +61,12 @@ pub struct InputArea<'a> {
     // method info bottom right
     method: ToolCallMethod,
 
+    // context window usage left of the method, estimated tokens and window
+    context_usage: Option<(u32, Option<u32>)>,
+
+    // plan mode reminder top left
+    plan_mode: bool,
+
     // bottom helper
     help: Option<HelpArea>,
     cmdnav: CommandNav,
@@ -93,6 +102,8 @@ impl InputArea<'_> {
             helper_duration: None,
             escape_press_time: None,
             method: ToolCallMethod::FunctionCall,
+            context_usage: None,
+            plan_mode: false,
             help: None,
             cmdnav: CommandNav{},
             history: Vec::new(),
@@ -259,6 +270,27 @@ impl InputArea<'_> {
             }
         }
     } 
+
+    pub fn set_context_usage(&mut self, used_tokens: u32, context_window: Option<u32>) {
+        self.context_usage = Some((used_tokens, context_window));
+    }
+
+    /// e.g. "ctx 45% (58k/128k)", only the tokens if the window is not known
+    pub fn context_str(&self) -> Option<String> {
+        fn short(tokens: u32) -> String {
+            if tokens >= 1000 { format!("{}k", tokens / 1000) } else { tokens.to_string() }
+        }
+        let (used, window) = self.context_usage?;
+        Some(match window.filter(|w| *w > 0) {
+            Some(window) => format!("ctx {}% ({}/{})", used as u64 * 100 / window as u64, short(used), short(window)),
+            None => format!("ctx {}", short(used)),
+        })
+    }
+
+    /// whether the trace takes most of the context window
+    fn context_nearly_full(&self) -> bool {
+        matches!(self.context_usage, Some((used, Some(window))) if window > 0 && used as u64 * 100 >= window as u64 * 80)
+    }
 }
 
 
@@ -279,6 +311,16 @@ impl InputArea<'_> {
         self
     }
 
+    /// Put text back in the input, e.g. queued messages that were withdrawn
+    pub fn restore_input(&mut self, text: &str) {
+        self.input = TextArea::new(text.lines().map(|s| s.to_string()).collect());
+        self.move_cursor_to_end_of_text();
+    }
+
+    pub fn set_plan_mode(&mut self, plan_mode: bool) {
+        self.plan_mode = plan_mode;
+    }
+
     pub fn set_status(&mut self, text: &str) {
         self.status_message = Some(text.to_string());
     }
@@ -296,7 +338,10 @@ impl InputArea<'_> {
             let spinner_chars = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
             let elapsed = animation_start.elapsed().as_millis();
             let index = (elapsed / 100) % spinner_chars.len() as u128;
-            format!(" {} Agent is working... (press esc to cancel)", spinner_chars[index as usize])
+            format!(" {} Agent is working... (esc to cancel, enter to queue a message, ctrl^s to interrupt and send)", spinner_chars[index as usize])
+        } else if self.plan_mode {
+            // Agent is waiting for the plan to be reviewed
+            " plan mode: /plan approve to execute it, /plan reject to drop it, or reply to revise it".to_string()
         } else {
             // Agent is waiting for input, no status to show
             String::new()
@@ -316,10 +361,6 @@ impl InputArea<'_> {
         if let Some(enter_time) = self.pending_enter {
             if enter_time.elapsed() >= Duration::from_millis(100) {
                 self.pending_enter = None;
-                
-                if self.agent_running {
-                    return Some(UserAction::Nope);
-                }
 
                 let lines = self.input.lines();
                 if !lines[0].is_empty() {
@@ -333,6 +374,11 @@ impl InputArea<'_> {
                         return Some(UserAction::UserAppCommand { 
                             command: input
                          });
+                    } else if self.agent_running {
+                        // do not interrupt the agent, it reads the message at its next step
+                        return Some(UserAction::QueueInput {
+                            input
+                        });
                     } else {
                         return Some(UserAction::UserInput { 
                             input
@@ -439,6 +485,17 @@ impl InputArea<'_> {
                 self.input.input(event);
                 return UserAction::Nope;
             }
+            KeyCode::Char('s') if key_event.modifiers.contains(KeyModifiers::CONTROL) && self.agent_running => {
+                // interrupt the agent and send the message right away
+                let input = self.input.lines().join("\n");
+                if input.trim().is_empty() {
+                    return UserAction::Nope;
+                }
+                self.history.push(input.clone());
+                self.history_index = self.history.len();
+                self.input = TextArea::default();
+                return UserAction::UserInput { input };
+            }
             KeyCode::Enter => {
                 // Alt+Enter creates a new line immediately
                 if key_event.modifiers.contains(KeyModifiers::ALT) {
@@ -636,9 +693,11 @@ impl InputArea<'_> {
         f.render_widget(&self.input, prompt);
         
         // Helper text area below input
-        let [helper_left, _, helper_right] = Layout::horizontal([
+        let context_text = self.context_str().map(|ctx| format!("{}  ", ctx)).unwrap_or_default();
+        let [helper_left, _, helper_context, helper_right] = Layout::horizontal([
             Constraint::Fill(1), 
             Constraint::Fill(1), 
+            Constraint::Length(context_text.len() as u16),
             Constraint::Length(self.method_str().len() as u16)
         ]).areas(helper);
 
@@ -648,6 +707,13 @@ impl InputArea<'_> {
             helper_left
         );
                 
+        // Context window usage
+        let context_color = if self.context_nearly_full() { self.palette.status } else { self.palette.method_label };
+        f.render_widget(
+            Span::styled(context_text, Style::default().fg(context_color).dim()), 
+            helper_context
+        );
+
         // Status
         f.render_widget(
             Span::styled(self.method_str(), Style::default().fg(self.palette.method_label)), 
@@ -1,17 +1,26 @@
 use chrono::Utc;
 use openai_dive::v1::resources::chat::ChatMessage;
-use tracing::info;
+use tracing::{info, warn};
 use tokio_util::sync::CancellationToken;
+use tokio::sync::{broadcast, RwLock};
+use std::sync::Arc;
 use crate::agent::{AgentCore, AgentError, AgentEvent, InternalAgentEvent, InternalAgentState, ThinkerContext, ThinkerDecision, ThinkerFlowControl};
+use crate::runners::compacter::Compactor;
+use crate::tools::AnyTool;
+use crate::tools::types::IntoToolBox;
 
 impl AgentCore {
     /// Launch a brain task to decide next step
     pub async fn spawn_next_step(&mut self) {         
+        // steer the brain with mode changes and what the user sent in the meantime
+        self.deliver_notes().await;
+        self.deliver_queued_inputs().await;
+
         let cancellation_token = CancellationToken::new();
         let cancel_token_clone = cancellation_token.clone();
         let trace = self.trace.clone();
         let tx_clone = self.internal_tx.clone();
-        let available_tools = self.available_tools.clone();
+        let available_tools = self.visible_tools();
         let method = self.method.clone();
         let context = ThinkerContext {
             trace,
@@ -19,13 +28,19 @@ impl AgentCore {
             method
         };
         let brain = self.brain.clone();
+        let session_id = self.session_id.clone();
+        let compactor = self.compactor.clone();
         
         //////////////////////// TOKIO SPAWN
         tokio::spawn(async move {
             tokio::select! {
-                result = async {
+                // the llm calls of this step are audited under the agent session
+                result = shai_llm::audit::scope_session(session_id, async {
+                    if let Some(compactor) = compactor {
+                        fit_context(&compactor, &context.trace, &context.available_tools, &tx_clone).await;
+                    }
                     brain.write().await.next_step(context).await
-                } => {
+                }) => {
                     let _ = tx_clone.send(InternalAgentEvent::BrainResult {
                         result
                     });
@@ -47,7 +62,7 @@ impl AgentCore {
 
     /// Process a brain task result
     pub async fn process_next_step(&mut self, result: Result<ThinkerDecision, AgentError>) -> Result<(), AgentError> {
-        let ThinkerDecision{message, flow, token_usage} = self.handle_brain_error(result).await?;
+        let ThinkerDecision{message, flow, token_usage, finish_reason} = self.handle_brain_error(result).await?;
         let ChatMessage::Assistant { content, reasoning_content, tool_calls, .. } = message.clone() else {
             return self.handle_brain_error::<ThinkerDecision>(
                 Err(AgentError::InvalidResponse(format!("ChatMessage::Assistant expected, but got {:?} instead", message)))).await.map(|_| ()
@@ -62,7 +77,8 @@ impl AgentCore {
         // Emit event to external consumers
         let _ = self.emit_event(AgentEvent::BrainResult {
             timestamp: Utc::now(),
-            thought: Ok(message.clone())
+            thought: Ok(message.clone()),
+            finish_reason
         }).await;
 
         // Emit token usage event if available
@@ -86,7 +102,7 @@ impl AgentCore {
                 self.set_state(InternalAgentState::Running).await;
             }
             ThinkerFlowControl::AgentPause => {
-                self.set_state(InternalAgentState::Paused).await;
+                self.end_turn().await;
             }
         }
         Ok(())
@@ -97,13 +113,51 @@ impl AgentCore {
         match result {
             Ok(value) => Ok(value),
             Err(error) => {
-                self.set_state(InternalAgentState::Paused).await;
+                self.end_turn().await;
                 let _ = self.emit_event(AgentEvent::BrainResult { 
                     timestamp: Utc::now(),
-                    thought: Err(error.clone())
+                    thought: Err(error.clone()),
+                    finish_reason: None
                 }).await;
                 Err(error)
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Summarize the oldest messages of the trace if it nears the context window of the model,
+/// and report how much of the window the trace takes. A failed summary leaves the trace as is
+async fn fit_context(
+    compactor: &Compactor,
+    trace: &Arc<RwLock<Vec<ChatMessage>>>,
+    tools: &[Arc<dyn AnyTool>],
+    tx: &broadcast::Sender<InternalAgentEvent>
+) {
+    let tools = tools.to_vec().into_toolbox();
+    let current = trace.read().await.clone();
+    let mut usage = compactor.usage(&current, &tools).await;
+
+    if compactor.should_compact(&usage) {
+        match compactor.compact(&current).await {
+            Ok(Some(compacted)) => {
+                let before_tokens = usage.used_tokens;
+                usage = compactor.usage(&compacted.trace, &tools).await;
+                *trace.write().await = compacted.trace;
+                let _ = tx.send(InternalAgentEvent::TraceCompacted {
+                    removed: compacted.removed,
+                    before_tokens,
+                    after_tokens: usage.used_tokens
+                });
+            }
+            Ok(None) => {}
+            Err(error) => {
+                warn!(target: "agent::compact", error = %error, "failed to compact the trace");
+            }
+        }
+    }
+
+    let _ = tx.send(InternalAgentEvent::ContextUsage {
+        used_tokens: usage.used_tokens,
+        context_window: usage.context_window
+    });
+}
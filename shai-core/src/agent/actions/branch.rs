@@ -0,0 +1,127 @@
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use tracing::info;
+use uuid::Uuid;
+use crate::agent::{AgentCore, AgentError, AgentResponse, InternalAgentEvent, InternalAgentState, LoopDetector};
+use crate::agent::agent::AgentSocket;
+use crate::agent::branch::{user_messages, Branches};
+
+impl AgentCore {
+    /// Remember how many file operations were made before the user message at `position`
+    pub async fn record_checkpoint(&mut self, position: usize) {
+        if let Some(log) = &self.operation_log {
+            self.checkpoints.insert(position, log.operation_count().await);
+        }
+    }
+
+    /// Position in the trace and text of the `user_message`-th user message (0 based)
+    async fn user_message(&self, user_message: usize) -> Result<(usize, String), AgentError> {
+        let trace = self.trace.read().await;
+        user_messages(&trace).into_iter().nth(user_message)
+            .ok_or_else(|| AgentError::InvalidState(format!("no user message #{} in the conversation", user_message)))
+    }
+
+    /// Create a new agent sharing the brain, tools and permissions of this one, whose trace
+    /// stops right before the `user_message`-th user message, so that the branch runs on its own
+    pub async fn fork_at(&self, user_message: usize) -> Result<AgentCore, AgentError> {
+        let (position, _) = self.user_message(user_message).await?;
+        let trace = self.trace.read().await[..position].to_vec();
+        let (internal_tx, internal_rx) = broadcast::channel(1024);
+
+        Ok(AgentCore {
+            session_id: self.session_id.clone(),
+            spill_id: format!("{}-{}", self.session_id, Uuid::new_v4().simple()),
+            socket: AgentSocket { tx_command: None, rx_command: None, tx_event: None, rx_event: None },
+            brain: self.brain.clone(),
+            method: self.method,
+            trace: Arc::new(RwLock::new(trace)),
+            available_tools: self.available_tools.clone(),
+            permissions: self.permissions.clone(),
+            state: InternalAgentState::Starting,
+            output_budget: self.output_budget.clone(),
+            max_parallel_tools: self.max_parallel_tools,
+            queued_inputs: Vec::new(),
+            pending_notes: Vec::new(),
+            plan_mode: self.plan_mode,
+            loop_guard: self.loop_guard.clone(),
+            loop_detector: LoopDetector::default(),
+            working_dir: self.working_dir.clone(),
+            branches: Branches::default(),
+            operation_log: self.operation_log.clone(),
+            checkpoints: self.checkpoints.iter()
+                .filter(|(p, _)| **p < position)
+                .map(|(p, c)| (*p, *c))
+                .collect(),
+            compactor: self.compactor.clone(),
+            internal_tx,
+            internal_rx,
+        })
+    }
+
+    /// Move to a new branch whose trace stops right before the `user_message`-th user message,
+    /// optionally undoing the file changes made since then
+    pub async fn handle_fork(&mut self, user_message: usize, restore_files: bool) -> Result<AgentResponse, AgentError> {
+        let (position, message) = self.user_message(user_message).await?;
+        let checkpoint = match (restore_files, self.checkpoints.get(&position)) {
+            (false, _) => None,
+            (true, Some(count)) => Some(*count),
+            (true, None) => return Err(AgentError::InvalidState(
+                "the file changes made since this message were not recorded, they cannot be restored".to_string()
+            )),
+        };
+
+        self.handle_event(InternalAgentEvent::CancelTask).await?;
+
+        let restored = match (checkpoint, &self.operation_log) {
+            (Some(count), Some(log)) => log.restore_to(count).await
+                .map_err(|e| AgentError::ExecutionError(format!("failed to restore files: {}", e)))?,
+            _ => vec![],
+        };
+
+        {
+            let mut trace = self.trace.write().await;
+            let (branch, prefix) = self.branches.fork(std::mem::take(&mut *trace), position);
+            info!(target: "agent::branch", branch = %branch, messages = prefix.len(), restored = restored.len(), "forked conversation");
+            *trace = prefix;
+        }
+
+        // checkpoints after the fork point belong to the other branch, or were undone
+        self.checkpoints.retain(|p, _| *p < position);
+        self.queued_inputs.clear();
+        self.loop_detector.reset();
+        self.set_state(InternalAgentState::Paused).await;
+
+        Ok(AgentResponse::Forked { branch: self.branches.current.clone(), message, restored })
+    }
+
+    /// Move to another branch of the conversation
+    pub async fn handle_switch_branch(&mut self, id: String) -> Result<AgentResponse, AgentError> {
+        if !self.branches.branches.iter().any(|b| b.id == id) {
+            return Err(AgentError::InvalidState(format!("unknown branch: {}", id)));
+        }
+
+        self.handle_event(InternalAgentEvent::CancelTask).await?;
+        {
+            let mut trace = self.trace.write().await;
+            let current = std::mem::take(&mut *trace);
+            *trace = self.branches.switch(current, &id).map_err(AgentError::InvalidState)?;
+        }
+        info!(target: "agent::branch", branch = %id, "switched branch");
+
+        // file checkpoints only make sense on the branch that made the changes
+        self.checkpoints.clear();
+        self.queued_inputs.clear();
+        self.loop_detector.reset();
+        self.set_state(InternalAgentState::Paused).await;
+
+        self.branches_response().await
+    }
+
+    pub async fn branches_response(&self) -> Result<AgentResponse, AgentError> {
+        let current_len = self.trace.read().await.len();
+        Ok(AgentResponse::Branches {
+            branches: self.branches.clone(),
+            infos: self.branches.list(current_len),
+        })
+    }
+}
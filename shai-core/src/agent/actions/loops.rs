@@ -0,0 +1,67 @@
+use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent};
+use tracing::warn;
+use crate::agent::{AgentCore, AgentEvent};
+use crate::agent::guard::LoopVerdict;
+use crate::agent::branch::NOTE_PREFIX;
+
+impl AgentCore {
+    /// Inspect the tool step that just completed, given the errors its tools reported
+    /// returns an error message if the agent should stop and hand over to the user
+    pub async fn check_loop(&mut self, errors: &[String]) -> Option<String> {
+        let calls = {
+            let trace = self.trace.read().await;
+            let Some(start) = trace.iter().rposition(|m| matches!(m, ChatMessage::Assistant { tool_calls: Some(_), .. })) else {
+                return None;
+            };
+            let ChatMessage::Assistant { tool_calls: Some(tool_calls), .. } = &trace[start] else {
+                return None;
+            };
+
+            let results: Vec<String> = trace[start + 1..].iter()
+                .filter_map(|m| match m {
+                    ChatMessage::Tool { content, .. } => Some(content.to_string()),
+                    _ => None,
+                })
+                .collect();
+            let calls: Vec<(String, String, String)> = tool_calls.iter()
+                .zip(results.iter().map(Some).chain(std::iter::repeat(None)))
+                .map(|(tc, result)| (
+                    tc.function.name.clone(),
+                    canonical_arguments(&tc.function.arguments),
+                    result.cloned().unwrap_or_default()
+                ))
+                .collect();
+            calls
+        };
+
+        match self.loop_detector.observe(&self.loop_guard, &calls, errors) {
+            LoopVerdict::Continue => None,
+            LoopVerdict::Warn { reason } => {
+                warn!(target: "agent::loop_guard", "{}", reason);
+                // a user note, providers move system messages out of the conversation
+                self.trace.write().await.push(ChatMessage::User {
+                    content: ChatMessageContent::Text(format!(
+                        "{}{}. Repeating it will not give a different outcome. Step back, reconsider your approach \
+                        and try something different, or stop and ask the user for guidance.",
+                        NOTE_PREFIX, reason
+                    )),
+                    name: None
+                });
+                None
+            }
+            LoopVerdict::Stop { reason } => {
+                warn!(target: "agent::loop_guard", "stopping: {}", reason);
+                let error = format!("agent paused: {}. Send a message to continue.", reason);
+                let _ = self.emit_event(AgentEvent::Error { error: error.clone() }).await;
+                Some(error)
+            }
+        }
+    }
+}
+
+/// Arguments with a stable key order so that identical calls compare equal
+fn canonical_arguments(arguments: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(arguments)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|_| arguments.to_string())
+}
@@ -1,2 +1,5 @@
 pub mod brain;
 pub mod tools;
+pub mod loops;
+pub mod plan;
+pub mod branch;
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent};
+use serde::{Serialize, Deserialize};
+use tracing::info;
+use crate::agent::{AgentCore, AgentError, AgentResponse, InternalAgentState};
+use crate::agent::branch::NOTE_PREFIX;
+use crate::tools::{AnyTool, ToolCapability};
+
+/// Tools that stay available in plan mode besides the read-only ones
+const PLAN_TOOLS: [&str; 2] = ["todo_read", "todo_write"];
+
+const PLAN_MODE_NOTE: &str = "You are now in plan mode. You can only use read-only tools and the todo list. \
+Investigate what is needed, then write a step by step plan with todo_write and end your turn by presenting it \
+to the user. Do not try to change anything yet, the plan must be approved first.";
+
+const PLAN_APPROVED_NOTE: &str = "The user approved the plan, you have access to all your tools again. \
+Carry out the plan from the todo list, updating each item as you progress.";
+
+const PLAN_REJECTED_NOTE: &str = "The user rejected the plan and left plan mode, do not carry it out. \
+Wait for further instructions.";
+
+/// Plan mode operations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PlanRequest {
+    /// restrict the agent to read-only and todo tools until a plan is approved
+    Enter,
+    /// leave plan mode and execute the plan
+    Approve,
+    /// leave plan mode without executing the plan
+    Reject,
+    /// only get the current mode
+    Status,
+}
+
+/// Whether a tool can run in plan mode
+pub fn allowed_in_plan_mode(tool: &dyn AnyTool) -> bool {
+    tool.capabilities() == &[ToolCapability::Read] || PLAN_TOOLS.contains(&tool.name().as_str())
+}
+
+impl AgentCore {
+    /// Tools the brain is offered, depending on the mode
+    pub fn visible_tools(&self) -> Vec<Arc<dyn AnyTool>> {
+        self.available_tools.iter()
+            .filter(|tool| !self.plan_mode || allowed_in_plan_mode(tool.as_ref()))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn handle_plan_request(&mut self, request: PlanRequest) -> Result<AgentResponse, AgentError> {
+        match request {
+            PlanRequest::Status => {}
+            PlanRequest::Enter => {
+                if !self.plan_mode {
+                    info!(target: "agent::plan", "entering plan mode");
+                    self.plan_mode = true;
+                    self.pending_notes.push(PLAN_MODE_NOTE.to_string());
+                }
+            }
+            PlanRequest::Approve | PlanRequest::Reject if !self.plan_mode => {
+                return Err(AgentError::InvalidState("agent is not in plan mode".to_string()));
+            }
+            PlanRequest::Approve => {
+                info!(target: "agent::plan", "plan approved");
+                self.plan_mode = false;
+                self.pending_notes.push(PLAN_APPROVED_NOTE.to_string());
+
+                // a new turn starts to execute the plan
+                if matches!(self.state, InternalAgentState::Paused) {
+                    self.loop_detector.reset();
+                    self.set_state(InternalAgentState::Running).await;
+                }
+            }
+            PlanRequest::Reject => {
+                info!(target: "agent::plan", "plan rejected");
+                self.plan_mode = false;
+                self.pending_notes.push(PLAN_REJECTED_NOTE.to_string());
+            }
+        }
+        Ok(AgentResponse::PlanMode { enabled: self.plan_mode })
+    }
+
+    /// Add the pending notes (mode changes...) to the trace, before the next brain step
+    /// They are user notes, providers move system messages out of the conversation
+    pub async fn deliver_notes(&mut self) {
+        let notes = std::mem::take(&mut self.pending_notes);
+        if notes.is_empty() {
+            return;
+        }
+        let mut trace = self.trace.write().await;
+        for note in notes {
+            trace.push(ChatMessage::User {
+                content: ChatMessageContent::Text(format!("{}{}", NOTE_PREFIX, note)),
+                name: None
+            });
+        }
+    }
+}
@@ -1,20 +1,29 @@
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use chrono::{TimeDelta, Utc};
 use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent, ToolCall as LlmToolCall};
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{broadcast, watch, RwLock, Semaphore};
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 use tracing::info;
 use serde_json::from_str;
 use uuid::Uuid;
-use crate::agent::{AgentCore, AgentEvent, ClaimManager, InternalAgentEvent, InternalAgentState, PermissionRequest, PermissionResponse};
-use crate::tools::{AnyTool, ToolCall, ToolCapability, ToolResult};
+use crate::agent::{allowed_in_plan_mode, AgentCore, AgentEvent, ClaimManager, ToolOutputBudget, InternalAgentEvent, InternalAgentState, PermissionRequest, PermissionResponse};
+use crate::tools::{AnyTool, ToolCall, ToolCapability, ToolProgress, ToolResult};
 use tracing::debug;
 
+/// Partial tool output is coalesced and forwarded at most this often, a chatty command
+/// must not flood the event channel and make its subscribers lag
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
 impl AgentCore {
 
     /// Spawn a cancellable coroutine that runs all tool call in parrallel and waits for them to finish
+    /// Calls that conflict with an earlier call of the same batch (same path, or both able to write)
+    /// wait for it, at most `max_parallel_tools` calls run at once, and results are added to the
+    /// trace in call order whatever their completion order
     pub async fn spawn_tools(&mut self, tool_calls: Vec<LlmToolCall>) {
         let cancellation_token = CancellationToken::new();
         let cancel_clone = cancellation_token.clone();
@@ -25,12 +34,26 @@ impl AgentCore {
         let available_tools = self.available_tools.clone();
         let claims = self.permissions.clone();
         let trace = self.trace.clone();
+        let output_budget = self.output_budget.clone();
+        let spill_id = self.spill_id.clone();
+        let slots = Arc::new(Semaphore::new(self.max_parallel_tools.max(1)));
+        let plan_mode = self.plan_mode;
+        let working_dir = self.working_dir.clone();
 
         // Spawn a task to wait for all tool executions
         let mut join_handles = Vec::new();
+        let mut scheduled: Vec<(ToolFootprint, watch::Receiver<bool>)> = Vec::new();
         
         // Spawn all tool executions
         for tc in tool_calls {
+            let footprint = ToolFootprint::of(&tc, &available_tools, working_dir.as_deref());
+            let wait_for = scheduled.iter()
+                .filter(|(earlier, _)| footprint.conflicts_with(earlier))
+                .map(|(_, done)| done.clone())
+                .collect();
+            let (done_tx, done_rx) = watch::channel(false);
+            scheduled.push((footprint, done_rx));
+
             let handle = Self::spawn_tool_static(
                 tc,
                 cancel_clone.clone(),
@@ -38,31 +61,35 @@ impl AgentCore {
                 available_tools.clone(),
                 claims.clone(),
                 internal_tx.clone(),
-                trace.clone(),
+                output_budget.clone(),
+                spill_id.clone(),
+                plan_mode,
+                ToolSlot { wait_for, slots: slots.clone(), done: done_tx },
             );
             join_handles.push(handle);
         }
             
         // Wait for all tools to complete or be cancelled
+        // tools return promptly once cancelled, so their results are always collected
         tokio::spawn(async move {
-            tokio::select! {
-                _ = cancel_clone.cancelled() => {
-                    // Tools were cancelled, no need to send completion event
-                }
-                any_denied = async {
-                    // wait for all tools completion and collect denial status
-                    let mut result = false;
-                    for handle in join_handles {
-                        if let Ok(was_denied) = handle.await {
-                            result = result || was_denied;
-                        }
-                    }
-                    result
-                } => {
-                    // All tools completed, move to Running state
-                    let _ = internal_tx.send(InternalAgentEvent::ToolsCompleted { any_denied });
+            let mut any_denied = false;
+            let mut errors = Vec::new();
+            let mut messages = Vec::new();
+            for handle in join_handles {
+                if let Ok(outcome) = handle.await {
+                    any_denied = any_denied || outcome.denied;
+                    errors.extend(outcome.error);
+                    messages.extend(outcome.message);
                 }
             }
+
+            // add tool results to trace, in call order
+            trace.write().await.extend(messages);
+
+            if !cancel_clone.is_cancelled() {
+                // All tools completed, move to Running state
+                let _ = internal_tx.send(InternalAgentEvent::ToolsCompleted { any_denied, errors });
+            }
         });
         
         // Set state to Processing with cancellation token
@@ -75,6 +102,8 @@ impl AgentCore {
 
     /// Spawn a cancellable coroutine that runs a single tool call
     /// coordinating the appropriate tool specific event (start/completed)
+    /// returns whether the call was denied, its error if any and the tool message to add to the trace
+    #[allow(clippy::too_many_arguments)]
     fn spawn_tool_static(
         tc: LlmToolCall,
         cancel_token: CancellationToken,
@@ -82,13 +111,43 @@ impl AgentCore {
         available_tools: Vec<Arc<dyn AnyTool>>,
         claims: Arc<RwLock<ClaimManager>>,
         internal_tx: broadcast::Sender<InternalAgentEvent>,
-        trace: Arc<RwLock<Vec<ChatMessage>>>,
-    ) -> tokio::task::JoinHandle<bool> {
+        output_budget: Arc<ToolOutputBudget>,
+        spill_id: String,
+        plan_mode: bool,
+        slot: ToolSlot,
+    ) -> tokio::task::JoinHandle<ToolOutcome> {
         tokio::spawn(async move {
+            // wait for conflicting calls and for a free slot, released when this task ends
+            let ToolSlot { wait_for, slots, done } = slot;
+            let _done = DoneGuard(done);
+            let _permit = tokio::select! {
+                permit = async {
+                    for mut earlier in wait_for {
+                        let _ = earlier.wait_for(|finished| *finished).await;
+                    }
+                    slots.acquire_owned().await.ok()
+                } => permit,
+                _ = cancel_token.cancelled() => None,
+            };
+
+            // cancelled before it could start
+            if cancel_token.is_cancelled() {
+                let error = "tool call was cancelled by the user".to_string();
+                return ToolOutcome {
+                    denied: false,
+                    message: Some(ChatMessage::Tool {
+                        tool_call_id: tc.id.clone(),
+                        content: ChatMessageContent::Text(ToolResult::error(error.clone()).to_string())
+                    }),
+                    error: Some(error),
+                };
+            }
+
             let tc_for_error = tc.clone();
             match Self::tool_exist(available_tools, tc) {
                 // tool does not exist, we fail immediately
                 Err(tool_result) => {
+                    let error = tool_result.error_message();
                     if let Some(tx) = public_event_tx.clone() {
                         let _ = tx.send(AgentEvent::ToolCallCompleted { 
                             duration: TimeDelta::zero(), 
@@ -100,7 +159,7 @@ impl AgentCore {
                             result: tool_result
                         });
                     }
-                    false
+                    ToolOutcome { denied: false, error, message: None }
                 }
 
                 // emit tool call
@@ -121,6 +180,7 @@ impl AgentCore {
                     let tool_handle = Self::spawn_tool_exec(
                         tool, call.clone(), 
                         cancel_token.clone(), 
+                        plan_mode,
                         claims, 
                         public_event_tx.clone(), 
                         internal_tx.subscribe());
@@ -142,16 +202,18 @@ impl AgentCore {
                         }
                     };
 
-                    // let's first add tool result to trace
-                    let _ = {
-                        trace.write().await.push(ChatMessage::Tool {
-                            tool_call_id: call.tool_call_id.clone(),
-                            content: ChatMessageContent::Text(result.to_string())
-                        });
+                    // keep oversized outputs out of the context window
+                    let result = output_budget.apply(&spill_id, &call, result).await;
+
+                    // tool result for the trace, added in call order once all calls are done
+                    let message = ChatMessage::Tool {
+                        tool_call_id: call.tool_call_id.clone(),
+                        content: ChatMessageContent::Text(result.to_string())
                     };
 
                     // Emit tool call finish event
                     let tool_was_denied = result.is_denied();
+                    let error = result.error_message();
                     info!(target: "agent::tool_completed", call = ?tc_for_error.function.name.clone(), result = ?result);
                     if let Some(tx) = public_event_tx.clone() {
                         let _ = tx.send(AgentEvent::ToolCallCompleted { 
@@ -161,7 +223,7 @@ impl AgentCore {
                         });   
                     }
 
-                    tool_was_denied                    
+                    ToolOutcome { denied: tool_was_denied, error, message: Some(message) }
                 }
             }
         })
@@ -173,10 +235,19 @@ impl AgentCore {
         tool: Arc<dyn AnyTool>, 
         call: ToolCall, 
         cancel_token: CancellationToken,
+        plan_mode: bool,
         claims: Arc<RwLock<ClaimManager>>, 
         public_event_tx: Option<broadcast::Sender<AgentEvent>>, 
         mut internal_rx: broadcast::Receiver<InternalAgentEvent>) -> JoinHandle<ToolResult> {
         tokio::spawn(async move {
+            // in plan mode nothing may be changed until the plan is approved
+            if plan_mode && !allowed_in_plan_mode(tool.as_ref()) {
+                return ToolResult::error(format!(
+                    "{} is not available in plan mode, only read-only and todo tools can be used until the user approves the plan",
+                    tool.name()
+                ));
+            }
+
             // check permission, we allow all Read Tool
             let can_run = tool.capabilities().is_empty()  
             || tool.capabilities() == &[ToolCapability::Read]
@@ -192,9 +263,40 @@ impl AgentCore {
                 return ToolResult::denied()
             }
             
+            // Forward partial output of the tool as progress events
+            let execution = tool.execute_json(call.parameters.clone(), Some(cancel_token.clone()));
+            let execution = async {
+                match &public_event_tx {
+                    Some(tx) => {
+                        let pending = Arc::new(std::sync::Mutex::new(String::new()));
+                        let buffer = pending.clone();
+                        let progress = ToolProgress::new(move |chunk| buffer.lock().unwrap().push_str(&chunk));
+                        let flush = || {
+                            let chunk = std::mem::take(&mut *pending.lock().unwrap());
+                            if !chunk.is_empty() {
+                                let _ = tx.send(AgentEvent::ToolCallProgress { call_id: call.tool_call_id.clone(), chunk });
+                            }
+                        };
+
+                        let execution = progress.scope(execution);
+                        tokio::pin!(execution);
+                        let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+                        let result = loop {
+                            tokio::select! {
+                                result = &mut execution => break result,
+                                _ = ticker.tick() => flush(),
+                            }
+                        };
+                        flush();
+                        result
+                    }
+                    None => execution.await,
+                }
+            };
+
             // Execute tool with cancellation support
             tokio::select! {
-                result = tool.execute_json(call.parameters.clone(), Some(cancel_token.clone())) => result,
+                result = execution => result,
                 _ = cancel_token.cancelled() => {
                     ToolResult::error("tool call was cancelled by the user".to_string())
                 }
@@ -283,4 +385,113 @@ impl AgentCore {
                 .map(|tool| (tool, tool_call))
         })
     }
-}
\ No newline at end of file
+}
+
+/// Scheduling constraints of a single call within a batch
+struct ToolSlot {
+    /// completion flags of the earlier conflicting calls
+    wait_for: Vec<watch::Receiver<bool>>,
+    /// limit on the number of calls running at once
+    slots: Arc<Semaphore>,
+    /// completion flag of this call
+    done: watch::Sender<bool>,
+}
+
+/// Flags the call as done however its task ends
+struct DoneGuard(watch::Sender<bool>);
+
+impl Drop for DoneGuard {
+    fn drop(&mut self) {
+        let _ = self.0.send(true);
+    }
+}
+
+/// What a finished tool call brings back to the agent loop
+struct ToolOutcome {
+    denied: bool,
+    /// error reported by the tool, watched by the loop guard
+    error: Option<String>,
+    message: Option<ChatMessage>,
+}
+
+/// What a call may touch, used to order conflicting calls
+struct ToolFootprint {
+    write: bool,
+    paths: Vec<PathBuf>,
+}
+
+impl ToolFootprint {
+    fn of(tc: &LlmToolCall, tools: &[Arc<dyn AnyTool>], root: Option<&Path>) -> Self {
+        // unknown tools fail immediately, they never conflict
+        let write = tools.iter()
+            .find(|t| t.name() == tc.function.name)
+            .is_some_and(|t| t.capabilities().contains(&ToolCapability::Write));
+
+        let params: serde_json::Value = from_str(&tc.function.arguments).unwrap_or_default();
+        let paths = ["path", "file_path"].iter()
+            .filter_map(|key| params.get(*key).and_then(|p| p.as_str()))
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .map(|p| absolute_path(root, p))
+            .collect();
+
+        Self { write, paths }
+    }
+
+    /// Calls able to write never overlap, nor does a write with any call on the same path
+    fn conflicts_with(&self, other: &ToolFootprint) -> bool {
+        let shared_path = self.paths.iter().any(|p| other.paths.contains(p));
+        (self.write && other.write) || ((self.write || other.write) && shared_path)
+    }
+}
+
+/// Resolve a path the way the tools do, against the workspace root or the process directory,
+/// so that `a.rs`, `./a.rs` and `/root/a.rs` compare equal
+fn absolute_path(root: Option<&Path>, path: &str) -> PathBuf {
+    let base = root.map(Path::to_path_buf)
+        .or_else(|| std::env::current_dir().ok())
+        .unwrap_or_default();
+    let mut resolved = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => { resolved.pop(); }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openai_dive::v1::resources::chat::Function;
+
+    fn call(name: &str, arguments: serde_json::Value) -> LlmToolCall {
+        LlmToolCall {
+            id: "call".to_string(),
+            r#type: "function".to_string(),
+            function: Function { name: name.to_string(), arguments: arguments.to_string() },
+        }
+    }
+
+    #[test]
+    fn test_footprint_paths_resolved_against_root() {
+        let root = Path::new("/work/session");
+        let footprint = |path: &str| ToolFootprint::of(&call("read", serde_json::json!({"path": path})), &[], Some(root));
+
+        let relative = ToolFootprint { write: true, ..footprint("src/a.rs") };
+        for same in ["./src/a.rs", "src/./a.rs", "/work/session/src/a.rs", "src/../src/a.rs", "src/a.rs/"] {
+            assert!(relative.conflicts_with(&footprint(same)), "{} should be the same file", same);
+        }
+        assert!(!relative.conflicts_with(&footprint("src/b.rs")));
+        assert!(!relative.conflicts_with(&footprint("/elsewhere/src/a.rs")));
+    }
+
+    #[test]
+    fn test_reads_of_a_path_run_together() {
+        let footprint = |path: &str| ToolFootprint::of(&call("read", serde_json::json!({"path": path})), &[], Some(Path::new("/work")));
+        assert!(!footprint("a.rs").conflicts_with(&footprint("./a.rs")));
+        assert!(ToolFootprint { write: true, ..footprint("a.rs") }.conflicts_with(&footprint("a.rs")));
+    }
+}
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::boxed::Box;
 use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent};
@@ -7,6 +8,14 @@ use serde::{Serialize, Deserialize};
 use async_trait::async_trait;
 use crate::tools::AnyTool;
 use crate::agent::ClaimManager;
+use crate::agent::ToolOutputBudget;
+use crate::agent::{LoopGuard, LoopDetector};
+use crate::agent::Branches;
+use crate::agent::branch::QUEUED_INPUT_HEADER;
+use crate::tools::FsOperationLog;
+use crate::runners::compacter::Compactor;
+use std::collections::HashMap;
+use uuid::Uuid;
 
 // Helper functions to make the main loop more readable
 
@@ -53,9 +62,15 @@ pub struct AgentResult {
     pub trace:   Vec<ChatMessage>,
 }
 
+/// Number of tool calls of a single turn running at once
+pub const DEFAULT_MAX_PARALLEL_TOOLS: usize = 8;
+
 /// Core agent implementation that orchestrates any Thinker implementation
 pub struct AgentCore {
     pub session_id: String,
+    /// unique to this agent, names the directory of its saved tool outputs. Sessions of a
+    /// same configured agent share their id but must not share or delete each other's outputs
+    pub spill_id: String,
 
     /// public controler and event watcher
     pub socket: AgentSocket,
@@ -69,6 +84,23 @@ pub struct AgentCore {
     pub available_tools: Vec<Arc<dyn AnyTool>>,
     pub permissions:     Arc<RwLock<ClaimManager>>,
     pub state:           InternalAgentState,
+    pub output_budget:   Arc<ToolOutputBudget>,
+    pub max_parallel_tools: usize,
+    pub queued_inputs:   Vec<String>,
+    pub pending_notes:   Vec<String>,
+    pub plan_mode:       bool,
+    pub loop_guard:      LoopGuard,
+    pub loop_detector:   LoopDetector,
+    /// directory the tools resolve relative paths against, None = process directory
+    pub working_dir:     Option<PathBuf>,
+
+    /// conversation branches, and the file operation count before each user message of the current one
+    pub branches:        Branches,
+    pub operation_log:   Option<Arc<FsOperationLog>>,
+    pub checkpoints:     HashMap<usize, usize>,
+
+    /// summarizes the oldest messages when the trace nears the context window
+    pub compactor:       Option<Arc<Compactor>>,
 
     /// internal event
     pub internal_tx: broadcast::Sender<InternalAgentEvent>,   // event may be produced from many part of the agent
@@ -92,6 +124,7 @@ impl AgentCore {
     ) -> Self {
         let (internal_tx, internal_rx) = broadcast::channel(1024);
         Self {
+            spill_id: format!("{}-{}", session_id, Uuid::new_v4().simple()),
             session_id: session_id.clone(),
             socket: AgentSocket{
                 tx_command: None,
@@ -105,6 +138,18 @@ impl AgentCore {
             available_tools: available_tools.into_iter().map(|t| Arc::from(t) as Arc<dyn AnyTool>).collect(),
             permissions: Arc::new(RwLock::new(permissions)),
             state: InternalAgentState::Starting,
+            output_budget: Arc::new(ToolOutputBudget::default()),
+            max_parallel_tools: DEFAULT_MAX_PARALLEL_TOOLS,
+            queued_inputs: Vec::new(),
+            pending_notes: Vec::new(),
+            plan_mode: false,
+            loop_guard: LoopGuard::default(),
+            loop_detector: LoopDetector::default(),
+            working_dir: None,
+            branches: Branches::default(),
+            operation_log: None,
+            checkpoints: HashMap::new(),
+            compactor: None,
             internal_tx,
             internal_rx,
         }
@@ -129,6 +174,12 @@ impl AgentCore {
     }
 }
 
+impl Drop for AgentCore {
+    fn drop(&mut self) {
+        // the full outputs are only referenced by the trace of this agent
+        self.output_budget.remove_spilled(&self.spill_id);
+    }
+}
 
 #[async_trait]
 impl Agent for AgentCore {
@@ -266,6 +317,13 @@ impl AgentCore {
         });
     }
 
+    /// Let tools release what they hold (background processes...) once the agent terminates
+    async fn shutdown_tools(&self) {
+        for tool in &self.available_tools {
+            tool.shutdown().await;
+        }
+    }
+
     /// Returns true if there's a controller 
     pub fn has_io(&self) -> bool {
         match &self.socket.rx_command {
@@ -285,6 +343,9 @@ impl AgentCore {
             }
 
             // Handle terminal states - exit immediately
+            if matches!(self.state, InternalAgentState::Completed { .. } | InternalAgentState::Failed { .. }) {
+                self.shutdown_tools().await;
+            }
             match &self.state {
                 InternalAgentState::Completed { success } => {
                     debug!(target: "agent::terminated", "completed");
@@ -367,6 +428,9 @@ impl AgentCore {
             AgentRequest::GetState => {
                 Ok(AgentResponse::State { state: self.state.to_public()})
             }
+            AgentRequest::GetTrace => {
+                Ok(AgentResponse::Trace { messages: self.trace.read().await.clone() })
+            }
             AgentRequest::Sudo(operation) => {
                 let mut guard = self.permissions.write().await;
                 match operation {
@@ -397,27 +461,49 @@ impl AgentCore {
                 }
                 Ok(AgentResponse::Method { method: self.method })
             }
-            AgentRequest::SendUserInput{ input } => {
-                self.handle_event(InternalAgentEvent::CancelTask).await
-                .and({
-                    // Emit UserInput event
-                    let _ = self.emit_event(AgentEvent::UserInput {
-                        input: input.clone()
-                    }).await;
-
-                    self.trace.write().await.push(ChatMessage::User {
-                        content: ChatMessageContent::Text(input),
-                        name: None
-                    });
-
-                    self.set_state(InternalAgentState::Running).await;
+            AgentRequest::SetLoopGuard { guard } => {
+                if let Some(guard) = guard {
+                    self.loop_guard = guard;
+                }
+                Ok(AgentResponse::LoopGuard { guard: self.loop_guard.clone() })
+            }
+            AgentRequest::QueueUserInput{ input } => {
+                if matches!(self.state, InternalAgentState::Running | InternalAgentState::Processing { .. }) {
+                    // delivered to the brain at its next step, without interrupting the current work
+                    self.queued_inputs.push(input.clone());
+                    let _ = self.emit_event(AgentEvent::UserInputQueued { input }).await;
                     Ok(AgentResponse::Ack)
-                })
+                } else {
+                    self.send_user_input(input).await
+                }
+            }
+            AgentRequest::Plan(request) => {
+                self.handle_plan_request(request).await
+            }
+            AgentRequest::Fork { user_message, restore_files } => {
+                self.handle_fork(user_message, restore_files).await
+            }
+            AgentRequest::SwitchBranch { id } => {
+                self.handle_switch_branch(id).await
+            }
+            AgentRequest::GetBranches => {
+                self.branches_response().await
+            }
+            AgentRequest::TakeQueuedInputs => {
+                Ok(AgentResponse::QueuedInputs { inputs: std::mem::take(&mut self.queued_inputs) })
+            }
+            AgentRequest::SendUserInput{ input } => {
+                self.send_user_input(input).await
             }
             AgentRequest::SendTrace{ messages } => {
                 self.handle_event(InternalAgentEvent::CancelTask).await
                 .and({
-                    // Add all messages to trace at once
+                    // Add all messages to trace at once, a new turn starts
+                    self.loop_detector.reset();
+                    let start = self.trace.read().await.len();
+                    for position in super::branch::user_message_positions(&messages) {
+                        self.record_checkpoint(start + position).await;
+                    }
                     self.trace.write().await.extend(messages);
 
                     self.set_state(InternalAgentState::Running).await;
@@ -452,6 +538,59 @@ impl AgentCore {
     }
 
 
+    /// Cancel the current task and start a new turn with the user input
+    async fn send_user_input(&mut self, input: String) -> Result<AgentResponse, AgentError> {
+        self.handle_event(InternalAgentEvent::CancelTask).await
+        .and({
+            // notes and messages queued before this one come first
+            self.deliver_notes().await;
+            self.deliver_queued_inputs().await;
+
+            // Emit UserInput event
+            let _ = self.emit_event(AgentEvent::UserInput {
+                input: input.clone()
+            }).await;
+
+            // a new turn starts
+            self.loop_detector.reset();
+            let position = self.trace.read().await.len();
+            self.record_checkpoint(position).await;
+            self.trace.write().await.push(ChatMessage::User {
+                content: ChatMessageContent::Text(input),
+                name: None
+            });
+
+            self.set_state(InternalAgentState::Running).await;
+            Ok(AgentResponse::Ack)
+        })
+    }
+
+    /// Pause at the end of a turn. Messages queued while the agent was working are
+    /// not left behind: they start the next turn right away
+    pub async fn end_turn(&mut self) {
+        if self.queued_inputs.is_empty() {
+            self.set_state(InternalAgentState::Paused).await;
+        } else {
+            self.loop_detector.reset();
+            self.set_state(InternalAgentState::Running).await;
+        }
+    }
+
+    /// Add the messages queued while the agent was working to the trace
+    pub async fn deliver_queued_inputs(&mut self) {
+        for input in std::mem::take(&mut self.queued_inputs) {
+            let _ = self.emit_event(AgentEvent::UserInput {
+                input: input.clone()
+            }).await;
+
+            // delivered mid turn, not a fork point so no file checkpoint
+            self.trace.write().await.push(ChatMessage::User {
+                content: ChatMessageContent::Text(format!("{}\n{}", QUEUED_INPUT_HEADER, input)),
+                name: None
+            });
+        }
+    }
+
     /// Handle an event
     async fn handle_event(&mut self, event: InternalAgentEvent) -> Result<(), AgentError> {
         debug!(target: "agent::internal_event", event = ?event);
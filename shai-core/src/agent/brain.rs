@@ -1,6 +1,7 @@
 use std::sync::Arc;
 use async_trait::async_trait;
 use openai_dive::v1::resources::chat::ChatMessage;
+use openai_dive::v1::resources::shared::FinishReason;
 use shai_llm::ToolCallMethod;
 use tokio::sync::RwLock;
 
@@ -30,6 +31,7 @@ pub struct ThinkerDecision {
     pub message: ChatMessage,
     pub flow:    ThinkerFlowControl,
     pub token_usage: Option<(u32, u32)>, // (input_tokens, output_tokens)
+    pub finish_reason: Option<FinishReason>,
 }
 
 impl ThinkerDecision {
@@ -38,6 +40,7 @@ impl ThinkerDecision {
             message,
             flow: ThinkerFlowControl::AgentPause,
             token_usage: None,
+            finish_reason: None,
         }
     }
 
@@ -46,6 +49,7 @@ impl ThinkerDecision {
             message,
             flow: ThinkerFlowControl::AgentContinue,
             token_usage: None,
+            finish_reason: None,
         }
     }
 
@@ -54,6 +58,7 @@ impl ThinkerDecision {
             message,
             flow: ThinkerFlowControl::AgentPause,
             token_usage: None,
+            finish_reason: None,
         }
     }
 
@@ -62,6 +67,7 @@ impl ThinkerDecision {
             message,
             flow: ThinkerFlowControl::AgentContinue,
             token_usage: Some((input_tokens, output_tokens)),
+            finish_reason: None,
         }
     }
 
@@ -70,9 +76,16 @@ impl ThinkerDecision {
             message,
             flow: ThinkerFlowControl::AgentPause,
             token_usage: Some((input_tokens, output_tokens)),
+            finish_reason: None,
         }
     }
 
+    /// Why the model stopped, as reported by the provider
+    pub fn with_finish_reason(mut self, finish_reason: Option<FinishReason>) -> Self {
+        self.finish_reason = finish_reason;
+        self
+    }
+
     pub fn unwrap(self) -> ChatMessage {
         self.message
     }
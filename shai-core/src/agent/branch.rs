@@ -0,0 +1,152 @@
+use chrono::{DateTime, Utc};
+use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent, ChatMessageContentPart};
+use serde::{Serialize, Deserialize};
+
+/// Name of the branch every conversation starts on
+pub const MAIN_BRANCH: &str = "main";
+
+/// An alternative version of the conversation, forked from an earlier user message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Branch {
+    pub id: String,
+    /// Branch this one was forked from, None for the main branch
+    pub parent: Option<String>,
+    /// Number of messages of the parent trace kept by the fork
+    pub forked_at: usize,
+    pub created_at: DateTime<Utc>,
+    /// Trace of the branch, left empty for the current branch whose trace lives in the agent
+    #[serde(default)]
+    pub trace: Vec<ChatMessage>,
+}
+
+impl Branch {
+    fn new(id: String, parent: Option<String>, forked_at: usize) -> Self {
+        Self { id, parent, forked_at, created_at: Utc::now(), trace: vec![] }
+    }
+}
+
+/// Short description of a branch
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BranchInfo {
+    pub id: String,
+    pub parent: Option<String>,
+    pub forked_at: usize,
+    pub messages: usize,
+    pub current: bool,
+}
+
+/// All the branches of a conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Branches {
+    pub current: String,
+    pub branches: Vec<Branch>,
+}
+
+impl Default for Branches {
+    fn default() -> Self {
+        Self {
+            current: MAIN_BRANCH.to_string(),
+            branches: vec![Branch::new(MAIN_BRANCH.to_string(), None, 0)],
+        }
+    }
+}
+
+impl Branches {
+    fn get_mut(&mut self, id: &str) -> Option<&mut Branch> {
+        self.branches.iter_mut().find(|b| b.id == id)
+    }
+
+    fn next_id(&self) -> String {
+        (1..)
+            .map(|n| format!("branch-{}", n))
+            .find(|id| self.branches.iter().all(|b| &b.id != id))
+            .unwrap()
+    }
+
+    /// Keep the current trace in its branch, create a branch holding its first `keep` messages
+    /// and make it current. Returns the id and the trace of the new branch
+    pub fn fork(&mut self, current_trace: Vec<ChatMessage>, keep: usize) -> (String, Vec<ChatMessage>) {
+        let prefix = current_trace[..keep.min(current_trace.len())].to_vec();
+        let id = self.next_id();
+        let parent = self.current.clone();
+        if let Some(branch) = self.get_mut(&parent) {
+            branch.trace = current_trace;
+        }
+        self.branches.push(Branch::new(id.clone(), Some(parent), prefix.len()));
+        self.current = id.clone();
+        (id, prefix)
+    }
+
+    /// Keep the current trace in its branch and make `id` current, returns its trace
+    pub fn switch(&mut self, current_trace: Vec<ChatMessage>, id: &str) -> Result<Vec<ChatMessage>, String> {
+        if id == self.current {
+            return Ok(current_trace);
+        }
+        let target = self.get_mut(id)
+            .map(|branch| std::mem::take(&mut branch.trace))
+            .ok_or_else(|| format!("unknown branch: {}", id))?;
+        let current = self.current.clone();
+        if let Some(branch) = self.get_mut(&current) {
+            branch.trace = current_trace;
+        }
+        self.current = id.to_string();
+        Ok(target)
+    }
+
+    /// Describe the branches, `current_len` is the length of the trace held by the agent
+    pub fn list(&self, current_len: usize) -> Vec<BranchInfo> {
+        self.branches.iter()
+            .map(|b| BranchInfo {
+                id: b.id.clone(),
+                parent: b.parent.clone(),
+                forked_at: b.forked_at,
+                messages: if b.id == self.current { current_len } else { b.trace.len() },
+                current: b.id == self.current,
+            })
+            .collect()
+    }
+}
+
+/// Start of the notes the agent adds as user messages (loop warnings, plan mode changes)
+pub const NOTE_PREFIX: &str = "Note: ";
+
+/// First line of the user messages sent while the agent was working, delivered in the middle of a turn
+pub const QUEUED_INPUT_HEADER: &str = "[The user sent this message while you were working, take it into account from now on]";
+
+/// Whether a user message was added by the agent in the middle of a turn rather than starting one
+pub fn is_injected(text: &str) -> bool {
+    text.starts_with(NOTE_PREFIX) || text.starts_with(QUEUED_INPUT_HEADER)
+}
+
+/// Position in the trace and text of each user message starting a turn, notes and queued
+/// messages are left out. Forks number the user messages in this order, starting at 0
+pub fn user_messages(trace: &[ChatMessage]) -> Vec<(usize, String)> {
+    trace.iter()
+        .enumerate()
+        .filter_map(|(i, m)| match m {
+            ChatMessage::User { content, .. } => Some((i, user_text(content))),
+            _ => None,
+        })
+        .filter(|(_, text)| !is_injected(text))
+        .collect()
+}
+
+/// Position in the trace of each user message
+pub fn user_message_positions(trace: &[ChatMessage]) -> Vec<usize> {
+    user_messages(trace).into_iter().map(|(i, _)| i).collect()
+}
+
+/// Text parts of a user message, images are left out
+fn user_text(content: &ChatMessageContent) -> String {
+    match content {
+        ChatMessageContent::Text(text) => text.clone(),
+        ChatMessageContent::ContentPart(parts) => parts.iter()
+            .filter_map(|part| match part {
+                ChatMessageContentPart::Text(part) => Some(part.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ChatMessageContent::None => String::new(),
+    }
+}
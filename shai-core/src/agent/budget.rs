@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+use tracing::warn;
+
+use crate::tools::{ToolCall, ToolResult};
+
+/// Limit on the size of a tool output that enters the trace
+/// Larger outputs keep their head and tail, the full text is written to a file
+/// that the model can page through with the read tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolOutputBudget {
+    /// Maximum size in bytes of a tool output, 0 disables the limit
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: usize,
+    /// Per tool override of max_bytes
+    #[serde(default)]
+    pub per_tool: HashMap<String, usize>,
+    /// Where full outputs are saved, defaults to <tmp>/shai-tool-outputs
+    #[serde(default)]
+    pub spill_dir: Option<PathBuf>,
+}
+
+fn default_max_bytes() -> usize {
+    32 * 1024
+}
+
+impl Default for ToolOutputBudget {
+    fn default() -> Self {
+        Self {
+            max_bytes: default_max_bytes(),
+            per_tool: HashMap::new(),
+            spill_dir: None,
+        }
+    }
+}
+
+impl ToolOutputBudget {
+    /// No limit at all
+    pub fn unlimited() -> Self {
+        Self { max_bytes: 0, ..Self::default() }
+    }
+
+    pub fn with_tool_limit(mut self, tool_name: &str, max_bytes: usize) -> Self {
+        self.per_tool.insert(tool_name.to_string(), max_bytes);
+        self
+    }
+
+    /// Keep a single output within a quarter of the model context window
+    /// (about 4 bytes per token), the per tool limits are left as set
+    pub fn fit_context(mut self, context_tokens: Option<u32>) -> Self {
+        if let Some(context_tokens) = context_tokens.filter(|c| *c > 0) {
+            let cap = context_tokens as usize;
+            if self.max_bytes == 0 || self.max_bytes > cap {
+                self.max_bytes = cap;
+            }
+        }
+        self
+    }
+
+    pub fn limit_for(&self, tool_name: &str) -> usize {
+        self.per_tool.get(tool_name).copied().unwrap_or(self.max_bytes)
+    }
+
+    fn spill_dir(&self) -> PathBuf {
+        self.spill_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("shai-tool-outputs"))
+    }
+
+    /// Enforce the budget on a tool result
+    pub async fn apply(&self, spill_id: &str, call: &ToolCall, result: ToolResult) -> ToolResult {
+        let limit = self.limit_for(&call.tool_name);
+        if limit == 0 {
+            return result;
+        }
+
+        let (text, metadata) = match &result {
+            ToolResult::Success { output, metadata } => (output, metadata),
+            ToolResult::Error { error, metadata } => (error, metadata),
+            ToolResult::Denied => return result,
+        };
+        if text.len() <= limit {
+            return result;
+        }
+
+        let spilled = self.spill(spill_id, call, text).await;
+        let truncated = truncate_middle(text, limit, spilled.as_ref());
+
+        let mut metadata = metadata.clone().unwrap_or_default();
+        metadata.insert("truncated".to_string(), json!(true));
+        metadata.insert("original_bytes".to_string(), json!(text.len()));
+        metadata.insert("kept_bytes".to_string(), json!(truncated.len()));
+        if let Some(path) = &spilled {
+            metadata.insert("full_output_path".to_string(), json!(path.to_string_lossy()));
+        }
+
+        match result {
+            ToolResult::Success { .. } => ToolResult::Success { output: truncated, metadata: Some(metadata) },
+            ToolResult::Error { .. } => ToolResult::Error { error: truncated, metadata: Some(metadata) },
+            ToolResult::Denied => unreachable!(),
+        }
+    }
+
+    /// Directory holding the full outputs of an agent
+    fn spill_dir_of(&self, spill_id: &str) -> PathBuf {
+        self.spill_dir().join(sanitize(spill_id))
+    }
+
+    /// Delete the full outputs saved by an agent, called when it goes away
+    pub fn remove_spilled(&self, spill_id: &str) {
+        let dir = self.spill_dir_of(spill_id);
+        match std::fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                warn!(target: "agent::budget", "failed to remove saved outputs {}: {}", dir.display(), e);
+            }
+            _ => {}
+        }
+    }
+
+    async fn spill(&self, spill_id: &str, call: &ToolCall, text: &str) -> Option<PathBuf> {
+        let dir = self.spill_dir_of(spill_id);
+        let path = dir.join(format!("{}-{}.txt", sanitize(&call.tool_name), sanitize(&call.tool_call_id)));
+        let written = async {
+            tokio::fs::create_dir_all(&dir).await?;
+            tokio::fs::write(&path, text).await
+        }.await;
+
+        match written {
+            Ok(()) => Some(path),
+            Err(e) => {
+                warn!(target: "agent::budget", "failed to save full output of {} to {}: {}", call.tool_name, path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// Keep the head and the tail of a text within `limit` bytes, cutting on line boundaries when possible
+fn truncate_middle(text: &str, limit: usize, spilled: Option<&PathBuf>) -> String {
+    let head_len = floor_boundary(text, limit / 2);
+    let head_len = text[..head_len].rfind('\n').map(|i| i + 1).filter(|&i| i > head_len / 2).unwrap_or(head_len);
+
+    let tail_start = ceil_boundary(text, text.len() - (limit - head_len).min(text.len() - head_len));
+    let tail_start = text[tail_start..].find('\n')
+        .map(|i| tail_start + i + 1)
+        .filter(|&i| text.len() - i > (text.len() - tail_start) / 2)
+        .unwrap_or(tail_start);
+
+    let head = &text[..head_len];
+    let tail = &text[tail_start..];
+    let omitted = &text[head_len..tail_start];
+    let omitted_lines = omitted.matches('\n').count();
+    let first_omitted_line = head.matches('\n').count() + 1;
+
+    let marker = match spilled {
+        Some(path) => format!(
+            "[... {} bytes ({} lines) truncated. The full output ({} bytes) was saved to {}, use the read tool with line_start={} to see the missing part ...]",
+            omitted.len(), omitted_lines, text.len(), path.display(), first_omitted_line
+        ),
+        None => format!("[... {} bytes ({} lines) truncated ...]", omitted.len(), omitted_lines),
+    };
+
+    let separator_before = if head.is_empty() || head.ends_with('\n') { "" } else { "\n" };
+    format!("{}{}{}\n{}", head, separator_before, marker, tail)
+}
+
+fn floor_boundary(text: &str, mut index: usize) -> usize {
+    index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_boundary(text: &str, mut index: usize) -> usize {
+    index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
@@ -1,17 +1,33 @@
 use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent};
 use shai_llm::LlmClient;
 use uuid::Uuid;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use crate::tools::mcp::mcp_oauth::signin_oauth;
-use crate::tools::{create_mcp_client, get_mcp_tools, AnyTool, BashTool, EditTool, FetchTool, FindTool, FsOperationLog, LsTool, McpConfig, MultiEditTool, ReadTool, TodoReadTool, TodoStorage, TodoWriteTool, WriteTool};
-use crate::config::agent::AgentConfig;
-use crate::config::config::ShaiConfig;
+use crate::tools::{create_mcp_client, get_mcp_tools, AnyTool, BashInputTool, BashKillTool, BashListTool, BashOutputTool, BashTool, EditTool, FetchTool, FindTool, FsOperationLog, LsTool, McpConfig, MultiEditTool, ProcessRegistry, ReadTool, ShellTool, TodoReadTool, TodoStorage, TodoWriteTool, WriteTool};
+use crate::config::agent::{AgentConfig, BUILTIN_TOOLS};
+use crate::config::config::{ModelRole, ShaiConfig};
 use crate::runners::coder::CoderBrain;
+use crate::runners::compacter::Compactor;
 use super::Brain;
 use super::AgentCore;
 use super::claims::ClaimManager;
 use super::AgentError;
+use super::ToolOutputBudget;
+use super::LoopGuard;
+use super::Branches;
+use super::agent::DEFAULT_MAX_PARALLEL_TOOLS;
+
+/// Box a tool, resolving its paths against the workspace root if any
+macro_rules! rooted {
+    ($tool:expr, $root:expr) => {
+        match $root {
+            Some(root) => Box::new($tool.with_root(root.to_path_buf())) as Box<dyn AnyTool>,
+            None => Box::new($tool) as Box<dyn AnyTool>,
+        }
+    };
+}
 
 /// Builder for AgentCore
 pub struct AgentBuilder {
@@ -21,6 +37,13 @@ pub struct AgentBuilder {
     pub trace: Vec<ChatMessage>,
     pub available_tools: Vec<Box<dyn AnyTool>>,
     pub permissions: ClaimManager,
+    pub output_budget: ToolOutputBudget,
+    pub max_parallel_tools: usize,
+    pub loop_guard: LoopGuard,
+    pub operation_log: Option<Arc<FsOperationLog>>,
+    pub branches: Branches,
+    pub compactor: Option<Compactor>,
+    pub working_dir: Option<PathBuf>,
 }
 
 impl AgentBuilder {
@@ -28,30 +51,57 @@ impl AgentBuilder {
     /// If None, creates a default agent with LLM from ShaiConfig
     /// If Some(name), loads agent from config file
     pub async fn create(config_name: Option<String>) -> Result<Self, AgentError> {
+        Self::create_in(config_name, None).await
+    }
+
+    /// Same as `create` but tools, bash and the prompt environment
+    /// operate inside `working_dir` instead of the process directory
+    pub async fn create_in(config_name: Option<String>, working_dir: Option<PathBuf>) -> Result<Self, AgentError> {
         match config_name {
             Some(name) => {
                 let config = AgentConfig::load(&name)
                     .map_err(|e| AgentError::ConfigurationError(format!("Failed to load agent '{}': {}", name, e)))?;
-                Self::from_config(config).await
+                Self::from_config_in(config, working_dir).await
             }
-            None => Self::default().await,
+            None => Self::default_in(working_dir).await,
         }
     }
 
     /// Create a default AgentBuilder using ShaiConfig LLM and default tools
     pub async fn default() -> Result<Self, AgentError> {
+        Self::default_in(None).await
+    }
+
+    /// Create a default AgentBuilder operating inside `working_dir` if given
+    pub async fn default_in(working_dir: Option<PathBuf>) -> Result<Self, AgentError> {
         // Get LLM from ShaiConfig
-        let (llm_client, model) = ShaiConfig::get_llm().await
+        let (llm_client, model) = ShaiConfig::get_llm_for(ModelRole::Coder).await
             .map_err(|e| AgentError::ConfigurationError(format!("Failed to get LLM from config: {}", e)))?;
 
+        // Size tool outputs after the model context window
+        let output_budget = ToolOutputBudget::default()
+            .fit_context(llm_client.model_info(&model).await.context_length);
+
+        // Summaries are written by the compaction model
+        let llm_client = Arc::new(llm_client);
+        let mut compactor = Compactor::new(llm_client.clone(), model.clone());
+        if let Ok((summarizer, summary_model)) = ShaiConfig::get_llm_for(ModelRole::Compaction).await {
+            compactor = compactor.with_summarizer(Arc::new(summarizer), summary_model);
+        }
+
         // Create default brain
-        let brain = Box::new(CoderBrain::new(Arc::new(llm_client), model));
+        let mut brain = CoderBrain::new(llm_client, model);
+        if let Some(dir) = &working_dir {
+            brain = brain.with_working_dir(dir.clone());
+        }
 
         // Create default toolbox (using ToolConfig from shai-cli)
         // For now, create basic tools - we can expand this later
-        let tools = Self::create_default_tools();
+        let fs_log = Arc::new(FsOperationLog::new());
+        let tools = Self::create_default_tools(working_dir.as_deref(), fs_log.clone());
+        let brain = Box::new(brain);
 
-        Ok(Self::with_brain(brain).tools(tools))
+        Ok(Self::with_brain(brain).tools(tools).operation_log(fs_log).output_budget(output_budget).compactor(compactor).working_dir(working_dir))
     }
 
     /// Create AgentBuilder with a specific brain
@@ -63,25 +113,37 @@ impl AgentBuilder {
             trace: vec![],
             available_tools: vec![],
             permissions: ClaimManager::new(),
+            output_budget: ToolOutputBudget::default(),
+            max_parallel_tools: DEFAULT_MAX_PARALLEL_TOOLS,
+            loop_guard: LoopGuard::default(),
+            operation_log: None,
+            branches: Branches::default(),
+            compactor: None,
+            working_dir: None,
         }
     }
 
     /// Create default set of tools
-    fn create_default_tools() -> Vec<Box<dyn AnyTool>> {
-        let fs_log = Arc::new(FsOperationLog::new());
+    fn create_default_tools(root: Option<&Path>, fs_log: Arc<FsOperationLog>) -> Vec<Box<dyn AnyTool>> {
         let todo_storage = Arc::new(TodoStorage::new());
+        let processes = Arc::new(ProcessRegistry::new());
 
         vec![
-            Box::new(BashTool::new()),
-            Box::new(EditTool::new(fs_log.clone())),
-            Box::new(MultiEditTool::new(fs_log.clone())),
+            rooted!(BashTool::new().with_processes(processes.clone()), root),
+            Box::new(BashOutputTool::new(processes.clone())),
+            Box::new(BashInputTool::new(processes.clone())),
+            Box::new(BashListTool::new(processes.clone())),
+            Box::new(BashKillTool::new(processes)),
+            rooted!(ShellTool::new(), root),
+            rooted!(EditTool::new(fs_log.clone()), root),
+            rooted!(MultiEditTool::new(fs_log.clone()), root),
             Box::new(FetchTool::new()),
-            Box::new(FindTool::new()),
-            Box::new(LsTool::new()),
-            Box::new(ReadTool::new(fs_log.clone())),
+            rooted!(FindTool::new(), root),
+            rooted!(LsTool::new(), root),
+            rooted!(ReadTool::new(fs_log.clone()), root),
             Box::new(TodoReadTool::new(todo_storage.clone())),
             Box::new(TodoWriteTool::new(todo_storage.clone())),
-            Box::new(WriteTool::new(fs_log)),
+            rooted!(WriteTool::new(fs_log), root),
         ]
     }
 }
@@ -117,6 +179,48 @@ impl AgentBuilder {
         self
     }
 
+    /// Limit the size of tool outputs kept in the trace
+    pub fn output_budget(mut self, output_budget: ToolOutputBudget) -> Self {
+        self.output_budget = output_budget;
+        self
+    }
+
+    /// Maximum number of tool calls of a turn running at once
+    pub fn max_parallel_tools(mut self, max_parallel_tools: usize) -> Self {
+        self.max_parallel_tools = max_parallel_tools;
+        self
+    }
+
+    /// Limit the number of steps of a turn and the repetitions tolerated
+    pub fn loop_guard(mut self, loop_guard: LoopGuard) -> Self {
+        self.loop_guard = loop_guard;
+        self
+    }
+
+    /// Operation log shared by the file system tools, used to restore files when forking
+    pub fn operation_log(mut self, operation_log: Arc<FsOperationLog>) -> Self {
+        self.operation_log = Some(operation_log);
+        self
+    }
+
+    /// Restore the branches of a conversation, the trace of the current one is given by `with_traces`
+    pub fn with_branches(mut self, branches: Branches) -> Self {
+        self.branches = branches;
+        self
+    }
+
+    /// Summarize the oldest messages of the trace when it nears the context window
+    pub fn compactor(mut self, compactor: Compactor) -> Self {
+        self.compactor = Some(compactor);
+        self
+    }
+
+    /// Directory the tools of the agent operate in, relative paths are resolved against it
+    pub fn working_dir(mut self, working_dir: Option<PathBuf>) -> Self {
+        self.working_dir = working_dir;
+        self
+    }
+
     /// Enable sudo mode - bypasses all permission checks
     pub fn sudo(mut self) -> Self {
         self.permissions.sudo();
@@ -130,33 +234,66 @@ impl AgentBuilder {
         }
 
 
-        AgentCore::new(
+        let mut agent = AgentCore::new(
             self.session_id.clone(),
             self.brain,
             self.trace,
             self.available_tools,
             self.permissions
-        )
+        );
+        agent.output_budget = Arc::new(self.output_budget);
+        agent.max_parallel_tools = self.max_parallel_tools;
+        agent.loop_guard = self.loop_guard;
+        agent.operation_log = self.operation_log;
+        agent.branches = self.branches;
+        agent.compactor = self.compactor.map(Arc::new);
+        agent.working_dir = self.working_dir;
+        agent
     }
 
     /// Create an AgentBuilder from an AgentConfig
-    pub async fn from_config(mut config: AgentConfig) -> Result<Self, AgentError> {
+    pub async fn from_config(config: AgentConfig) -> Result<Self, AgentError> {
+        Self::from_config_in(config, None).await
+    }
+
+    /// Create an AgentBuilder from an AgentConfig operating inside `working_dir` if given
+    pub async fn from_config_in(mut config: AgentConfig, working_dir: Option<PathBuf>) -> Result<Self, AgentError> {
         // Create LLM client from provider config using the utility method
         let llm_client = Arc::new(
             LlmClient::create_provider(&config.llm_provider.provider, &config.llm_provider.env_vars)
                 .map_err(|e| AgentError::LlmError(e.to_string()))?
+                .with_reasoning(config.reasoning)
         );
         
+        // Size tool outputs after the model context window
+        let model_info = llm_client.model_info(&config.llm_provider.model).await;
+        let output_budget = config.tool_output.clone().fit_context(model_info.context_length);
+
         // Create brain with custom system prompt and temperature
-        let brain = Box::new(CoderBrain::with_custom_prompt(
+        let mut brain = CoderBrain::with_custom_prompt(
             llm_client.clone(),
             config.llm_provider.model.clone(),
             config.system_prompt.clone(),
             config.temperature,
-        ));
+        );
+        if let Some(dir) = &working_dir {
+            brain = brain.with_working_dir(dir.clone());
+        }
+        let brain = Box::new(brain);
+
+        // Summaries are written by the compaction model if that role is routed, an unrouted
+        // role would fall back to the selected provider rather than the one of this agent
+        let mut compactor = Compactor::new(llm_client, config.llm_provider.model.clone());
+        let compaction_routed = ShaiConfig::load().is_ok_and(|c| c.roles.contains_key(&ModelRole::Compaction));
+        if compaction_routed {
+            if let Ok((summarizer, summary_model)) = ShaiConfig::get_llm_for(ModelRole::Compaction).await {
+                compactor = compactor.with_summarizer(Arc::new(summarizer), summary_model);
+            }
+        }
 
-        // Create tools
-        let tools = Self::create_tools_from_config(&mut config).await?;
+        // Create tools, file system tools share an operation log
+        let fs_log = Arc::new(FsOperationLog::new());
+        let tools = Self::create_tools_from_config(&mut config, working_dir.as_deref(), fs_log.clone()).await?;
         
         // Display available tools by category
         let mut tool_groups: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
@@ -180,23 +317,29 @@ impl AgentBuilder {
 
         Ok(Self::with_brain(brain)
             .tools(tools)
+            .output_budget(output_budget)
+            .max_parallel_tools(config.max_parallel_tools)
+            .loop_guard(config.loop_guard.clone())
+            .operation_log(fs_log)
+            .compactor(compactor)
+            .working_dir(working_dir)
             .id(&format!("agent-{}", config.name)))
     }
 
     /// Create tools from config
-    async fn create_tools_from_config(config: &mut AgentConfig) -> Result<Vec<Box<dyn AnyTool>>, AgentError> {
+    async fn create_tools_from_config(config: &mut AgentConfig, root: Option<&Path>, fs_log: Arc<FsOperationLog>) -> Result<Vec<Box<dyn AnyTool>>, AgentError> {
         let mut tools: Vec<Box<dyn AnyTool>> = Vec::new();
 
         // Create shared storage for todo tools
         let todo_storage = Arc::new(TodoStorage::new());
         
-        // Create shared operation log for file system tools
-        let fs_log = Arc::new(FsOperationLog::new());
+        // Create shared registry for background processes
+        let processes = Arc::new(ProcessRegistry::new());
 
         // Add builtin tools based on config
         let builtin_tools_to_add = if config.tools.builtin.contains(&"*".to_string()) {
             // Add all builtin tools
-            vec!["bash", "edit", "multiedit", "fetch", "find", "ls", "read", "todo_read", "todo_write", "write"]
+            BUILTIN_TOOLS.to_vec()
         } else {
             // Add only specified tools
             config.tools.builtin.iter().map(|s| s.as_str()).collect()
@@ -209,16 +352,21 @@ impl AgentBuilder {
             }
             
             match tool_name {
-                "bash" => tools.push(Box::new(BashTool::new())),
-                "edit" => tools.push(Box::new(EditTool::new(fs_log.clone()))),
-                "multiedit" => tools.push(Box::new(MultiEditTool::new(fs_log.clone()))),
+                "bash" => tools.push(rooted!(BashTool::new().with_processes(processes.clone()), root)),
+                "bash_output" => tools.push(Box::new(BashOutputTool::new(processes.clone()))),
+                "bash_input" => tools.push(Box::new(BashInputTool::new(processes.clone()))),
+                "bash_list" => tools.push(Box::new(BashListTool::new(processes.clone()))),
+                "bash_kill" => tools.push(Box::new(BashKillTool::new(processes.clone()))),
+                "shell" => tools.push(rooted!(ShellTool::new(), root)),
+                "edit" => tools.push(rooted!(EditTool::new(fs_log.clone()), root)),
+                "multiedit" => tools.push(rooted!(MultiEditTool::new(fs_log.clone()), root)),
                 "fetch" => tools.push(Box::new(FetchTool::new())),
-                "find" => tools.push(Box::new(FindTool::new())),
-                "ls" => tools.push(Box::new(LsTool::new())),
-                "read" => tools.push(Box::new(ReadTool::new(fs_log.clone()))),
+                "find" => tools.push(rooted!(FindTool::new(), root)),
+                "ls" => tools.push(rooted!(LsTool::new(), root)),
+                "read" => tools.push(rooted!(ReadTool::new(fs_log.clone()), root)),
                 "todo_read" => tools.push(Box::new(TodoReadTool::new(todo_storage.clone()))),
                 "todo_write" => tools.push(Box::new(TodoWriteTool::new(todo_storage.clone()))),
-                "write" => tools.push(Box::new(WriteTool::new(fs_log.clone()))),
+                "write" => tools.push(rooted!(WriteTool::new(fs_log.clone()), root)),
                 _ => return Err(AgentError::ConfigurationError(format!("Unknown builtin tool: {}", tool_name))),
             }
         }
@@ -236,32 +384,17 @@ impl AgentBuilder {
             let all_mcp_tools = get_mcp_tools(mcp_client, mcp_name).await
                 .map_err(|e| AgentError::ConfigurationError(format!("Failed to get tools from MCP '{}': {}", mcp_name, e)))?;
             
-            // Check if we should add all tools or filter by enabled_tools
-            if mcp_tool_config.enabled_tools.contains(&"*".to_string()) {
-                // Add all tools from this MCP client (except excluded ones)
-                for tool in all_mcp_tools {
-                    let tool_name = tool.name();
-                    if !mcp_tool_config.excluded_tools.contains(&tool_name) {
-                        tools.push(tool);
-                    }
-                }
-            } else {
-                // Filter and add only enabled tools (except excluded ones)
-                for tool in all_mcp_tools {
-                    let tool_name = tool.name();
-                    if mcp_tool_config.enabled_tools.contains(&tool_name) && !mcp_tool_config.excluded_tools.contains(&tool_name) {
-                        tools.push(tool);
-                    }
-                }
-                
-                // Check if all enabled tools were found (only when not using wildcard)
-                for enabled_tool in &mcp_tool_config.enabled_tools {
-                    let found = tools.iter().any(|t| t.name() == *enabled_tool);
-                    if !found {
-                        return Err(AgentError::ConfigurationError(format!("Tool '{}' not found in MCP client '{}'", enabled_tool, mcp_name)));
-                    }
+            let mcp_tools: Vec<_> = all_mcp_tools.into_iter()
+                .filter(|tool| mcp_tool_config.is_enabled(&tool.name()))
+                .collect();
+
+            // tools enabled by name must exist on the server
+            for enabled_tool in mcp_tool_config.enabled_tools.iter().filter(|name| *name != "*") {
+                if !mcp_tools.iter().any(|t| t.name() == *enabled_tool) && !mcp_tool_config.excluded_tools.contains(enabled_tool) {
+                    return Err(AgentError::ConfigurationError(format!("Tool '{}' not found in MCP client '{}'", enabled_tool, mcp_name)));
                 }
             }
+            tools.extend(mcp_tools);
         }
 
         // Save config if OAuth flow added new tokens
@@ -2,6 +2,7 @@ use std::sync::Arc;
 use std::future::Future;
 use futures::future::BoxFuture;
 use openai_dive::v1::resources::chat::ChatMessage;
+use openai_dive::v1::resources::shared::FinishReason;
 use serde::{Serialize, Deserialize};
 use async_trait::async_trait;
 use super::brain::ThinkerDecision;
@@ -38,6 +39,8 @@ pub enum InternalAgentEvent {
     /// All tools completed execution
     ToolsCompleted {
         any_denied: bool,
+        /// errors reported by the tools of the step, in call order
+        errors: Vec<String>,
     },
     /// User response received from controller
     UserResponseReceived { 
@@ -48,6 +51,17 @@ pub enum InternalAgentEvent {
     PermissionResponseReceived { 
         request_id: String,
         response: PermissionResponse
+    },
+    /// Estimated size of the trace sent for the next step
+    ContextUsage {
+        used_tokens: u32,
+        context_window: Option<u32>
+    },
+    /// The oldest `removed` messages of the trace were replaced by a summary
+    TraceCompacted {
+        removed: usize,
+        before_tokens: u32,
+        after_tokens: u32
     }
 }
 
@@ -65,13 +79,20 @@ pub enum AgentEvent {
     /// Agent is thinking - provides the thought content to display to user
     BrainResult { 
         timestamp: DateTime<Utc>,
-        thought: Result<ChatMessage, AgentError>
+        thought: Result<ChatMessage, AgentError>,
+        /// why the model stopped, None when the provider does not say
+        finish_reason: Option<FinishReason>
     },
     /// Agent started executing a tool
     ToolCallStarted { 
         timestamp: DateTime<Utc>,
         call: ToolCall 
     },
+    /// Partial output of a running tool
+    ToolCallProgress {
+        call_id: String,
+        chunk: String
+    },
     /// Tool execution completed and returned a result
     ToolCallCompleted {
         duration: TimeDelta,
@@ -82,6 +103,10 @@ pub enum AgentEvent {
     UserInput { 
         input: String,
     },
+    /// User input was queued until the next step of the agent
+    UserInputQueued {
+        input: String,
+    },
     /// Agent requires user input to continue
     UserInputRequired { 
         request_id: String,
@@ -101,6 +126,17 @@ pub enum AgentEvent {
         input_tokens: u32,
         output_tokens: u32
     },
+    /// Estimated tokens of the trace against the context window of the model
+    ContextUsage {
+        used_tokens: u32,
+        context_window: Option<u32>
+    },
+    /// The oldest messages of the trace were summarized to fit the context window
+    ContextCompacted {
+        removed: usize,
+        before_tokens: u32,
+        after_tokens: u32
+    },
 }
 
 /// Types of user input that an agent can request
@@ -219,10 +255,11 @@ impl std::fmt::Debug for AgentEvent {
                 f.debug_struct("ThinkingStart")
                     .finish()
             }
-            AgentEvent::BrainResult { timestamp, thought } => {
+            AgentEvent::BrainResult { timestamp, thought, finish_reason } => {
                 f.debug_struct("BrainResult")
                     .field("timestamp", timestamp)
                     .field("thought", thought)
+                    .field("finish_reason", finish_reason)
                     .finish()
             }
             AgentEvent::ToolCallStarted { timestamp, call } => {
@@ -231,6 +268,12 @@ impl std::fmt::Debug for AgentEvent {
                     .field("call", call)
                     .finish()
             }
+            AgentEvent::ToolCallProgress { call_id, chunk } => {
+                f.debug_struct("ToolCallProgress")
+                    .field("call_id", call_id)
+                    .field("chunk", chunk)
+                    .finish()
+            }
             AgentEvent::ToolCallCompleted { duration, call, result } => {
                 f.debug_struct("ToolCallCompleted")
                     .field("timestamp", duration)
@@ -243,6 +286,11 @@ impl std::fmt::Debug for AgentEvent {
                     .field("input", input)
                     .finish()
             }
+            AgentEvent::UserInputQueued { input } => {
+                f.debug_struct("UserInputQueued")
+                    .field("input", input)
+                    .finish()
+            }
             AgentEvent::UserInputRequired { request_id: input_id, request: input_type, .. } => {
                 f.debug_struct("UserInputRequired")
                     .field("input_id", input_id)
@@ -274,6 +322,19 @@ impl std::fmt::Debug for AgentEvent {
                     .field("output_tokens", output_tokens)
                     .finish()
             }
+            AgentEvent::ContextUsage { used_tokens, context_window } => {
+                f.debug_struct("ContextUsage")
+                    .field("used_tokens", used_tokens)
+                    .field("context_window", context_window)
+                    .finish()
+            }
+            AgentEvent::ContextCompacted { removed, before_tokens, after_tokens } => {
+                f.debug_struct("ContextCompacted")
+                    .field("removed", removed)
+                    .field("before_tokens", before_tokens)
+                    .field("after_tokens", after_tokens)
+                    .finish()
+            }
         }
     }
 }
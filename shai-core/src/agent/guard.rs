@@ -0,0 +1,130 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use serde::{Serialize, Deserialize};
+
+/// Limits that keep a confused model from looping forever within a single user turn
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoopGuard {
+    /// Maximum number of tool steps per user turn, 0 disables the limit
+    #[serde(default = "default_max_steps")]
+    pub max_steps: usize,
+    /// Number of identical steps (same calls and same results) or identical errors in a row
+    /// after which the model is warned, the agent pauses if it goes on. 0 disables the detection
+    #[serde(default = "default_max_repeats")]
+    pub max_repeats: usize,
+}
+
+fn default_max_steps() -> usize {
+    100
+}
+
+fn default_max_repeats() -> usize {
+    3
+}
+
+impl Default for LoopGuard {
+    fn default() -> Self {
+        Self {
+            max_steps: default_max_steps(),
+            max_repeats: default_max_repeats(),
+        }
+    }
+}
+
+impl LoopGuard {
+    /// No limit at all
+    pub fn disabled() -> Self {
+        Self { max_steps: 0, max_repeats: 0 }
+    }
+
+    /// Override the limits that are given
+    pub fn with_overrides(mut self, max_steps: Option<usize>, max_repeats: Option<usize>) -> Self {
+        if let Some(max_steps) = max_steps {
+            self.max_steps = max_steps;
+        }
+        if let Some(max_repeats) = max_repeats {
+            self.max_repeats = max_repeats;
+        }
+        self
+    }
+}
+
+/// What to do after a step
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopVerdict {
+    Continue,
+    /// the model is repeating itself, tell it to change its approach
+    Warn { reason: String },
+    /// give the hand back to the user
+    Stop { reason: String },
+}
+
+/// Tracks the steps of the current user turn
+#[derive(Debug, Clone, Default)]
+pub struct LoopDetector {
+    steps: usize,
+    last_step: Option<u64>,
+    step_repeats: usize,
+    last_error: Option<u64>,
+    error_repeats: usize,
+    warned: bool,
+}
+
+impl LoopDetector {
+    /// Forget about the previous steps, called when the user speaks
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Record a step made of tool calls (name and arguments) and their results
+    /// `errors` holds the results of the calls that failed
+    pub fn observe<C, E>(&mut self, guard: &LoopGuard, calls: &[C], errors: &[E]) -> LoopVerdict
+    where
+        C: Hash,
+        E: Hash,
+    {
+        self.steps += 1;
+
+        let step = fingerprint(calls);
+        self.step_repeats = if self.last_step == Some(step) { self.step_repeats + 1 } else { 1 };
+        self.last_step = Some(step);
+
+        let error = (!errors.is_empty()).then(|| fingerprint(errors));
+        self.error_repeats = match error {
+            Some(error) if self.last_error == Some(error) => self.error_repeats + 1,
+            Some(_) => 1,
+            None => 0,
+        };
+        self.last_error = error;
+
+        if guard.max_steps > 0 && self.steps >= guard.max_steps {
+            return LoopVerdict::Stop {
+                reason: format!("reached the limit of {} steps for this turn", guard.max_steps)
+            };
+        }
+
+        let repeats = self.step_repeats.max(self.error_repeats);
+        if guard.max_repeats == 0 || repeats < guard.max_repeats {
+            self.warned = false;
+            return LoopVerdict::Continue;
+        }
+
+        let reason = if self.step_repeats >= self.error_repeats {
+            format!("the same tool calls were made {} times in a row with the same result", repeats)
+        } else {
+            format!("the same tool error occurred {} times in a row", repeats)
+        };
+        if self.warned {
+            LoopVerdict::Stop { reason }
+        } else {
+            self.warned = true;
+            LoopVerdict::Warn { reason }
+        }
+    }
+}
+
+fn fingerprint<T: Hash>(items: &[T]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    items.hash(&mut hasher);
+    hasher.finish()
+}
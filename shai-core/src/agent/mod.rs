@@ -8,6 +8,9 @@ pub mod events;
 pub mod states;
 pub mod actions;
 pub mod output;
+pub mod budget;
+pub mod guard;
+pub mod branch;
 
 #[cfg(test)]
 mod tests;
@@ -28,6 +31,10 @@ pub use events::{
 pub use output::StdoutEventManager;
     
 pub use builder::AgentBuilder;
+pub use budget::ToolOutputBudget;
+pub use guard::{LoopGuard, LoopDetector, LoopVerdict};
+pub use branch::{Branch, BranchInfo, Branches};
+pub use actions::plan::{PlanRequest, allowed_in_plan_mode};
 pub use claims::{ClaimManager, PermissionError};
 pub use error::{AgentError, AgentExecutionError};
 pub use brain::{Brain, ThinkerContext, ThinkerDecision, ThinkerFlowControl};
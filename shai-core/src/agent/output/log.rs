@@ -29,18 +29,24 @@ impl FileEventLogger {
             AgentEvent::ThinkingStart => {
                 format!("ThinkingStart")
             }
-            AgentEvent::BrainResult { timestamp: event_time, thought } => {
+            AgentEvent::BrainResult { timestamp: event_time, thought, .. } => {
                 format!("BrainResult: {:?} - {:?}", event_time, thought)
             }
             AgentEvent::ToolCallStarted { timestamp: event_time, call } => {
                 format!("ToolCallStarted: {:?} - {}", event_time, call.tool_name)
             }
+            AgentEvent::ToolCallProgress { call_id, chunk } => {
+                format!("ToolCallProgress: {} - {} bytes", call_id, chunk.len())
+            }
             AgentEvent::ToolCallCompleted { duration, call, result } => {
                 format!("ToolCallCompleted: {} in {:?} - {:?}", call.tool_name, duration, result)
             }
             AgentEvent::UserInput { input } => {
                 format!("UserInput: {}", input)
             }
+            AgentEvent::UserInputQueued { input } => {
+                format!("UserInputQueued: {}", input)
+            }
             AgentEvent::UserInputRequired { request_id, request } => {
                 format!("UserInputRequired: {} - {:?}", request_id, request)
             }
@@ -56,6 +62,12 @@ impl FileEventLogger {
             AgentEvent::TokenUsage { input_tokens, output_tokens } => {
                 format!("Token Usage: input={} output={} total={}", input_tokens, output_tokens, input_tokens + output_tokens)
             }
+            AgentEvent::ContextUsage { used_tokens, context_window } => {
+                format!("ContextUsage: {} tokens of {:?}", used_tokens, context_window)
+            }
+            AgentEvent::ContextCompacted { removed, before_tokens, after_tokens } => {
+                format!("ContextCompacted: {} messages summarized, {} -> {} tokens", removed, before_tokens, after_tokens)
+            }
         };
 
         let log_line = format!("[{}] {}\n", timestamp.format("%Y-%m-%d %H:%M:%S%.3f"), event_str);
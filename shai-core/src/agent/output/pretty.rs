@@ -35,9 +35,17 @@ impl PrettyFormatter {
                 // do nothing because tool can be call in parallel, we only display the result
                 None
             },
+            AgentEvent::ToolCallProgress { .. } => {
+                // partial output is superseded by the result
+                None
+            },
             AgentEvent::ToolCallCompleted { call, result, .. } => {
                 Some(self.format_tool_result(call, result))
             },
+            AgentEvent::UserInputQueued { .. } => {
+                // shown once delivered, as UserInput
+                None
+            },
             AgentEvent::StatusChanged { .. } => {
                 // Don't format state changes - only show brain results and tool calls
                 None
@@ -114,6 +122,17 @@ impl PrettyFormatter {
                 // Don't display token usage in the main output - it's handled by /tokens command
                 None
             },
+            AgentEvent::ContextUsage { .. } => {
+                // shown in the status line
+                None
+            },
+            AgentEvent::ContextCompacted { removed, before_tokens, after_tokens } => {
+                let markdown = format!("*{} messages summarized to fit the context window ({} → {} tokens)*", removed, before_tokens, after_tokens);
+                let mut compact_skin = self.skin.clone();
+                compact_skin.paragraph.set_fg(rgb(120, 120, 120)); // Dim text
+                compact_skin.italic.set_fg(rgb(120, 120, 120));
+                Some(compact_skin.term_text(&markdown).to_string())
+            },
         }.map(|s| format!("\n{}", s))
     }
 
@@ -4,7 +4,7 @@ use tokio::sync::{mpsc, oneshot};
 use tokio::time::{timeout, Duration};
 use crate::agent::AgentError;
 
-use super::{PermissionResponse, PublicAgentState, UserResponse};
+use super::{BranchInfo, Branches, LoopGuard, PermissionResponse, PlanRequest, PublicAgentState, UserResponse};
 
 /// Commands that can be sent to a running agent
 #[derive(Debug, Clone)]
@@ -15,10 +15,19 @@ pub enum AgentRequest {
     StopCurrentTask,    
     /// Send user input (cancels current task, adds to trace, resumes agent)
     GetState,
+    /// Get a snapshot of the conversation trace
+    GetTrace,
     /// Send user input (cancels current task, adds to trace, resumes agent)
     SendUserInput{
         input: String
     },
+    /// Queue user input without interrupting the current task, it is added to the trace
+    /// at the next brain step (sent right away if the agent is idle)
+    QueueUserInput{
+        input: String
+    },
+    /// Withdraw the queued user inputs that were not delivered yet
+    TakeQueuedInputs,
     /// Send multiple messages as a trace (cancels current task, adds all to trace, resumes agent)
     SendTrace{
         messages: Vec<ChatMessage>
@@ -27,6 +36,10 @@ pub enum AgentRequest {
     SwitchToolCallMethod {
         method: Option<ToolCallMethod>
     },
+    /// Change the step limits of the agent, None = get the current ones
+    SetLoopGuard {
+        guard: Option<LoopGuard>
+    },
     /// Send user input (cancels current task, adds to trace, resumes agent)
     UserQueryResponse{
         request_id: String,
@@ -39,6 +52,20 @@ pub enum AgentRequest {
     },
     /// Wait until the agent reaches the Paused state
     WaitTurn,
+    /// Continue on a new branch whose trace stops right before the given user message (0 based),
+    /// optionally undoing the file changes made since then. The agent pauses on the new branch
+    Fork {
+        user_message: usize,
+        restore_files: bool
+    },
+    /// Move to another branch of the conversation, the agent pauses on it
+    SwitchBranch {
+        id: String
+    },
+    /// Get the branches of the conversation
+    GetBranches,
+    /// Enter plan mode, approve or reject the plan. Always returns whether plan mode is on
+    Plan(PlanRequest),
     /// Manage sudo mode: Some(true) = enable, Some(false) = disable, None = get status
     /// Always returns current sudo status after operation
     Sudo(Option<bool>),
@@ -54,9 +81,32 @@ pub enum AgentResponse {
     Method {
         method: ToolCallMethod
     },
+    LoopGuard {
+        guard: LoopGuard
+    },
     State {
         state: PublicAgentState
     },
+    Trace {
+        messages: Vec<ChatMessage>
+    },
+    PlanMode {
+        enabled: bool
+    },
+    QueuedInputs {
+        inputs: Vec<String>
+    },
+    Forked {
+        branch: String,
+        /// the user message the branch was forked before
+        message: String,
+        /// files put back in their previous state
+        restored: Vec<String>
+    },
+    Branches {
+        branches: Branches,
+        infos: Vec<BranchInfo>
+    },
     SudoStatus {
         enabled: bool
     },
@@ -116,10 +166,28 @@ impl AgentController {
         }
     }
 
+    pub async fn set_loop_guard(&self, guard: Option<LoopGuard>) -> Result<LoopGuard, AgentError> {
+        match self.send(AgentRequest::SetLoopGuard { guard }).await? {
+            AgentResponse::LoopGuard{guard} => Ok(guard),
+            _ => Err(AgentError::InvalidResponse("Expected LoopGuard response".to_string()))
+        }
+    }
+
     pub async fn send_user_input(&self, input: String) -> Result<(), AgentError> {
         self.send(AgentRequest::SendUserInput { input: input }).await.map(|_| Ok(()))?
     }
 
+    pub async fn queue_user_input(&self, input: String) -> Result<(), AgentError> {
+        self.send(AgentRequest::QueueUserInput { input }).await.map(|_| Ok(()))?
+    }
+
+    pub async fn take_queued_inputs(&self) -> Result<Vec<String>, AgentError> {
+        match self.send(AgentRequest::TakeQueuedInputs).await? {
+            AgentResponse::QueuedInputs{inputs} => Ok(inputs),
+            _ => Err(AgentError::InvalidResponse("Expected QueuedInputs response".to_string()))
+        }
+    }
+
     pub async fn send_trace(&self, messages: Vec<ChatMessage>) -> Result<(), AgentError> {
         self.send(AgentRequest::SendTrace { messages }).await.map(|_| Ok(()))?
     }
@@ -139,6 +207,13 @@ impl AgentController {
         }
     }
 
+    pub async fn get_trace(&self) -> Result<Vec<ChatMessage>, AgentError> {
+        match self.send(AgentRequest::GetTrace).await? {
+            AgentResponse::Trace{messages} => Ok(messages),
+            _ => Err(AgentError::InvalidResponse("Expected Trace response".to_string()))
+        }
+    }
+
     /// Wait until the agent reaches the Paused state
     pub async fn wait_turn(&self, timeout_ms: Option<u64>) -> Result<(), AgentError> {
         let (tx, rx) = oneshot::channel();
@@ -160,6 +235,45 @@ impl AgentController {
         }
     }
 
+    /// Enter plan mode, approve or reject the plan, returns whether plan mode is on
+    pub async fn plan(&self, request: PlanRequest) -> Result<bool, AgentError> {
+        match self.send(AgentRequest::Plan(request)).await? {
+            AgentResponse::PlanMode{enabled} => Ok(enabled),
+            AgentResponse::Error{error} => Err(AgentError::InvalidState(error)),
+            _ => Err(AgentError::InvalidResponse("Expected PlanMode response".to_string()))
+        }
+    }
+
+    /// Fork the conversation before the given user message, returns the new branch id,
+    /// the text of that message and the files that were restored
+    pub async fn fork(&self, user_message: usize, restore_files: bool) -> Result<(String, String, Vec<String>), AgentError> {
+        match self.send(AgentRequest::Fork { user_message, restore_files }).await? {
+            AgentResponse::Forked{branch, message, restored} => Ok((branch, message, restored)),
+            AgentResponse::Error{error} => Err(AgentError::InvalidState(error)),
+            _ => Err(AgentError::InvalidResponse("Expected Forked response".to_string()))
+        }
+    }
+
+    pub async fn switch_branch(&self, id: String) -> Result<Vec<BranchInfo>, AgentError> {
+        match self.send(AgentRequest::SwitchBranch { id }).await? {
+            AgentResponse::Branches{infos, ..} => Ok(infos),
+            AgentResponse::Error{error} => Err(AgentError::InvalidState(error)),
+            _ => Err(AgentError::InvalidResponse("Expected Branches response".to_string()))
+        }
+    }
+
+    pub async fn list_branches(&self) -> Result<Vec<BranchInfo>, AgentError> {
+        self.get_branches().await.map(|(_, infos)| infos)
+    }
+
+    /// All the branches, to persist them along with the trace of the current one
+    pub async fn get_branches(&self) -> Result<(Branches, Vec<BranchInfo>), AgentError> {
+        match self.send(AgentRequest::GetBranches).await? {
+            AgentResponse::Branches{branches, infos} => Ok((branches, infos)),
+            _ => Err(AgentError::InvalidResponse("Expected Branches response".to_string()))
+        }
+    }
+
     /// Enable sudo mode - bypasses all permission checks
     pub async fn sudo(&self) -> Result<bool, AgentError> {
         match self.send(AgentRequest::Sudo(Some(true))).await? {
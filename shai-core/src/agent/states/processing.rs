@@ -1,5 +1,5 @@
 use crate::agent::{
-    AgentCore, AgentError, InternalAgentEvent
+    AgentCore, AgentError, AgentEvent, InternalAgentEvent
 };
 use super::InternalAgentState;
 
@@ -12,14 +12,27 @@ impl AgentCore {
             InternalAgentEvent::BrainResult { result } => {
                 self.process_next_step(result).await
             },
-            InternalAgentEvent::ToolsCompleted { any_denied } => {
-                if any_denied {
-                    self.set_state(InternalAgentState::Paused).await;
+            InternalAgentEvent::ToolsCompleted { any_denied, errors } => {
+                if any_denied || self.check_loop(&errors).await.is_some() {
+                    self.end_turn().await;
                 } else {
                     self.set_state(InternalAgentState::Running).await;
                 }
                 Ok(())
             },
+            InternalAgentEvent::ContextUsage { used_tokens, context_window } => {
+                let _ = self.emit_event(AgentEvent::ContextUsage { used_tokens, context_window }).await;
+                Ok(())
+            },
+            InternalAgentEvent::TraceCompacted { removed, before_tokens, after_tokens } => {
+                // the summary took the place of the removed messages
+                self.checkpoints = self.checkpoints.drain()
+                    .filter(|(p, _)| *p >= removed)
+                    .map(|(p, c)| (p - removed + 1, c))
+                    .collect();
+                let _ = self.emit_event(AgentEvent::ContextCompacted { removed, before_tokens, after_tokens }).await;
+                Ok(())
+            },
             _ => {
                 Ok(())
             }
@@ -26,6 +26,7 @@ impl AgentCore {
         let trace = self.trace.clone();
         let guard = trace.read().await;
         if let Some(ChatMessage::User { .. }) = guard.last() {
+            self.record_checkpoint(guard.len() - 1).await;
             self.set_state(InternalAgentState::Running).await;
         } else {
             self.set_state(InternalAgentState::Paused).await;
@@ -461,3 +461,661 @@ async fn test_agent_with_real_tools() {
         }
     }
 }
+
+#[tokio::test]
+async fn test_tool_output_budget() {
+    use super::budget::ToolOutputBudget;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let budget = ToolOutputBudget {
+        max_bytes: 200,
+        spill_dir: Some(temp_dir.path().to_path_buf()),
+        ..ToolOutputBudget::default()
+    }.with_tool_limit("ls", 0);
+
+    let call = crate::tools::ToolCall {
+        tool_call_id: "call_1".to_string(),
+        tool_name: "bash".to_string(),
+        parameters: serde_json::json!({}),
+    };
+    let output: String = (1..=100).map(|i| format!("line {}\n", i)).collect();
+
+    // small outputs are untouched
+    let small = ToolResult::success("ok".to_string());
+    assert_eq!(budget.apply("session", &call, small.clone()).await, small);
+
+    let result = budget.apply("session", &call, ToolResult::success(output.clone())).await;
+    let ToolResult::Success { output: truncated, metadata } = result else {
+        panic!("Expected success result");
+    };
+    let metadata = metadata.unwrap();
+    assert!(truncated.starts_with("line 1\n"));
+    assert!(truncated.ends_with("line 100\n"));
+    assert!(truncated.contains("truncated"));
+    assert!(!truncated.contains("line 50\n"));
+    assert_eq!(metadata["truncated"], serde_json::json!(true));
+    assert_eq!(metadata["original_bytes"], serde_json::json!(output.len()));
+
+    let path = metadata["full_output_path"].as_str().unwrap();
+    assert_eq!(std::fs::read_to_string(path).unwrap(), output);
+
+    // the saved outputs go away with the agent
+    budget.remove_spilled("session");
+    assert!(!std::path::Path::new(path).exists());
+    assert!(temp_dir.path().exists());
+    budget.remove_spilled("session");
+
+    // agents of a same session id keep their outputs apart
+    let first = AgentBuilder::with_brain(Box::new(SleepingThinker::new())).id("agent-coder").build();
+    let second = AgentBuilder::with_brain(Box::new(SleepingThinker::new())).id("agent-coder").build();
+    assert_ne!(first.spill_id, second.spill_id);
+
+    // per tool override disables the limit
+    let ls_call = crate::tools::ToolCall { tool_name: "ls".to_string(), ..call };
+    let untouched = budget.apply("session", &ls_call, ToolResult::success(output.clone())).await;
+    assert_eq!(untouched, ToolResult::success(output));
+
+    // small context windows lower the limit, never raise it
+    assert_eq!(ToolOutputBudget::default().fit_context(Some(8_192)).max_bytes, 8_192);
+    assert_eq!(ToolOutputBudget::default().fit_context(Some(1_000_000)).max_bytes, 32 * 1024);
+    assert_eq!(ToolOutputBudget::unlimited().fit_context(None).max_bytes, 0);
+}
+
+// Test tool that sleeps for the duration given in its parameters
+struct DelayTool;
+
+#[tool(name = "delay_tool", description = "A tool that sleeps for the requested duration")]
+impl DelayTool {
+    async fn execute(&self, params: SleepParams) -> ToolResult {
+        tokio::time::sleep(Duration::from_millis(params.duration_ms)).await;
+        ToolResult::success(format!("slept {}ms", params.duration_ms))
+    }
+}
+
+// Test thinker that calls the delay tool twice in parallel, the slowest first
+struct ParallelThinker {
+    called_tools: bool,
+}
+
+#[async_trait]
+impl Brain for ParallelThinker {
+    async fn next_step(&mut self, _: ThinkerContext) -> Result<ThinkerDecision, AgentError> {
+        if self.called_tools {
+            return Ok(ThinkerDecision::agent_pause(ChatMessage::Assistant {
+                content: Some(ChatMessageContent::Text("we are done".to_string())),
+                reasoning_content: None,
+                tool_calls: None,
+                name: None,
+                audio: None,
+                refusal: None,
+            }));
+        }
+
+        self.called_tools = true;
+        let call = |id: &str, duration_ms: u64| ToolCall {
+            id: id.to_string(),
+            r#type: "function".to_string(),
+            function: Function {
+                name: "delay_tool".to_string(),
+                arguments: format!("{{\"duration_ms\": {}}}", duration_ms),
+            },
+        };
+        Ok(ThinkerDecision::agent_continue(ChatMessage::Assistant {
+            content: None,
+            reasoning_content: None,
+            tool_calls: Some(vec![call("call_slow", 300), call("call_fast", 10)]),
+            name: None,
+            audio: None,
+            refusal: None,
+        }))
+    }
+}
+
+#[tokio::test]
+async fn test_parallel_tool_results_keep_call_order() {
+    init_test_logging();
+
+    let mut agent = AgentBuilder::with_brain(Box::new(ParallelThinker { called_tools: false }))
+        .id("test-parallel-order-agent")
+        .goal("Test goal to start running")
+        .tools(vec![Box::new(DelayTool) as Box<dyn AnyTool>])
+        .sudo()
+        .build();
+
+    let agent_result = agent.run().await.expect("agent should complete");
+    let tool_results: Vec<_> = agent_result.trace.iter()
+        .filter_map(|msg| match msg {
+            ChatMessage::Tool { tool_call_id, .. } => Some(tool_call_id.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    // the fast call completes first but its result still comes second
+    assert_eq!(tool_results, vec!["call_slow", "call_fast"]);
+}
+
+// Test tool that reports its output in many small progress chunks
+struct ChattyTool;
+
+#[tool(name = "chatty_tool", description = "A tool that prints a lot")]
+impl ChattyTool {
+    async fn execute(&self, params: SleepParams) -> ToolResult {
+        for i in 0..1000 {
+            crate::tools::report_progress(format!("{}\n", i));
+        }
+        ToolResult::success("printed".to_string())
+    }
+}
+
+// Test thinker that calls the chatty tool once then completes
+struct ChattyThinker {
+    called_tool: bool,
+}
+
+#[async_trait]
+impl Brain for ChattyThinker {
+    async fn next_step(&mut self, _: ThinkerContext) -> Result<ThinkerDecision, AgentError> {
+        let tool_calls = (!self.called_tool).then(|| vec![ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: Function {
+                name: "chatty_tool".to_string(),
+                arguments: "{}".to_string(),
+            },
+        }]);
+        let message = ChatMessage::Assistant {
+            content: self.called_tool.then(|| ChatMessageContent::Text("we are done".to_string())),
+            reasoning_content: None,
+            tool_calls,
+            name: None,
+            audio: None,
+            refusal: None,
+        };
+        match std::mem::replace(&mut self.called_tool, true) {
+            false => Ok(ThinkerDecision::agent_continue(message)),
+            true => Ok(ThinkerDecision::agent_pause(message)),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_tool_progress_is_coalesced() {
+    use super::AgentEvent;
+    init_test_logging();
+
+    let mut agent = AgentBuilder::with_brain(Box::new(ChattyThinker { called_tool: false }))
+        .id("test-progress-agent")
+        .goal("Test goal to start running")
+        .tools(vec![Box::new(ChattyTool) as Box<dyn AnyTool>])
+        .sudo()
+        .build();
+
+    let mut events = agent.watch();
+    agent.run().await.expect("agent should complete");
+
+    let mut chunks = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let AgentEvent::ToolCallProgress { chunk, .. } = event {
+            chunks.push(chunk);
+        }
+    }
+
+    // a burst of output becomes a few events, nothing is lost
+    let expected: String = (0..1000).map(|i| format!("{}\n", i)).collect();
+    assert!(!chunks.is_empty() && chunks.len() < 10, "{} progress events", chunks.len());
+    assert_eq!(chunks.concat(), expected);
+}
+
+#[test]
+fn test_loop_detector() {
+    use super::guard::{LoopDetector, LoopGuard, LoopVerdict};
+
+    let guard = LoopGuard { max_steps: 10, max_repeats: 3 };
+    let mut detector = LoopDetector::default();
+    let call = [("bash", "{\"command\":\"make\"}", "error: missing target")];
+    let error = ["error: missing target"];
+    let no_error: [&str; 0] = [];
+
+    // identical steps: warned at the third one, stopped at the fourth
+    assert_eq!(detector.observe(&guard, &call, &no_error), LoopVerdict::Continue);
+    assert_eq!(detector.observe(&guard, &call, &no_error), LoopVerdict::Continue);
+    assert!(matches!(detector.observe(&guard, &call, &no_error), LoopVerdict::Warn { .. }));
+    assert!(matches!(detector.observe(&guard, &call, &no_error), LoopVerdict::Stop { .. }));
+
+    // the same error from different calls counts as a repetition too
+    detector.reset();
+    for i in 0..2 {
+        let call = [("edit", format!("{{\"attempt\":{}}}", i))];
+        assert_eq!(detector.observe(&guard, &call, &error), LoopVerdict::Continue);
+    }
+    let verdict = detector.observe(&guard, &[("edit", "{\"attempt\":2}")], &error);
+    assert!(matches!(verdict, LoopVerdict::Warn { ref reason } if reason.contains("error")));
+
+    // changing approach clears the warning
+    assert_eq!(detector.observe(&guard, &[("read", "{}")], &no_error), LoopVerdict::Continue);
+
+    // step limit of the turn
+    detector.reset();
+    for i in 0..9 {
+        assert_eq!(detector.observe(&guard, &[("ls", i.to_string())], &no_error), LoopVerdict::Continue);
+    }
+    assert!(matches!(detector.observe(&guard, &[("ls", "9")], &no_error), LoopVerdict::Stop { .. }));
+
+    // disabled guard never stops
+    let mut detector = LoopDetector::default();
+    for _ in 0..20 {
+        assert_eq!(detector.observe(&LoopGuard::disabled(), &call, &error), LoopVerdict::Continue);
+    }
+}
+
+// Test tool that always fails the same way
+struct FailingTool;
+
+#[tool(name = "failing_tool", description = "A tool that always fails")]
+impl FailingTool {
+    async fn execute(&self, params: SleepParams) -> ToolResult {
+        ToolResult::error("disk full".to_string())
+    }
+}
+
+// Test thinker that keeps retrying the failing tool with new arguments
+struct RetryingThinker {
+    attempts: u64,
+}
+
+#[async_trait]
+impl Brain for RetryingThinker {
+    async fn next_step(&mut self, _: ThinkerContext) -> Result<ThinkerDecision, AgentError> {
+        self.attempts += 1;
+        Ok(ThinkerDecision::agent_continue(ChatMessage::Assistant {
+            content: None,
+            reasoning_content: None,
+            tool_calls: Some(vec![ToolCall {
+                id: format!("call_{}", self.attempts),
+                r#type: "function".to_string(),
+                function: Function {
+                    name: "failing_tool".to_string(),
+                    arguments: format!("{{\"duration_ms\":{}}}", self.attempts),
+                },
+            }]),
+            name: None,
+            audio: None,
+            refusal: None,
+        }))
+    }
+}
+
+#[tokio::test]
+async fn test_repeated_tool_error_is_noted_then_stopped() {
+    use super::LoopGuard;
+    init_test_logging();
+
+    let mut agent = AgentBuilder::with_brain(Box::new(RetryingThinker { attempts: 0 }))
+        .id("test-repeated-error-agent")
+        .goal("Test goal to start running")
+        .tools(vec![Box::new(FailingTool) as Box<dyn AnyTool>])
+        .loop_guard(LoopGuard { max_steps: 10, max_repeats: 2 })
+        .sudo()
+        .build();
+
+    let mut controller = agent.controller();
+    let handle = tokio::spawn(async move {
+        agent.run().await
+    });
+    controller.wait_turn(Some(5000)).await.expect("turn should end");
+    controller.drop().await.expect("failed to drop the controller");
+    let agent_result = handle.await.unwrap().expect("agent should complete");
+
+    // warned once as a user note, then stopped at the next identical error
+    let notes: Vec<_> = agent_result.trace.iter()
+        .filter(|msg| matches!(msg, ChatMessage::User { content: ChatMessageContent::Text(text), .. } if text.starts_with("Note:")))
+        .collect();
+    assert_eq!(notes.len(), 1, "{:?}", agent_result.trace);
+    assert!(!agent_result.trace.iter().any(|msg| matches!(msg, ChatMessage::System { .. })));
+    let calls = agent_result.trace.iter().filter(|msg| matches!(msg, ChatMessage::Tool { .. })).count();
+    assert_eq!(calls, 3);
+}
+
+#[tokio::test]
+async fn test_queued_input_is_delivered_at_next_step() {
+    init_test_logging();
+
+    let sleeping_tool: Box<dyn AnyTool> = Box::new(SleepingTool::new(500));
+    let mut agent = AgentBuilder::with_brain(Box::new(SleepingThinker::new()))
+        .id("test-queue-input-agent")
+        .goal("Test goal to start running")
+        .tools(vec![sleeping_tool])
+        .sudo()
+        .build();
+
+    let mut controller = agent.controller();
+    let handle = tokio::spawn(async move {
+        agent.run().await
+    });
+
+    // queue while the tool is running, it must not be cancelled
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    controller.queue_user_input("also check the tests".to_string()).await.expect("Failed to queue input");
+
+    controller.wait_turn(Some(5000)).await.expect("turn should end");
+    controller.drop().await.expect("failed to drop the controller");
+    let agent_result = handle.await.unwrap().expect("agent should complete");
+
+    let tool_result = agent_result.trace.iter().position(|msg| matches!(
+        msg,
+        ChatMessage::Tool { content: ChatMessageContent::Text(text), .. } if text == "Finished sleeping"
+    ));
+    let queued = agent_result.trace.iter().position(|msg| matches!(
+        msg,
+        ChatMessage::User { content: ChatMessageContent::Text(text), .. } if text.ends_with("also check the tests")
+    ));
+    assert!(tool_result.is_some(), "tool should have completed: {:?}", agent_result.trace);
+    assert!(queued > tool_result, "queued input should follow the tool result: {:?}", agent_result.trace);
+}
+
+// Test thinker that fails its first step, then answers
+struct FlakyThinker {
+    call_count: u32,
+}
+
+#[async_trait]
+impl Brain for FlakyThinker {
+    async fn next_step(&mut self, _: ThinkerContext) -> Result<ThinkerDecision, AgentError> {
+        self.call_count += 1;
+        if self.call_count == 1 {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            return Err(AgentError::LlmError("provider unavailable".to_string()));
+        }
+        Ok(ThinkerDecision::agent_pause(ChatMessage::Assistant {
+            content: Some(ChatMessageContent::Text("Answered".to_string())),
+            reasoning_content: None,
+            tool_calls: None,
+            name: None,
+            audio: None,
+            refusal: None,
+        }))
+    }
+}
+
+#[tokio::test]
+async fn test_queued_input_is_delivered_after_a_pause() {
+    init_test_logging();
+
+    let mut agent = AgentBuilder::with_brain(Box::new(FlakyThinker { call_count: 0 }))
+        .id("test-queue-after-pause-agent")
+        .goal("Test goal to start running")
+        .sudo()
+        .build();
+
+    let mut controller = agent.controller();
+    let handle = tokio::spawn(async move {
+        agent.run().await
+    });
+
+    // queue while the brain is thinking, the failed step pauses the agent
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    controller.queue_user_input("try again".to_string()).await.expect("Failed to queue input");
+
+    controller.wait_turn(Some(5000)).await.expect("turn should end");
+    controller.drop().await.expect("failed to drop the controller");
+    let agent_result = handle.await.unwrap().expect("agent should complete");
+
+    let queued = agent_result.trace.iter().position(|msg| matches!(
+        msg,
+        ChatMessage::User { content: ChatMessageContent::Text(text), .. } if text.ends_with("try again")
+    ));
+    let answer = agent_result.trace.iter().position(|msg| matches!(
+        msg,
+        ChatMessage::Assistant { content: Some(ChatMessageContent::Text(text)), .. } if text == "Answered"
+    ));
+    assert!(queued.is_some(), "queued input should be delivered: {:?}", agent_result.trace);
+    assert!(answer > queued, "the next turn should answer it: {:?}", agent_result.trace);
+}
+
+#[tokio::test]
+async fn test_plan_mode_restricts_tools() {
+    use crate::tools::{FsOperationLog, TodoStorage, TodoWriteTool, WriteTool};
+    use super::PlanRequest;
+
+    let fs_log = Arc::new(FsOperationLog::new());
+    let tools: Vec<Box<dyn AnyTool>> = vec![
+        Box::new(LsTool::new()),
+        Box::new(ReadTool::new(fs_log.clone())),
+        Box::new(WriteTool::new(fs_log)),
+        Box::new(TodoWriteTool::new(Arc::new(TodoStorage::new()))),
+    ];
+    let mut agent = AgentBuilder::with_brain(Box::new(SleepingThinker::new()))
+        .tools(tools)
+        .build();
+
+    let names = |agent: &super::AgentCore| agent.visible_tools().iter().map(|t| t.name()).collect::<Vec<_>>();
+    assert_eq!(names(&agent), vec!["ls", "read", "write", "todo_write"]);
+
+    // approving without a plan is an error
+    assert!(agent.handle_plan_request(PlanRequest::Approve).await.is_err());
+
+    agent.handle_plan_request(PlanRequest::Enter).await.unwrap();
+    assert!(agent.plan_mode);
+    assert_eq!(names(&agent), vec!["ls", "read", "todo_write"]);
+
+    agent.handle_plan_request(PlanRequest::Reject).await.unwrap();
+    assert!(!agent.plan_mode);
+    assert_eq!(names(&agent).len(), 4);
+    assert_eq!(agent.pending_notes.len(), 2, "entering and leaving plan mode are both noted");
+
+    // delivered as user notes, providers move system messages out of the conversation
+    agent.deliver_notes().await;
+    let trace = agent.trace.read().await;
+    let notes = trace.iter()
+        .filter(|msg| matches!(msg, ChatMessage::User { content: ChatMessageContent::Text(text), .. } if text.starts_with("Note: ")))
+        .count();
+    assert_eq!(notes, 2, "{:?}", trace);
+    assert!(!trace.iter().any(|msg| matches!(msg, ChatMessage::System { .. })));
+}
+
+#[tokio::test]
+async fn test_fork_and_switch_branches() {
+    use crate::tools::FsOperationLog;
+    use super::AgentResponse;
+
+    let user = |text: &str| ChatMessage::User { content: ChatMessageContent::Text(text.to_string()), name: None };
+    let assistant = |text: &str| ChatMessage::Assistant {
+        content: Some(ChatMessageContent::Text(text.to_string())),
+        reasoning_content: None,
+        tool_calls: None,
+        name: None,
+        audio: None,
+        refusal: None,
+    };
+
+    let fs_log = Arc::new(FsOperationLog::new());
+    let mut agent = AgentBuilder::with_brain(Box::new(SleepingThinker::new()))
+        .with_traces(vec![user("write a file"), assistant("done"), user("now delete it"), assistant("done")])
+        .operation_log(fs_log.clone())
+        .build();
+
+    // a file created while answering the second message
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("created.txt");
+    agent.record_checkpoint(2).await;
+    std::fs::write(&file, "hello").unwrap();
+    fs_log.log_creation(file.to_string_lossy().to_string()).await;
+
+    // the first message has no checkpoint, its files cannot be restored
+    assert!(agent.handle_fork(0, true).await.is_err());
+    assert_eq!(agent.trace.read().await.len(), 4);
+
+    let AgentResponse::Forked { branch, message, restored } = agent.handle_fork(1, true).await.unwrap() else {
+        panic!("expected Forked response");
+    };
+    assert_eq!(branch, "branch-1");
+    assert_eq!(message, "now delete it");
+    assert_eq!(restored.len(), 1);
+    assert!(!file.exists(), "file created after the fork point should be removed");
+    assert_eq!(agent.trace.read().await.len(), 2);
+
+    // back to the original conversation
+    assert!(agent.handle_switch_branch("unknown".to_string()).await.is_err());
+    let AgentResponse::Branches { infos, .. } = agent.handle_switch_branch("main".to_string()).await.unwrap() else {
+        panic!("expected Branches response");
+    };
+    assert_eq!(agent.trace.read().await.len(), 4);
+    assert_eq!(infos.len(), 2);
+    assert!(infos.iter().any(|b| b.id == "main" && b.current && b.messages == 4));
+    assert!(infos.iter().any(|b| b.id == "branch-1" && !b.current && b.messages == 2 && b.forked_at == 2));
+
+    // the numbering shown to the user when picking a message to fork from
+    assert_eq!(
+        super::branch::user_messages(&agent.trace.read().await),
+        vec![(0, "write a file".to_string()), (2, "now delete it".to_string())]
+    );
+
+    // notes and messages delivered mid turn are not fork points
+    agent.trace.write().await.extend([
+        user("Note: plan mode"),
+        user(&format!("{}\nalso the tests", super::branch::QUEUED_INPUT_HEADER)),
+    ]);
+    assert_eq!(super::branch::user_messages(&agent.trace.read().await).len(), 2);
+
+    // a separate agent for the branch, this one is left as is
+    let fork = agent.fork_at(1).await.unwrap();
+    assert_eq!(fork.trace.read().await.len(), 2);
+    assert_ne!(fork.spill_id, agent.spill_id);
+    assert_eq!(agent.trace.read().await.len(), 6);
+    assert!(agent.fork_at(2).await.is_err());
+}
+
+#[tokio::test]
+async fn test_coder_brain_with_scripted_llm() {
+    use crate::runners::coder::coder::CoderBrain;
+    use shai_llm::client::LlmClient;
+    use shai_llm::providers::replay::ReplayProvider;
+
+    init_test_logging();
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    std::fs::write(temp_dir.path().join("notes.txt"), "hello").unwrap();
+
+    let script = ReplayProvider::scripted()
+        .then_tool_call("ls", serde_json::json!({"directory": temp_dir.path()}))
+        .then_text("There is a single file, notes.txt");
+    let llm = Arc::new(LlmClient::from_provider(Box::new(script.clone())));
+    let brain = CoderBrain::new(llm, "replay".to_string());
+
+    let ls_tool: Box<dyn AnyTool> = Box::new(LsTool::new());
+    let mut agent = AgentBuilder::with_brain(Box::new(brain))
+        .id("test-scripted-llm-agent")
+        .goal("What is in the directory?")
+        .tools(vec![ls_tool])
+        .sudo()
+        .build();
+
+    let mut controller = agent.controller();
+    let handle = tokio::spawn(async move {
+        agent.run().await
+    });
+
+    controller.wait_turn(Some(5000)).await.expect("turn should end");
+    controller.drop().await.expect("failed to drop the controller");
+    let agent_result = handle.await.unwrap().expect("agent should complete");
+
+    let listed = agent_result.trace.iter().any(|msg| matches!(
+        msg,
+        ChatMessage::Tool { content: ChatMessageContent::Text(text), tool_call_id, .. } if tool_call_id == "call_0_0" && text.contains("notes.txt")
+    ));
+    assert!(listed, "ls result should be in the trace: {:?}", agent_result.trace);
+    assert!(matches!(
+        agent_result.trace.last(),
+        Some(ChatMessage::Assistant { content: Some(ChatMessageContent::Text(text)), .. }) if text.contains("single file")
+    ));
+
+    // the second request carries the tool result back to the model
+    let requests = script.requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].messages.iter().any(|msg| matches!(msg, ChatMessage::Tool { .. })));
+}
+
+#[tokio::test]
+async fn test_trace_compacted_near_context_window() {
+    use crate::runners::coder::coder::CoderBrain;
+    use crate::runners::compacter::Compactor;
+    use shai_llm::client::LlmClient;
+    use shai_llm::providers::replay::ReplayProvider;
+    use shai_llm::ModelInfo;
+    use super::AgentEvent;
+
+    init_test_logging();
+
+    let script = ReplayProvider::scripted().then_text("done");
+    let llm = Arc::new(LlmClient::from_provider(Box::new(script.clone()))
+        .with_model_info(ModelInfo { context_length: Some(500), ..ModelInfo::new("replay") }));
+    let summarizer_script = ReplayProvider::scripted().then_text("The user asked ten questions about the repository");
+    let summarizer = Arc::new(LlmClient::from_provider(Box::new(summarizer_script.clone())));
+    let compactor = Compactor::new(llm.clone(), "replay".to_string())
+        .with_summarizer(summarizer, "replay".to_string())
+        .with_kept_messages(2);
+
+    // ten exchanges of about 60 tokens each, well over 80% of the window
+    let history: Vec<ChatMessage> = (0..10).flat_map(|i| [
+        ChatMessage::User { content: ChatMessageContent::Text(format!("question {} {}", i, "x".repeat(200))), name: None },
+        ChatMessage::Assistant { content: Some(ChatMessageContent::Text(format!("answer {} {}", i, "y".repeat(200)))), reasoning_content: None, refusal: None, name: None, audio: None, tool_calls: None },
+    ]).collect();
+
+    let mut agent = AgentBuilder::with_brain(Box::new(CoderBrain::new(llm, "replay".to_string())))
+        .id("test-compaction-agent")
+        .with_traces(history)
+        .goal("one more question")
+        .compactor(compactor)
+        .sudo()
+        .build();
+
+    let mut controller = agent.controller();
+    let mut events = agent.watch();
+    let handle = tokio::spawn(async move {
+        agent.run().await
+    });
+
+    controller.wait_turn(Some(5000)).await.expect("turn should end");
+    controller.drop().await.expect("failed to drop the controller");
+    let agent_result = handle.await.unwrap().expect("agent should complete");
+
+    // the summary replaced all but the last answer and the new question
+    assert_eq!(summarizer_script.requests().len(), 1);
+    assert_eq!(agent_result.trace.len(), 4, "unexpected trace: {:?}", agent_result.trace);
+    assert!(matches!(
+        &agent_result.trace[0],
+        ChatMessage::User { content: ChatMessageContent::Text(text), .. } if text.contains("ten questions")
+    ));
+    assert_eq!(script.requests()[0].messages.len(), 4);
+
+    let mut compacted = None;
+    let mut usage = None;
+    while let Ok(event) = events.try_recv() {
+        match event {
+            AgentEvent::ContextCompacted { removed, before_tokens, after_tokens } => compacted = Some((removed, before_tokens, after_tokens)),
+            AgentEvent::ContextUsage { used_tokens, context_window } => usage = Some((used_tokens, context_window)),
+            _ => {}
+        }
+    }
+    let (removed, before_tokens, after_tokens) = compacted.expect("compaction should be reported");
+    assert_eq!(removed, 19);
+    assert!(after_tokens < before_tokens);
+    assert_eq!(usage, Some((after_tokens, Some(500))));
+}
+
+#[tokio::test]
+async fn test_compaction_keeps_the_last_message() {
+    use crate::runners::compacter::Compactor;
+    use shai_llm::client::LlmClient;
+    use shai_llm::providers::replay::ReplayProvider;
+
+    let summarizer = ReplayProvider::scripted().then_text("The user said hello");
+    let llm = Arc::new(LlmClient::from_provider(Box::new(summarizer)));
+    let compactor = Compactor::new(llm, "replay".to_string()).with_kept_messages(0);
+
+    let user = |text: &str| ChatMessage::User { content: ChatMessageContent::Text(text.to_string()), name: None };
+    let compacted = compactor.compact(&[user("hello"), user("what now?")]).await.unwrap().expect("trace should be compacted");
+    assert_eq!(compacted.removed, 1);
+    assert_eq!(compacted.trace.len(), 2);
+    assert!(matches!(&compacted.trace[1], ChatMessage::User { content: ChatMessageContent::Text(text), .. } if text == "what now?"));
+}
@@ -1,8 +1,13 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use serde::{Serialize, Deserialize};
-use shai_llm::ToolCallMethod;
-use crate::tools::mcp::McpConfig;
+use shai_llm::{ReasoningOptions, ToolCallMethod};
+use crate::tools::mcp::{create_mcp_client, get_mcp_tools, McpConfig};
+use crate::agent::{LoopGuard, ToolOutputBudget};
+use crate::agent::agent::DEFAULT_MAX_PARALLEL_TOOLS;
+
+/// Names of all builtin tools, in registration order
+pub const BUILTIN_TOOLS: &[&str] = &["bash", "bash_output", "bash_input", "bash_list", "bash_kill", "shell", "edit", "multiedit", "fetch", "find", "ls", "read", "todo_read", "todo_write", "write"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentProviderConfig {
@@ -44,6 +49,18 @@ pub struct AgentConfig {
     pub max_tokens: u32,
     #[serde(default = "default_temperature")]
     pub temperature: f32,
+    /// Size limit of tool outputs kept in the conversation
+    #[serde(default)]
+    pub tool_output: ToolOutputBudget,
+    /// Maximum number of tool calls of a turn running at once
+    #[serde(default = "default_max_parallel_tools")]
+    pub max_parallel_tools: usize,
+    /// Step limit of a turn and loop detection
+    #[serde(default)]
+    pub loop_guard: LoopGuard,
+    /// Whether the reasoning of the model is shown and sent back to it
+    #[serde(default)]
+    pub reasoning: ReasoningOptions,
 }
 
 fn default_system_prompt() -> String {
@@ -58,10 +75,45 @@ fn default_temperature() -> f32 {
     0.3
 }
 
+fn default_max_parallel_tools() -> usize {
+    DEFAULT_MAX_PARALLEL_TOOLS
+}
+
 fn default_enabled_tools() -> Vec<String> {
     vec!["*".to_string()]
 }
 
+impl McpToolConfig {
+    /// Whether a tool of the server is enabled, `*` enables all of them but the excluded ones
+    pub fn is_enabled(&self, tool_name: &str) -> bool {
+        self.enabled_tools.iter().any(|name| name == "*" || name == tool_name)
+            && !self.excluded_tools.iter().any(|name| name == tool_name)
+    }
+
+    /// Names of the enabled tools, the server is asked for its tools when `*` enables all of them
+    pub async fn enabled_tool_names(&self, mcp_name: &str) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.enabled_tools.iter().any(|name| name == "*") {
+            return Ok(self.enabled_tools.iter().filter(|name| self.is_enabled(name)).cloned().collect());
+        }
+        let tools = get_mcp_tools(create_mcp_client(self.config.clone()), mcp_name).await?;
+        Ok(tools.iter().map(|tool| tool.name()).filter(|name| self.is_enabled(name)).collect())
+    }
+}
+
+impl AgentTools {
+    /// Builtin tool names enabled by this config, with `*` expanded and exclusions removed
+    pub fn enabled_builtin(&self) -> Vec<String> {
+        let builtin: Vec<String> = if self.builtin.contains(&"*".to_string()) {
+            BUILTIN_TOOLS.iter().map(|s| s.to_string()).collect()
+        } else {
+            self.builtin.clone()
+        };
+        builtin.into_iter()
+            .filter(|name| !self.builtin_excluded.contains(name))
+            .collect()
+    }
+}
+
 impl Default for AgentTools {
     fn default() -> Self {
         Self {
@@ -169,7 +221,7 @@ impl AgentConfig {
     pub fn is_mcp_tool_enabled(&self, mcp_name: &str, tool_name: &str) -> bool {
         self.tools.mcp
             .get(mcp_name)
-            .map(|mcp_tool| mcp_tool.enabled_tools.contains(&tool_name.to_string()))
+            .map(|mcp_tool| mcp_tool.is_enabled(tool_name))
             .unwrap_or(false)
     }
 
@@ -4,7 +4,7 @@ use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use reqwest::Url;
 use serde::{Serialize, Deserialize};
-use shai_llm::{LlmClient, ToolCallMethod};
+use shai_llm::{LlmClient, ReasoningOptions, ToolCallMethod};
 use crate::tools::mcp::McpConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,12 +15,60 @@ pub struct ProviderConfig {
     pub tool_method: ToolCallMethod
 }
 
+/// The tasks shai gives to a model, each can be routed to its own provider and model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelRole {
+    /// the main coding agent
+    Coder,
+    /// the read-only code search agent
+    Searcher,
+    /// shell command fixes suggested after a failed command
+    Clifix,
+    /// summaries of long conversations
+    Compaction,
+    /// short titles and status gerunds
+    Title,
+    /// agents spawned by another agent
+    SubAgent,
+}
+
+impl ModelRole {
+    /// Role whose routing applies when this one has none, before the selected provider
+    pub fn fallback(self) -> Option<ModelRole> {
+        match self {
+            ModelRole::Title | ModelRole::SubAgent => Some(ModelRole::Coder),
+            _ => None,
+        }
+    }
+}
+
+/// Provider and model used for a role, anything missing comes from the selected provider
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoleAssignment {
+    /// index in `providers`
+    #[serde(default)]
+    pub provider: Option<usize>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShaiConfig {
     pub providers: Vec<ProviderConfig>,
     pub selected_provider: usize,
     #[serde(default)]
     pub mcp_configs: HashMap<String, McpConfig>,
+    /// Per role routing, roles without an assignment use the selected provider
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub roles: HashMap<ModelRole, RoleAssignment>,
+    /// Whether the reasoning of the models is shown and sent back to them
+    #[serde(default, skip_serializing_if = "is_default_reasoning")]
+    pub reasoning: ReasoningOptions,
+}
+
+fn is_default_reasoning(reasoning: &ReasoningOptions) -> bool {
+    *reasoning == ReasoningOptions::default()
 }
 
 impl ShaiConfig {
@@ -149,6 +197,14 @@ impl ShaiConfig {
 
         let removed = self.providers.remove(index);
 
+        // Roles routed to the removed provider fall back to the selected one
+        self.roles.retain(|_, assignment| assignment.provider != Some(index));
+        for assignment in self.roles.values_mut() {
+            if let Some(provider) = assignment.provider.as_mut().filter(|p| **p > index) {
+                *provider -= 1;
+            }
+        }
+
         // Adjust selected_provider if needed
         if self.selected_provider >= self.providers.len() {
             self.selected_provider = self.providers.len() - 1;
@@ -216,6 +272,39 @@ impl ShaiConfig {
     pub fn has_mcp_config(&self, name: &str) -> bool {
         self.mcp_configs.contains_key(name)
     }
+
+    /// Route a role to a provider (index in `providers`) and optionally a model
+    pub fn set_role(&mut self, role: ModelRole, provider: Option<usize>, model: Option<String>) -> Result<(), String> {
+        if let Some(index) = provider.filter(|i| *i >= self.providers.len()) {
+            return Err(format!("Provider index {} out of bounds (have {} providers)", index, self.providers.len()));
+        }
+        self.roles.insert(role, RoleAssignment { provider, model });
+        Ok(())
+    }
+
+    /// Remove the routing of a role, it falls back to the selected provider
+    pub fn clear_role(&mut self, role: ModelRole) -> Option<RoleAssignment> {
+        self.roles.remove(&role)
+    }
+
+    /// The role whose routing applies to `role`, itself or its fallback
+    fn routed_role(&self, role: ModelRole) -> ModelRole {
+        match role.fallback() {
+            Some(fallback) if !self.roles.contains_key(&role) => fallback,
+            _ => role,
+        }
+    }
+
+    /// Provider config and model override of a role, the selected provider if neither the role
+    /// nor its fallback is routed
+    pub fn provider_for(&self, role: ModelRole) -> Option<(&ProviderConfig, Option<&str>)> {
+        let assignment = self.roles.get(&self.routed_role(role));
+        let provider = assignment
+            .and_then(|a| a.provider)
+            .and_then(|index| self.providers.get(index))
+            .or_else(|| self.get_selected_provider())?;
+        Some((provider, assignment.and_then(|a| a.model.as_deref())))
+    }
 }
 
 impl Default for ShaiConfig {
@@ -232,6 +321,8 @@ impl Default for ShaiConfig {
             }],
             selected_provider: 0,
             mcp_configs: HashMap::new(),
+            roles: HashMap::new(),
+            reasoning: ReasoningOptions::default(),
         }
     }
 }
@@ -248,6 +339,7 @@ impl ShaiConfig {
                 &provider_config.provider, 
                 &provider_config.env_vars)
                 .map_err(|e| format!("Failed to create {} client: {}", provider_config.provider, e))?
+                .with_reasoning(config.reasoning)
         } else {
             return Err("No provider configured".into());
         };
@@ -255,4 +347,33 @@ impl ShaiConfig {
         let model = llm.default_model().await.map_err(|_| "no Model available")?;
         Ok((llm, model))
     }
+
+    /// Client and model for a role, falls back to the routing of `ModelRole::fallback`
+    /// then to `get_llm` when the role is not routed
+    pub async fn get_llm_for(role: ModelRole) -> Result<(LlmClient, String), Box<dyn std::error::Error>>{
+        let config = ShaiConfig::load()
+            .unwrap_or_else(|_| ShaiConfig::default());
+
+        let role = config.routed_role(role);
+        if !config.roles.contains_key(&role) {
+            return Self::get_llm().await;
+        }
+        config.set_env_vars();
+
+        let Some((provider_config, model)) = config.provider_for(role) else {
+            return Err("No provider configured".into());
+        };
+        let llm = LlmClient::create_provider(
+            &provider_config.provider,
+            &provider_config.env_vars)
+            .map_err(|e| format!("Failed to create {} client for {:?}: {}", provider_config.provider, role, e))?
+            .with_reasoning(config.reasoning);
+
+        let model = match model {
+            Some(model) => model.to_string(),
+            None if !provider_config.model.is_empty() => provider_config.model.clone(),
+            None => llm.provider().default_model().await.map_err(|_| "no Model available")?,
+        };
+        Ok((llm, model))
+    }
 }
\ No newline at end of file
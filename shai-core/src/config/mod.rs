@@ -1,2 +1,5 @@
 pub mod config;
-pub mod agent;
\ No newline at end of file
+pub mod agent;
+
+#[cfg(test)]
+mod tests;
\ No newline at end of file
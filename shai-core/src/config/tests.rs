@@ -0,0 +1,81 @@
+use super::config::{ModelRole, ProviderConfig, ShaiConfig};
+use shai_llm::ToolCallMethod;
+use std::collections::HashMap;
+
+fn config_with(providers: &[(&str, &str)]) -> ShaiConfig {
+    let mut config = ShaiConfig::default();
+    config.providers = providers.iter()
+        .map(|(provider, model)| ProviderConfig {
+            provider: provider.to_string(),
+            env_vars: HashMap::new(),
+            model: model.to_string(),
+            tool_method: ToolCallMethod::FunctionCall,
+        })
+        .collect();
+    config
+}
+
+#[test]
+fn test_provider_for_falls_back_to_selected_provider() {
+    let mut config = config_with(&[("openai", "gpt-4.1"), ("anthropic", "claude-sonnet-4")]);
+    config.set_selected_provider(1).unwrap();
+
+    // roles without routing use the selected provider and its model
+    let (provider, model) = config.provider_for(ModelRole::Searcher).unwrap();
+    assert_eq!(provider.provider, "anthropic");
+    assert_eq!(model, None);
+
+    // a model override alone keeps the selected provider
+    config.set_role(ModelRole::Compaction, None, Some("claude-haiku".to_string())).unwrap();
+    let (provider, model) = config.provider_for(ModelRole::Compaction).unwrap();
+    assert_eq!(provider.provider, "anthropic");
+    assert_eq!(model, Some("claude-haiku"));
+
+    config.set_role(ModelRole::Clifix, Some(0), None).unwrap();
+    let (provider, model) = config.provider_for(ModelRole::Clifix).unwrap();
+    assert_eq!(provider.provider, "openai");
+    assert_eq!(model, None);
+
+    assert!(config.set_role(ModelRole::Coder, Some(2), None).is_err());
+}
+
+#[test]
+fn test_roles_follow_removed_provider() {
+    let mut config = config_with(&[("openai", "gpt-4.1"), ("anthropic", "claude-sonnet-4"), ("ollama", "qwen3")]);
+    config.set_role(ModelRole::Searcher, Some(1), None).unwrap();
+    config.set_role(ModelRole::Compaction, Some(2), Some("qwen3:4b".to_string())).unwrap();
+
+    config.remove_provider(1).unwrap();
+
+    // the role routed to the removed provider falls back, the next one is renumbered
+    assert!(!config.roles.contains_key(&ModelRole::Searcher));
+    assert_eq!(config.roles[&ModelRole::Compaction].provider, Some(1));
+    let (provider, model) = config.provider_for(ModelRole::Compaction).unwrap();
+    assert_eq!(provider.provider, "ollama");
+    assert_eq!(model, Some("qwen3:4b"));
+    let (provider, _) = config.provider_for(ModelRole::Searcher).unwrap();
+    assert_eq!(provider.provider, "openai");
+}
+
+#[test]
+fn test_title_and_sub_agent_fall_back_to_coder() {
+    let mut config = config_with(&[("openai", "gpt-4.1"), ("ollama", "qwen3")]);
+
+    // nothing routed, the selected provider
+    let (provider, _) = config.provider_for(ModelRole::Title).unwrap();
+    assert_eq!(provider.provider, "openai");
+
+    // the coder routing applies until the role has its own
+    config.set_role(ModelRole::Coder, Some(1), Some("qwen3:32b".to_string())).unwrap();
+    let (provider, model) = config.provider_for(ModelRole::SubAgent).unwrap();
+    assert_eq!(provider.provider, "ollama");
+    assert_eq!(model, Some("qwen3:32b"));
+
+    config.set_role(ModelRole::Title, Some(1), Some("qwen3:0.6b".to_string())).unwrap();
+    let (_, model) = config.provider_for(ModelRole::Title).unwrap();
+    assert_eq!(model, Some("qwen3:0.6b"));
+
+    // other roles keep the selected provider
+    let (provider, _) = config.provider_for(ModelRole::Searcher).unwrap();
+    assert_eq!(provider.provider, "openai");
+}
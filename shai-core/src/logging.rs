@@ -7,6 +7,7 @@ use tracing_subscriber::{
 };
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing::{Event, Subscriber};
+use shai_llm::AuditConfig;
 use chrono;
 
 /// Custom formatter that colors different event types
@@ -74,6 +75,8 @@ pub struct LoggingConfig {
     pub include_spans: bool,
     /// JSON format instead of human-readable
     pub json_format: bool,
+    /// Optional audit log of the raw LLM requests and responses
+    pub audit: Option<AuditConfig>,
 }
 
 impl Default for LoggingConfig {
@@ -83,6 +86,7 @@ impl Default for LoggingConfig {
             file_path: None,
             include_spans: false,
             json_format: false,
+            audit: None,
         }
     }
 }
@@ -95,6 +99,7 @@ impl LoggingConfig {
             file_path: std::env::var("SHAI_LOG_FILE").ok().map(PathBuf::from),
             include_spans: std::env::var("SHAI_LOG_SPANS").map(|v| v == "true").unwrap_or(false),
             json_format: std::env::var("SHAI_LOG_JSON").map(|v| v == "true").unwrap_or(false),
+            audit: AuditConfig::from_env(),
         }
     }
 
@@ -122,8 +127,18 @@ impl LoggingConfig {
         self
     }
 
+    /// Write every LLM request and response to this JSONL file
+    pub fn audit_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.audit = Some(AuditConfig::new(path));
+        self
+    }
+
     /// Initialize the global tracing subscriber (safe for multiple calls)
     pub fn init(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(audit) = &self.audit {
+            shai_llm::audit::init(audit.clone());
+        }
+
         // Set default level for all modules, then override specific shai modules
         let filter = EnvFilter::from_default_env()
             .add_directive("warn".parse()?)
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use openai_dive::v1::resources::chat::{ChatCompletionParametersBuilder, ChatMessage, ChatMessageContent};
@@ -9,9 +10,10 @@ use crate::agent::brain::ThinkerDecision;
 use crate::agent::{Agent, AgentBuilder, AgentError, Brain, ThinkerContext};
 use crate::tools::types::{ContainsAnyTool, IntoToolBox};
 use shai_llm::tool::LlmToolCall;
-use crate::tools::{AnyTool, BashTool, EditTool, FetchTool, FindTool, LsTool, MultiEditTool, ReadTool, TodoReadTool, TodoWriteTool, WriteTool, TodoStorage, FsOperationLog};
+use crate::tools::{AnyTool, BashTool, BashOutputTool, BashInputTool, BashListTool, BashKillTool, ProcessRegistry, ShellTool, EditTool, FetchTool, FindTool, LsTool, MultiEditTool, ReadTool, TodoReadTool, TodoWriteTool, WriteTool, TodoStorage, FsOperationLog};
 
-use super::prompt::{render_system_prompt_template, get_todo_read};
+use crate::runners::compacter::Compactor;
+use super::prompt::{render_system_prompt_template_in, get_todo_read};
 
 #[derive(Clone)]
 pub struct CoderBrain {
@@ -19,6 +21,7 @@ pub struct CoderBrain {
     pub model: String,
     pub system_prompt_template: String,
     pub temperature: f32,
+    pub working_dir: Option<PathBuf>,
 }
 
 impl CoderBrain {
@@ -29,6 +32,7 @@ impl CoderBrain {
             model,
             system_prompt_template: "{{CODER_BASE_PROMPT}}".to_string(),
             temperature: 0.3,
+            working_dir: None,
         }
     }
 
@@ -39,8 +43,15 @@ impl CoderBrain {
             model,
             system_prompt_template,
             temperature,
+            working_dir: None,
         }
     }
+
+    /// Render the environment section of the prompt from this directory
+    pub fn with_working_dir(mut self, working_dir: PathBuf) -> Self {
+        self.working_dir = Some(working_dir);
+        self
+    }
 }
 
 
@@ -50,7 +61,7 @@ impl Brain for CoderBrain {
         let mut trace = context.trace.read().await.clone();
 
         // Render the user's system prompt template
-        let mut system_prompt = render_system_prompt_template(&self.system_prompt_template);
+        let mut system_prompt = render_system_prompt_template_in(&self.system_prompt_template, self.working_dir.as_deref());
         
         // Add todo status if available
         if let Some(tool) = context.available_tools.get_tool("todo_read") {
@@ -86,31 +97,46 @@ impl Brain for CoderBrain {
         });
 
         // stop here if there's no other tool calls
-        let message = brain_decision.choices.into_iter().next().unwrap().message;
+        let choice = brain_decision.choices.into_iter().next().unwrap();
+        let (message, finish_reason) = (choice.message, choice.finish_reason);
         if let ChatMessage::Assistant { reasoning_content, content, tool_calls, .. } = &message {
             if tool_calls.as_ref().map_or(true, |calls| calls.is_empty()) {
                 return Ok(match token_usage {
                     Some((input_tokens, output_tokens)) => ThinkerDecision::agent_pause_with_tokens(message, input_tokens, output_tokens),
                     None => ThinkerDecision::agent_pause(message),
-                });
+                }.with_finish_reason(finish_reason));
             }
         }
         Ok(match token_usage {
             Some((input_tokens, output_tokens)) => ThinkerDecision::agent_continue_with_tokens(message, input_tokens, output_tokens),
             None => ThinkerDecision::agent_continue(message),
-        })
+        }.with_finish_reason(finish_reason))
     }
 }
 
 
 pub fn coder(llm: Arc<LlmClient>, model: String) -> impl Agent {
+    let compactor = Compactor::new(llm.clone(), model.clone());
+    coder_with_compactor(llm, model, compactor)
+}
+
+/// Coder agent whose trace is summarized by `compactor` when it nears the context window
+pub fn coder_with_compactor(llm: Arc<LlmClient>, model: String, compactor: Compactor) -> impl Agent {
     // Create shared storage for todo tools
     let todo_storage = Arc::new(TodoStorage::new());
     
     // Create shared operation log for file system tools
     let fs_log = Arc::new(FsOperationLog::new());
     
-    let bash = Box::new(BashTool::new());
+    // Create shared registry for background processes
+    let processes = Arc::new(ProcessRegistry::new());
+
+    let bash = Box::new(BashTool::new().with_processes(processes.clone()));
+    let bash_output = Box::new(BashOutputTool::new(processes.clone()));
+    let bash_input = Box::new(BashInputTool::new(processes.clone()));
+    let bash_list = Box::new(BashListTool::new(processes.clone()));
+    let bash_kill = Box::new(BashKillTool::new(processes));
+    let shell = Box::new(ShellTool::new());
     let edit = Box::new(EditTool::new(fs_log.clone()));
     let multiedit = Box::new(MultiEditTool::new(fs_log.clone()));
     let fetch = Box::new(FetchTool::new());
@@ -120,9 +146,11 @@ pub fn coder(llm: Arc<LlmClient>, model: String) -> impl Agent {
     let todoread = Box::new(TodoReadTool::new(todo_storage.clone()));
     let todowrite = Box::new(TodoWriteTool::new(todo_storage.clone()));
     let write = Box::new(WriteTool::new(fs_log.clone()));
-    let toolbox: Vec<Box<dyn AnyTool>> = vec![bash, edit, multiedit, fetch, find, ls, read, todoread, todowrite, write];
+    let toolbox: Vec<Box<dyn AnyTool>> = vec![bash, bash_output, bash_input, bash_list, bash_kill, shell, edit, multiedit, fetch, find, ls, read, todoread, todowrite, write];
 
     AgentBuilder::with_brain(Box::new(CoderBrain::new(llm.clone(), model)))
     .tools(toolbox)
+    .operation_log(fs_log)
+    .compactor(compactor)
     .build()
 }
\ No newline at end of file
@@ -11,9 +11,15 @@ pub fn get_working_dir() -> String {
 
 /// Check if the current directory is a git repository
 pub fn is_git_repo() -> bool {
-    Path::new(".git").exists() || 
+    is_git_repo_in(Path::new("."))
+}
+
+/// Check if the given directory is a git repository
+pub fn is_git_repo_in(dir: &Path) -> bool {
+    dir.join(".git").exists() || 
     Command::new("git")
         .args(&["rev-parse", "--git-dir"])
+        .current_dir(dir)
         .output()
         .map(|output| output.status.success())
         .unwrap_or(false)
@@ -98,8 +104,14 @@ pub fn get_today() -> String {
 
 /// Get the current git branch
 pub fn get_git_branch() -> String {
+    get_git_branch_in(Path::new("."))
+}
+
+/// Get the git branch checked out in the given directory
+pub fn get_git_branch_in(dir: &Path) -> String {
     Command::new("git")
         .args(&["branch", "--show-current"])
+        .current_dir(dir)
         .output()
         .ok()
         .and_then(|output| {
@@ -116,8 +128,14 @@ pub fn get_git_branch() -> String {
 
 /// Get git status
 pub fn get_git_status() -> String {
+    get_git_status_in(Path::new("."))
+}
+
+/// Get git status of the given directory
+pub fn get_git_status_in(dir: &Path) -> String {
     Command::new("git")
         .args(&["status", "--porcelain"])
+        .current_dir(dir)
         .output()
         .ok()
         .and_then(|output| {
@@ -139,8 +157,14 @@ pub fn get_git_status() -> String {
 
 /// Get recent git log (last 5 commits)
 pub fn get_git_log() -> String {
+    get_git_log_in(Path::new("."))
+}
+
+/// Get recent git log (last 5 commits) of the given directory
+pub fn get_git_log_in(dir: &Path) -> String {
     Command::new("git")
         .args(&["log", "--oneline", "-5"])
+        .current_dir(dir)
         .output()
         .ok()
         .and_then(|output| {
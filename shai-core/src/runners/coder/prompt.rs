@@ -1,5 +1,6 @@
 use std::sync::Arc;
 use std::fs;
+use std::path::Path;
 use std::sync::OnceLock;
 
 use crate::tools::{AnyTool, ToolResult};
@@ -82,11 +83,22 @@ Recent commits:
 "#;
 
 pub fn render_system_prompt_template(template: &str) -> String {
+    render_system_prompt_template_in(template, None)
+}
+
+/// Render a system prompt template, gathering the working directory and git
+/// information from `working_dir` instead of the process directory if given
+pub fn render_system_prompt_template_in(template: &str, working_dir: Option<&Path>) -> String {
     // Early return if template has no placeholders
     if !template.contains("{{") {
         return template.to_string();
     }
 
+    let dir = working_dir.unwrap_or(Path::new("."));
+    let working_dir_display = match working_dir {
+        Some(dir) => dir.display().to_string(),
+        None => get_working_dir(),
+    };
     let mut result = template.to_string();
     
     // Only gather environment info if needed
@@ -100,10 +112,10 @@ pub fn render_system_prompt_template(template: &str) -> String {
         result = result.replace("{{OS_VERSION}}", &get_os_version());
     }
     if result.contains("{{WORKING_DIR}}") {
-        result = result.replace("{{WORKING_DIR}}", &get_working_dir());
+        result = result.replace("{{WORKING_DIR}}", &working_dir_display);
     }
     if result.contains("{{IS_GIT_REPO}}") {
-        result = result.replace("{{IS_GIT_REPO}}", &is_git_repo().to_string());
+        result = result.replace("{{IS_GIT_REPO}}", &is_git_repo_in(dir).to_string());
     }
 
     // Handle CODER_GUIDELINE placeholder
@@ -117,28 +129,28 @@ pub fn render_system_prompt_template(template: &str) -> String {
             .replace("{{TODAY}}", &get_today())
             .replace("{{PLATFORM}}", &get_platform())
             .replace("{{OS_VERSION}}", &get_os_version())
-            .replace("{{WORKING_DIR}}", &get_working_dir())
-            .replace("{{IS_GIT_REPO}}", &is_git_repo().to_string());
+            .replace("{{WORKING_DIR}}", &working_dir_display)
+            .replace("{{IS_GIT_REPO}}", &is_git_repo_in(dir).to_string());
         result = result.replace("{{CODER_ENV}}", &coder_env);
     }
 
     // Only build coder base prompt if needed
     if result.contains("{{CODER_BASE_PROMPT}}") {
-        let git_repo = is_git_repo();
+        let git_repo = is_git_repo_in(dir);
         let mut coder_base_prompt = CODER_PROMPT
             .replace("{{CODER_GUIDELINE}}", CODER_GUIDELINE)
             .replace("{{CODER_ENV}}", &CODER_ENV
                 .replace("{{TODAY}}", &get_today())
                 .replace("{{PLATFORM}}", &get_platform())
                 .replace("{{OS_VERSION}}", &get_os_version())
-                .replace("{{WORKING_DIR}}", &get_working_dir())
+                .replace("{{WORKING_DIR}}", &working_dir_display)
                 .replace("{{IS_GIT_REPO}}", &git_repo.to_string()));
 
         if git_repo {
             let git_info = CODER_PROMPT_GIT
-                .replace("{{GIT_BRANCH}}", &get_git_branch())
-                .replace("{{GIT_STATUS}}", &get_git_status())
-                .replace("{{GIT_LOG}}", &get_git_log());
+                .replace("{{GIT_BRANCH}}", &get_git_branch_in(dir))
+                .replace("{{GIT_STATUS}}", &get_git_status_in(dir))
+                .replace("{{GIT_LOG}}", &get_git_log_in(dir));
             coder_base_prompt += &git_info;
         }
         result = result.replace("{{CODER_BASE_PROMPT}}", &coder_base_prompt);
@@ -148,7 +160,14 @@ pub fn render_system_prompt_template(template: &str) -> String {
     // Also handle SHAI_PROMPT placeholder
     if result.contains("{{SHAI_PROMPT}}") {
         static SHAI_CONTENT: OnceLock<String> = OnceLock::new();
-        let content = SHAI_CONTENT.get_or_init(|| fs::read_to_string("SHAI.md").unwrap_or_default());
+        let workspace_content;
+        let content = match working_dir {
+            Some(dir) => {
+                workspace_content = fs::read_to_string(dir.join("SHAI.md")).unwrap_or_default();
+                &workspace_content
+            }
+            None => SHAI_CONTENT.get_or_init(|| fs::read_to_string("SHAI.md").unwrap_or_default()),
+        };
 
         if !content.is_empty() {
             result = result
@@ -161,15 +180,15 @@ pub fn render_system_prompt_template(template: &str) -> String {
 
     // Only get git info if individual git placeholders are used
     if result.contains("{{GIT_BRANCH}}") || result.contains("{{GIT_STATUS}}") || result.contains("{{GIT_LOG}}") {
-        if is_git_repo() {
+        if is_git_repo_in(dir) {
             if result.contains("{{GIT_BRANCH}}") {
-                result = result.replace("{{GIT_BRANCH}}", &get_git_branch());
+                result = result.replace("{{GIT_BRANCH}}", &get_git_branch_in(dir));
             }
             if result.contains("{{GIT_STATUS}}") {
-                result = result.replace("{{GIT_STATUS}}", &get_git_status());
+                result = result.replace("{{GIT_STATUS}}", &get_git_status_in(dir));
             }
             if result.contains("{{GIT_LOG}}") {
-                result = result.replace("{{GIT_LOG}}", &get_git_log());
+                result = result.replace("{{GIT_LOG}}", &get_git_log_in(dir));
             }
         } else {
             result = result.replace("{{GIT_BRANCH}}", "");
@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use openai_dive::v1::resources::chat::{ChatCompletionParametersBuilder, ChatMessage, ChatMessageContent};
+use serde_json::Value;
+use shai_llm::{client::LlmClient, provider::LlmError, tool::ToolBox};
+use tracing::info;
+
+use super::prompt::compact_prompt;
+
+/// Fraction of the context window above which the trace is compacted
+pub const DEFAULT_COMPACTION_THRESHOLD: f32 = 0.8;
+/// Number of most recent messages kept as they are
+pub const DEFAULT_KEPT_MESSAGES: usize = 6;
+/// Tool outputs are cut to this many characters in the transcript given to the summarizer
+const MAX_TOOL_OUTPUT_CHARS: usize = 2000;
+
+/// Estimated size of the trace against the context window of the model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextUsage {
+    pub used_tokens: u32,
+    pub context_window: Option<u32>,
+}
+
+impl ContextUsage {
+    /// Fraction of the context window in use, None if the window is not known
+    pub fn ratio(&self) -> Option<f32> {
+        self.context_window
+            .filter(|window| *window > 0)
+            .map(|window| self.used_tokens as f32 / window as f32)
+    }
+}
+
+/// A trace whose oldest messages were replaced by a summary
+#[derive(Debug, Clone)]
+pub struct Compacted {
+    pub trace: Vec<ChatMessage>,
+    /// number of messages replaced by the summary
+    pub removed: usize,
+}
+
+/// Keeps the trace of an agent within the context window of its model by
+/// summarizing the oldest messages once the window is nearly full
+pub struct Compactor {
+    pub llm: Arc<LlmClient>,
+    pub model: String,
+    pub summarizer: Arc<LlmClient>,
+    pub summary_model: String,
+    pub threshold: f32,
+    pub kept_messages: usize,
+}
+
+impl Compactor {
+    pub fn new(llm: Arc<LlmClient>, model: String) -> Self {
+        Self {
+            summarizer: llm.clone(),
+            summary_model: model.clone(),
+            llm,
+            model,
+            threshold: DEFAULT_COMPACTION_THRESHOLD,
+            kept_messages: DEFAULT_KEPT_MESSAGES,
+        }
+    }
+
+    /// Write the summaries with another model than the one of the agent
+    pub fn with_summarizer(mut self, summarizer: Arc<LlmClient>, summary_model: String) -> Self {
+        self.summarizer = summarizer;
+        self.summary_model = summary_model;
+        self
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Number of the last messages kept as is, at least one
+    pub fn with_kept_messages(mut self, kept_messages: usize) -> Self {
+        self.kept_messages = kept_messages;
+        self
+    }
+
+    /// Estimated prompt tokens of the trace and tool definitions
+    pub async fn usage(&self, trace: &[ChatMessage], tools: &ToolBox) -> ContextUsage {
+        ContextUsage {
+            used_tokens: self.llm.estimate_tokens(&self.model, trace, tools),
+            context_window: self.llm.model_info(&self.model).await.context_length,
+        }
+    }
+
+    pub fn should_compact(&self, usage: &ContextUsage) -> bool {
+        usage.ratio().is_some_and(|ratio| ratio >= self.threshold)
+    }
+
+    /// Replace all but the last messages of the trace by a summary.
+    /// None if the trace is too short to be compacted
+    pub async fn compact(&self, trace: &[ChatMessage]) -> Result<Option<Compacted>, LlmError> {
+        // tool results stay with the assistant message that called them
+        // the last message is always kept, the agent answers it
+        let mut split = trace.len().saturating_sub(self.kept_messages.max(1));
+        while split > 0 && matches!(trace[split], ChatMessage::Tool { .. }) {
+            split -= 1;
+        }
+        if split == 0 {
+            return Ok(None);
+        }
+
+        let messages = vec![
+            ChatMessage::System { content: ChatMessageContent::Text(compact_prompt()), name: None },
+            ChatMessage::User { content: ChatMessageContent::Text(transcript(&trace[..split])), name: None },
+        ];
+        let request = ChatCompletionParametersBuilder::default()
+            .model(self.summary_model.clone())
+            .messages(messages)
+            .temperature(0.1)
+            .build()?;
+
+        let response = self.summarizer.chat(request).await?;
+        let summary = match response.choices.first().map(|choice| &choice.message) {
+            Some(ChatMessage::Assistant { content: Some(ChatMessageContent::Text(text)), .. }) if !text.trim().is_empty() => text.trim().to_string(),
+            _ => return Err("the summarizer returned an empty summary".into()),
+        };
+        info!(target: "agent::compact", removed = split, kept = trace.len() - split, summary_len = summary.len());
+
+        let mut compacted = vec![ChatMessage::User {
+            content: ChatMessageContent::Text(format!("Summary of the conversation so far:\n\n{}", summary)),
+            name: None,
+        }];
+        compacted.extend_from_slice(&trace[split..]);
+        Ok(Some(Compacted { trace: compacted, removed: split }))
+    }
+}
+
+/// Plain text rendering of messages for the summarizer
+fn transcript(messages: &[ChatMessage]) -> String {
+    messages.iter()
+        .filter_map(|message| serde_json::to_value(message).ok())
+        .map(|json| {
+            let role = json["role"].as_str().unwrap_or("unknown").to_string();
+            let mut text = content_text(&json["content"]);
+            if role == "tool" && text.chars().count() > MAX_TOOL_OUTPUT_CHARS {
+                text = text.chars().take(MAX_TOOL_OUTPUT_CHARS).collect::<String>() + "\n[...]";
+            }
+            for call in json["tool_calls"].as_array().into_iter().flatten() {
+                text += &format!(
+                    "\n-> {}({})",
+                    call["function"]["name"].as_str().unwrap_or(""),
+                    call["function"]["arguments"].as_str().unwrap_or("")
+                );
+            }
+            format!("[{}]\n{}", role, text.trim())
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter()
+            .map(|part| part["text"].as_str().unwrap_or("[image]"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
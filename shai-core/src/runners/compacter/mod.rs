@@ -1 +1,4 @@
-pub mod compact;
\ No newline at end of file
+pub mod prompt;
+pub mod compact;
+
+pub use compact::{Compactor, ContextUsage};
@@ -0,0 +1,17 @@
+static COMPACT_PROMPT: &str = r#"
+You are summarizing the beginning of a conversation between a user and a coding agent, so that the agent can carry on the task once these messages are gone from its context.
+
+Write a dense summary that keeps:
+• The goal of the user and every requirement or constraint they stated
+• The decisions made and why, including the approaches that were tried and failed
+• The files read, created or modified, with the details that matter for the rest of the task
+• The commands run and their outcome when it is still relevant
+• What remains to be done
+
+Write it as notes for the agent, without greetings nor commentary. Do not invent anything that is not in the conversation.
+"#;
+
+
+pub fn compact_prompt() -> String {
+    COMPACT_PROMPT.to_string()
+}
@@ -2,6 +2,7 @@ use openai_dive::v1::resources::chat::{ChatCompletionParametersBuilder, ChatMess
 use shai_llm::{client::LlmClient, provider::LlmError};
 
 use super::prompt::gerund_prompt;
+use crate::config::config::{ModelRole, ShaiConfig};
 
 
 
@@ -25,4 +26,10 @@ pub async fn gerund(llm: LlmClient, model: String, message: String) -> Result<Ch
         .await?;
 
         Ok(response.choices[0].message.clone())
-}
\ No newline at end of file
+}
+
+/// Gerund from the provider and model routed to the title role
+pub async fn gerund_from_config(message: String) -> Result<ChatMessage, Box<dyn std::error::Error>> {
+    let (llm, model) = ShaiConfig::get_llm_for(ModelRole::Title).await?;
+    gerund(llm, model, message).await.map_err(|e| e as Box<dyn std::error::Error>)
+}
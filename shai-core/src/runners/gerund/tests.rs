@@ -2,6 +2,7 @@ use super::gerund::gerund;
 use super::prompt::gerund_prompt;
 use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent};
 use shai_llm::client::LlmClient;
+use shai_llm::providers::replay::ReplayProvider;
 
 /// Try to get an LLM client from available environment variables, fallback to Ollama
 fn get_test_llm_client() -> LlmClient {
@@ -36,6 +37,7 @@ async fn get_test_model_for_provider(client: &LlmClient) -> String {
         match client.provider_name() {
             "openai" => "gpt-3.5-turbo".to_string(),
             "anthropic" => "claude-3-haiku-20240307".to_string(),
+            "gemini" => "gemini-2.5-flash".to_string(),
             "openrouter" => "openai/gpt-3.5-turbo".to_string(),
             "ovhcloud" => "gpt-3.5-turbo".to_string(),
             "mistral" => "mistral-tiny".to_string(),
@@ -73,31 +75,20 @@ async fn test_gerund_with_simple_message() {
 
 #[tokio::test]
 async fn test_gerund_with_coding_message() {
-    let llm_client = get_test_llm_client();
-    let model = get_test_model_for_provider(&llm_client).await;
-    println!("{:?} {:?}", llm_client, model);
+    // recorded from gpt-4o-mini, runs without credentials
+    let cassette = concat!(env!("CARGO_MANIFEST_DIR"), "/src/runners/gerund/cassettes/coding_message.json");
+    let llm_client = LlmClient::from_provider(Box::new(ReplayProvider::replay(cassette).unwrap()));
 
     let message = "Debugging the authentication system".to_string();
-    let result = gerund(llm_client, model, message.clone()).await;
-    println!("{:?}", result);
+    let response = gerund(llm_client, "gpt-4o-mini".to_string(), message.clone()).await
+        .expect("Gerund should successfully process coding message");
 
-    assert!(result.is_ok(), "Gerund should successfully process coding message");
-    
-    let response = result.unwrap();
-    
     match response {
-        ChatMessage::Assistant { content, .. } => {
-            assert!(content.is_some(), "Assistant should provide content");
-            if let Some(ChatMessageContent::Text(text)) = content {
-                println!("🔤 Gerund for '{}': '{}'", message, text);
-                assert!(!text.is_empty(), "Gerund response should not be empty");
-                assert!(!text.contains(' '), "Gerund should be a single word");
-                assert!(text.chars().next().unwrap().is_uppercase(), "Gerund should start with capital letter");
-                // Should end with "ing" (gerund form)
-                assert!(text.to_lowercase().ends_with("ing"), "Should be in gerund form ending with 'ing'");
-            }
+        ChatMessage::Assistant { content: Some(ChatMessageContent::Text(text)), .. } => {
+            assert_eq!(text, "Untangling");
+            assert!(text.to_lowercase().ends_with("ing"), "Should be in gerund form ending with 'ing'");
         }
-        _ => panic!("Expected Assistant message"),
+        other => panic!("Expected Assistant message with text, got {:?}", other),
     }
 }
 
@@ -200,7 +191,7 @@ fn test_llm_client_selection() {
     
     // Should be one of our supported providers
     assert!(
-        ["openai", "anthropic", "openrouter", "openai_compatible", "ovhcloud", "mistral", "ollama"]
+        ["openai", "anthropic", "gemini", "openrouter", "openai_compatible", "azure_openai", "ovhcloud", "mistral", "ollama", "openai_responses"]
             .contains(&provider_name),
         "Should select a valid provider, got: {}",
         provider_name
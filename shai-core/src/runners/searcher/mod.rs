@@ -4,4 +4,4 @@ pub mod prompt;
 #[cfg(test)]
 mod tests;
 
-pub use searcher::searcher;
\ No newline at end of file
+pub use searcher::{searcher, searcher_sub_agent};
\ No newline at end of file
@@ -6,6 +6,7 @@ use async_trait::async_trait;
 
 use crate::agent::brain::ThinkerDecision;
 use crate::agent::{Agent, AgentBuilder, AgentError, Brain, ThinkerContext};
+use crate::config::config::{ModelRole, ShaiConfig};
 use crate::tools::{AnyTool, FetchTool, FindTool, LsTool, ReadTool, TodoReadTool, TodoWriteTool, TodoStorage};
 
 use super::prompt::searcher_next_step;
@@ -93,4 +94,10 @@ pub fn searcher(llm: Arc<LlmClient>, model: String) -> impl Agent {
     AgentBuilder::with_brain(Box::new(SearcherBrain{llm: llm.clone(), model}))
     .tools(toolbox)
     .build()
-}
\ No newline at end of file
+}
+
+/// Searcher spawned by another agent, on the provider and model routed to sub-agents
+pub async fn searcher_sub_agent() -> Result<impl Agent, Box<dyn std::error::Error>> {
+    let (llm, model) = ShaiConfig::get_llm_for(ModelRole::SubAgent).await?;
+    Ok(searcher(Arc::new(llm), model))
+}
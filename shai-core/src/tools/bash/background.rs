@@ -0,0 +1,147 @@
+use super::process::{ProcessInfo, ProcessRegistry};
+use crate::tools::ToolEmptyParams;
+use crate::tools::{tool, ToolResult};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn process_metadata(info: &ProcessInfo) -> HashMap<String, serde_json::Value> {
+    let mut meta = HashMap::new();
+    meta.insert("process_id".to_string(), json!(info.id));
+    meta.insert("command".to_string(), json!(info.command));
+    meta.insert("pid".to_string(), json!(info.pid));
+    meta.insert("running".to_string(), json!(info.running));
+    meta.insert("exit_code".to_string(), json!(info.exit_code));
+    meta
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BashProcessParams {
+    /// Id of the background process, as returned by bash
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BashInputParams {
+    /// Id of the background process, as returned by bash
+    pub id: String,
+    /// Text written to the process standard input, include a trailing newline to submit a line
+    pub input: String,
+}
+
+// Output Tool
+#[derive(Clone)]
+pub struct BashOutputTool {
+    processes: Arc<ProcessRegistry>
+}
+
+#[tool(name = "bash_output", description = "Reads the output (stdout and stderr) a background process produced since the last read, along with its status.", capabilities = [ToolCapability::Read])]
+impl BashOutputTool {
+    pub fn new(processes: Arc<ProcessRegistry>) -> Self {
+        Self { processes }
+    }
+
+    async fn execute(&self, params: BashProcessParams) -> ToolResult {
+        match self.processes.read_output(&params.id).await {
+            Ok((output, info)) => {
+                let output = if output.is_empty() {
+                    format!("[no new output, process {}]", info.status())
+                } else {
+                    format!("{}\n[process {}]", output, info.status())
+                };
+                ToolResult::Success {
+                    output,
+                    metadata: Some(process_metadata(&info)),
+                }
+            }
+            Err(e) => ToolResult::error(e),
+        }
+    }
+}
+
+// Input Tool
+#[derive(Clone)]
+pub struct BashInputTool {
+    processes: Arc<ProcessRegistry>
+}
+
+#[tool(name = "bash_input", description = "Writes text to the standard input of a running background process. Read the reaction with bash_output.", capabilities = [ToolCapability::Write])]
+impl BashInputTool {
+    pub fn new(processes: Arc<ProcessRegistry>) -> Self {
+        Self { processes }
+    }
+
+    async fn execute(&self, params: BashInputParams) -> ToolResult {
+        match self.processes.write_input(&params.id, &params.input).await {
+            Ok(info) => ToolResult::Success {
+                output: format!("Wrote {} bytes to {}", params.input.len(), info.id),
+                metadata: Some(process_metadata(&info)),
+            },
+            Err(e) => ToolResult::error(e),
+        }
+    }
+}
+
+// List Tool
+#[derive(Clone)]
+pub struct BashListTool {
+    processes: Arc<ProcessRegistry>
+}
+
+#[tool(name = "bash_list", description = "Lists the background processes started with bash and their status.", capabilities = [ToolCapability::Read])]
+impl BashListTool {
+    pub fn new(processes: Arc<ProcessRegistry>) -> Self {
+        Self { processes }
+    }
+
+    async fn execute(&self, params: ToolEmptyParams) -> ToolResult {
+        let infos = self.processes.list().await;
+        let output = if infos.is_empty() {
+            "No background processes".to_string()
+        } else {
+            infos.iter()
+                .map(|info| format!("{} (pid {}): {} [{}, {}s]",
+                    info.id,
+                    info.pid.map(|pid| pid.to_string()).unwrap_or_else(|| "-".to_string()),
+                    info.command,
+                    info.status(),
+                    info.elapsed_secs))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        ToolResult::Success {
+            output,
+            metadata: Some({
+                let mut meta = HashMap::new();
+                meta.insert("process_count".to_string(), json!(infos.len()));
+                meta
+            }),
+        }
+    }
+}
+
+// Kill Tool
+#[derive(Clone)]
+pub struct BashKillTool {
+    processes: Arc<ProcessRegistry>
+}
+
+#[tool(name = "bash_kill", description = "Stops a background process (and its children) started with bash.", capabilities = [ToolCapability::Write])]
+impl BashKillTool {
+    pub fn new(processes: Arc<ProcessRegistry>) -> Self {
+        Self { processes }
+    }
+
+    async fn execute(&self, params: BashProcessParams) -> ToolResult {
+        match self.processes.kill(&params.id).await {
+            Ok(info) => ToolResult::Success {
+                output: format!("Killed {} ({})", info.id, info.command),
+                metadata: Some(process_metadata(&info)),
+            },
+            Err(e) => ToolResult::error(e),
+        }
+    }
+}
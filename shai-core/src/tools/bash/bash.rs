@@ -1,56 +1,52 @@
 use super::structs::BashToolParams;
-use crate::tools::{tool, ToolResult};
+use super::process::{kill_process_group, ProcessRegistry};
+use crate::tools::{tool, ToolProgress, ToolResult};
 use serde_json::json;
 use tokio_util::sync::CancellationToken;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::process::Stdio;
 use std::time::{Duration, Instant};
 use tokio::process::Command;
-use tokio::io::{AsyncReadExt, BufReader};
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
 
-pub struct BashTool;
+pub struct BashTool {
+    root: Option<PathBuf>,
+    processes: Arc<ProcessRegistry>,
+}
 
 impl BashTool {
     pub fn new() -> Self {
-        Self
+        Self { root: None, processes: Arc::new(ProcessRegistry::new()) }
     }
 
-    async fn kill_process_group(child: &mut tokio::process::Child) {
-        #[cfg(unix)]
-        {
-            // Try to kill the entire process group
-            if let Some(pid) = child.id() {
-                unsafe {
-                    // Kill the process group (negative PID kills the group)
-                    libc::kill(-(pid as i32), libc::SIGTERM);
-                    
-                    // Give it a moment to terminate gracefully
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-                    
-                    // Force kill if still running
-                    libc::kill(-(pid as i32), libc::SIGKILL);
-                }
-            }
-        }
-        
-        // Fallback: kill just the immediate child
-        let _ = child.kill().await;
-        let _ = child.wait().await;
+    /// Share the background process registry with the bash_* companion tools
+    pub fn with_processes(mut self, processes: Arc<ProcessRegistry>) -> Self {
+        self.processes = processes;
+        self
     }
 
-    async fn execute_command(&self, params: &BashToolParams, cancel_token: Option<CancellationToken>) -> Result<(String, String, i32), Box<dyn std::error::Error + Send + Sync>> {       
-        // Validate command is not empty
-        if params.command.trim().is_empty() {
-            return Err("Command cannot be empty".into());
-        }
+    /// Run commands inside the given workspace root by default
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
+    }
 
-        // Create the command with process group
+    fn build_command(&self, params: &BashToolParams) -> Command {
+        // Create the command
         let mut cmd = Command::new("bash");
         cmd.args(["-c", &params.command]);
 
-        // Set working directory if specified
-        if let Some(working_dir) = &params.working_dir {
-            cmd.current_dir(working_dir);
+        // Set working directory if specified, relative to the workspace root if any
+        match (&params.working_dir, &self.root) {
+            (Some(working_dir), root) => {
+                cmd.current_dir(crate::tools::fs::resolve_path(root.as_deref(), working_dir));
+            }
+            (None, Some(root)) => {
+                cmd.current_dir(root);
+            }
+            (None, None) => {}
         }
 
         // Set environment variables
@@ -58,6 +54,63 @@ impl BashTool {
             cmd.env(key, value);
         }
 
+        cmd
+    }
+
+    async fn read_stream<R: AsyncRead + Unpin>(stream: R, progress: Option<ToolProgress>) -> std::io::Result<String> {
+        let mut reader = BufReader::new(stream);
+        let Some(progress) = progress else {
+            let mut output = String::new();
+            reader.read_to_string(&mut output).await?;
+            return Ok(output);
+        };
+
+        let mut bytes = Vec::new();
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = reader.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            progress.report(String::from_utf8_lossy(&buf[..n]));
+            bytes.extend_from_slice(&buf[..n]);
+        }
+        String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    async fn execute_background(&self, params: &BashToolParams) -> ToolResult {
+        if params.command.trim().is_empty() {
+            return ToolResult::error("Command cannot be empty".to_string());
+        }
+
+        match self.processes.spawn(self.build_command(params), &params.command).await {
+            Ok(info) => {
+                let mut metadata = HashMap::new();
+                metadata.insert("command".to_string(), json!(params.command));
+                metadata.insert("process_id".to_string(), json!(info.id));
+                metadata.insert("pid".to_string(), json!(info.pid));
+                metadata.insert("background".to_string(), json!(true));
+
+                ToolResult::Success {
+                    output: format!(
+                        "Started in background with id {}. Use bash_output to read its output, bash_input to write to its stdin and bash_kill to stop it.",
+                        info.id
+                    ),
+                    metadata: Some(metadata),
+                }
+            }
+            Err(e) => ToolResult::error(format!("Failed to start background command: {}", e)),
+        }
+    }
+
+    async fn execute_command(&self, params: &BashToolParams, cancel_token: Option<CancellationToken>) -> Result<(String, String, i32), Box<dyn std::error::Error + Send + Sync>> {       
+        // Validate command is not empty
+        if params.command.trim().is_empty() {
+            return Err("Command cannot be empty".into());
+        }
+
+        let mut cmd = self.build_command(params);
+
         // Configure stdio
         cmd.stdout(Stdio::piped())
            .stderr(Stdio::piped())
@@ -70,20 +123,12 @@ impl BashTool {
         let mut child = cmd.spawn()?;
         
         // Read output asynchronously (needed to prevent blocking on full buffers)
+        // and forward it as progress while the command runs
+        let progress = ToolProgress::current();
         let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
-        let stdout_task = tokio::spawn(async move {
-            let mut reader = BufReader::new(stdout);
-            let mut output = String::new();
-            reader.read_to_string(&mut output).await?;
-            Ok::<String, std::io::Error>(output)
-        });
+        let stdout_task = tokio::spawn(Self::read_stream(stdout, progress.clone()));
         let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
-        let stderr_task = tokio::spawn(async move {
-            let mut reader = BufReader::new(stderr);
-            let mut output = String::new();
-            reader.read_to_string(&mut output).await?;
-            Ok::<String, std::io::Error>(output)
-        });
+        let stderr_task = tokio::spawn(Self::read_stream(stderr, progress));
 
 
         // Optionable Future
@@ -116,14 +161,14 @@ impl BashTool {
             _ = cancel_future => {
                 stdout_task.abort();
                 stderr_task.abort();
-                Self::kill_process_group(&mut child).await;
+                kill_process_group(&mut child).await;
                 Err("Command was cancelled by user".into())
             }
             // Timeout occurred
             _ = timeout_future => {
                 stdout_task.abort();
                 stderr_task.abort();
-                Self::kill_process_group(&mut child).await;
+                kill_process_group(&mut child).await;
                 Err(format!("Command timed out after {} seconds", params.timeout.unwrap()).into())
             }
         }
@@ -146,6 +191,7 @@ Usage Guidelines:
 - this tool always runs from the same path. If you need to execute command in another directory, chain the commands with && for instance "cd subcrate && cargo test"
 - For file system navigation and inspection, prefer the built-in ls, read, and find tools. Use bash for executing other programs or scripts.
 - Always provide a clear, concise description of the command's purpose for the user.
+- Long running programs (dev servers, watchers, interactive programs) must be started with background set to true. The call returns a process id right away; use bash_output to read new output, bash_input to write to stdin, bash_list to see running processes and bash_kill to stop them. Background processes are killed when the session ends.
 - Chain commands using && to ensure that subsequent commands only run if the previous ones succeed.
 - Enclose file paths and arguments in double quotes (") to handle spaces and special characters correctly.
 
@@ -159,6 +205,10 @@ Examples:
 "#, capabilities = [ToolCapability::Read, ToolCapability::Write, ToolCapability::Network])]
 impl BashTool {
     async fn execute(&self, params: BashToolParams, cancel_token: Option<CancellationToken>) -> ToolResult {
+        if params.background.unwrap_or(false) {
+            return self.execute_background(&params).await;
+        }
+
         let start_time = Instant::now();
         
         match self.execute_command(&params, cancel_token).await {
@@ -241,4 +291,8 @@ impl BashTool {
             }
         }
     }
+
+    async fn shutdown(&self) {
+        self.processes.kill_all().await;
+    }
 }
\ No newline at end of file
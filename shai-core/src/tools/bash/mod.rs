@@ -1,8 +1,12 @@
 pub mod structs;
 pub mod bash;
+pub mod process;
+pub mod background;
 
 #[cfg(test)]
 mod tests;
 
 pub use structs::BashToolParams;
-pub use bash::BashTool;
\ No newline at end of file
+pub use bash::BashTool;
+pub use process::{ProcessRegistry, ProcessInfo};
+pub use background::{BashOutputTool, BashInputTool, BashListTool, BashKillTool, BashProcessParams, BashInputParams};
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::Mutex;
+
+/// Output kept per background process, older output is dropped past this size
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Kill a child and its whole process group
+pub async fn kill_process_group(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        // Try to kill the entire process group
+        if let Some(pid) = child.id() {
+            unsafe {
+                // Kill the process group (negative PID kills the group)
+                libc::kill(-(pid as i32), libc::SIGTERM);
+
+                // Give it a moment to terminate gracefully
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+                // Force kill if still running
+                libc::kill(-(pid as i32), libc::SIGKILL);
+            }
+        }
+    }
+
+    // Fallback: kill just the immediate child
+    let _ = child.kill().await;
+    let _ = child.wait().await;
+}
+
+/// Interleaved stdout/stderr of a background process
+/// Offsets are absolute so that readers keep their position when old output is dropped
+#[derive(Default)]
+struct OutputBuffer {
+    data: String,
+    dropped: usize,
+}
+
+impl OutputBuffer {
+    fn push(&mut self, chunk: &str) {
+        self.data.push_str(chunk);
+        if self.data.len() > MAX_OUTPUT_BYTES {
+            let mut cut = self.data.len() - MAX_OUTPUT_BYTES / 2;
+            while !self.data.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.data.drain(..cut);
+            self.dropped += cut;
+        }
+    }
+
+    fn end(&self) -> usize {
+        self.dropped + self.data.len()
+    }
+
+    fn since(&self, offset: usize) -> &str {
+        let start = offset.saturating_sub(self.dropped).min(self.data.len());
+        &self.data[start..]
+    }
+}
+
+struct BackgroundProcess {
+    command: String,
+    started_at: Instant,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    output: Arc<Mutex<OutputBuffer>>,
+    read_offset: usize,
+    exit_code: Option<i32>,
+}
+
+impl BackgroundProcess {
+    fn refresh(&mut self) {
+        if self.exit_code.is_none() {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                self.exit_code = Some(status.code().unwrap_or(-1));
+            }
+        }
+    }
+
+    fn info(&self, id: &str) -> ProcessInfo {
+        ProcessInfo {
+            id: id.to_string(),
+            command: self.command.clone(),
+            pid: self.child.id(),
+            running: self.exit_code.is_none(),
+            exit_code: self.exit_code,
+            elapsed_secs: self.started_at.elapsed().as_secs(),
+        }
+    }
+}
+
+/// Snapshot of a background process
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub id: String,
+    pub command: String,
+    pub pid: Option<u32>,
+    pub running: bool,
+    pub exit_code: Option<i32>,
+    pub elapsed_secs: u64,
+}
+
+impl ProcessInfo {
+    pub fn status(&self) -> String {
+        match self.exit_code {
+            None => "running".to_string(),
+            Some(code) => format!("exited with code {}", code),
+        }
+    }
+}
+
+/// Registry of background processes shared by the bash tools of an agent
+pub struct ProcessRegistry {
+    processes: Mutex<HashMap<String, BackgroundProcess>>,
+    next_id: AtomicU32,
+}
+
+impl Default for ProcessRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessRegistry {
+    pub fn new() -> Self {
+        Self {
+            processes: Mutex::new(HashMap::new()),
+            next_id: AtomicU32::new(1),
+        }
+    }
+
+    /// Spawn a command in the background, stdio of the command is overridden
+    pub async fn spawn(&self, mut cmd: Command, command: &str) -> std::io::Result<ProcessInfo> {
+        cmd.stdout(std::process::Stdio::piped())
+           .stderr(std::process::Stdio::piped())
+           .stdin(std::process::Stdio::piped())
+           .kill_on_drop(true);
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = cmd.spawn()?;
+        let output = Arc::new(Mutex::new(OutputBuffer::default()));
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(Self::collect(stdout, output.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(Self::collect(stderr, output.clone()));
+        }
+
+        let id = format!("proc_{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+        let process = BackgroundProcess {
+            command: command.to_string(),
+            started_at: Instant::now(),
+            stdin: child.stdin.take(),
+            child,
+            output,
+            read_offset: 0,
+            exit_code: None,
+        };
+        let info = process.info(&id);
+        self.processes.lock().await.insert(id, process);
+        Ok(info)
+    }
+
+    async fn collect<R: AsyncRead + Unpin>(mut reader: R, output: Arc<Mutex<OutputBuffer>>) {
+        let mut buf = [0u8; 4096];
+        loop {
+            match reader.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => output.lock().await.push(&String::from_utf8_lossy(&buf[..n])),
+            }
+        }
+    }
+
+    /// Output produced since the previous read
+    pub async fn read_output(&self, id: &str) -> Result<(String, ProcessInfo), String> {
+        let mut processes = self.processes.lock().await;
+        let process = processes.get_mut(id).ok_or_else(|| format!("No background process with id {}", id))?;
+        process.refresh();
+
+        let output = process.output.lock().await;
+        let new_output = output.since(process.read_offset).to_string();
+        let end = output.end();
+        drop(output);
+
+        process.read_offset = end;
+        Ok((new_output, process.info(id)))
+    }
+
+    /// Write to the standard input of a process
+    pub async fn write_input(&self, id: &str, input: &str) -> Result<ProcessInfo, String> {
+        let mut processes = self.processes.lock().await;
+        let process = processes.get_mut(id).ok_or_else(|| format!("No background process with id {}", id))?;
+        process.refresh();
+        if process.exit_code.is_some() {
+            return Err(format!("Process {} is no longer running", id));
+        }
+
+        let stdin = process.stdin.as_mut().ok_or_else(|| format!("Standard input of {} is closed", id))?;
+        stdin.write_all(input.as_bytes()).await.map_err(|e| e.to_string())?;
+        stdin.flush().await.map_err(|e| e.to_string())?;
+        Ok(process.info(id))
+    }
+
+    pub async fn list(&self) -> Vec<ProcessInfo> {
+        let mut processes = self.processes.lock().await;
+        let mut infos: Vec<ProcessInfo> = processes
+            .iter_mut()
+            .map(|(id, process)| {
+                process.refresh();
+                process.info(id)
+            })
+            .collect();
+        infos.sort_by(|a, b| a.id.cmp(&b.id));
+        infos
+    }
+
+    /// Kill a process and forget it
+    pub async fn kill(&self, id: &str) -> Result<ProcessInfo, String> {
+        let mut process = self.processes.lock().await
+            .remove(id)
+            .ok_or_else(|| format!("No background process with id {}", id))?;
+        process.refresh();
+        if process.exit_code.is_none() {
+            kill_process_group(&mut process.child).await;
+            process.refresh();
+        }
+        Ok(process.info(id))
+    }
+
+    /// Kill every process, used when the agent terminates
+    pub async fn kill_all(&self) {
+        let processes: Vec<(String, BackgroundProcess)> = self.processes.lock().await.drain().collect();
+        for (_, mut process) in processes {
+            process.refresh();
+            if process.exit_code.is_none() {
+                kill_process_group(&mut process.child).await;
+            }
+        }
+    }
+}
@@ -13,4 +13,7 @@ pub struct BashToolParams {
     /// Environment variables to set (optional)
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Run the command in the background and return a process id immediately (optional, default false)
+    /// Use bash_output, bash_input and bash_kill with the returned id
+    pub background: Option<bool>,
 }
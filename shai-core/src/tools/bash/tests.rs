@@ -1,8 +1,11 @@
 use super::structs::BashToolParams;
 use super::bash::BashTool;
+use super::background::{BashInputTool, BashKillTool, BashOutputTool, BashProcessParams, BashInputParams};
+use super::process::ProcessRegistry;
 use crate::tools::{Tool, ToolCapability};
 use shai_llm::ToolDescription;
 use std::collections::HashMap;
+use std::sync::Arc;
 use serde_json::json;
 
 #[test]
@@ -30,6 +33,7 @@ async fn test_bash_tool_execution() {
         timeout: None,
         working_dir: None,
         env: HashMap::new(),
+        background: None,
     };
     
     let result = Tool::execute(&tool, params, None).await;
@@ -42,4 +46,71 @@ async fn test_bash_tool_execution() {
     } else {
         panic!("Expected success result");
     }
-}
\ No newline at end of file
+}
+
+#[tokio::test]
+async fn test_bash_tool_background() {
+    let processes = Arc::new(ProcessRegistry::new());
+    let tool = BashTool::new().with_processes(processes.clone());
+    let params = BashToolParams {
+        command: "read line; echo \"got $line\"; sleep 30".to_string(),
+        timeout: None,
+        working_dir: None,
+        env: HashMap::new(),
+        background: Some(true),
+    };
+
+    let result = Tool::execute(&tool, params, None).await;
+    let id = match result {
+        crate::tools::types::ToolResult::Success { metadata, .. } => {
+            metadata.unwrap()["process_id"].as_str().unwrap().to_string()
+        }
+        _ => panic!("Expected success result"),
+    };
+
+    let input = BashInputTool::new(processes.clone());
+    let result = Tool::execute(&input, BashInputParams { id: id.clone(), input: "ping\n".to_string() }, None).await;
+    assert!(result.is_success());
+
+    let output = BashOutputTool::new(processes.clone());
+    let mut seen = String::new();
+    for _ in 0..50 {
+        if let crate::tools::types::ToolResult::Success { output, .. } = Tool::execute(&output, BashProcessParams { id: id.clone() }, None).await {
+            seen.push_str(&output);
+        }
+        if seen.contains("got ping") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert!(seen.contains("got ping"));
+    assert_eq!(processes.list().await.len(), 1);
+
+    let kill = BashKillTool::new(processes.clone());
+    assert!(Tool::execute(&kill, BashProcessParams { id: id.clone() }, None).await.is_success());
+    assert!(processes.list().await.is_empty());
+    assert!(!Tool::execute(&output, BashProcessParams { id }, None).await.is_success());
+}
+
+#[tokio::test]
+async fn test_bash_tool_reports_progress() {
+    let chunks = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+    let sink = chunks.clone();
+    let progress = crate::tools::ToolProgress::new(move |chunk| sink.lock().unwrap().push(chunk));
+
+    let tool = BashTool::new();
+    let params = BashToolParams {
+        command: "echo first; sleep 0.2; echo second".to_string(),
+        timeout: None,
+        working_dir: None,
+        env: HashMap::new(),
+        background: None,
+    };
+
+    let result = progress.scope(Tool::execute(&tool, params, None)).await;
+    assert!(result.is_success());
+
+    let streamed = chunks.lock().unwrap().concat();
+    assert_eq!(streamed, "first\nsecond\n");
+    assert!(chunks.lock().unwrap().len() >= 2);
+}
@@ -1,17 +1,18 @@
 use super::structs::EditToolParams;
-use super::super::{FsOperationLog, FsOperationType};
+use super::super::{resolve_path, FsOperationLog, FsOperationType};
 use crate::tools::{tool, ToolResult};
 use similar::{ChangeTag, TextDiff};
 use serde_json::json;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct EditTool {
     operation_log: Arc<FsOperationLog>,
     context_lines: usize,
+    root: Option<PathBuf>,
 }
 
 impl EditTool {
@@ -23,9 +24,16 @@ impl EditTool {
         Self {
             operation_log,
             context_lines,
+            root: None,
         }
     }
 
+    /// Resolve relative paths against the given workspace root
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
+    }
+
     pub fn myers_diff(&self, before_content: &str, after_content: &str) -> String {
         let diff = TextDiff::from_lines(before_content, after_content);
 
@@ -177,7 +185,8 @@ impl EditTool {
         fs::write(path, new_content).map_err(|e| e.to_string())
     }
 
-    fn perform_edit(&self, params: &EditToolParams, preview: bool) -> Result<(String, usize), String> {
+    /// returns the diff, the number of replacements and the content before the edit
+    fn perform_edit(&self, params: &EditToolParams, preview: bool) -> Result<(String, usize, String), String> {
         let path = Path::new(&params.path);
 
         // Check if file exists
@@ -203,7 +212,7 @@ impl EditTool {
             self.commit_edit(&params.path, &new_content)?;
         }
 
-        Ok((diff_output.join("\n"), replacements))
+        Ok((diff_output.join("\n"), replacements, content))
     }
 }
 
@@ -227,7 +236,9 @@ impl EditTool {
         self.execute_internal(params, false).await
     }
 
-    async fn execute_internal(&self, params: EditToolParams, preview: bool) -> ToolResult {
+    async fn execute_internal(&self, mut params: EditToolParams, preview: bool) -> ToolResult {
+        params.path = resolve_path(self.root.as_deref(), &params.path);
+
         // Validate that old_string and new_string are different
         if params.old_string == params.new_string {
             return ToolResult::error("old_string and new_string cannot be the same".to_string());
@@ -239,10 +250,10 @@ impl EditTool {
         }
 
         match self.perform_edit(&params, preview) {
-            Ok((message, replacement_count)) => {
+            Ok((message, replacement_count, previous)) => {
                 // Log the edit operation only if not preview
                 if !preview {
-                    self.operation_log.log_operation(FsOperationType::Edit, params.path.clone()).await;
+                    self.operation_log.log_change(FsOperationType::Edit, params.path.clone(), Some(previous)).await;
                 }
                 
                 let mut meta = HashMap::new();
@@ -2,17 +2,26 @@ use super::structs::{FindToolParams, SearchResult, FindType};
 use crate::tools::{tool, ToolResult};
 use serde_json::json;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use super::super::resolve_path;
 use regex::Regex;
 use walkdir::WalkDir;
 use std::fs;
 use std::io::{BufRead, BufReader};
 
-pub struct FindTool;
+pub struct FindTool {
+    root: Option<PathBuf>,
+}
 
 impl FindTool {
     pub fn new() -> Self {
-        Self
+        Self { root: None }
+    }
+
+    /// Resolve relative paths against the given workspace root
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
     }
 
     fn should_include_file(&self, path: &Path, include_extensions: &Option<String>, exclude_patterns: &Option<String>) -> bool {
@@ -129,7 +138,7 @@ impl FindTool {
         let mut meta = HashMap::new();
         meta.insert("pattern".to_string(), json!(params.pattern));
         let default_path = ".".to_string();
-        let search_path = params.path.as_ref().unwrap_or(&default_path);
+        let search_path = &resolve_path(self.root.as_deref(), params.path.as_ref().unwrap_or(&default_path));
         meta.insert("path".to_string(), json!(search_path));
         meta.insert("case_sensitive".to_string(), json!(params.case_sensitive));
         meta.insert("max_results".to_string(), json!(params.max_results));
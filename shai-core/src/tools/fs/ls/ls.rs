@@ -3,13 +3,22 @@ use crate::tools::{tool, ToolResult};
 use serde_json::json;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use super::super::resolve_path;
 
-pub struct LsTool;
+pub struct LsTool {
+    root: Option<PathBuf>,
+}
 
 impl LsTool {
     pub fn new() -> Self {
-        Self
+        Self { root: None }
+    }
+
+    /// Resolve relative paths against the given workspace root
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
     }
 
     fn get_file_info(&self, path: &Path) -> Result<FileInfo, Box<dyn std::error::Error>> {
@@ -213,7 +222,8 @@ impl LsTool {
 - For large directories, consider using the `find` tool instead, which offers powerful filtering and search capabilities.
 - Use `recursive: true` carefully, especially in directories like `node_modules/` which contain thousands of files."#, capabilities = [ToolCapability::Read])]
 impl LsTool {
-    async fn execute(&self, params: LsToolParams) -> ToolResult {
+    async fn execute(&self, mut params: LsToolParams) -> ToolResult {
+        params.directory = resolve_path(self.root.as_deref(), &params.directory);
         let mut files_collected = 0;
         match self.list_directory(&params, 0, &mut files_collected) {
             Ok(files) => {
@@ -15,4 +15,15 @@ pub use ls::LsTool;
 pub use multiedit::MultiEditTool;
 pub use operation_log::{FsOperationLog, FsOperationType, FsOperation, FsOperationSummary};
 pub use read::ReadTool;
-pub use write::WriteTool;
\ No newline at end of file
+pub use write::WriteTool;
+
+use std::path::Path;
+
+/// Resolve a path given to a tool against an optional workspace root.
+/// Absolute paths and paths without a root are returned unchanged.
+pub fn resolve_path(root: Option<&Path>, path: &str) -> String {
+    match root {
+        Some(root) if !Path::new(path).is_absolute() => root.join(path).to_string_lossy().to_string(),
+        _ => path.to_string(),
+    }
+}
@@ -1,16 +1,17 @@
 use super::structs::MultiEditToolParams;
-use super::super::{FsOperationLog, FsOperationType, EditTool};
+use super::super::{resolve_path, FsOperationLog, FsOperationType, EditTool};
 use crate::tools::{tool, ToolResult};
 use serde_json::json;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Clone)]
 pub struct MultiEditTool {
     operation_log: Arc<FsOperationLog>,
     edit_tool: EditTool,
+    root: Option<PathBuf>,
 }
 
 impl MultiEditTool {
@@ -20,10 +21,17 @@ impl MultiEditTool {
 
     pub fn with_context_lines(operation_log: Arc<FsOperationLog>, context_lines: usize) -> Self {
         let edit_tool = EditTool::with_context_lines(operation_log.clone(), context_lines);
-        Self { operation_log, edit_tool }
+        Self { operation_log, edit_tool, root: None }
+    }
+
+    /// Resolve relative paths against the given workspace root
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
     }
     
-    async fn perform_multi_edit(&self, params: &MultiEditToolParams, preview: bool) -> Result<(String, Vec<usize>), String> {
+    /// returns the diff, the replacements made by each edit and the content before the edits
+    async fn perform_multi_edit(&self, params: &MultiEditToolParams, preview: bool) -> Result<(String, Vec<usize>, String), String> {
         let path = Path::new(&params.file_path);
 
         // Check if file exists
@@ -57,7 +65,7 @@ impl MultiEditTool {
             self.edit_tool.commit_edit(&params.file_path, &current_content)?;
         }
 
-        Ok((diff, replacements_per_edit))
+        Ok((diff, replacements_per_edit, original_content))
     }
 }
 
@@ -79,7 +87,9 @@ impl MultiEditTool {
         self.execute_internal(params, false).await
     }
 
-    async fn execute_internal(&self, params: MultiEditToolParams, preview: bool) -> ToolResult {
+    async fn execute_internal(&self, mut params: MultiEditToolParams, preview: bool) -> ToolResult {
+        params.file_path = resolve_path(self.root.as_deref(), &params.file_path);
+
         // Validate that we have at least one edit operation
         if params.edits.is_empty() {
             return ToolResult::error("At least one edit operation is required".to_string());
@@ -91,10 +101,10 @@ impl MultiEditTool {
         }
 
         match self.perform_multi_edit(&params, preview).await {
-            Ok((message, replacements_per_edit)) => {
+            Ok((message, replacements_per_edit, previous)) => {
                 // Log the multiedit operation only if not preview
                 if !preview {
-                    self.operation_log.log_operation(FsOperationType::MultiEdit, params.file_path.clone()).await;
+                    self.operation_log.log_change(FsOperationType::MultiEdit, params.file_path.clone(), Some(previous)).await;
                 }
                 
                 let mut meta = HashMap::new();
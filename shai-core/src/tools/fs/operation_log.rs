@@ -9,6 +9,12 @@ pub struct FsOperation {
     pub operation_type: FsOperationType,
     pub file_path: String,
     pub timestamp: DateTime<Utc>,
+    /// Content of the file before a write or an edit, if it could be saved
+    #[serde(default)]
+    pub previous_content: Option<String>,
+    /// The operation created the file
+    #[serde(default)]
+    pub created: bool,
 }
 
 /// Types of file system operations we track
@@ -38,10 +44,26 @@ impl FsOperationLog {
 
     /// Log a file operation
     pub async fn log_operation(&self, operation_type: FsOperationType, file_path: String) {
+        self.push(operation_type, file_path, None, false).await;
+    }
+
+    /// Log a change along with the content the file had before, so that it can be restored
+    pub async fn log_change(&self, operation_type: FsOperationType, file_path: String, previous_content: Option<String>) {
+        self.push(operation_type, file_path, previous_content, false).await;
+    }
+
+    /// Log the creation of a file, restoring it removes the file
+    pub async fn log_creation(&self, file_path: String) {
+        self.push(FsOperationType::Write, file_path, None, true).await;
+    }
+
+    async fn push(&self, operation_type: FsOperationType, file_path: String, previous_content: Option<String>, created: bool) {
         let operation = FsOperation {
             operation_type: operation_type.clone(),
             file_path: file_path.clone(),
             timestamp: Utc::now(),
+            previous_content,
+            created,
         };
 
         // Add to operations log
@@ -90,6 +112,36 @@ impl FsOperationLog {
         operations.clone()
     }
 
+    /// Number of operations logged so far, a checkpoint for `restore_to`
+    pub async fn operation_count(&self) -> usize {
+        self.operations.read().await.len()
+    }
+
+    /// Undo the file changes logged after the first `count` operations, latest first,
+    /// and forget them. Returns the paths that were restored
+    pub async fn restore_to(&self, count: usize) -> std::io::Result<Vec<String>> {
+        let mut operations = self.operations.write().await;
+        let mut restored = Vec::new();
+        while operations.len() > count {
+            let operation = operations.pop().unwrap();
+            if operation.created {
+                match std::fs::remove_file(&operation.file_path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            } else if let Some(content) = &operation.previous_content {
+                std::fs::write(&operation.file_path, content)?;
+            } else {
+                // reads, or changes whose previous content could not be saved
+                continue;
+            }
+            if !restored.contains(&operation.file_path) {
+                restored.push(operation.file_path);
+            }
+        }
+        Ok(restored)
+    }
+
     /// Get list of all files that have been read
     pub async fn get_read_files(&self) -> HashSet<String> {
         let read_files = self.read_files.read().await;
@@ -183,6 +235,31 @@ mod tests {
         assert_eq!(operations[0].file_path, "test.txt");
     }
 
+    #[tokio::test]
+    async fn test_restore_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let edited = dir.path().join("edited.txt").to_string_lossy().to_string();
+        let created = dir.path().join("created.txt").to_string_lossy().to_string();
+        std::fs::write(&edited, "v1").unwrap();
+
+        let log = FsOperationLog::new();
+        log.log_operation(FsOperationType::Read, edited.clone()).await;
+        let checkpoint = log.operation_count().await;
+
+        std::fs::write(&edited, "v2").unwrap();
+        log.log_change(FsOperationType::Edit, edited.clone(), Some("v1".to_string())).await;
+        std::fs::write(&edited, "v3").unwrap();
+        log.log_change(FsOperationType::Edit, edited.clone(), Some("v2".to_string())).await;
+        std::fs::write(&created, "new").unwrap();
+        log.log_creation(created.clone()).await;
+
+        let restored = log.restore_to(checkpoint).await.unwrap();
+        assert_eq!(restored, vec![created.clone(), edited.clone()]);
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "v1");
+        assert!(!std::path::Path::new(&created).exists());
+        assert_eq!(log.operation_count().await, checkpoint);
+    }
+
     #[tokio::test]
     async fn test_validate_edit_permission() {
         let log = FsOperationLog::new();
@@ -1,21 +1,28 @@
 use crate::tools::{ToolResult, tool};
 use super::structs::ReadToolParams;
-use super::super::{FsOperationLog, FsOperationType};
+use super::super::{resolve_path, FsOperationLog, FsOperationType};
 use serde_json::json;
 use std::collections::HashMap;
 use std::fs;
 use std::io::{self, BufRead, BufReader};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct ReadTool {
     operation_log: Arc<FsOperationLog>,
+    root: Option<PathBuf>,
 }
 
 impl ReadTool {
     pub fn new(operation_log: Arc<FsOperationLog>) -> Self {
-        Self { operation_log }
+        Self { operation_log, root: None }
+    }
+
+    /// Resolve relative paths against the given workspace root
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
     }
 
     fn read_file_content(&self, params: &ReadToolParams) -> io::Result<String> {
@@ -133,7 +140,8 @@ impl ReadTool {
 **Best Practices:**
 - When investigating a task, it is often effective to read multiple potentially relevant files in a single turn to build a complete understanding of the context."#, capabilities = [Read])]
 impl ReadTool {
-    async fn execute(&self, params: ReadToolParams) -> ToolResult {
+    async fn execute(&self, mut params: ReadToolParams) -> ToolResult {
+        params.path = resolve_path(self.root.as_deref(), &params.path);
         let path = Path::new(&params.path);
         
         // Check if file exists
@@ -332,4 +332,37 @@ mod integration_tests {
         assert!(read_files.contains(&config_path.to_string_lossy().to_string()));
         assert!(read_files.contains(&script_path.to_string_lossy().to_string()));
     }
-}
\ No newline at end of file
+
+    /// Test 4: Tools bound to a workspace root resolve relative paths inside it
+    #[tokio::test]
+    async fn test_tools_with_workspace_root() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        let fs_log = Arc::new(FsOperationLog::new());
+
+        let write_tool = WriteTool::new(fs_log.clone()).with_root(root.clone());
+        let read_tool = ReadTool::new(fs_log.clone()).with_root(root.clone());
+
+        let write_result = write_tool.execute(WriteToolParams {
+            path: "nested/notes.txt".to_string(),
+            content: "inside the workspace".to_string(),
+        }, None).await;
+        assert!(write_result.is_success());
+        assert!(root.join("nested/notes.txt").exists());
+
+        let read_result = read_tool.execute(ReadToolParams {
+            path: "nested/notes.txt".to_string(),
+            line_start: None,
+            line_end: None,
+            show_line_numbers: false,
+        }, None).await;
+        assert!(read_result.is_success());
+        if let crate::tools::types::ToolResult::Success { output, .. } = read_result {
+            assert!(output.contains("inside the workspace"));
+        }
+
+        // absolute paths are left untouched
+        let absolute = crate::tools::fs::resolve_path(Some(&root), "/etc/hosts");
+        assert_eq!(absolute, "/etc/hosts");
+    }
+}
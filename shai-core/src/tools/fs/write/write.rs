@@ -1,28 +1,37 @@
 use super::structs::WriteToolParams;
-use super::super::{FsOperationLog, FsOperationType};
+use super::super::{resolve_path, FsOperationLog, FsOperationType};
 use crate::tools::{ToolResult, tool};
 //use crate::tools::highlight::highlight_content;
 use serde_json::json;
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct WriteTool {
     operation_log: Arc<FsOperationLog>,
+    root: Option<PathBuf>,
 }
 
 impl WriteTool {
     pub fn new(operation_log: Arc<FsOperationLog>) -> Self {
-        Self { operation_log }
+        Self { operation_log, root: None }
     }
 
-    fn perform_write(&self, params: &WriteToolParams) -> Result<String, String> {
+    /// Resolve relative paths against the given workspace root
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    /// returns the outcome and the previous content of the file, None if it was created
+    fn perform_write(&self, params: &WriteToolParams) -> Result<(String, Option<Option<String>>), String> {
         let path = Path::new(&params.path);
 
-        // Check if file exists before writing
+        // Check if file exists before writing, keeping its content so that the write can be undone
         let file_existed = path.exists();
+        let previous = file_existed.then(|| fs::read_to_string(path).ok());
 
         // Create parent directories if they don't exist
         if let Some(parent) = path.parent() {
@@ -36,8 +45,8 @@ impl WriteTool {
 
         let action = if file_existed { "updated" } else { "created" };
         
-        Ok(format!("Successfully {} file '{}' with {} bytes", 
-                  action, params.path, params.content.len()))
+        Ok((format!("Successfully {} file '{}' with {} bytes", 
+                  action, params.path, params.content.len()), previous))
     }
 }
 
@@ -49,7 +58,8 @@ impl WriteTool {
 - Do not create files proactively, especially documentation. Only create files when the user's request cannot be fulfilled by modifying existing ones."#, capabilities = [ToolCapability::Write])]
 impl WriteTool {
 
-    async fn execute_preview(&self, params: WriteToolParams) -> Option<ToolResult> {
+    async fn execute_preview(&self, mut params: WriteToolParams) -> Option<ToolResult> {
+        params.path = resolve_path(self.root.as_deref(), &params.path);
         //let highlighted_content = highlight_content(&params.content, &params.path);
 
         let mut metadata = HashMap::new();
@@ -64,11 +74,15 @@ impl WriteTool {
         })
     }
 
-    async fn execute(&self, params: WriteToolParams) -> ToolResult {
+    async fn execute(&self, mut params: WriteToolParams) -> ToolResult {
+        params.path = resolve_path(self.root.as_deref(), &params.path);
         match self.perform_write(&params) {
-            Ok(message) => {
+            Ok((message, previous)) => {
                 // Log the write operation
-                self.operation_log.log_operation(FsOperationType::Write, params.path.clone()).await;
+                match previous {
+                    Some(previous) => self.operation_log.log_change(FsOperationType::Write, params.path.clone(), previous).await,
+                    None => self.operation_log.log_creation(params.path.clone()).await,
+                }
 
                 let output = format!("{}\n{}", message, params.content);
                 let mut meta = HashMap::new();
@@ -1,19 +1,23 @@
 pub mod types;
+pub mod progress;
 pub mod highlight;
 pub mod todo;
 pub mod fs;
 pub mod fetch;
 pub mod bash;
+pub mod shell;
 pub mod mcp;
 
 #[cfg(test)]
 mod tests_llm;
 
 pub use shai_macros::tool;
+pub use progress::{ToolProgress, report_progress};
 pub use types::{Tool, ToolCall, ToolResult, ToolError, ToolCapability, AnyTool, AnyToolBox, ToolEmptyParams};
 
 // Re-export all tools
-pub use bash::BashTool;
+pub use bash::{BashTool, BashOutputTool, BashInputTool, BashListTool, BashKillTool, ProcessRegistry};
+pub use shell::ShellTool;
 pub use fetch::FetchTool;
 pub use fs::{EditTool, FindTool, LsTool, MultiEditTool, ReadTool, WriteTool, FsOperationLog, FsOperationType, FsOperation, FsOperationSummary};
 pub use todo::{TodoReadTool, TodoWriteTool, TodoStorage, TodoItem, TodoStatus, TodoWriteParams, TodoItemInput};
@@ -0,0 +1,49 @@
+use std::future::Future;
+use std::sync::Arc;
+
+tokio::task_local! {
+    static PROGRESS: ToolProgress;
+}
+
+/// Sink for partial output of a running tool call
+/// The agent installs one around each tool execution, tools fetch it with `ToolProgress::current()`
+/// (or call `report_progress`) without any change to their signature
+#[derive(Clone)]
+pub struct ToolProgress {
+    sink: Arc<dyn Fn(String) + Send + Sync>,
+}
+
+impl ToolProgress {
+    pub fn new<F>(sink: F) -> Self
+    where
+        F: Fn(String) + Send + Sync + 'static,
+    {
+        Self { sink: Arc::new(sink) }
+    }
+
+    /// Progress sink of the tool call running in the current task, if any
+    /// Tasks spawned by a tool do not inherit it, fetch it before spawning and move it in
+    pub fn current() -> Option<ToolProgress> {
+        PROGRESS.try_with(|progress| progress.clone()).ok()
+    }
+
+    pub fn report(&self, chunk: impl Into<String>) {
+        let chunk = chunk.into();
+        if !chunk.is_empty() {
+            (self.sink)(chunk);
+        }
+    }
+
+    /// Run a future with this progress sink installed
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        PROGRESS.scope(self, future).await
+    }
+}
+
+/// Report a chunk of partial output for the tool call running in the current task
+/// Does nothing when nobody listens
+pub fn report_progress(chunk: impl Into<String>) {
+    if let Some(progress) = ToolProgress::current() {
+        progress.report(chunk);
+    }
+}
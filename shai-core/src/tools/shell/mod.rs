@@ -0,0 +1,10 @@
+pub mod structs;
+pub mod session;
+pub mod shell;
+
+#[cfg(test)]
+mod tests;
+
+pub use structs::ShellToolParams;
+pub use session::{ShellSession, ShellOutput};
+pub use shell::ShellTool;
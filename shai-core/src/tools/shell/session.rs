@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use uuid::Uuid;
+
+use crate::tools::bash::process::kill_process_group;
+use crate::tools::ToolProgress;
+
+/// Result of a command run in a shell session
+#[derive(Debug, Clone)]
+pub struct ShellOutput {
+    /// stdout and stderr of the command, interleaved
+    pub output: String,
+    /// None when the shell exited before the command completed (e.g. `exit`)
+    pub exit_code: Option<i32>,
+    /// working directory of the shell once the command completed
+    pub cwd: Option<String>,
+}
+
+/// A long-lived bash process fed through its stdin
+/// Every command is followed by a sentinel line carrying its exit code and the shell
+/// working directory, which is how the end of a command output is detected
+pub struct ShellSession {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    marker: String,
+}
+
+impl ShellSession {
+    pub async fn spawn(working_dir: Option<&Path>) -> std::io::Result<Self> {
+        let mut cmd = Command::new("bash");
+        cmd.args(["--noprofile", "--norc"])
+           .stdin(Stdio::piped())
+           .stdout(Stdio::piped())
+           .stderr(Stdio::null())
+           .kill_on_drop(true);
+        if let Some(dir) = working_dir {
+            cmd.current_dir(dir);
+        }
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = cmd.spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| std::io::Error::other("Failed to capture stdin"))?;
+        let stdout = child.stdout.take().ok_or_else(|| std::io::Error::other("Failed to capture stdout"))?;
+
+        let mut session = Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            marker: format!("__SHAI_DONE_{}__", Uuid::new_v4().simple()),
+        };
+
+        // merge stderr into stdout so that output keeps its order
+        session.stdin.write_all(b"exec 2>&1\n").await?;
+        session.stdin.flush().await?;
+        Ok(session)
+    }
+
+    /// Run a command and wait for its sentinel
+    /// The command is evaluated so that a syntax error fails the command rather than the shell,
+    /// its stdin is /dev/null since the shell stdin carries the commands
+    pub async fn run(&mut self, command: &str) -> std::io::Result<ShellOutput> {
+        let script = format!(
+            "eval '{}' < /dev/null\n__shai_status=$?\nprintf '\\n%s %d %s\\n' '{}' \"$__shai_status\" \"$PWD\"\n",
+            command.replace('\'', "'\\''"),
+            self.marker
+        );
+        self.stdin.write_all(script.as_bytes()).await?;
+        self.stdin.flush().await?;
+
+        let progress = ToolProgress::current();
+        let mut output = String::new();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if self.stdout.read_until(b'\n', &mut line).await? == 0 {
+                return Ok(ShellOutput { output, exit_code: None, cwd: None });
+            }
+
+            let text = String::from_utf8_lossy(&line);
+            if let Some(rest) = text.strip_prefix(&self.marker) {
+                let mut parts = rest.trim_end_matches('\n').trim_start().splitn(2, ' ');
+                let exit_code = parts.next().and_then(|code| code.parse().ok()).unwrap_or(-1);
+                let cwd = parts.next().map(|cwd| cwd.to_string());
+
+                // drop the newline printed in front of the sentinel
+                if output.ends_with('\n') {
+                    output.pop();
+                }
+                return Ok(ShellOutput { output, exit_code: Some(exit_code), cwd });
+            }
+            if let Some(progress) = &progress {
+                progress.report(text.as_ref());
+            }
+            output.push_str(&text);
+        }
+    }
+
+    /// Kill the shell and everything it started
+    pub async fn kill(mut self) {
+        kill_process_group(&mut self.child).await;
+    }
+}
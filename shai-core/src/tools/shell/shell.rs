@@ -0,0 +1,174 @@
+use super::structs::ShellToolParams;
+use super::session::{ShellOutput, ShellSession};
+use crate::tools::{tool, ToolResult};
+use serde_json::json;
+use tokio_util::sync::CancellationToken;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+pub struct ShellTool {
+    root: Option<PathBuf>,
+    session: Mutex<Option<ShellSession>>,
+}
+
+impl ShellTool {
+    pub fn new() -> Self {
+        Self { root: None, session: Mutex::new(None) }
+    }
+
+    /// Start the shell inside the given workspace root
+    pub fn with_root(mut self, root: PathBuf) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    async fn run_command(&self, params: &ShellToolParams, cancel_token: Option<CancellationToken>) -> Result<(ShellOutput, bool), String> {
+        // Validate command is not empty
+        if params.command.trim().is_empty() {
+            return Err("Command cannot be empty".to_string());
+        }
+
+        let mut guard = self.session.lock().await;
+
+        if params.restart.unwrap_or(false) {
+            if let Some(session) = guard.take() {
+                session.kill().await;
+            }
+        }
+
+        let fresh = guard.is_none();
+        if fresh {
+            let session = ShellSession::spawn(self.root.as_deref())
+                .await
+                .map_err(|e| format!("Failed to start shell: {}", e))?;
+            *guard = Some(session);
+        }
+        let session = guard.as_mut().unwrap();
+
+        // Optionable Future
+        let cancel_future = async {
+            if let Some(token) = cancel_token {
+                token.cancelled().await;
+            } else {
+                std::future::pending::<()>().await;
+            }
+        };
+        let timeout_future = async {
+            if let Some(timeout_secs) = params.timeout {
+                tokio::time::sleep(Duration::from_secs(timeout_secs as u64)).await;
+            } else {
+                std::future::pending::<()>().await;
+            }
+        };
+
+        // The shell cannot be brought back to a known state once a command is
+        // interrupted, so it is killed and the next call starts a new one
+        let result = tokio::select! {
+            result = session.run(&params.command) => result.map_err(|e| format!("Shell error: {}", e)),
+            _ = cancel_future => Err("Command was cancelled by user".to_string()),
+            _ = timeout_future => Err(format!("Command timed out after {} seconds", params.timeout.unwrap())),
+        };
+
+        let error = match result {
+            Ok(output) => {
+                if output.exit_code.is_none() {
+                    // the shell itself exited, start a new one next time
+                    *guard = None;
+                }
+                return Ok((output, fresh));
+            }
+            Err(error) => error,
+        };
+
+        if let Some(session) = guard.take() {
+            session.kill().await;
+        }
+        Err(format!("{}, the shell was restarted and its state (directory, environment) was lost", error))
+    }
+}
+
+#[tool(name = "shell", description = r#"
+Runs commands in a persistent bash session. Unlike the bash tool, the shell survives between calls: the current directory (cd), exported environment variables, activated virtualenvs, shell functions and aliases are kept for the following commands.
+
+SECURITY WARNING:
+ - You are operating in a live user environment without a sandbox.
+ - NEVER execute commands that could have unintended consequences, such as deleting files (rm), modifying system-wide configurations, or installing software without explicit, step-by-step user consent.
+
+Usage Guidelines:
+- Use it for multi-step work in one place, e.g. "cd backend" then "source .venv/bin/activate" then "pytest".
+- Commands do not read from stdin, interactive programs are not supported.
+- The output reports the exit code and the current directory of the shell after the command.
+- When a command times out or is cancelled, the shell is restarted and its state is lost. Set restart to true to start from a clean shell on purpose.
+"#, capabilities = [ToolCapability::Read, ToolCapability::Write, ToolCapability::Network])]
+impl ShellTool {
+    async fn execute(&self, params: ShellToolParams, cancel_token: Option<CancellationToken>) -> ToolResult {
+        let start_time = Instant::now();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("command".to_string(), json!(params.command));
+        if let Some(timeout_val) = params.timeout {
+            metadata.insert("timeout".to_string(), json!(timeout_val));
+        } else {
+            metadata.insert("timeout".to_string(), json!("none"));
+        }
+
+        match self.run_command(&params, cancel_token).await {
+            Ok((result, fresh)) => {
+                metadata.insert("execution_time_ms".to_string(), json!(start_time.elapsed().as_millis()));
+                metadata.insert("new_shell".to_string(), json!(fresh));
+
+                let Some(exit_code) = result.exit_code else {
+                    metadata.insert("success".to_string(), json!(false));
+                    return ToolResult::Error {
+                        error: format!("The shell exited, a new one will be started on the next call\n{}", result.output),
+                        metadata: Some(metadata),
+                    };
+                };
+
+                metadata.insert("exit_code".to_string(), json!(exit_code));
+                metadata.insert("success".to_string(), json!(exit_code == 0));
+                if let Some(cwd) = &result.cwd {
+                    metadata.insert("cwd".to_string(), json!(cwd));
+                }
+
+                let footer = match &result.cwd {
+                    Some(cwd) => format!("[exit code {}, cwd {}]", exit_code, cwd),
+                    None => format!("[exit code {}]", exit_code),
+                };
+                let output = if result.output.is_empty() {
+                    footer
+                } else {
+                    format!("{}\n{}", result.output, footer)
+                };
+
+                if exit_code == 0 {
+                    ToolResult::Success {
+                        output,
+                        metadata: Some(metadata),
+                    }
+                } else {
+                    ToolResult::Error {
+                        error: format!("Command failed with exit code {}: {}", exit_code, output),
+                        metadata: Some(metadata),
+                    }
+                }
+            }
+            Err(e) => {
+                metadata.insert("execution_time_ms".to_string(), json!(start_time.elapsed().as_millis()));
+                metadata.insert("success".to_string(), json!(false));
+                ToolResult::Error {
+                    error: e,
+                    metadata: Some(metadata),
+                }
+            }
+        }
+    }
+
+    async fn shutdown(&self) {
+        if let Some(session) = self.session.lock().await.take() {
+            session.kill().await;
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use serde::Deserialize;
+use schemars::JsonSchema;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct ShellToolParams {
+    /// The command to run in the persistent shell
+    pub command: String,
+    /// Timeout in seconds (optional, None = no timeout). The shell is restarted when it expires
+    pub timeout: Option<u32>,
+    /// Start a fresh shell before running the command, dropping directory and environment changes (optional, default false)
+    pub restart: Option<bool>,
+}
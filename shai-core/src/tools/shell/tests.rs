@@ -0,0 +1,73 @@
+use super::structs::ShellToolParams;
+use super::shell::ShellTool;
+use crate::tools::{Tool, ToolCapability, ToolResult};
+use shai_llm::ToolDescription;
+use serde_json::json;
+
+fn params(command: &str) -> ShellToolParams {
+    ShellToolParams {
+        command: command.to_string(),
+        timeout: None,
+        restart: None,
+    }
+}
+
+#[test]
+fn test_shell_tool_permissions() {
+    let tool = ShellTool::new();
+    assert_eq!(&tool.name(), "shell");
+    let perms = tool.capabilities();
+    assert!(perms.contains(&ToolCapability::Read));
+    assert!(perms.contains(&ToolCapability::Write));
+    assert!(perms.contains(&ToolCapability::Network));
+}
+
+#[tokio::test]
+async fn test_shell_tool_keeps_state() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let tool = ShellTool::new().with_root(temp_dir.path().to_path_buf());
+
+    let result = Tool::execute(&tool, params("mkdir sub && cd sub && export SHAI_TEST=kept"), None).await;
+    assert!(result.is_success());
+
+    let result = Tool::execute(&tool, params("echo \"$SHAI_TEST in $(basename $PWD)\""), None).await;
+    if let ToolResult::Success { output, metadata } = result {
+        assert!(output.starts_with("kept in sub\n"));
+        let metadata = metadata.unwrap();
+        assert_eq!(metadata["exit_code"], json!(0));
+        assert_eq!(metadata["new_shell"], json!(false));
+    } else {
+        panic!("Expected success result");
+    }
+
+    // syntax errors and failures do not kill the shell
+    let result = Tool::execute(&tool, params("echo ( "), None).await;
+    assert!(!result.is_success());
+    let result = Tool::execute(&tool, params("false"), None).await;
+    if let ToolResult::Error { metadata, .. } = result {
+        assert_eq!(metadata.unwrap()["exit_code"], json!(1));
+    } else {
+        panic!("Expected error result");
+    }
+    let result = Tool::execute(&tool, params("echo $SHAI_TEST"), None).await;
+    assert!(matches!(result, ToolResult::Success { ref output, .. } if output.starts_with("kept\n")));
+
+    let result = Tool::execute(&tool, ShellToolParams { restart: Some(true), ..params("echo ${SHAI_TEST:-gone}") }, None).await;
+    assert!(matches!(result, ToolResult::Success { ref output, .. } if output.starts_with("gone\n")));
+
+    tool.shutdown().await;
+}
+
+#[tokio::test]
+async fn test_shell_tool_timeout_restarts() {
+    let tool = ShellTool::new();
+
+    let result = Tool::execute(&tool, params("export SHAI_TEST=lost"), None).await;
+    assert!(result.is_success());
+
+    let result = Tool::execute(&tool, ShellToolParams { timeout: Some(1), ..params("sleep 30") }, None).await;
+    assert!(matches!(result, ToolResult::Error { ref error, .. } if error.contains("timed out")));
+
+    let result = Tool::execute(&tool, params("echo ${SHAI_TEST:-fresh}"), None).await;
+    assert!(matches!(result, ToolResult::Success { ref output, .. } if output.starts_with("fresh\n")));
+}
@@ -112,6 +112,14 @@ impl ToolResult {
     pub fn is_denied(&self) -> bool {
         matches!(self, Self::Denied)
     }
+
+    /// The error reported by the tool, if it failed
+    pub fn error_message(&self) -> Option<String> {
+        match self {
+            Self::Error { error, .. } => Some(error.clone()),
+            _ => None,
+        }
+    }
 }
 
 #[async_trait]
@@ -130,6 +138,10 @@ pub trait Tool: ToolDescription + Send + Sync {
         None
     }
 
+    /// release resources held by the tool (processes, connections...) when the agent terminates
+    /// Default implementation does nothing
+    async fn shutdown(&self) {}
+
     /// execute the tool.
     /// params are jsno-serialized then deserialized in tool specific parameter.
     async fn execute_json(&self, params: serde_json::Value, cancel_token: Option<CancellationToken>) -> ToolResult {
@@ -151,6 +163,8 @@ pub trait AnyTool: ToolDescription + Send + Sync {
     
     async fn execute_json(&self, params: serde_json::Value, cancel_token: Option<CancellationToken>) -> ToolResult;
     async fn execute_preview_json(&self, params: serde_json::Value) -> Option<ToolResult>;
+
+    async fn shutdown(&self) {}
 }
 
 /// Auto-implement AnyTool
@@ -175,6 +189,10 @@ where
         
         self.execute_preview(typed_params).await
     }
+
+    async fn shutdown(&self) {
+        <T as Tool>::shutdown(self).await
+    }
 }
 
 pub type ToolError = Box<dyn std::error::Error + Send + Sync>;
@@ -0,0 +1,309 @@
+use async_trait::async_trait;
+use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent};
+use openai_dive::v1::resources::shared::FinishReason;
+use serde_json::json;
+use shai_core::agent::{AgentEvent, PublicAgentState};
+use shai_llm::providers::anthropic::api::{
+    AnthropicContentBlock, AnthropicDelta, AnthropicMessage, AnthropicMessageDelta,
+    AnthropicStreamEvent, AnthropicUsage,
+};
+use uuid::Uuid;
+
+use crate::streaming::EventFormatter;
+
+/// Formatter for Anthropic Messages API
+/// Tool calls are streamed as tool_use blocks, tool outcomes as thinking blocks
+/// and the final answer as a text block
+pub struct MessagesFormatter {
+    pub model: String,
+    pub message_id: String,
+
+    // State management
+    started: bool,
+    finished: bool,
+    index: u32,
+    accumulated_text: String,
+    content: Vec<serde_json::Value>,
+    /// thinking block left open while tools stream their live output
+    progress: Option<String>,
+    input_tokens: u32,
+    output_tokens: u32,
+    /// the last step of the model ran out of tokens
+    truncated: bool,
+    /// tool calls started and not completed yet, the turn ends on them when the agent pauses
+    pending_tools: usize,
+    error: Option<String>,
+}
+
+impl MessagesFormatter {
+    pub fn new(model: String) -> Self {
+        Self {
+            model,
+            message_id: format!("msg_{}", Uuid::new_v4().simple()),
+            started: false,
+            finished: false,
+            index: 0,
+            accumulated_text: String::new(),
+            content: Vec::new(),
+            progress: None,
+            input_tokens: 0,
+            output_tokens: 0,
+            truncated: false,
+            pending_tools: 0,
+            error: None,
+        }
+    }
+
+    /// Build the message object, used for message_start and non-streaming responses
+    pub fn message(&self, stop_reason: Option<String>) -> AnthropicMessage {
+        AnthropicMessage {
+            id: self.message_id.clone(),
+            message_type: "message".to_string(),
+            role: "assistant".to_string(),
+            content: self.content.clone(),
+            model: self.model.clone(),
+            stop_reason,
+            stop_sequence: None,
+            usage: AnthropicUsage {
+                input_tokens: Some(self.input_tokens),
+                output_tokens: self.output_tokens,
+            },
+        }
+    }
+
+    /// Why the turn ended: "max_tokens" if the model was cut, "tool_use" if it
+    /// stopped on tool calls left to the client, "end_turn" otherwise
+    pub fn stop_reason(&self) -> String {
+        if self.truncated {
+            "max_tokens".to_string()
+        } else if self.pending_tools > 0 {
+            "tool_use".to_string()
+        } else {
+            "end_turn".to_string()
+        }
+    }
+
+    /// Error reported by the agent, if any
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn empty_block(block_type: &str) -> AnthropicContentBlock {
+        AnthropicContentBlock {
+            block_type: block_type.to_string(),
+            text: None,
+            thinking: None,
+            id: None,
+            name: None,
+            input: None,
+        }
+    }
+
+    /// Emit a complete content block (start, single delta, stop) and record it in the message content
+    fn push_block(&mut self, start: AnthropicContentBlock, delta: AnthropicDelta, full: serde_json::Value, out: &mut Vec<AnthropicStreamEvent>) {
+        self.close_progress(out);
+        let index = self.index;
+        out.push(AnthropicStreamEvent::ContentBlockStart { index, content_block: start });
+        out.push(AnthropicStreamEvent::ContentBlockDelta { index, delta });
+        out.push(AnthropicStreamEvent::ContentBlockStop { index });
+        self.content.push(full);
+        self.index += 1;
+    }
+
+    /// Stream live tool output as deltas of a thinking block opened on demand
+    fn push_progress(&mut self, chunk: String, out: &mut Vec<AnthropicStreamEvent>) {
+        let index = self.index;
+        let thinking = self.progress.get_or_insert_with(|| {
+            let mut start = Self::empty_block("thinking");
+            start.thinking = Some(String::new());
+            out.push(AnthropicStreamEvent::ContentBlockStart { index, content_block: start });
+            String::new()
+        });
+        thinking.push_str(&chunk);
+        out.push(AnthropicStreamEvent::ContentBlockDelta { index, delta: AnthropicDelta::ThinkingDelta { thinking: chunk } });
+    }
+
+    fn close_progress(&mut self, out: &mut Vec<AnthropicStreamEvent>) {
+        if let Some(thinking) = self.progress.take() {
+            out.push(AnthropicStreamEvent::ContentBlockStop { index: self.index });
+            self.content.push(json!({ "type": "thinking", "thinking": thinking }));
+            self.index += 1;
+        }
+    }
+
+    fn push_text(&mut self, out: &mut Vec<AnthropicStreamEvent>) {
+        if self.accumulated_text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.accumulated_text);
+        let mut start = Self::empty_block("text");
+        start.text = Some(String::new());
+        self.push_block(
+            start,
+            AnthropicDelta::TextDelta { text: text.clone() },
+            json!({ "type": "text", "text": text }),
+            out,
+        );
+    }
+
+    fn finish(&mut self, out: &mut Vec<AnthropicStreamEvent>) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.close_progress(out);
+        self.push_text(out);
+        out.push(AnthropicStreamEvent::MessageDelta {
+            delta: AnthropicMessageDelta {
+                stop_reason: Some(self.stop_reason()),
+                stop_sequence: None,
+            },
+            usage: Some(AnthropicUsage {
+                input_tokens: None,
+                output_tokens: self.output_tokens,
+            }),
+        });
+        out.push(AnthropicStreamEvent::MessageStop);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[async_trait]
+impl EventFormatter for MessagesFormatter {
+    type Output = AnthropicStreamEvent;
+
+    async fn format_event(
+        &mut self,
+        event: AgentEvent,
+        session_id: &str,
+    ) -> Option<Self::Output> {
+        self.format_events(event, session_id).await.into_iter().next()
+    }
+
+    async fn format_events(
+        &mut self,
+        event: AgentEvent,
+        _session_id: &str,
+    ) -> Vec<Self::Output> {
+        let mut out = Vec::new();
+        if self.finished {
+            return out;
+        }
+
+        // Send message_start on first call
+        if !self.started {
+            self.started = true;
+            out.push(AnthropicStreamEvent::MessageStart { message: self.message(None) });
+        }
+
+        match event {
+            // Capture assistant messages from brain results
+            AgentEvent::BrainResult { thought, finish_reason, .. } => {
+                self.truncated = matches!(finish_reason, Some(FinishReason::TokenLimitReached));
+                match thought {
+                    Ok(ChatMessage::Assistant { content: Some(ChatMessageContent::Text(text)), .. }) => {
+                        self.accumulated_text = text;
+                    }
+                    Err(error) => {
+                        self.error = Some(error.to_string());
+                    }
+                    _ => {}
+                }
+            }
+
+            // Tool call started - stream as a tool_use block
+            AgentEvent::ToolCallStarted { call, .. } => {
+                // text produced alongside the tool call comes first
+                self.push_text(&mut out);
+                self.pending_tools += 1;
+
+                let mut start = Self::empty_block("tool_use");
+                start.id = Some(call.tool_call_id.clone());
+                start.name = Some(call.tool_name.clone());
+                start.input = Some(json!({}));
+                self.push_block(
+                    start,
+                    AnthropicDelta::InputJsonDelta { partial_json: call.parameters.to_string() },
+                    json!({ "type": "tool_use", "id": call.tool_call_id, "name": call.tool_name, "input": call.parameters }),
+                    &mut out,
+                );
+            }
+
+            // Live tool output - stream as thinking deltas
+            AgentEvent::ToolCallProgress { chunk, .. } => {
+                self.push_progress(chunk, &mut out);
+            }
+
+            // Tool call completed - stream outcome as a thinking block
+            AgentEvent::ToolCallCompleted { call, result, .. } => {
+                use shai_core::tools::ToolResult;
+                self.pending_tools = self.pending_tools.saturating_sub(1);
+
+                let thinking_text = match &result {
+                    ToolResult::Success { .. } => {
+                        format!("[tool succeeded: {}]", call.tool_name)
+                    }
+                    ToolResult::Error { error, .. } => {
+                        let error_oneline = error.lines().next().unwrap_or(error);
+                        format!("[tool failed: {} - {}]", call.tool_name, error_oneline)
+                    }
+                    ToolResult::Denied => {
+                        format!("[tool denied: {}]", call.tool_name)
+                    }
+                };
+
+                let mut start = Self::empty_block("thinking");
+                start.thinking = Some(String::new());
+                self.push_block(
+                    start,
+                    AnthropicDelta::ThinkingDelta { thinking: thinking_text.clone() },
+                    json!({ "type": "thinking", "thinking": thinking_text }),
+                    &mut out,
+                );
+            }
+
+            AgentEvent::TokenUsage { input_tokens, output_tokens } => {
+                self.input_tokens += input_tokens;
+                self.output_tokens += output_tokens;
+            }
+
+            AgentEvent::Completed { message, .. } => {
+                if !message.is_empty() {
+                    self.accumulated_text = message;
+                }
+                self.finish(&mut out);
+            }
+
+            AgentEvent::StatusChanged { new_status: PublicAgentState::Paused, .. } => {
+                self.finish(&mut out);
+            }
+
+            AgentEvent::Error { error } => {
+                self.error = Some(error.clone());
+                out.push(AnthropicStreamEvent::Error {
+                    error: json!({ "error": { "type": "api_error", "message": error } }),
+                });
+            }
+
+            _ => {}
+        }
+
+        out
+    }
+
+    fn event_name(&self, output: &Self::Output) -> &str {
+        match output {
+            AnthropicStreamEvent::MessageStart { .. } => "message_start",
+            AnthropicStreamEvent::ContentBlockStart { .. } => "content_block_start",
+            AnthropicStreamEvent::ContentBlockDelta { .. } => "content_block_delta",
+            AnthropicStreamEvent::ContentBlockStop { .. } => "content_block_stop",
+            AnthropicStreamEvent::MessageDelta { .. } => "message_delta",
+            AnthropicStreamEvent::MessageStop => "message_stop",
+            AnthropicStreamEvent::Ping => "ping",
+            AnthropicStreamEvent::Error { .. } => "error",
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response, Sse, Json},
+};
+use futures::StreamExt;
+use shai_core::agent::{AgentEvent, PublicAgentState};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::formatter::MessagesFormatter;
+use super::types::{build_message_trace, MessagesRequest};
+use crate::{ApiJson, ServerState, ErrorResponse, EventFormatter, session_to_sse_stream};
+
+/// POST /v1/messages - Anthropic Messages API, supports both streaming and non-streaming
+/// Each request runs on an ephemeral session, the conversation is carried by `messages`
+pub async fn handle_messages(
+    State(state): State<ServerState>,
+    ApiJson(payload): ApiJson<MessagesRequest>,
+) -> Result<Response, ErrorResponse> {
+    let request_id = Uuid::new_v4();
+    let session_id = Uuid::new_v4().to_string();
+
+    info!("[{}] POST /v1/messages model={} stream={} (ephemeral)",
+        request_id, payload.model, payload.stream);
+
+    let trace = build_message_trace(&payload);
+    if trace.is_empty() {
+        return Err(ErrorResponse::invalid_request("messages must contain at least one text block".to_string()));
+    }
+
+    // Create ephemeral session
+    let agent_session = state.session_manager
+        .create_new_session(&request_id.to_string(), &session_id, Some(payload.model.clone()), true)
+        .await
+        .map_err(|e| ErrorResponse::internal_error(format!("Failed to create session: {}", e)))?;
+
+    // Create request session
+    let request_session = agent_session
+        .handle_request(&request_id.to_string(), trace)
+        .await
+        .map_err(|e| ErrorResponse::internal_error(format!("Failed to handle request: {}", e)))?;
+
+    let mut formatter = MessagesFormatter::new(payload.model.clone());
+
+    if payload.stream {
+        let stream = session_to_sse_stream(request_session, formatter, session_id, true);
+        return Ok(Sse::new(stream).into_response());
+    }
+
+    // Non-streaming: run events through the formatter and return the accumulated message
+    let mut event_stream = BroadcastStream::new(request_session.event_rx);
+    while let Some(result) = event_stream.next().await {
+        let event = match result {
+            Ok(event) => event,
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                warn!("[{}] Event stream lagged behind, {} events skipped", request_id, skipped);
+                continue;
+            }
+        };
+
+        let is_terminal = matches!(
+            event,
+            AgentEvent::Completed { .. }
+                | AgentEvent::StatusChanged { new_status: PublicAgentState::Paused, .. }
+        );
+
+        formatter.format_events(event, &session_id).await;
+
+        if let Some(error) = formatter.error() {
+            return Err(ErrorResponse::internal_error(format!("Agent error: {}", error)));
+        }
+        if is_terminal || formatter.is_finished() {
+            break;
+        }
+    }
+
+    Ok(Json(formatter.message(Some(formatter.stop_reason()))).into_response())
+}
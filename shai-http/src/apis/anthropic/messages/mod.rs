@@ -0,0 +1,5 @@
+pub mod handler;
+pub mod types;
+pub mod formatter;
+
+pub use handler::handle_messages;
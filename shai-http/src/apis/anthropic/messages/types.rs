@@ -0,0 +1,92 @@
+/// Request types for the Anthropic Messages API
+/// Streaming and response payloads reuse the types of the shai-llm anthropic provider.
+///
+/// Reference: https://docs.anthropic.com/en/api/messages
+
+use serde::{Deserialize, Serialize};
+use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent};
+
+/// Body of POST /v1/messages
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MessagesRequest {
+    /// Agent name ("default" or a configured agent)
+    pub model: String,
+    pub messages: Vec<InputMessage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<MessageContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InputMessage {
+    pub role: String,
+    pub content: MessageContent,
+}
+
+/// Content is either a plain string or a list of content blocks
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Blocks(Vec<serde_json::Value>),
+}
+
+impl MessageContent {
+    /// Concatenate the text blocks, other block types are ignored
+    pub fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Blocks(blocks) => blocks
+                .iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// Build message trace from Anthropic messages parameters
+pub fn build_message_trace(request: &MessagesRequest) -> Vec<ChatMessage> {
+    let mut trace = Vec::new();
+
+    if let Some(system) = &request.system {
+        let text = system.text();
+        if !text.is_empty() {
+            trace.push(ChatMessage::System {
+                content: ChatMessageContent::Text(text),
+                name: None,
+            });
+        }
+    }
+
+    for msg in &request.messages {
+        let text = msg.content.text();
+        if text.is_empty() {
+            continue;
+        }
+
+        match msg.role.as_str() {
+            "user" => trace.push(ChatMessage::User {
+                content: ChatMessageContent::Text(text),
+                name: None,
+            }),
+            "assistant" => trace.push(ChatMessage::Assistant {
+                content: Some(ChatMessageContent::Text(text)),
+                tool_calls: None,
+                name: None,
+                audio: None,
+                reasoning_content: None,
+                refusal: None,
+            }),
+            _ => {}
+        }
+    }
+
+    trace
+}
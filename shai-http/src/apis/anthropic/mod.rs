@@ -0,0 +1,3 @@
+pub mod messages;
+
+pub use messages::handle_messages;
@@ -1,2 +1,3 @@
 pub mod simple;
-pub mod openai;
\ No newline at end of file
+pub mod openai;
+pub mod anthropic;
@@ -88,6 +88,19 @@ impl EventFormatter for ChatCompletionFormatter {
                 Some(self.create_chunk(delta, None))
             }
 
+            // Live tool output - stream as thinking delta
+            AgentEvent::ToolCallProgress { chunk, .. } => {
+                let delta = DeltaChatMessage::Assistant {
+                    content: None,
+                    reasoning_content: Some(chunk),
+                    refusal: None,
+                    name: None,
+                    tool_calls: None,
+                };
+
+                Some(self.create_chunk(delta, None))
+            }
+
             // Tool call completed - stream result as thinking delta
             AgentEvent::ToolCallCompleted { call, result, .. } => {
                 use shai_core::tools::ToolResult;
@@ -1,5 +1,7 @@
 pub mod completion;
 pub mod response;
+pub mod models;
 
 pub use completion::handle_chat_completion;
 pub use response::{handle_response, handle_get_response, handle_cancel_response};
+pub use models::{handle_list_models, handle_get_model};
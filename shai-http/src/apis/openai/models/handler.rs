@@ -0,0 +1,50 @@
+use axum::{
+    extract::Path,
+    Json,
+};
+use shai_core::config::agent::AgentConfig;
+use tracing::{info, warn};
+
+use crate::ErrorResponse;
+use super::types::{ModelList, ModelObject};
+
+/// GET /v1/models - List the default agent and every configured agent
+pub async fn handle_list_models() -> Result<Json<ModelList>, ErrorResponse> {
+    info!("GET /v1/models");
+
+    let mut data = vec![ModelObject::default_agent()];
+    let agents = AgentConfig::list_agents()
+        .map_err(|e| ErrorResponse::internal_error(format!("Failed to list agents: {}", e)))?;
+
+    for name in agents {
+        match AgentConfig::load(&name) {
+            Ok(config) => data.push(ModelObject::from_agent_config(&config).await),
+            Err(e) => warn!("skipping agent '{}': {}", name, e),
+        }
+    }
+
+    Ok(Json(ModelList {
+        object: "list".to_string(),
+        data,
+    }))
+}
+
+/// GET /v1/models/{model_id} - Describe a single agent
+pub async fn handle_get_model(
+    Path(model_id): Path<String>,
+) -> Result<Json<ModelObject>, ErrorResponse> {
+    info!("GET /v1/models/{}", model_id);
+
+    if model_id == "default" {
+        return Ok(Json(ModelObject::default_agent()));
+    }
+
+    if !AgentConfig::exists(&model_id) {
+        return Err(ErrorResponse::not_found(format!("Model not found: {}", model_id)));
+    }
+
+    let config = AgentConfig::load(&model_id)
+        .map_err(|e| ErrorResponse::internal_error(format!("Failed to load agent '{}': {}", model_id, e)))?;
+
+    Ok(Json(ModelObject::from_agent_config(&config).await))
+}
@@ -0,0 +1,4 @@
+pub mod handler;
+pub mod types;
+
+pub use handler::{handle_list_models, handle_get_model};
@@ -0,0 +1,96 @@
+/// Model listing types for the OpenAI Models API
+/// Each shai agent is exposed as a model, with extra fields describing the agent.
+///
+/// Reference: https://platform.openai.com/docs/api-reference/models
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use shai_core::config::agent::{AgentConfig, BUILTIN_TOOLS};
+use shai_core::config::config::ShaiConfig;
+use tracing::warn;
+
+/// A single model entry (an agent)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModelObject {
+    pub id: String,
+    pub object: String,
+    pub created: i64,
+    pub owned_by: String,
+    pub description: String,
+    /// Underlying LLM provider, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Underlying LLM model, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Tool names grouped by origin ("builtin" or the MCP server name)
+    pub tools: BTreeMap<String, Vec<String>>,
+}
+
+/// Response of GET /v1/models
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ModelList {
+    pub object: String,
+    pub data: Vec<ModelObject>,
+}
+
+impl ModelObject {
+    /// The default agent, backed by the selected provider of the shai config
+    pub fn default_agent() -> Self {
+        let selected = ShaiConfig::load()
+            .ok()
+            .and_then(|config| config.get_selected_provider().cloned());
+
+        let mut tools = BTreeMap::new();
+        tools.insert("builtin".to_string(), BUILTIN_TOOLS.iter().map(|s| s.to_string()).collect());
+
+        Self {
+            id: "default".to_string(),
+            object: "model".to_string(),
+            created: 0,
+            owned_by: "shai".to_string(),
+            description: "Default coding agent using the selected provider".to_string(),
+            provider: selected.as_ref().map(|p| p.provider.clone()),
+            model: selected.map(|p| p.model),
+            tools,
+        }
+    }
+
+    /// An agent loaded from its config file, MCP servers enabling all their tools are asked for them
+    pub async fn from_agent_config(config: &AgentConfig) -> Self {
+        let created = AgentConfig::agent_config_path(&config.name)
+            .ok()
+            .and_then(|path| std::fs::metadata(path).ok())
+            .and_then(|meta| meta.modified().ok())
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        let mut tools = BTreeMap::new();
+        tools.insert("builtin".to_string(), config.tools.enabled_builtin());
+        for (mcp_name, mcp_tool_config) in &config.tools.mcp {
+            let enabled = match mcp_tool_config.enabled_tool_names(mcp_name).await {
+                Ok(enabled) => enabled,
+                Err(e) => {
+                    warn!("failed to list the tools of MCP '{}' for agent '{}': {}", mcp_name, config.name, e);
+                    mcp_tool_config.enabled_tools.iter()
+                        .filter(|name| !mcp_tool_config.excluded_tools.contains(name))
+                        .cloned()
+                        .collect()
+                }
+            };
+            tools.insert(mcp_name.clone(), enabled);
+        }
+
+        Self {
+            id: config.name.clone(),
+            object: "model".to_string(),
+            created,
+            owned_by: "shai".to_string(),
+            description: config.description.clone(),
+            provider: Some(config.llm_provider.provider.clone()),
+            model: Some(config.llm_provider.model.clone()),
+            tools,
+        }
+    }
+}
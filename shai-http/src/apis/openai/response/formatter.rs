@@ -145,6 +145,17 @@ impl EventFormatter for ResponseFormatter {
                 Some(event)
             }
 
+            AgentEvent::ToolCallProgress { call_id, chunk } => {
+                let idx = self.output.iter().position(|o| {
+                    matches!(o, ResponseOutput::FunctionToolCall(tc) if tc.id == call_id)
+                })?;
+
+                let event = ResponseStreamEvent::function_call_output_delta(self.sequence, call_id, idx, chunk);
+                self.sequence += 1;
+
+                Some(event)
+            }
+
             AgentEvent::ToolCallCompleted { call, result, .. } => {
                 use shai_core::tools::ToolResult;
 
@@ -33,6 +33,9 @@ pub enum ResponseEventType {
     ResponseOutputItemDone,
     #[serde(rename = "response.output_text.delta")]
     ResponseOutputTextDelta,
+    /// Not part of the OpenAI spec, live output of a running function call
+    #[serde(rename = "response.function_call_output.delta")]
+    ResponseFunctionCallOutputDelta,
     #[serde(rename = "response.completed")]
     ResponseCompleted,
 }
@@ -52,7 +55,7 @@ pub enum ResponseEventData {
         output_index: usize,
         item: ResponseOutput,
     },
-    /// response.output_text.delta
+    /// response.output_text.delta, response.function_call_output.delta
     TextDelta {
         sequence_number: u32,
         item_id: String,
@@ -129,6 +132,25 @@ impl ResponseStreamEvent {
         }
     }
 
+    /// Create a response.function_call_output.delta event
+    pub fn function_call_output_delta(
+        sequence_number: u32,
+        item_id: String,
+        output_index: usize,
+        delta: String,
+    ) -> Self {
+        Self {
+            event_type: ResponseEventType::ResponseFunctionCallOutputDelta,
+            data: ResponseEventData::TextDelta {
+                sequence_number,
+                item_id,
+                output_index,
+                content_index: 0,
+                delta,
+            },
+        }
+    }
+
     /// Create a response.completed event
     pub fn completed(sequence_number: u32, response: ResponseObject) -> Self {
         Self {
@@ -148,6 +170,7 @@ impl ResponseStreamEvent {
             ResponseEventType::ResponseOutputItemAdded => "response.output_item.added",
             ResponseEventType::ResponseOutputItemDone => "response.output_item.done",
             ResponseEventType::ResponseOutputTextDelta => "response.output_text.delta",
+            ResponseEventType::ResponseFunctionCallOutputDelta => "response.function_call_output.delta",
             ResponseEventType::ResponseCompleted => "response.completed",
         }
     }
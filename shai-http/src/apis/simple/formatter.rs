@@ -9,11 +9,13 @@ use crate::streaming::EventFormatter;
 /// Formatter for Simple API multimodal responses
 pub struct SimpleFormatter {
     pub model: String,
+    /// Running tool calls by id, progress events only carry the id
+    running: HashMap<String, ToolCall>,
 }
 
 impl SimpleFormatter {
     pub fn new(model: String) -> Self {
-        Self { model }
+        Self { model, running: HashMap::new() }
     }
 }
 
@@ -50,20 +52,41 @@ impl EventFormatter for SimpleFormatter {
                 }
                 None
             }
-            AgentEvent::ToolCallStarted { call, .. } => Some(MultiModalStreamingResponse {
-                id: session_id.to_string(),
-                model: self.model.clone(),
-                assistant: None,
-                call: Some(ToolCall {
+            AgentEvent::ToolCallStarted { call, .. } => {
+                let tool_call = ToolCall {
                     tool: call.tool_name.clone(),
                     args: parameters_to_args(&call.parameters),
                     output: None,
+                };
+                self.running.insert(call.tool_call_id.clone(), tool_call.clone());
+                Some(MultiModalStreamingResponse {
+                    id: session_id.to_string(),
+                    model: self.model.clone(),
+                    assistant: None,
+                    call: Some(tool_call),
+                    result: None,
+                })
+            }
+            AgentEvent::ToolCallProgress { call_id, chunk } => Some(MultiModalStreamingResponse {
+                id: session_id.to_string(),
+                model: self.model.clone(),
+                assistant: None,
+                call: self.running.get(&call_id).cloned(),
+                result: Some(ToolCallResult {
+                    text: None,
+                    text_stream: Some(chunk),
+                    image: None,
+                    speech: None,
+                    other: None,
+                    error: None,
+                    extra: None,
                 }),
-                result: None,
             }),
             AgentEvent::ToolCallCompleted { call, result, .. } => {
                 use shai_core::tools::ToolResult;
 
+                self.running.remove(&call.tool_call_id);
+
                 let (tool_result, output_str) = match &result {
                     ToolResult::Success { output, .. } => (
                         ToolCallResult {
@@ -8,6 +8,7 @@ use uuid::Uuid;
 
 use super::types::{MultiModalQuery, Message};
 use super::formatter::SimpleFormatter;
+use super::tools::{build_session_tools, restore_session_tools};
 use crate::{session_to_sse_stream, ApiJson, ErrorResponse, ServerState};
 
 /// Handle multimodal query - streaming response
@@ -33,23 +34,29 @@ pub async fn handle_multimodal_query_stream(
     let trace = build_message_trace(&payload);
 
     // Get or create session agent
-    let agent_session = if is_ephemeral {
-        // Ephemeral -> create new session
+    // Requested tools only apply when the session is created, a restored session
+    // only takes back the api keys of its openai tools from them
+    let existing = if is_ephemeral {
+        None
+    } else {
+        let resent = payload.tools.clone().unwrap_or_default();
         state.session_manager
-            .create_new_session(&request_id.to_string(), &session_id, Some(payload.model.clone()), is_ephemeral)
+            .get_or_restore_session(&request_id.to_string(), &session_id, |specs| async move {
+                restore_session_tools(specs, &resent).await
+            })
             .await
-            .map_err(|e| ErrorResponse::internal_error(format!("Failed to create session: {}", e)))?
-    } else {
-        // Persistent -> get existing or create new
-        match state.session_manager.get_session(&request_id.to_string(), &session_id).await {
-            Ok(session) => session,
-            Err(_) => {
-                // Doesn't exist, create it
-                state.session_manager
-                    .create_new_session(&request_id.to_string(), &session_id, Some(payload.model.clone()), is_ephemeral)
-                    .await
-                    .map_err(|e| ErrorResponse::internal_error(format!("Failed to create session: {}", e)))?
-            }
+            .map_err(|e| ErrorResponse::invalid_request(format!("Failed to restore session: {}", e)))?
+    };
+    let agent_session = match existing {
+        Some(session) => session,
+        None => {
+            let tools = build_session_tools(payload.tools.as_deref().unwrap_or_default())
+                .await
+                .map_err(ErrorResponse::invalid_request)?;
+            state.session_manager
+                .create_new_session_with_tools(&request_id.to_string(), &session_id, Some(payload.model.clone()), is_ephemeral, tools)
+                .await
+                .map_err(|e| ErrorResponse::internal_error(format!("Failed to create session: {}", e)))?
         }
     };
 
@@ -1,6 +1,7 @@
 pub mod types;
 pub mod handler;
 pub mod formatter;
+pub mod tools;
 
 pub use types::{MultiModalQuery, Message};
 pub use handler::handle_multimodal_query_stream;
@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use openai_dive::v1::resources::chat::{ChatCompletionParametersBuilder, ChatMessage, ChatMessageContent};
+use serde_json::json;
+use shai_core::config::agent::BUILTIN_TOOLS;
+use shai_core::tools::{create_mcp_client, get_mcp_tools, AnyTool, McpConfig, ToolCapability, ToolResult};
+use shai_llm::{LlmClient, ToolDescription};
+use tokio_util::sync::CancellationToken;
+
+use super::types::AgentTool;
+use crate::session::SessionTools;
+
+/// Tool forwarding a prompt to another model behind an OpenAI-compatible endpoint
+pub struct SubModelTool {
+    name: String,
+    description: String,
+    model: String,
+    llm: LlmClient,
+}
+
+impl SubModelTool {
+    pub fn new(url: String, api_key: Option<String>, model: String, description: String) -> Self {
+        let name = format!(
+            "ask_{}",
+            model.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+                .collect::<String>()
+        );
+        Self {
+            name,
+            description,
+            model,
+            llm: LlmClient::compatible(api_key.unwrap_or_default(), url),
+        }
+    }
+}
+
+impl ToolDescription for SubModelTool {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "prompt": {
+                    "type": "string",
+                    "description": "The full request to send to the model, it does not see the rest of the conversation"
+                }
+            },
+            "required": ["prompt"]
+        })
+    }
+
+    fn group(&self) -> Option<&str> {
+        Some("openai")
+    }
+}
+
+#[async_trait]
+impl AnyTool for SubModelTool {
+    fn capabilities(&self) -> &[ToolCapability] {
+        &[ToolCapability::Network]
+    }
+
+    async fn execute_json(&self, params: serde_json::Value, cancel_token: Option<CancellationToken>) -> ToolResult {
+        let Some(prompt) = params.get("prompt").and_then(|p| p.as_str()) else {
+            return ToolResult::error("Parameter deserialization failed: missing field `prompt`".to_string());
+        };
+
+        let request = match ChatCompletionParametersBuilder::default()
+            .model(&self.model)
+            .messages(vec![ChatMessage::User {
+                content: ChatMessageContent::Text(prompt.to_string()),
+                name: None,
+            }])
+            .build() {
+            Ok(request) => request,
+            Err(e) => return ToolResult::error(e.to_string()),
+        };
+
+        let cancelled = async {
+            match cancel_token {
+                Some(token) => token.cancelled().await,
+                None => std::future::pending::<()>().await,
+            }
+        };
+
+        let response = tokio::select! {
+            response = self.llm.chat(request) => response,
+            _ = cancelled => return ToolResult::error("Request was cancelled by user".to_string()),
+        };
+
+        match response {
+            Ok(response) => {
+                let text = response.choices.into_iter().next().and_then(|choice| match choice.message {
+                    ChatMessage::Assistant { content: Some(ChatMessageContent::Text(text)), .. } => Some(text),
+                    _ => None,
+                });
+                match text {
+                    Some(text) => ToolResult::success(text),
+                    None => ToolResult::error(format!("{} returned an empty response", self.model)),
+                }
+            }
+            Err(e) => ToolResult::error(format!("{} request failed: {}", self.model, e)),
+        }
+    }
+
+    async fn execute_preview_json(&self, _params: serde_json::Value) -> Option<ToolResult> {
+        None
+    }
+}
+
+/// Stored instead of the api key of an openai tool, keys are never written to the session store
+const REDACTED_API_KEY: &str = "<redacted>";
+
+/// Spec of a tool as persisted with its session
+fn stored_spec(tool: &AgentTool) -> Result<serde_json::Value, String> {
+    let mut tool = tool.clone();
+    if let AgentTool::OpenAi { api_key: Some(api_key), .. } = &mut tool {
+        *api_key = REDACTED_API_KEY.to_string();
+    }
+    serde_json::to_value(&tool).map_err(|e| e.to_string())
+}
+
+/// Turn the `tools` field of a query into toolbox adjustments for a new session
+/// - capability flags disable the matching builtin tools when set to false,
+///   only internet has builtin tools, enabling thinking, image or speech is an error
+/// - openai entries become sub-model tools
+/// - mcp entries are connected and all their tools are added
+/// - tools named like a builtin tool are rejected
+pub async fn build_session_tools(tools: &[AgentTool]) -> Result<SessionTools, String> {
+    let mut session_tools = SessionTools::default();
+
+    for tool in tools {
+        session_tools.specs.push(stored_spec(tool)?);
+        match tool {
+            AgentTool::Capability { thinking, internet, image, speech } => {
+                if *internet == Some(false) {
+                    session_tools.disabled.push("fetch".to_string());
+                }
+                // no builtin tool handles thinking, images or speech, they can only be left off
+                for (capability, enabled) in [("thinking", thinking), ("image", image), ("speech", speech)] {
+                    if *enabled == Some(true) {
+                        return Err(format!("The {} capability is not supported", capability));
+                    }
+                }
+            }
+            AgentTool::OpenAi { url, description, model, api_key } => {
+                session_tools.extra.push(Box::new(SubModelTool::new(
+                    url.clone(),
+                    api_key.clone(),
+                    model.clone(),
+                    description.clone(),
+                )));
+            }
+            AgentTool::Mcp { url } => {
+                let mcp_name = url
+                    .split("://")
+                    .last()
+                    .and_then(|rest| rest.split(['/', ':']).next())
+                    .filter(|host| !host.is_empty())
+                    .unwrap_or("mcp")
+                    .to_string();
+                let client = create_mcp_client(McpConfig::Http { url: url.clone(), bearer_token: None });
+                let mcp_tools = get_mcp_tools(client, &mcp_name)
+                    .await
+                    .map_err(|e| format!("Failed to get tools from MCP '{}': {}", url, e))?;
+                session_tools.extra.extend(mcp_tools);
+            }
+        }
+    }
+
+    // the model calls tools by name, two tools with the same one would be ambiguous
+    let mut names = std::collections::HashSet::new();
+    for tool in &session_tools.extra {
+        if BUILTIN_TOOLS.contains(&tool.name().as_str()) {
+            return Err(format!("Tool name '{}' is taken by a builtin tool", tool.name()));
+        }
+        if !names.insert(tool.name()) {
+            return Err(format!("Duplicate tool name '{}'", tool.name()));
+        }
+    }
+
+    Ok(session_tools)
+}
+
+/// Rebuild the tools of a restored session from its stored specs
+/// Redacted api keys are taken from the openai tools sent again by the client with the same url and model
+pub async fn restore_session_tools(specs: Vec<serde_json::Value>, resent: &[AgentTool]) -> Result<SessionTools, String> {
+    let mut tools: Vec<AgentTool> = serde_json::from_value(serde_json::Value::Array(specs))
+        .map_err(|e| format!("Invalid stored tools: {}", e))?;
+
+    for tool in &mut tools {
+        let AgentTool::OpenAi { url, model, api_key, .. } = tool else { continue };
+        if api_key.as_deref() != Some(REDACTED_API_KEY) {
+            continue;
+        }
+        *api_key = resent.iter().find_map(|other| match other {
+            AgentTool::OpenAi { url: other_url, model: other_model, api_key, .. }
+                if other_url == url && other_model == model => api_key.clone(),
+            _ => None,
+        });
+        if api_key.is_none() {
+            return Err(format!("the api_key of the openai tool '{}' at {} must be sent again", model, url));
+        }
+    }
+
+    build_session_tools(&tools).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn tools(value: serde_json::Value) -> Vec<AgentTool> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_capabilities() {
+        let session_tools = build_session_tools(&tools(json!([
+            {"type": "capability", "internet": false, "thinking": false}
+        ]))).await.unwrap();
+        // thinking is not a tool, the todo tools stay
+        assert_eq!(session_tools.disabled, vec!["fetch".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_capabilities_rejected() {
+        for capability in ["thinking", "image", "speech"] {
+            let error = build_session_tools(&tools(json!([
+                {"type": "capability", capability: true}
+            ]))).await.err().unwrap();
+            assert_eq!(error, format!("The {} capability is not supported", capability));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_sub_models_rejected() {
+        let error = build_session_tools(&tools(json!([
+            {"type": "openai", "url": "http://a/v1", "description": "first", "model": "gpt-4o"},
+            {"type": "openai", "url": "http://b/v1", "description": "second", "model": "gpt.4o"}
+        ]))).await.err().unwrap();
+        assert_eq!(error, "Duplicate tool name 'ask_gpt_4o'");
+    }
+
+    #[tokio::test]
+    async fn test_api_key_is_not_stored() {
+        let sent = tools(json!([
+            {"type": "openai", "url": "http://a/v1", "description": "ask", "model": "gpt-4o", "api_key": "sk-secret"}
+        ]));
+        let specs = build_session_tools(&sent).await.unwrap().specs;
+        assert!(!serde_json::to_string(&specs).unwrap().contains("sk-secret"));
+
+        // the key has to come back with the request restoring the session
+        let error = restore_session_tools(specs.clone(), &[]).await.err().unwrap();
+        assert!(error.contains("must be sent again"), "{}", error);
+
+        let restored = restore_session_tools(specs.clone(), &sent).await.unwrap();
+        assert_eq!(restored.extra.len(), 1);
+        assert_eq!(restored.specs, specs);
+    }
+}
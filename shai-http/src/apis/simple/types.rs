@@ -107,6 +107,8 @@ pub enum AgentTool {
         url: String,
         description: String,
         model: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        api_key: Option<String>,
     },
     #[serde(rename = "mcp")]
     Mcp {
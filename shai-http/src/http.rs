@@ -6,7 +6,7 @@ use std::sync::Arc;
 use tower_http::cors::CorsLayer;
 use tracing::info;
 
-use crate::session::{SessionManager, SessionManagerConfig};
+use crate::session::{SessionManager, SessionManagerConfig, SessionStore, WorkspaceCleanup, WorkspaceConfig, WorkspaceMode};
 use crate::apis;
 
 /// Configuration for the HTTP server
@@ -38,6 +38,18 @@ impl ServerConfig {
         self.session_manager.max_sessions = max_sessions;
         self
     }
+
+    /// Persist sessions to the given store so they survive restarts
+    pub fn with_store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.session_manager.store = Some(store);
+        self
+    }
+
+    /// Set how session workspaces are provisioned and cleaned up
+    pub fn with_workspace(mut self, workspace: WorkspaceConfig) -> Self {
+        self.session_manager.workspace = workspace;
+        self
+    }
 }
 
 /// Server state holding the session manager
@@ -61,6 +73,17 @@ pub async fn start_server(
         println!("  Max sessions: \x1b[1munlimited\x1b[0m");
     }
     println!("  Default mode: \x1b[1m{}\x1b[0m", if config.session_manager.ephemeral { "ephemeral" } else { "persistent" });
+    match &config.session_manager.store {
+        Some(store) => println!("  Session store: \x1b[1m{:?}\x1b[0m", store),
+        None => println!("  Session store: \x1b[1min memory\x1b[0m"),
+    }
+    let workspace = &config.session_manager.workspace;
+    if workspace.mode == WorkspaceMode::Shared {
+        println!("  Workspace: \x1b[1mshared\x1b[0m");
+    } else {
+        let cleanup = if workspace.cleanup == WorkspaceCleanup::Keep { "kept" } else { "deleted" };
+        println!("  Workspace: \x1b[1m{}\x1b[0m (per session, {} on exit)", workspace.mode, cleanup);
+    }
     println!();
 
     let state = ServerState {
@@ -77,6 +100,11 @@ pub async fn start_server(
         .route("/v1/responses/{response_id}/cancel", post(apis::openai::handle_cancel_response))
         // OpenAI-compatible Chat Completion API
         .route("/v1/chat/completions", post(apis::openai::handle_chat_completion))
+        // Anthropic-compatible Messages API
+        .route("/v1/messages", post(apis::anthropic::handle_messages))
+        // OpenAI-compatible Models API
+        .route("/v1/models", get(apis::openai::handle_list_models))
+        .route("/v1/models/{model_id}", get(apis::openai::handle_get_model))
         .layer(CorsLayer::permissive())
         .with_state(state);
 
@@ -89,8 +117,11 @@ pub async fn start_server(
     println!("  \x1b[1mPOST /v1/responses\x1b[0m                    - OpenAI Responses API (stateful/stateless)");
     println!("  \x1b[1mGET  /v1/responses/:id\x1b[0m                - Get response by ID");
     println!("  \x1b[1mPOST /v1/responses/:id/cancel\x1b[0m        - Cancel a response");
+    println!("  \x1b[1mPOST /v1/messages\x1b[0m                     - Anthropic Messages API (ephemeral)");
     println!("  \x1b[1mPOST /v1/multimodal\x1b[0m                   - Simple multimodal API (streaming)");
     println!("  \x1b[1mPOST /v1/multimodal/:session_id\x1b[0m      - Simple multimodal API (with session)");
+    println!("  \x1b[1mGET  /v1/models\x1b[0m                      - List available agents");
+    println!("  \x1b[1mGET  /v1/models/:id\x1b[0m                  - Describe an agent");
 
     // List available agents
     use shai_core::config::agent::AgentConfig;
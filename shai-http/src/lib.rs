@@ -5,6 +5,6 @@ pub mod session;
 pub mod streaming;
 
 pub use error::{ApiJson, ErrorResponse};
-pub use session::{SessionManager, SessionManagerConfig, AgentSession};
+pub use session::{SessionManager, SessionManagerConfig, AgentSession, WorkspaceConfig, WorkspaceMode, WorkspaceCleanup, SessionStore, JsonlSessionStore};
 pub use streaming::{EventFormatter, event_to_sse_stream, session_to_sse_stream};
 pub use http::{ServerConfig, ServerState, start_server};
\ No newline at end of file
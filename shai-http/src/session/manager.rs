@@ -1,13 +1,17 @@
-use shai_core::agent::{Agent, AgentError};
-use std::collections::HashMap;
+use shai_core::agent::{Agent, AgentError, AgentEvent, PublicAgentState};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::Mutex;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use shai_core::agent::AgentBuilder;
+use shai_core::tools::AnyTool;
+use shai_llm::ToolDescription;
 use crate::session::{log_event, logger::colored_session_id};
 
-use super::AgentSession;
+use super::{AgentSession, SessionRecord, SessionStore, Workspace, WorkspaceConfig};
 
 /// Configuration for the session manager
 #[derive(Clone, Debug)]
@@ -16,6 +20,10 @@ pub struct SessionManagerConfig {
     pub max_sessions: Option<usize>,
     /// Whether sessions are ephemeral or background (ephemeral session is destroyed after a single query)
     pub ephemeral: bool,
+    /// How each session working directory is provisioned
+    pub workspace: WorkspaceConfig,
+    /// Durable store for non-ephemeral sessions (None = in memory only)
+    pub store: Option<Arc<dyn SessionStore>>,
 }
 
 impl Default for SessionManagerConfig {
@@ -23,24 +31,44 @@ impl Default for SessionManagerConfig {
         Self {
             max_sessions: Some(100),
             ephemeral: false,
+            workspace: WorkspaceConfig::default(),
+            store: None,
         }
     }
 }
 
+/// Per-session adjustments to the agent toolbox, requested by the client
+#[derive(Default)]
+pub struct SessionTools {
+    /// Names of tools to remove from the agent
+    pub disabled: Vec<String>,
+    /// Additional tools given to the agent
+    pub extra: Vec<Box<dyn AnyTool>>,
+    /// Specs the tools were built from, persisted to rebuild them on restore
+    pub specs: Vec<serde_json::Value>,
+}
+
 /// Session manager - manages multiple agent sessions by ID
 /// Handles creation, deletion, and access control for sessions
 pub struct SessionManager {
     sessions: Arc<Mutex<HashMap<String, Arc<AgentSession>>>>,
+    /// Ids of the sessions being built, the lock is not held while their agent is created
+    starting: Arc<std::sync::Mutex<HashSet<String>>>,
     max_sessions: Option<usize>,
-    ephemeral: bool
+    ephemeral: bool,
+    workspace: WorkspaceConfig,
+    store: Option<Arc<dyn SessionStore>>,
 }
 
 impl SessionManager {
     pub fn new(config: SessionManagerConfig) -> Self {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            starting: Arc::new(std::sync::Mutex::new(HashSet::new())),
             max_sessions: config.max_sessions,
-            ephemeral: config.ephemeral
+            ephemeral: config.ephemeral,
+            workspace: config.workspace,
+            store: config.store,
         }
     }
 
@@ -50,25 +78,93 @@ impl SessionManager {
         session_id: &str,
         agent_name: Option<String>,
         ephemeral: bool,
+        record: Option<SessionRecord>,
+        tools: SessionTools,
     ) -> Result<Arc<AgentSession>, AgentError> {
         info!("[{}] - {} Creating new session", http_request_id, colored_session_id(session_id));
 
+        let mut record = record.unwrap_or_else(|| SessionRecord::new(session_id.to_string(), agent_name.clone()));
+        let trace = std::mem::take(&mut record.trace);
+        let branches = record.branches.take().unwrap_or_default();
+
+        // Provision the session workspace (released when dropped, including on build failure),
+        // a restored session goes back to the one its trace refers to
+        let restored_root = record.workspace_root.clone();
+        let workspace = match restored_root.clone() {
+            Some(root) => Workspace::attach(&self.workspace, session_id, root)
+                .map(Some)
+                .map_err(|e| AgentError::ExecutionError(format!("Failed to restore workspace: {}", e)))?,
+            None => Workspace::create(&self.workspace, session_id)
+                .await
+                .map_err(|e| AgentError::ExecutionError(format!("Failed to create workspace: {}", e)))?,
+        };
+        let workspace_root = workspace.as_ref().map(|w| w.root.clone());
+        // a persisted session can be restored after its agent ends, its workspace must outlive it
+        let restorable = !ephemeral && self.store.is_some();
+        record.workspace_root = workspace_root.clone();
+        record.tools = tools.specs.clone();
+
         // Build the agent
-        let mut agent = AgentBuilder::create(agent_name.clone().filter(|name| name != "default"))
-            .await
-            .map_err(|e| AgentError::ExecutionError(format!("Failed to create agent: {}", e)))?
+        let mut builder = match AgentBuilder::create_in(agent_name.clone().filter(|name| name != "default"), workspace_root.clone()).await {
+            Ok(builder) => builder,
+            Err(e) => {
+                // a failed restore must not wipe the workspace of the session
+                if let (Some(workspace), Some(_)) = (workspace, restored_root) {
+                    workspace.keep();
+                }
+                return Err(AgentError::ExecutionError(format!("Failed to create agent: {}", e)));
+            }
+        };
+        builder.available_tools.retain(|tool| !tools.disabled.contains(&tool.name()));
+        builder.available_tools.extend(tools.extra);
+        let mut agent = builder
+            .id(session_id)
+            .with_traces(trace)
+            .with_branches(branches)
             .sudo()
             .build();
 
         let controller = agent.controller();
         let event_rx = agent.watch();
 
-        // Spawn logging task alongside agent
+        // Spawn logging task alongside agent, it also persists the trace after each turn
         let mut event_for_logger = event_rx.resubscribe();
         let sid_for_logger = session_id.to_string();
+        let store = if ephemeral { None } else { self.store.clone() };
+        let controller_for_store = controller.clone();
         let logging_task = tokio::spawn(async move {
-            while let Ok(event) = event_for_logger.recv().await {
+            loop {
+                let event = match event_for_logger.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("{} - Logger lagged behind, {} events skipped", colored_session_id(&sid_for_logger), skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                };
                 log_event(&event, &sid_for_logger);
+
+                let Some(store) = &store else { continue };
+                if !matches!(event, AgentEvent::StatusChanged { new_status: PublicAgentState::Paused, .. }) {
+                    continue;
+                }
+                match controller_for_store.get_trace().await {
+                    Ok(trace) => {
+                        record.trace = trace;
+                        match controller_for_store.get_branches().await {
+                            Ok((branches, _)) => record.branches = (branches.branches.len() > 1).then_some(branches),
+                            Err(e) => warn!("{} - Failed to snapshot branches: {}", colored_session_id(&sid_for_logger), e),
+                        }
+                        record.turns += 1;
+                        record.updated_at = chrono::Utc::now();
+                        if let Err(e) = store.save(&record).await {
+                            warn!("{} - Failed to persist session: {}", colored_session_id(&sid_for_logger), e);
+                        }
+                    }
+                    Err(e) => {
+                        warn!("{} - Failed to snapshot trace: {}", colored_session_id(&sid_for_logger), e);
+                    }
+                }
             }
         });
 
@@ -84,6 +180,10 @@ impl SessionManager {
                     error!("{} - Agent execution error: {}", colored_session_id(&sid_for_cleanup), e);
                 }
             }
+            match workspace {
+                Some(workspace) if restorable => workspace.keep(),
+                workspace => drop(workspace),
+            }
             sessions_for_cleanup.lock().await.remove(&sid_for_cleanup);
             info!("{} - Session removed from manager", colored_session_id(&sid_for_cleanup));
         });
@@ -96,69 +196,149 @@ impl SessionManager {
             agent_task,
             agent_name,
             ephemeral,
+            workspace_root,
         ));
 
         Ok(session)
     }
 
     /// Get an existing session by ID
+    /// Sessions that are not loaded are rehydrated from the store if present,
+    /// as long as they were created without client tools
     /// Returns error if session doesn't exist
     pub async fn get_session(
         &self,
         http_request_id: &str,
         session_id: &str,
     ) -> Result<Arc<AgentSession>, AgentError> {
-        let sessions = self.sessions.lock().await;
-
-        if let Some(session) = sessions.get(session_id) {
-            info!("[{}] - {} Using existing session", http_request_id, colored_session_id(&session_id));
-            Ok(session.clone())
-        } else {
-            Err(AgentError::ExecutionError(format!(
-                "Session not found: {}",
-                session_id
-            )))
-        }
+        let session = self.get_or_restore_session(http_request_id, session_id, |specs| async move {
+            match specs.is_empty() {
+                true => Ok(SessionTools::default()),
+                false => Err("the session has client tools, it must be resumed through the API that created it".to_string()),
+            }
+        }).await?;
+
+        session.ok_or_else(|| AgentError::ExecutionError(format!(
+            "Session not found: {}",
+            session_id
+        )))
     }
 
-    /// Create a new session with the given ID
-    /// Returns error if session already exists
-    pub async fn create_new_session(
+    /// Get an existing session by ID, rehydrating it from the store if present
+    /// `restore_tools` rebuilds the client tools of a restored session from the specs saved in its record
+    /// Returns None if the session doesn't exist
+    pub async fn get_or_restore_session<F, Fut>(
         &self,
         http_request_id: &str,
         session_id: &str,
-        agent_name: Option<String>,
-        ephemeral: bool,
-    ) -> Result<Arc<AgentSession>, AgentError> {
-        let mut sessions = self.sessions.lock().await;
+        restore_tools: F,
+    ) -> Result<Option<Arc<AgentSession>>, AgentError>
+    where
+        F: FnOnce(Vec<serde_json::Value>) -> Fut,
+        Fut: Future<Output = Result<SessionTools, String>>,
+    {
+        let reservation = {
+            let sessions = self.sessions.lock().await;
+            if let Some(session) = sessions.get(session_id) {
+                info!("[{}] - {} Using existing session", http_request_id, colored_session_id(&session_id));
+                return Ok(Some(session.clone()));
+            }
+            self.reserve(&sessions, session_id)?
+        };
+
+        let record = match &self.store {
+            Some(store) => store.load(session_id).await
+                .map_err(|e| AgentError::ExecutionError(format!("Failed to load session {}: {}", session_id, e)))?,
+            None => None,
+        };
+
+        let Some(mut record) = record else {
+            return Ok(None);
+        };
+
+        info!("[{}] - {} Restoring session from store ({} messages)", http_request_id, colored_session_id(&session_id), record.trace.len());
+        let tools = restore_tools(std::mem::take(&mut record.tools))
+            .await
+            .map_err(|e| AgentError::ExecutionError(format!("Failed to restore tools of session {}: {}", session_id, e)))?;
+        let agent_name = record.agent_name.clone();
+        let session = self.create_session(&http_request_id.to_string(), session_id, agent_name, false, Some(record), tools).await?;
+        self.sessions.lock().await.insert(session_id.to_string(), session.clone());
+        drop(reservation);
+
+        Ok(Some(session))
+    }
 
-        // Check if session already exists
-        if sessions.contains_key(session_id) {
+    /// Reserve a session id while its agent is built outside of the sessions lock
+    fn reserve(&self, sessions: &HashMap<String, Arc<AgentSession>>, session_id: &str) -> Result<Reservation, AgentError> {
+        let mut starting = self.starting.lock().unwrap();
+        if starting.contains(session_id) {
             return Err(AgentError::ExecutionError(format!(
-                "Session already exists: {}",
+                "Session is being created: {}",
                 session_id
             )));
         }
+        self.check_max_sessions(sessions.len() + starting.len())?;
+        starting.insert(session_id.to_string());
+        Ok(Reservation { starting: self.starting.clone(), session_id: session_id.to_string() })
+    }
 
-        // Check max sessions limit
+    fn check_max_sessions(&self, count: usize) -> Result<(), AgentError> {
         if let Some(max) = self.max_sessions {
-            if sessions.len() >= max {
+            if count >= max {
                 return Err(AgentError::ExecutionError(format!(
                     "Maximum number of sessions reached: {}",
                     max
                 )));
             }
         }
+        Ok(())
+    }
 
-         // Check max sessions limit
+    /// Create a new session with the given ID
+    /// Returns error if session already exists
+    pub async fn create_new_session(
+        &self,
+        http_request_id: &str,
+        session_id: &str,
+        agent_name: Option<String>,
+        ephemeral: bool,
+    ) -> Result<Arc<AgentSession>, AgentError> {
+        self.create_new_session_with_tools(http_request_id, session_id, agent_name, ephemeral, SessionTools::default()).await
+    }
+
+    /// Same as `create_new_session` with a customized toolbox
+    pub async fn create_new_session_with_tools(
+        &self,
+        http_request_id: &str,
+        session_id: &str,
+        agent_name: Option<String>,
+        ephemeral: bool,
+        tools: SessionTools,
+    ) -> Result<Arc<AgentSession>, AgentError> {
         if self.ephemeral && !ephemeral {
             return Err(AgentError::ExecutionError(format!(
                 "Only Ephemeral session are authorized on this server"
             )));
         }
 
-        let session = self.create_session(&http_request_id.to_string(), session_id, agent_name, ephemeral).await?;
-        sessions.insert(session_id.to_string(), session.clone());
+        let reservation = {
+            let sessions = self.sessions.lock().await;
+
+            // Check if session already exists
+            if sessions.contains_key(session_id) {
+                return Err(AgentError::ExecutionError(format!(
+                    "Session already exists: {}",
+                    session_id
+                )));
+            }
+
+            // Reserve the id, checks max sessions limit
+            self.reserve(&sessions, session_id)?
+        };
+
+        let session = self.create_session(&http_request_id.to_string(), session_id, agent_name, ephemeral, None, tools).await?;
+        self.sessions.lock().await.insert(session_id.to_string(), session.clone());
+        drop(reservation);
 
         Ok(session)
     }
@@ -176,3 +356,97 @@ impl SessionManager {
         self.sessions.lock().await.len()
     }
 }
+
+/// Id of a session being built, released once it is inserted or its build failed
+struct Reservation {
+    starting: Arc<std::sync::Mutex<HashSet<String>>>,
+    session_id: String,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.starting.lock().unwrap().remove(&self.session_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::{JsonlSessionStore, WorkspaceCleanup, WorkspaceMode};
+    use serde_json::json;
+    use std::path::Path;
+
+    fn manager(store_dir: &Path, workspaces: &Path) -> SessionManager {
+        SessionManager::new(SessionManagerConfig {
+            workspace: WorkspaceConfig {
+                mode: WorkspaceMode::TempDir,
+                cleanup: WorkspaceCleanup::Delete,
+                base_dir: Some(workspaces.to_path_buf()),
+            },
+            store: Some(Arc::new(JsonlSessionStore::new(store_dir.to_path_buf()).unwrap())),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_restore_fails_when_workspace_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(&dir.path().join("store"), &dir.path().join("ws"));
+
+        let mut record = SessionRecord::new("s1".to_string(), None);
+        record.workspace_root = Some(dir.path().join("ws").join("s1"));
+        manager.store.as_ref().unwrap().save(&record).await.unwrap();
+
+        let error = manager.get_session("req", "s1").await.unwrap_err();
+        assert!(error.to_string().contains("no longer exists"), "{}", error);
+        assert_eq!(manager.session_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_restore_reattaches_existing_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(&dir.path().join("store"), &dir.path().join("ws"));
+
+        // the workspace was kept on disk by the previous run
+        let root = dir.path().join("ws").join("s2");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("main.py"), "print('hello')").unwrap();
+
+        let mut record = SessionRecord::new("s2".to_string(), Some("no-such-agent".to_string()));
+        record.workspace_root = Some(root.clone());
+        manager.store.as_ref().unwrap().save(&record).await.unwrap();
+
+        // the agent config is missing, the workspace is reused rather than created again
+        // and survives the failed restore
+        let error = manager.get_session("req", "s2").await.unwrap_err();
+        assert!(error.to_string().contains("Failed to create agent"), "{}", error);
+        assert!(root.join("main.py").exists());
+    }
+
+    #[tokio::test]
+    async fn test_restore_rebuilds_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(&dir.path().join("store"), &dir.path().join("ws"));
+
+        let mut record = SessionRecord::new("s3".to_string(), None);
+        record.tools = vec![json!({"type": "capability", "internet": false})];
+        manager.store.as_ref().unwrap().save(&record).await.unwrap();
+
+        // the manager does not know client tools, a plain lookup cannot restore them
+        let error = manager.get_session("req", "s3").await.unwrap_err();
+        assert!(error.to_string().contains("client tools"), "{}", error);
+
+        let error = manager
+            .get_or_restore_session("req", "s3", |specs| async move {
+                assert_eq!(specs, vec![json!({"type": "capability", "internet": false})]);
+                Err("missing api key".to_string())
+            })
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Failed to restore tools of session s3: missing api key"), "{}", error);
+
+        // failed restores release the id
+        assert!(manager.starting.lock().unwrap().is_empty());
+        assert!(manager.get_or_restore_session("req", "unknown", |_| async { Ok(SessionTools::default()) }).await.unwrap().is_none());
+    }
+}
@@ -2,9 +2,13 @@ mod lifecycle;
 mod session;
 mod manager;
 mod logger;
+mod workspace;
+mod store;
 
 pub use logger::log_event;
 pub use lifecycle::{RequestLifecycle};
 pub use session::{AgentSession, RequestSession};
-pub use manager::{SessionManager, SessionManagerConfig};
+pub use manager::{SessionManager, SessionManagerConfig, SessionTools};
+pub use store::{JsonlSessionStore, SessionRecord, SessionStore};
+pub use workspace::{Workspace, WorkspaceCleanup, WorkspaceConfig, WorkspaceMode};
 
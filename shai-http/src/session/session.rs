@@ -1,5 +1,6 @@
 use shai_core::agent::{AgentController, AgentError, AgentEvent};
 use openai_dive::v1::resources::chat::ChatMessage;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{broadcast::Receiver, Mutex};
 use tokio::task::JoinHandle;
@@ -30,6 +31,8 @@ pub struct AgentSession {
     pub session_id: String,
     pub agent_name: String,
     pub ephemeral: bool,
+    /// Isolated working directory of this session (None when sharing the server directory)
+    pub workspace_root: Option<PathBuf>,
 }
 
 impl AgentSession {
@@ -41,6 +44,7 @@ impl AgentSession {
         logging_task: JoinHandle<()>,
         agent_name: Option<String>,
         ephemeral: bool,
+        workspace_root: Option<PathBuf>,
     ) -> Self {
         let agent_name_display = agent_name.unwrap_or_else(|| "default".to_string());
 
@@ -52,6 +56,7 @@ impl AgentSession {
             session_id,
             agent_name: agent_name_display,
             ephemeral: ephemeral,
+            workspace_root,
         }
     }
 
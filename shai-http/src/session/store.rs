@@ -0,0 +1,247 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use openai_dive::v1::resources::chat::ChatMessage;
+use serde::{Deserialize, Serialize};
+use shai_core::agent::Branches;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything needed to bring a session back after a server restart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub session_id: String,
+    pub agent_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Number of completed turns
+    pub turns: u32,
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub trace: Vec<ChatMessage>,
+    /// Other branches of the conversation, `trace` is the one of the current branch
+    #[serde(default)]
+    pub branches: Option<Branches>,
+    /// Workspace the session runs in, None in shared mode
+    #[serde(default)]
+    pub workspace_root: Option<PathBuf>,
+    /// Tool specs sent by the client, the tools are rebuilt from them on restore
+    #[serde(default)]
+    pub tools: Vec<serde_json::Value>,
+}
+
+impl SessionRecord {
+    pub fn new(session_id: String, agent_name: Option<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            session_id,
+            agent_name,
+            created_at: now,
+            updated_at: now,
+            turns: 0,
+            metadata: HashMap::new(),
+            trace: vec![],
+            branches: None,
+            workspace_root: None,
+            tools: vec![],
+        }
+    }
+}
+
+/// Durable storage for sessions, written after each turn and read when
+/// a session id is referenced that is not loaded in memory
+#[async_trait]
+pub trait SessionStore: Send + Sync + std::fmt::Debug {
+    /// Insert or replace the record of a session
+    async fn save(&self, record: &SessionRecord) -> io::Result<()>;
+
+    /// Load a session record, None if the session was never stored
+    async fn load(&self, session_id: &str) -> io::Result<Option<SessionRecord>>;
+
+    /// List stored session ids
+    async fn list(&self) -> io::Result<Vec<String>>;
+}
+
+/// Header line of a session file, followed by one line per trace message
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonlHeader {
+    session_id: String,
+    agent_name: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    turns: u32,
+    #[serde(default)]
+    metadata: HashMap<String, serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    branches: Option<Branches>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    workspace_root: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<serde_json::Value>,
+}
+
+/// Session store keeping one JSON-lines file per session in a directory
+#[derive(Debug, Clone)]
+pub struct JsonlSessionStore {
+    dir: PathBuf,
+}
+
+impl JsonlSessionStore {
+    pub fn new(dir: PathBuf) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, session_id: &str) -> PathBuf {
+        let name: String = session_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.jsonl", name))
+    }
+}
+
+#[async_trait]
+impl SessionStore for JsonlSessionStore {
+    async fn save(&self, record: &SessionRecord) -> io::Result<()> {
+        let header = JsonlHeader {
+            session_id: record.session_id.clone(),
+            agent_name: record.agent_name.clone(),
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            turns: record.turns,
+            metadata: record.metadata.clone(),
+            branches: record.branches.clone(),
+            workspace_root: record.workspace_root.clone(),
+            tools: record.tools.clone(),
+        };
+
+        let mut content = serde_json::to_string(&header).map_err(io::Error::other)?;
+        content.push('\n');
+        for message in &record.trace {
+            content.push_str(&serde_json::to_string(message).map_err(io::Error::other)?);
+            content.push('\n');
+        }
+
+        // write then rename so that a crash never leaves a truncated session
+        let path = self.path(&record.session_id);
+        let tmp = path.with_extension("jsonl.tmp");
+        tokio::fs::write(&tmp, content).await?;
+        tokio::fs::rename(&tmp, &path).await
+    }
+
+    async fn load(&self, session_id: &str) -> io::Result<Option<SessionRecord>> {
+        let content = match tokio::fs::read_to_string(self.path(session_id)).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+        let header: JsonlHeader = match lines.next() {
+            Some(line) => serde_json::from_str(line).map_err(io::Error::other)?,
+            None => return Ok(None),
+        };
+
+        // distinct ids may map to the same file name
+        if header.session_id != session_id {
+            return Ok(None);
+        }
+
+        let trace = lines
+            .map(|line| serde_json::from_str(line).map_err(io::Error::other))
+            .collect::<io::Result<Vec<ChatMessage>>>()?;
+
+        Ok(Some(SessionRecord {
+            session_id: header.session_id,
+            agent_name: header.agent_name,
+            created_at: header.created_at,
+            updated_at: header.updated_at,
+            turns: header.turns,
+            metadata: header.metadata,
+            trace,
+            branches: header.branches,
+            workspace_root: header.workspace_root,
+            tools: header.tools,
+        }))
+    }
+
+    async fn list(&self) -> io::Result<Vec<String>> {
+        let mut ids = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().map_or(true, |ext| ext != "jsonl") {
+                continue;
+            }
+            // the real id lives in the header, file names are sanitized
+            let content = tokio::fs::read_to_string(&path).await?;
+            if let Some(line) = content.lines().next() {
+                if let Ok(header) = serde_json::from_str::<JsonlHeader>(line) {
+                    ids.push(header.session_id);
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openai_dive::v1::resources::chat::ChatMessageContent;
+    use serde_json::json;
+
+    fn user(text: &str) -> ChatMessage {
+        ChatMessage::User { content: ChatMessageContent::Text(text.to_string()), name: None }
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JsonlSessionStore::new(dir.path().to_path_buf()).unwrap();
+
+        let mut record = SessionRecord::new("session/1".to_string(), Some("coder".to_string()));
+        record.turns = 2;
+        record.metadata.insert("user".to_string(), json!("alice"));
+        record.trace = vec![user("hello"), user("multi\nline")];
+        record.workspace_root = Some(dir.path().join("ws"));
+        record.tools = vec![json!({"type": "capability", "internet": false})];
+        store.save(&record).await.unwrap();
+
+        let loaded = store.load("session/1").await.unwrap().unwrap();
+        assert_eq!(loaded.session_id, "session/1");
+        assert_eq!(loaded.agent_name.as_deref(), Some("coder"));
+        assert_eq!(loaded.turns, 2);
+        assert_eq!(loaded.metadata, record.metadata);
+        assert_eq!(serde_json::to_value(&loaded.trace).unwrap(), serde_json::to_value(&record.trace).unwrap());
+        assert_eq!(loaded.workspace_root, record.workspace_root);
+        assert_eq!(loaded.tools, record.tools);
+        assert_eq!(store.list().await.unwrap(), vec!["session/1".to_string()]);
+
+        // the sanitized file name is shared, the header tells them apart
+        assert!(store.load("session_1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_reads_records_without_workspace_nor_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JsonlSessionStore::new(dir.path().to_path_buf()).unwrap();
+        let header = json!({
+            "session_id": "old",
+            "agent_name": null,
+            "created_at": "2026-01-01T00:00:00Z",
+            "updated_at": "2026-01-01T00:00:00Z",
+            "turns": 1
+        });
+        let message = serde_json::to_string(&user("hi")).unwrap();
+        std::fs::write(dir.path().join("old.jsonl"), format!("{}\n{}\n", header, message)).unwrap();
+
+        let loaded = store.load("old").await.unwrap().unwrap();
+        assert_eq!(loaded.trace.len(), 1);
+        assert!(loaded.workspace_root.is_none());
+        assert!(loaded.tools.is_empty());
+    }
+}
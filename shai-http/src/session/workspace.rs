@@ -0,0 +1,371 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+use crate::session::logger::colored_session_id;
+
+/// How the working directory of a new session is provisioned
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorkspaceMode {
+    /// All sessions share the server process working directory
+    Shared,
+    /// Each session gets a fresh empty directory
+    TempDir,
+    /// Each session gets a copy of the given directory
+    Template(PathBuf),
+    /// Each session gets its own git worktree of the given repository
+    GitWorktree { repo: PathBuf, base_ref: Option<String> },
+}
+
+impl FromStr for WorkspaceMode {
+    type Err = String;
+
+    /// Parse `shared`, `temp`, `template:<path>`, `worktree` or `worktree:<repo>[@ref]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = match s.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (s, None),
+        };
+
+        match (kind, arg) {
+            ("shared", None) => Ok(WorkspaceMode::Shared),
+            ("temp", None) => Ok(WorkspaceMode::TempDir),
+            ("template", Some(path)) if !path.is_empty() => Ok(WorkspaceMode::Template(PathBuf::from(path))),
+            ("worktree", None) => Ok(WorkspaceMode::GitWorktree { repo: PathBuf::from("."), base_ref: None }),
+            ("worktree", Some(arg)) if !arg.is_empty() => {
+                let (repo, base_ref) = match arg.rsplit_once('@') {
+                    Some((repo, base_ref)) => (repo, Some(base_ref.to_string())),
+                    None => (arg, None),
+                };
+                Ok(WorkspaceMode::GitWorktree { repo: PathBuf::from(repo), base_ref })
+            }
+            _ => Err(format!(
+                "invalid workspace mode '{}' (expected shared, temp, template:<path>, worktree or worktree:<repo>[@ref])",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for WorkspaceMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorkspaceMode::Shared => write!(f, "shared"),
+            WorkspaceMode::TempDir => write!(f, "temp"),
+            WorkspaceMode::Template(path) => write!(f, "template:{}", path.display()),
+            WorkspaceMode::GitWorktree { repo, base_ref: None } => write!(f, "worktree:{}", repo.display()),
+            WorkspaceMode::GitWorktree { repo, base_ref: Some(base_ref) } => write!(f, "worktree:{}@{}", repo.display(), base_ref),
+        }
+    }
+}
+
+/// What happens to a session workspace once the session ends
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorkspaceCleanup {
+    /// Remove the directory (and the worktree branch)
+    Delete,
+    /// Leave everything on disk for inspection
+    Keep,
+}
+
+/// Workspace configuration for the session manager
+#[derive(Clone, Debug)]
+pub struct WorkspaceConfig {
+    pub mode: WorkspaceMode,
+    pub cleanup: WorkspaceCleanup,
+    /// Where session workspaces are created (defaults to <tmp>/shai-workspaces)
+    pub base_dir: Option<PathBuf>,
+}
+
+impl Default for WorkspaceConfig {
+    fn default() -> Self {
+        Self {
+            mode: WorkspaceMode::Shared,
+            cleanup: WorkspaceCleanup::Delete,
+            base_dir: None,
+        }
+    }
+}
+
+impl WorkspaceConfig {
+    fn base_dir(&self) -> PathBuf {
+        self.base_dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("shai-workspaces"))
+    }
+}
+
+/// An isolated working directory owned by a single session
+/// The directory is released according to the cleanup policy when dropped
+#[derive(Debug)]
+pub struct Workspace {
+    pub root: PathBuf,
+    session_id: String,
+    cleanup: WorkspaceCleanup,
+    worktree: Option<(PathBuf, String)>,
+}
+
+impl Workspace {
+    /// Provision the workspace for a session, returns None in shared mode
+    pub async fn create(config: &WorkspaceConfig, session_id: &str) -> Result<Option<Self>, String> {
+        if config.mode == WorkspaceMode::Shared {
+            return Ok(None);
+        }
+
+        let base_dir = config.base_dir();
+        tokio::fs::create_dir_all(&base_dir)
+            .await
+            .map_err(|e| format!("failed to create workspace directory {}: {}", base_dir.display(), e))?;
+
+        let root = base_dir.join(sanitize(session_id));
+        if root.exists() {
+            return Err(format!("workspace {} already exists", root.display()));
+        }
+
+        let mut workspace = Workspace {
+            root: root.clone(),
+            session_id: session_id.to_string(),
+            cleanup: config.cleanup,
+            worktree: None,
+        };
+
+        match &config.mode {
+            WorkspaceMode::Shared => unreachable!(),
+            WorkspaceMode::TempDir => {
+                tokio::fs::create_dir(&root)
+                    .await
+                    .map_err(|e| format!("failed to create workspace {}: {}", root.display(), e))?;
+            }
+            WorkspaceMode::Template(template) => {
+                let template = template.clone();
+                let dest = root.clone();
+                tokio::task::spawn_blocking(move || copy_dir(&template, &dest))
+                    .await
+                    .map_err(|e| format!("workspace copy task failed: {}", e))?
+                    .map_err(|e| format!("failed to copy template into {}: {}", root.display(), e))?;
+            }
+            WorkspaceMode::GitWorktree { repo, base_ref } => {
+                let branch = format!("shai/{}", sanitize(session_id));
+                let mut cmd = tokio::process::Command::new("git");
+                cmd.arg("-C").arg(repo)
+                    .args(["worktree", "add", "-b", &branch])
+                    .arg(&root);
+                if let Some(base_ref) = base_ref {
+                    cmd.arg(base_ref);
+                }
+                let output = cmd.output()
+                    .await
+                    .map_err(|e| format!("failed to run git worktree: {}", e))?;
+                if !output.status.success() {
+                    return Err(format!("git worktree add failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+                }
+                workspace.worktree = Some((repo.clone(), branch));
+            }
+        }
+
+        // canonical path so that tools and prompt show a stable absolute location
+        workspace.root = std::fs::canonicalize(&root).unwrap_or(root);
+        info!("{} - Workspace ready at {}", colored_session_id(session_id), workspace.root.display());
+        Ok(Some(workspace))
+    }
+
+    /// Reattach a restored session to the workspace it was created with
+    pub fn attach(config: &WorkspaceConfig, session_id: &str, root: PathBuf) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err(format!("workspace {} of the session no longer exists", root.display()));
+        }
+
+        let worktree = match &config.mode {
+            WorkspaceMode::GitWorktree { repo, .. } => Some((repo.clone(), format!("shai/{}", sanitize(session_id)))),
+            _ => None,
+        };
+        info!("{} - Workspace reattached at {}", colored_session_id(session_id), root.display());
+        Ok(Workspace {
+            root,
+            session_id: session_id.to_string(),
+            cleanup: config.cleanup,
+            worktree,
+        })
+    }
+
+    /// Release the workspace but leave it on disk whatever the cleanup policy
+    pub fn keep(mut self) {
+        self.cleanup = WorkspaceCleanup::Keep;
+    }
+
+    fn release(&mut self) {
+        if self.cleanup == WorkspaceCleanup::Keep {
+            info!("{} - Workspace kept at {}", colored_session_id(&self.session_id), self.root.display());
+            return;
+        }
+
+        let session_id = std::mem::take(&mut self.session_id);
+        let root = std::mem::take(&mut self.root);
+        let worktree = self.worktree.take();
+        // git and the recursive removal block, keep them off the runtime workers
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(move || remove_workspace(&session_id, &root, worktree));
+            }
+            Err(_) => remove_workspace(&session_id, &root, worktree),
+        }
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Remove a workspace directory, along with its worktree and branch
+fn remove_workspace(session_id: &str, root: &Path, worktree: Option<(PathBuf, String)>) {
+    if let Some((repo, branch)) = worktree {
+        let removed = Command::new("git")
+            .arg("-C").arg(&repo)
+            .args(["worktree", "remove", "--force"])
+            .arg(root)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+        if !removed {
+            warn!("{} - Failed to remove worktree {}", colored_session_id(session_id), root.display());
+        }
+        let _ = Command::new("git")
+            .arg("-C").arg(&repo)
+            .args(["branch", "-D", &branch])
+            .output();
+    }
+
+    if root.exists() {
+        if let Err(e) = std::fs::remove_dir_all(root) {
+            warn!("{} - Failed to remove workspace {}: {}", colored_session_id(session_id), root.display(), e);
+            return;
+        }
+    }
+    info!("{} - Workspace removed", colored_session_id(session_id));
+}
+
+/// Keep session ids usable as a directory and branch name
+fn sanitize(session_id: &str) -> String {
+    session_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Recursively copy a directory, symlinks are recreated rather than followed
+fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = dst.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            let link = std::fs::read_link(entry.path())?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(link, &target)?;
+            #[cfg(not(unix))]
+            std::fs::copy(entry.path(), &target).map(|_| ())?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn config(mode: WorkspaceMode, cleanup: WorkspaceCleanup, base_dir: &Path) -> WorkspaceConfig {
+        WorkspaceConfig { mode, cleanup, base_dir: Some(base_dir.to_path_buf()) }
+    }
+
+    /// Removal runs in the background once the workspace is dropped
+    async fn wait_removed(root: &Path) {
+        for _ in 0..100 {
+            if !root.exists() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("workspace {} was not removed", root.display());
+    }
+
+    #[test]
+    fn test_parse_workspace_mode() {
+        let parse = |spec: &str| spec.parse::<WorkspaceMode>();
+        assert_eq!(parse("shared"), Ok(WorkspaceMode::Shared));
+        assert_eq!(parse("temp"), Ok(WorkspaceMode::TempDir));
+        assert_eq!(parse("template:/srv/app"), Ok(WorkspaceMode::Template(PathBuf::from("/srv/app"))));
+        assert_eq!(parse("worktree"), Ok(WorkspaceMode::GitWorktree { repo: PathBuf::from("."), base_ref: None }));
+        assert_eq!(
+            parse("worktree:/srv/repo@main"),
+            Ok(WorkspaceMode::GitWorktree { repo: PathBuf::from("/srv/repo"), base_ref: Some("main".to_string()) })
+        );
+
+        for invalid in ["", "temp:x", "template", "template:", "worktree:", "docker"] {
+            assert!(parse(invalid).is_err(), "{} should be rejected", invalid);
+        }
+
+        for spec in ["shared", "temp", "template:/srv/app", "worktree:/srv/repo", "worktree:/srv/repo@main"] {
+            assert_eq!(parse(spec).unwrap().to_string(), spec);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shared_mode_has_no_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(WorkspaceMode::Shared, WorkspaceCleanup::Delete, dir.path());
+        assert!(Workspace::create(&config, "s1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_temp_workspace_removed_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(WorkspaceMode::TempDir, WorkspaceCleanup::Delete, dir.path());
+
+        let workspace = Workspace::create(&config, "a/b").await.unwrap().unwrap();
+        let root = workspace.root.clone();
+        assert!(root.is_dir());
+        assert_eq!(root.file_name().unwrap(), "a_b");
+        assert!(Workspace::create(&config, "a/b").await.unwrap_err().contains("already exists"));
+
+        drop(workspace);
+        wait_removed(&root).await;
+    }
+
+    #[tokio::test]
+    async fn test_template_workspace_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("template");
+        std::fs::create_dir_all(template.join("src")).unwrap();
+        std::fs::write(template.join("src").join("main.py"), "print('hello')").unwrap();
+        let config = config(WorkspaceMode::Template(template), WorkspaceCleanup::Keep, &dir.path().join("ws"));
+
+        let workspace = Workspace::create(&config, "s1").await.unwrap().unwrap();
+        let root = workspace.root.clone();
+        assert_eq!(std::fs::read_to_string(root.join("src").join("main.py")).unwrap(), "print('hello')");
+
+        drop(workspace);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(root.join("src").join("main.py").exists());
+    }
+
+    #[test]
+    fn test_drop_outside_runtime_removes_inline() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("s1");
+        std::fs::create_dir(&root).unwrap();
+
+        let config = config(WorkspaceMode::TempDir, WorkspaceCleanup::Delete, dir.path());
+        drop(Workspace::attach(&config, "s1", root.clone()).unwrap());
+        assert!(!root.exists());
+    }
+}
@@ -3,10 +3,11 @@ use axum::response::sse::Event;
 use futures::stream::{Stream, StreamExt};
 use serde::Serialize;
 use shai_core::agent::{AgentEvent, PublicAgentState};
+use std::collections::VecDeque;
 use std::convert::Infallible;
 use tokio::sync::broadcast::Receiver;
-use tokio_stream::wrappers::BroadcastStream;
-use tracing::error;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tracing::{error, warn};
 
 use crate::session::RequestSession;
 
@@ -23,8 +24,18 @@ pub trait EventFormatter: Send {
         session_id: &str,
     ) -> Option<Self::Output>;
 
+    /// Convert an AgentEvent to zero or more outputs
+    /// Override for APIs that emit several SSE events per agent event
+    async fn format_events(
+        &mut self,
+        event: AgentEvent,
+        session_id: &str,
+    ) -> Vec<Self::Output> {
+        self.format_event(event, session_id).await.into_iter().collect()
+    }
+
     /// Get the SSE event name for this output
-    /// Default is "message"
+    /// Default is "message", which is sent without an explicit `event:` field
     fn event_name(&self, _output: &Self::Output) -> &str {
         "message"
     }
@@ -43,44 +54,45 @@ where
     L: Send + 'static,
 {
     futures::stream::unfold(
-        (BroadcastStream::new(event_rx), formatter, false, lifecycle),
+        (BroadcastStream::new(event_rx), formatter, false, VecDeque::new(), lifecycle),
         move |state| {
             let session_id = session_id.clone();
             async move {
-                let (mut rx, mut fmt, done, lifecycle) = state;
-
-                if done {
-                    return None;
-                }
+                let (mut rx, mut fmt, mut done, mut pending, lifecycle) = state;
 
                 loop {
-                    match rx.next().await {
-                        Some(Ok(event)) => {
-                            let is_terminal = is_terminal_event(&event, stop_on_pause);
-                            let formatted = fmt.format_event(event, &session_id).await;
-                            let new_done = if is_terminal { true } else { done };
-
-                            if let Some(output) = formatted {
-                                match serde_json::to_string(&output) {
-                                    Ok(json) => {
-                                        let sse_event = Event::default().data(json);
-                                        return Some((Ok(sse_event), (rx, fmt, new_done, lifecycle)));
-                                    }
-                                    Err(e) => {
-                                        error!("[{}] Failed to serialize event: {}", session_id, e);
-                                        continue;
-                                    }
-                                }
-                            } else {
-                                if new_done {
-                                    return None;
+                    // Flush outputs already produced before looking at new events
+                    if let Some(output) = pending.pop_front() {
+                        match serde_json::to_string(&output) {
+                            Ok(json) => {
+                                let mut sse_event = Event::default().data(json);
+                                let name = fmt.event_name(&output);
+                                if name != "message" {
+                                    sse_event = sse_event.event(name);
                                 }
+                                return Some((Ok(sse_event), (rx, fmt, done, pending, lifecycle)));
+                            }
+                            Err(e) => {
+                                error!("[{}] Failed to serialize event: {}", session_id, e);
                                 continue;
                             }
                         }
-                        Some(Err(e)) => {
-                            error!("[{}] Error receiving event: {}", session_id, e);
-                            return None;
+                    }
+
+                    if done {
+                        return None;
+                    }
+
+                    match rx.next().await {
+                        Some(Ok(event)) => {
+                            if is_terminal_event(&event, stop_on_pause) {
+                                done = true;
+                            }
+                            pending.extend(fmt.format_events(event, &session_id).await);
+                        }
+                        Some(Err(BroadcastStreamRecvError::Lagged(skipped))) => {
+                            // missed events are lost, the ones still buffered are worth sending
+                            warn!("[{}] Stream lagged behind, {} events skipped", session_id, skipped);
                         }
                         None => {
                             return None;
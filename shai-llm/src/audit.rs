@@ -0,0 +1,571 @@
+// Opt-in audit log of the raw requests sent to the providers and of what they answered,
+// one JSON record per call appended to a rotating JSONL file
+use crate::chat::JsonHooks;
+use crate::provider::{LlmError, LlmStream};
+use async_trait::async_trait;
+use futures::StreamExt;
+use openai_dive::v1::error::APIError;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Instant;
+
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_FILES: usize = 5;
+const REDACTED: &str = "[REDACTED]";
+
+/// Object keys whose string value is never written
+const SENSITIVE_KEYS: &[&str] = &["api_key", "apikey", "api-key", "x-api-key", "x-goog-api-key", "authorization", "access_token", "password", "secret"];
+
+tokio::task_local! {
+    static SESSION: String;
+}
+
+/// Where the audit log is written and when it rotates
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditConfig {
+    pub file_path: PathBuf,
+    /// size from which the file is rotated
+    pub max_bytes: u64,
+    /// rotated files kept, `<file>.1` being the most recent
+    pub max_files: usize,
+}
+
+impl AuditConfig {
+    pub fn new<P: Into<PathBuf>>(file_path: P) -> Self {
+        Self {
+            file_path: file_path.into(),
+            max_bytes: DEFAULT_MAX_BYTES,
+            max_files: DEFAULT_MAX_FILES,
+        }
+    }
+
+    /// Config from SHAI_LLM_AUDIT_FILE, SHAI_LLM_AUDIT_MAX_BYTES and SHAI_LLM_AUDIT_MAX_FILES
+    /// Returns None if the audit file is not set
+    pub fn from_env() -> Option<Self> {
+        let file_path = std::env::var("SHAI_LLM_AUDIT_FILE").ok().filter(|p| !p.is_empty())?;
+        let mut config = Self::new(file_path);
+        if let Some(max_bytes) = std::env::var("SHAI_LLM_AUDIT_MAX_BYTES").ok().and_then(|v| v.parse().ok()) {
+            config.max_bytes = max_bytes;
+        }
+        if let Some(max_files) = std::env::var("SHAI_LLM_AUDIT_MAX_FILES").ok().and_then(|v| v.parse().ok()) {
+            config.max_files = max_files;
+        }
+        Some(config)
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+}
+
+fn global() -> &'static RwLock<Option<Arc<AuditLog>>> {
+    static AUDIT: OnceLock<RwLock<Option<Arc<AuditLog>>>> = OnceLock::new();
+    AUDIT.get_or_init(|| RwLock::new(AuditConfig::from_env().map(|config| Arc::new(AuditLog::new(config)))))
+}
+
+/// Audit every call from now on, replacing the config read from the environment
+pub fn init(config: AuditConfig) {
+    *global().write().unwrap() = Some(Arc::new(AuditLog::new(config)));
+}
+
+/// Stop auditing
+pub fn disable() {
+    *global().write().unwrap() = None;
+}
+
+/// The audit log, if auditing is enabled
+pub fn current() -> Option<Arc<AuditLog>> {
+    global().read().unwrap().clone()
+}
+
+/// Start auditing a call to `url`, if auditing is enabled
+pub fn call(url: &str) -> Option<AuditCall> {
+    current().map(|log| AuditCall::new(log, url))
+}
+
+/// Run `f`, the calls it makes are recorded with this session id
+pub async fn scope_session<F: Future>(session_id: String, f: F) -> F::Output {
+    SESSION.scope(session_id, f).await
+}
+
+/// Session of the running task, if any
+pub fn current_session() -> Option<String> {
+    SESSION.try_with(|session| session.clone()).ok()
+}
+
+/// Rotating JSONL file the records are appended to
+#[derive(Debug)]
+pub struct AuditLog {
+    config: AuditConfig,
+    /// serializes writes and rotations
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new(config: AuditConfig) -> Self {
+        Self { config, lock: Mutex::new(()) }
+    }
+
+    pub fn config(&self) -> &AuditConfig {
+        &self.config
+    }
+
+    /// Append a record, rotating the file first if it would grow past its max size
+    pub fn write(&self, record: &Value) -> std::io::Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let path = &self.config.file_path;
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.config.max_bytes {
+            self.rotate()?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(line.as_bytes())
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.config.file_path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+
+    fn rotate(&self) -> std::io::Result<()> {
+        let path = &self.config.file_path;
+        if self.config.max_files == 0 {
+            return std::fs::remove_file(path);
+        }
+        let _ = std::fs::remove_file(self.rotated(self.config.max_files));
+        for n in (1..self.config.max_files).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                std::fs::rename(from, self.rotated(n + 1))?;
+            }
+        }
+        std::fs::rename(path, self.rotated(1))
+    }
+}
+
+#[derive(Default)]
+struct CallState {
+    request: Option<Value>,
+    response: Option<Value>,
+    chunks: Vec<Value>,
+    first_chunk_ms: Option<u128>,
+    error: Option<String>,
+}
+
+/// One call being audited, its record is written when it is dropped, that is when
+/// the response was read or the stream ended
+pub struct AuditCall {
+    log: Arc<AuditLog>,
+    url: String,
+    session_id: Option<String>,
+    secrets: Vec<String>,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    started: Instant,
+    state: Mutex<CallState>,
+}
+
+impl AuditCall {
+    pub fn new(log: Arc<AuditLog>, url: &str) -> Self {
+        Self {
+            log,
+            url: url.to_string(),
+            session_id: current_session(),
+            secrets: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            started: Instant::now(),
+            state: Mutex::new(CallState::default()),
+        }
+    }
+
+    /// Redact this value wherever it shows up, like the api key of the provider
+    pub fn with_secret(mut self, secret: &str) -> Self {
+        if !secret.is_empty() {
+            self.secrets.push(secret.to_string());
+        }
+        self
+    }
+
+    pub fn request(&self, json: &Value) {
+        self.state.lock().unwrap().request = Some(json.clone());
+    }
+
+    pub fn response(&self, json: &Value) {
+        self.state.lock().unwrap().response = Some(json.clone());
+    }
+
+    pub fn chunk(&self, json: &Value) {
+        let mut state = self.state.lock().unwrap();
+        state.first_chunk_ms.get_or_insert(self.started.elapsed().as_millis());
+        state.chunks.push(json.clone());
+    }
+
+    pub fn error(&self, error: impl std::fmt::Display) {
+        self.state.lock().unwrap().error = Some(error.to_string());
+    }
+
+    fn record(&self) -> Value {
+        let state = self.state.lock().unwrap();
+        let streamed = !state.chunks.is_empty();
+        let response = match &state.response {
+            Some(response) => Some(response.clone()),
+            None if streamed => Some(reassemble(&state.chunks)),
+            None => None,
+        };
+
+        let mut record = json!({
+            "timestamp": self.timestamp.to_rfc3339(),
+            "session_id": self.session_id,
+            "url": self.url,
+            "model": state.request.as_ref().map(|r| r["model"].clone()),
+            "duration_ms": self.started.elapsed().as_millis() as u64,
+            "stream": streamed,
+            "usage": response.as_ref().and_then(usage),
+            "request": state.request,
+            "response": response,
+            "error": state.error,
+        });
+        if streamed {
+            record["chunks"] = json!(state.chunks.len());
+            record["first_chunk_ms"] = json!(state.first_chunk_ms.map(|ms| ms as u64));
+        }
+        redact(&mut record, &self.secrets);
+        record
+    }
+}
+
+impl Drop for AuditCall {
+    fn drop(&mut self) {
+        // auditing must never break a call
+        let _ = self.log.write(&self.record());
+    }
+}
+
+/// Token usage of a response, in the OpenAI, Anthropic or Gemini format
+fn usage(response: &Value) -> Option<Value> {
+    let usage = if response["usage"].is_object() { &response["usage"] } else { &response["usageMetadata"] };
+    let count = |fields: &[&str]| fields.iter().find_map(|f| usage[*f].as_u64());
+    let prompt_tokens = count(&["prompt_tokens", "input_tokens", "promptTokenCount"]);
+    let completion_tokens = count(&["completion_tokens", "output_tokens", "candidatesTokenCount"]);
+    if prompt_tokens.is_none() && completion_tokens.is_none() {
+        return None;
+    }
+    let total_tokens = count(&["total_tokens", "totalTokenCount"])
+        .unwrap_or(prompt_tokens.unwrap_or(0) + completion_tokens.unwrap_or(0));
+    Some(json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": total_tokens,
+    }))
+}
+
+/// The completion the chunks of a stream add up to
+pub fn reassemble(chunks: &[Value]) -> Value {
+    let mut choices: BTreeMap<u64, Value> = BTreeMap::new();
+    let mut usage = Value::Null;
+
+    for chunk in chunks {
+        if !chunk["usage"].is_null() {
+            usage = chunk["usage"].clone();
+        }
+        for choice in chunk["choices"].as_array().into_iter().flatten() {
+            let index = choice["index"].as_u64().unwrap_or(0);
+            let entry = choices.entry(index).or_insert_with(|| json!({
+                "index": index,
+                "message": {"role": "assistant"},
+                "finish_reason": null,
+            }));
+            let delta = &choice["delta"];
+            let message = &mut entry["message"];
+
+            for field in ["content", "reasoning_content"] {
+                if let Some(text) = delta[field].as_str() {
+                    let joined = format!("{}{}", message[field].as_str().unwrap_or(""), text);
+                    message[field] = json!(joined);
+                }
+            }
+
+            for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                if !message["tool_calls"].is_array() {
+                    message["tool_calls"] = json!([]);
+                }
+                let calls = message["tool_calls"].as_array_mut().unwrap();
+                let position = match calls.iter().position(|c| c["index"] == call["index"]) {
+                    Some(position) => position,
+                    None => {
+                        calls.push(json!({
+                            "index": call["index"],
+                            "type": "function",
+                            "function": {"name": "", "arguments": ""},
+                        }));
+                        calls.len() - 1
+                    }
+                };
+                let entry = &mut calls[position];
+                if let Some(id) = call["id"].as_str() {
+                    entry["id"] = json!(id);
+                }
+                for field in ["name", "arguments"] {
+                    if let Some(part) = call["function"][field].as_str() {
+                        let joined = format!("{}{}", entry["function"][field].as_str().unwrap_or(""), part);
+                        entry["function"][field] = json!(joined);
+                    }
+                }
+            }
+
+            if !choice["finish_reason"].is_null() {
+                entry["finish_reason"] = choice["finish_reason"].clone();
+            }
+        }
+    }
+
+    let first = chunks.first().cloned().unwrap_or_default();
+    json!({
+        "id": first["id"],
+        "object": "chat.completion",
+        "created": first["created"],
+        "model": first["model"],
+        "choices": choices.into_values().collect::<Vec<_>>(),
+        "usage": usage,
+    })
+}
+
+fn key_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"sk-[A-Za-z0-9_\-]{16,}|AIza[0-9A-Za-z_\-]{30,}").unwrap())
+}
+
+/// Blank the api keys found in `value`, by key name, by value and by shape
+pub fn redact(value: &mut Value, secrets: &[String]) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if value.is_string() && SENSITIVE_KEYS.contains(&key.to_lowercase().as_str()) {
+                    *value = json!(REDACTED);
+                } else {
+                    redact(value, secrets);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| redact(v, secrets)),
+        Value::String(text) => {
+            let mut redacted = text.clone();
+            for secret in secrets {
+                redacted = redacted.replace(secret.as_str(), REDACTED);
+            }
+            if let std::borrow::Cow::Owned(replaced) = key_pattern().replace_all(&redacted, REDACTED) {
+                redacted = replaced;
+            }
+            *text = redacted;
+        }
+        _ => {}
+    }
+}
+
+/// Hooks recording what goes through `inner` when the call is audited,
+/// the request as sent and the response as received
+pub struct AuditHooks<H> {
+    inner: H,
+    call: Option<AuditCall>,
+}
+
+impl<H: JsonHooks> AuditHooks<H> {
+    pub fn new(inner: H, call: Option<AuditCall>) -> Self {
+        Self { inner, call }
+    }
+}
+
+#[async_trait]
+impl<H: JsonHooks> JsonHooks for AuditHooks<H> {
+    async fn before_send(&self, json: Value) -> Result<Value, APIError> {
+        let json = self.inner.before_send(json).await?;
+        if let Some(call) = &self.call {
+            call.request(&json);
+        }
+        Ok(json)
+    }
+
+    async fn after_receive(&self, json: Value) -> Result<Value, APIError> {
+        if let Some(call) = &self.call {
+            call.response(&json);
+        }
+        self.inner.after_receive(json).await
+    }
+
+    async fn after_receive_stream(&self, json: Value) -> Result<Value, APIError> {
+        if let Some(call) = &self.call {
+            call.chunk(&json);
+        }
+        self.inner.after_receive_stream(json).await
+    }
+
+    fn on_error(&self, error: &APIError) {
+        if let Some(call) = &self.call {
+            call.error(error);
+        }
+        self.inner.on_error(error);
+    }
+}
+
+/// Record the chunks of a converted stream, for the providers not going through `ChatClient`
+pub fn audit_stream(stream: LlmStream, call: Option<AuditCall>) -> LlmStream {
+    let Some(call) = call else {
+        return stream;
+    };
+    Box::new(stream.inspect(move |result| match result {
+        Ok(chunk) => call.chunk(&serde_json::to_value(chunk).unwrap_or_default()),
+        Err(e) => call.error(e),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log(max_bytes: u64, max_files: usize) -> (PathBuf, Arc<AuditLog>) {
+        let dir = std::env::temp_dir().join(format!("shai-audit-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("audit.jsonl");
+        let config = AuditConfig::new(&path).with_max_bytes(max_bytes).with_max_files(max_files);
+        (path, Arc::new(AuditLog::new(config)))
+    }
+
+    fn read_records(path: &Path) -> Vec<Value> {
+        std::fs::read_to_string(path).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_redact() {
+        let mut value = json!({
+            "api_key": "plain",
+            "headers": {"Authorization": "Bearer abc"},
+            "max_tokens": 100,
+            "messages": [{"content": "my key is sk-abcdefghijklmnopqrstuvwx and my-secret-value"}],
+        });
+        redact(&mut value, &["my-secret-value".to_string()]);
+
+        assert_eq!(value["api_key"], REDACTED);
+        assert_eq!(value["headers"]["Authorization"], REDACTED);
+        assert_eq!(value["max_tokens"], 100);
+        assert_eq!(value["messages"][0]["content"], "my key is [REDACTED] and [REDACTED]");
+    }
+
+    #[test]
+    fn test_reassemble_stream() {
+        let chunks = vec![
+            json!({"id": "c1", "created": 1, "model": "m", "choices": [{"index": 0, "delta": {"role": "assistant", "content": "Hel"}}]}),
+            json!({"id": "c1", "created": 1, "model": "m", "choices": [{"index": 0, "delta": {"content": "lo", "tool_calls": [{"index": 0, "id": "call_1", "function": {"name": "ls", "arguments": "{\"dir"}}]}}]}),
+            json!({"id": "c1", "created": 1, "model": "m", "choices": [{"index": 0, "delta": {"tool_calls": [{"index": 0, "function": {"arguments": "\":\".\"}"}}]}, "finish_reason": "tool_calls"}]}),
+            json!({"id": "c1", "created": 1, "model": "m", "choices": [], "usage": {"prompt_tokens": 10, "completion_tokens": 5, "total_tokens": 15}}),
+        ];
+        let response = reassemble(&chunks);
+
+        let message = &response["choices"][0]["message"];
+        assert_eq!(message["content"], "Hello");
+        assert_eq!(message["tool_calls"][0]["id"], "call_1");
+        assert_eq!(message["tool_calls"][0]["function"]["arguments"], "{\"dir\":\".\"}");
+        assert_eq!(response["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(usage(&response).unwrap()["total_tokens"], 15);
+    }
+
+    #[tokio::test]
+    async fn test_call_record() {
+        let (path, log) = temp_log(DEFAULT_MAX_BYTES, DEFAULT_MAX_FILES);
+
+        scope_session("session-1".to_string(), async {
+            let call = AuditCall::new(log.clone(), "https://api.example.com/v1").with_secret("secret-key");
+            call.request(&json!({"model": "m", "messages": [{"role": "user", "content": "secret-key"}]}));
+            call.response(&json!({"choices": [], "usage": {"input_tokens": 3, "output_tokens": 4}}));
+        }).await;
+
+        let records = read_records(&path);
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record["session_id"], "session-1");
+        assert_eq!(record["model"], "m");
+        assert_eq!(record["stream"], false);
+        assert_eq!(record["usage"]["total_tokens"], 7);
+        assert_eq!(record["request"]["messages"][0]["content"], REDACTED);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_chat_client_is_audited() {
+        use crate::chat::{ChatClient, NoHooks};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1700000000,
+                "model": "gpt-4o",
+                "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}],
+                "usage": {"prompt_tokens": 9, "completion_tokens": 1, "total_tokens": 10}
+            })))
+            .mount(&server)
+            .await;
+
+        let (log_path, _) = temp_log(DEFAULT_MAX_BYTES, DEFAULT_MAX_FILES);
+        init(AuditConfig::new(&log_path));
+        let client = ChatClient::new("test-api-key".to_string(), server.uri());
+        let request = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "my key is test-api-key"}]
+        })).unwrap();
+        scope_session("session-2".to_string(), client.chat_completion(&request, &NoHooks)).await.unwrap();
+        disable();
+
+        // other tests may run calls while auditing is on
+        let records: Vec<_> = read_records(&log_path).into_iter()
+            .filter(|r| r["url"] == server.uri())
+            .collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["session_id"], "session-2");
+        assert_eq!(records[0]["usage"]["total_tokens"], 10);
+        assert_eq!(records[0]["request"]["messages"][0]["content"], "my key is [REDACTED]");
+        assert_eq!(records[0]["response"]["choices"][0]["message"]["content"], "Hi");
+
+        std::fs::remove_dir_all(log_path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_rotation() {
+        let (path, log) = temp_log(200, 2);
+        for n in 0..10 {
+            log.write(&json!({"n": n, "padding": "x".repeat(100)})).unwrap();
+        }
+
+        assert_eq!(read_records(&path).last().unwrap()["n"], 9);
+        assert_eq!(read_records(&log.rotated(1)).last().unwrap()["n"], 8);
+        assert!(log.rotated(2).exists());
+        assert!(!log.rotated(3).exists());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,6 +1,7 @@
 /// Blatant COPY / PASTE from openai_dive to add hooks for json manipulation
 /// 
 // Flexible chat client with JSON manipulation hooks
+use crate::audit::{self, AuditCall, AuditHooks};
 use async_trait::async_trait;
 use futures::{Stream, StreamExt};
 use openai_dive::v1::{
@@ -32,6 +33,9 @@ pub trait JsonHooks: Send + Sync {
         // Default: use the same logic as after_receive
         self.after_receive(json).await
     }
+
+    /// Called when the API answers with an error or the stream breaks
+    fn on_error(&self, error: &APIError) {}
 }
 
 /// Default implementation with no hooks
@@ -40,13 +44,44 @@ pub struct NoHooks;
 #[async_trait]
 impl JsonHooks for NoHooks {}
 
+#[async_trait]
+impl<H: JsonHooks + ?Sized> JsonHooks for &H {
+    async fn before_send(&self, json: Value) -> Result<Value, APIError> {
+        (**self).before_send(json).await
+    }
+
+    async fn after_receive(&self, json: Value) -> Result<Value, APIError> {
+        (**self).after_receive(json).await
+    }
+
+    async fn after_receive_stream(&self, json: Value) -> Result<Value, APIError> {
+        (**self).after_receive_stream(json).await
+    }
+
+    fn on_error(&self, error: &APIError) {
+        (**self).on_error(error)
+    }
+}
+
+/// How the api key is sent
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChatAuth {
+    /// `Authorization: Bearer <api_key>`
+    Bearer,
+    /// the key as is in the given header, like Azure `api-key`
+    Header(String),
+}
+
 /// Flexible chat client
 #[derive(Clone, Debug)]
 pub struct ChatClient {
     pub http_client: reqwest::Client,
     pub base_url: String,
     pub api_key: String,
+    pub auth: ChatAuth,
     pub headers: Option<HashMap<String, String>>,
+    /// query parameters added to every request, like Azure `api-version`
+    pub query: Vec<(String, String)>,
     pub organization: Option<String>,
     pub project: Option<String>,
 }
@@ -58,20 +93,46 @@ impl ChatClient {
             http_client: reqwest::Client::new(),
             base_url,
             api_key,
+            auth: ChatAuth::Bearer,
             headers: None,
+            query: Vec::new(),
             organization: None,
             project: None,
         }
     }
 
+    /// Send the api key in `header` instead of a bearer token
+    pub fn with_api_key_header(mut self, header: &str) -> Self {
+        self.auth = ChatAuth::Header(header.to_string());
+        self
+    }
+
+    pub fn with_query(mut self, key: &str, value: &str) -> Self {
+        self.query.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Audit record of a call to this client, if auditing is enabled
+    fn audit_call(&self) -> Option<AuditCall> {
+        audit::call(&self.base_url).map(|call| call.with_secret(&self.api_key))
+    }
+
     /// Build a request with authentication headers
     fn build_request(&self, method: Method, path: &str, content_type: &str) -> RequestBuilder {
         let url = format!("{}{}", self.base_url, path);
         let mut request = self
             .http_client
             .request(method, &url)
-            .header(reqwest::header::CONTENT_TYPE, content_type)
-            .bearer_auth(&self.api_key);
+            .header(reqwest::header::CONTENT_TYPE, content_type);
+
+        request = match &self.auth {
+            ChatAuth::Bearer => request.bearer_auth(&self.api_key),
+            ChatAuth::Header(header) => request.header(header.as_str(), &self.api_key),
+        };
+
+        if !self.query.is_empty() {
+            request = request.query(&self.query);
+        }
 
         if let Some(headers) = &self.headers {
             for (key, value) in headers {
@@ -124,6 +185,8 @@ impl ChatClient {
         parameters: &ChatCompletionParameters,
         hooks: &H,
     ) -> Result<ChatCompletionResponse, APIError> {
+        let hooks = AuditHooks::new(hooks, self.audit_call());
+
         // Serialize to JSON and apply before_send hook
         let mut json = serde_json::to_value(parameters)
             .map_err(|e| APIError::ParseError(e.to_string()))?;
@@ -136,7 +199,8 @@ impl ChatClient {
             .send()
             .await;
 
-        let response = Self::check_status_code(result).await?;
+        let response = Self::check_status_code(result).await
+            .inspect_err(|e| hooks.on_error(e))?;
 
         // Get response text and apply after_receive hook
         let response_text = response
@@ -162,6 +226,8 @@ impl ChatClient {
         parameters: &ChatCompletionParameters,
         hooks: H,
     ) -> Result<Pin<Box<dyn Stream<Item = Result<ChatCompletionChunkResponse, APIError>> + Send>>, APIError> {
+        let hooks = AuditHooks::new(hooks, self.audit_call());
+
         // Serialize to JSON and apply before_send hook
         let mut json = serde_json::to_value(parameters)
             .map_err(|e| APIError::ParseError(e.to_string()))?;
@@ -203,7 +269,11 @@ impl ChatClient {
                             Err(e) => yield Err(APIError::ParseError(e.to_string())),
                         }
                     }
-                    Err(e) => yield Err(APIError::StreamError(e.to_string())),
+                    Err(e) => {
+                        let error = APIError::StreamError(e.to_string());
+                        hooks.on_error(&error);
+                        yield Err(error);
+                    }
                 }
             }
         };
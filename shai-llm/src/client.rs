@@ -6,11 +6,15 @@ use super::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo};
 use super::providers::{
     openai::OpenAIProvider,
     openai_compatible::OpenAICompatibleProvider,
+    openai_responses::OpenAIResponsesProvider,
+    azure_openai::AzureOpenAIProvider,
     openrouter::OpenRouterProvider,
     ovhcloud::OvhCloudProvider,
     anthropic::AnthropicProvider,
+    gemini::GeminiProvider,
     ollama::OllamaProvider,
-    mistral::MistralProvider
+    mistral::MistralProvider,
+    replay::ReplayProvider
 };
 use openai_dive::v1::resources::chat::ChatCompletionParametersBuilder;
 use openai_dive::v1::resources::{
@@ -18,110 +22,147 @@ use openai_dive::v1::resources::{
     model::ListModelResponse,
 };
 use regex::Regex;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use crate::model_info::{known_model_info, ModelInfo};
+use crate::reasoning::{split_think_stream, ReasoningOptions};
+use crate::tokens::Tokenizer;
+use crate::tool::ToolBox;
+use openai_dive::v1::resources::shared::Usage;
 
 #[derive(Debug)]
 pub struct LlmClient {
     provider: Box<dyn LlmProvider>,
+    /// model info resolved so far, and the ones set by the user
+    model_infos: RwLock<HashMap<String, ModelInfo>>,
+    model_overrides: HashMap<String, ModelInfo>,
+    reasoning: ReasoningOptions,
+    /// per model correction of the heuristic token counts, learnt from the reported usage
+    token_ratios: RwLock<HashMap<String, f32>>,
+    /// when the provider last failed to describe a model
+    failed_listings: RwLock<HashMap<String, Instant>>,
 }
 
+/// A model the provider failed to describe is asked again after this long
+const MODEL_LISTING_RETRY: Duration = Duration::from_secs(300);
+
 /// Provider Factory related method
 impl LlmClient {
+    pub fn from_provider(provider: Box<dyn LlmProvider>) -> Self {
+        Self {
+            provider,
+            model_infos: RwLock::new(HashMap::new()),
+            model_overrides: HashMap::new(),
+            reasoning: ReasoningOptions::default(),
+            token_ratios: RwLock::new(HashMap::new()),
+            failed_listings: RwLock::new(HashMap::new()),
+        }
+    }
+
     /// Create an OpenAI provider from environment variables
     /// Returns None if required environment variables are not set
     pub fn from_env_openai() -> Option<Self> {
-        OpenAIProvider::from_env().map(|provider| Self {
-            provider: Box::new(provider),
-        })
+        OpenAIProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
+    }
+
+    /// Create an OpenAI Responses API provider from environment variables
+    /// Returns None if required environment variables are not set
+    pub fn from_env_openai_responses() -> Option<Self> {
+        OpenAIResponsesProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
     }
 
     /// Create an Anthropic provider from environment variables
     /// Returns None if required environment variables are not set
     pub fn from_env_anthropic() -> Option<Self> {
-        AnthropicProvider::from_env().map(|provider| Self {
-            provider: Box::new(provider),
-        })
+        AnthropicProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
+    }
+
+    /// Create a Gemini provider from environment variables
+    /// Returns None if required environment variables are not set
+    pub fn from_env_gemini() -> Option<Self> {
+        GeminiProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
     }
 
     /// Create an Ollama provider from environment variables
     /// Always returns Some since Ollama has a default base URL
     pub fn from_env_ollama() -> Option<Self> {
-        OllamaProvider::from_env().map(|provider| Self {
-            provider: Box::new(provider),
-        })
+        OllamaProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
     }
 
     /// Create an OpenRouter provider from environment variables
     /// Returns None if required environment variables are not set
     pub fn from_env_openrouter() -> Option<Self> {
-        OpenRouterProvider::from_env().map(|provider| Self {
-            provider: Box::new(provider),
-        })
+        OpenRouterProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
     }
 
     /// Create an OpenAI Compatible provider from environment variables
     /// Returns None if required environment variables are not set
     pub fn from_env_openai_compatible() -> Option<Self> {
-        OpenAICompatibleProvider::from_env().map(|provider| Self {
-            provider: Box::new(provider),
-        })
+        OpenAICompatibleProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
+    }
+
+    /// Create an Azure OpenAI provider from environment variables
+    /// Returns None if required environment variables are not set
+    pub fn from_env_azure_openai() -> Option<Self> {
+        AzureOpenAIProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
     }
 
     /// Create an OVH Cloud provider from environment variables
     /// Returns None if required environment variables are not set
     pub fn from_env_ovhcloud() -> Option<Self> {
-        OvhCloudProvider::from_env().map(|provider| Self {
-            provider: Box::new(provider),
-        })
+        OvhCloudProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
     }
 
     /// Create a Mistral provider from environment variables
     /// Returns None if required environment variables are not set
     pub fn from_env_mistral() -> Option<Self> {
-        MistralProvider::from_env().map(|provider| Self {
-            provider: Box::new(provider),
-        })
+        MistralProvider::from_env().map(|provider| Self::from_provider(Box::new(provider)))
     }
 
-    pub fn openai(api_key: String) -> Self {
-        Self {
-            provider: Box::new(OpenAIProvider::new(api_key)),
+    /// Create a replay provider from environment variables, it records the provider
+    /// named by SHAI_REPLAY_PROVIDER when set and replays SHAI_REPLAY_CASSETTE otherwise
+    /// Returns None if SHAI_REPLAY_CASSETTE is not set or cannot be read
+    pub fn from_env_replay() -> Option<Self> {
+        let cassette = std::env::var("SHAI_REPLAY_CASSETTE").ok()?;
+        if let Some(recorded) = std::env::var("SHAI_REPLAY_PROVIDER").ok().filter(|p| p != "replay") {
+            let env_values: HashMap<String, String> = std::env::vars().collect();
+            let inner = Self::create_provider(&recorded, &env_values).ok()?;
+            return Some(Self::from_provider(Box::new(ReplayProvider::record(inner.provider, cassette))));
         }
+        ReplayProvider::replay(cassette).ok().map(|provider| Self::from_provider(Box::new(provider)))
+    }
+
+    pub fn openai(api_key: String) -> Self {
+        Self::from_provider(Box::new(OpenAIProvider::new(api_key)))
     }
 
     pub fn compatible(api_key: String, base_url: String) -> Self {
-        Self {
-            provider: Box::new(OpenAICompatibleProvider::new(api_key, base_url)),
-        }
+        Self::from_provider(Box::new(OpenAICompatibleProvider::new(api_key, base_url)))
     }
 
     pub fn openrouter(api_key: String) -> Self {
-        Self {
-            provider: Box::new(OpenRouterProvider::new(api_key)),
-        }
+        Self::from_provider(Box::new(OpenRouterProvider::new(api_key)))
     }
 
     pub fn ovhcloud(api_key: String, base_url: Option<String>) -> Self {
-        Self {
-            provider: Box::new(OvhCloudProvider::new(api_key, base_url)),
-        }
+        Self::from_provider(Box::new(OvhCloudProvider::new(api_key, base_url)))
     }
 
     pub fn anthropic(api_key: String) -> Self {
-        Self {
-            provider: Box::new(AnthropicProvider::new(api_key)),
-        }
+        Self::from_provider(Box::new(AnthropicProvider::new(api_key)))
+    }
+
+    pub fn gemini(api_key: String) -> Self {
+        Self::from_provider(Box::new(GeminiProvider::new(api_key)))
     }
 
     pub fn ollama(base_url: String) -> Self {
-        Self {
-            provider: Box::new(OllamaProvider::new(Some(base_url))),
-        }
+        Self::from_provider(Box::new(OllamaProvider::new(Some(base_url))))
     }
 
     pub fn mistral(api_key: String) -> Self {
-        Self {
-            provider: Box::new(MistralProvider::new(api_key)),
-        }
+        Self::from_provider(Box::new(MistralProvider::new(api_key)))
     }
 
 
@@ -132,11 +173,15 @@ impl LlmClient {
             match provider.as_str() {
                 "ovhcloud" => return Self::from_env_ovhcloud(),
                 "openai" => return Self::from_env_openai(),
+                "openai_responses" => return Self::from_env_openai_responses(),
                 "mistral" => return Self::from_env_mistral(),
                 "anthropic" => return Self::from_env_anthropic(),
+                "gemini" => return Self::from_env_gemini(),
                 "openrouter" => return Self::from_env_openrouter(),
                 "openai_compatible" => return Self::from_env_openai_compatible(),
+                "azure_openai" => return Self::from_env_azure_openai(),
                 "ollama" => return Self::from_env_ollama(),
+                "replay" => return Self::from_env_replay(),
                 _ => {} // Fall through to default behavior
             }
         }
@@ -147,12 +192,18 @@ impl LlmClient {
         if let Some(client) = Self::from_env_openai() {
             return Some(client);
         }
+        if let Some(client) = Self::from_env_azure_openai() {
+            return Some(client);
+        }
         if let Some(client) = Self::from_env_mistral() {
             return Some(client);
         }
         if let Some(client) = Self::from_env_anthropic() {
             return Some(client);
         }
+        if let Some(client) = Self::from_env_gemini() {
+            return Some(client);
+        }
         if let Some(client) = Self::from_env_openrouter() {
             return Some(client);
         }
@@ -172,9 +223,12 @@ impl LlmClient {
             MistralProvider::info(),
             OllamaProvider::info(),
             OpenAICompatibleProvider::info(),
+            AzureOpenAIProvider::info(),
             OpenRouterProvider::info(),
             AnthropicProvider::info(),
+            GeminiProvider::info(),
             OpenAIProvider::info(),
+            OpenAIResponsesProvider::info(),
         ]
     }
 
@@ -186,16 +240,46 @@ impl LlmClient {
                     .ok_or("OPENAI_API_KEY not found")?;
                 Ok(Self::openai(api_key.clone()))
             },
+            "openai_responses" => {
+                let api_key = env_values.get("OPENAI_API_KEY")
+                    .ok_or("OPENAI_API_KEY not found")?;
+                Ok(Self::from_provider(Box::new(OpenAIResponsesProvider::configure(
+                    api_key.clone(),
+                    env_values.get("OPENAI_RESPONSES_BASE_URL").cloned(),
+                    env_values.get("OPENAI_REASONING_SUMMARY").cloned(),
+                ))))
+            },
             "anthropic" => {
                 let api_key = env_values.get("ANTHROPIC_API_KEY")
                     .ok_or("ANTHROPIC_API_KEY not found")?;
-                Ok(Self::anthropic(api_key.clone()))
+                let provider = AnthropicProvider::new(api_key.clone());
+                let provider = match env_values.get("ANTHROPIC_THINKING_BUDGET").and_then(|b| b.parse().ok()) {
+                    Some(budget) => provider.with_thinking(budget),
+                    None => provider,
+                };
+                Ok(Self::from_provider(Box::new(provider)))
+            },
+            "gemini" => {
+                let api_key = env_values.get("GEMINI_API_KEY")
+                    .ok_or("GEMINI_API_KEY not found")?;
+                Ok(Self::from_provider(Box::new(GeminiProvider::configure(
+                    api_key.clone(),
+                    env_values.get("GEMINI_BASE_URL").cloned(),
+                    env_values.get("GEMINI_THINKING_BUDGET").cloned(),
+                    env_values.get("GEMINI_SAFETY_THRESHOLD").cloned(),
+                ))))
             },
             "ollama" => {
                 let base_url = env_values.get("OLLAMA_BASE_URL")
                     .cloned()
                     .unwrap_or_else(|| "http://localhost:11434/v1".to_string());
-                Ok(Self::ollama(base_url))
+                Ok(Self::from_provider(Box::new(OllamaProvider::configure(
+                    Some(base_url),
+                    env_values.get("OLLAMA_NATIVE").cloned(),
+                    env_values.get("OLLAMA_NUM_CTX").cloned(),
+                    env_values.get("OLLAMA_KEEP_ALIVE").cloned(),
+                    env_values.get("OLLAMA_NUM_PREDICT").cloned(),
+                ))))
             },
             "mistral" => {
                 let api_key = env_values.get("MISTRAL_API_KEY")
@@ -219,6 +303,31 @@ impl LlmClient {
                     .ok_or("OPENAI_COMPATIBLE_BASE_URL not found")?;
                 Ok(Self::compatible(api_key.clone(), base_url.clone()))
             },
+            "azure_openai" => {
+                let endpoint = env_values.get("AZURE_OPENAI_ENDPOINT")
+                    .ok_or("AZURE_OPENAI_ENDPOINT not found")?;
+                let deployments = env_values.get("AZURE_OPENAI_DEPLOYMENT")
+                    .ok_or("AZURE_OPENAI_DEPLOYMENT not found")?;
+                let provider = AzureOpenAIProvider::configure(
+                    endpoint.clone(),
+                    deployments.clone(),
+                    env_values.get("AZURE_OPENAI_API_VERSION").cloned(),
+                    env_values.get("AZURE_OPENAI_API_KEY").cloned(),
+                    env_values.get("AZURE_OPENAI_AD_TOKEN").cloned(),
+                )?;
+                Ok(Self::from_provider(Box::new(provider)))
+            },
+            "replay" => {
+                let cassette = env_values.get("SHAI_REPLAY_CASSETTE")
+                    .ok_or("SHAI_REPLAY_CASSETTE not found")?;
+                match env_values.get("SHAI_REPLAY_PROVIDER").filter(|p| p.as_str() != "replay") {
+                    Some(recorded) => {
+                        let inner = Self::create_provider(recorded, env_values)?;
+                        Ok(Self::from_provider(Box::new(ReplayProvider::record(inner.provider, cassette))))
+                    }
+                    None => Ok(Self::from_provider(Box::new(ReplayProvider::replay(cassette)?))),
+                }
+            },
             _ => Err(format!("Unknown provider: {}", provider_name).into())
         }
     }
@@ -249,25 +358,181 @@ impl LlmClient {
     }
 }
 
+/// Model capabilities
+impl LlmClient {
+    /// Override what is known about a model, the fields left to None are still resolved
+    pub fn with_model_info(mut self, info: ModelInfo) -> Self {
+        self.model_infos.write().unwrap().remove(&info.id);
+        self.model_overrides.insert(info.id.clone(), info);
+        self
+    }
+
+    /// What is known about a model: user overrides first, then the provider listing,
+    /// then the bundled table. Unknown fields are left to None
+    pub async fn model_info(&self, model: &str) -> ModelInfo {
+        if let Some(info) = self.model_infos.read().unwrap().get(model) {
+            return info.clone();
+        }
+
+        // a failed listing is not retried on every call, the provider may be down for a while
+        let failed_recently = self.failed_listings.read().unwrap().get(model)
+            .is_some_and(|failed_at| failed_at.elapsed() < MODEL_LISTING_RETRY);
+        let listed = match failed_recently {
+            true => None,
+            false => Some(self.provider.model_info(model).await),
+        };
+
+        let mut info = match &listed {
+            Some(Ok(Some(listed))) => ModelInfo { id: model.to_string(), ..listed.clone() },
+            _ => ModelInfo::new(model),
+        };
+        if let Some(known) = known_model_info(model) {
+            info = info.or(known);
+        }
+        if let Some(user) = self.model_overrides.get(model) {
+            info = user.clone().or(info);
+        }
+
+        match listed {
+            Some(Ok(_)) => {
+                self.failed_listings.write().unwrap().remove(model);
+                self.model_infos.write().unwrap().insert(model.to_string(), info.clone());
+            }
+            Some(Err(_)) => {
+                self.failed_listings.write().unwrap().insert(model.to_string(), Instant::now());
+            }
+            None => {}
+        }
+        info
+    }
+
+    /// Whether the model can call tools, as far as its info tells, else as the provider claims
+    pub async fn supports_functions(&self, model: &str) -> bool {
+        match self.model_info(model).await.supports_tools {
+            Some(supported) => supported,
+            None => self.provider.supports_functions(model.to_string()),
+        }
+    }
+
+    /// Whether the model follows a json schema, as far as its info tells, else as the provider claims
+    pub async fn supports_structured_output(&self, model: &str) -> bool {
+        match self.model_info(model).await.supports_structured_output {
+            Some(supported) => supported,
+            None => self.provider.supports_structured_output(model.to_string()),
+        }
+    }
+}
+
+/// Token estimation
+impl LlmClient {
+    /// Estimated prompt tokens of these messages and tool definitions for `model`.
+    /// Exact for the OpenAI tokenizers, otherwise a chars per token heuristic
+    /// corrected by the usage the provider reported so far
+    pub fn estimate_tokens(&self, model: &str, messages: &[ChatMessage], tools: &ToolBox) -> u32 {
+        let tokenizer = Tokenizer::for_model(model);
+        let tokens = tokenizer.count_messages(messages) + tokenizer.count_tools(tools);
+        self.calibrated(model, &tokenizer, tokens)
+    }
+
+    /// Estimated prompt tokens of a request
+    pub fn estimate_request_tokens(&self, request: &ChatCompletionParameters) -> u32 {
+        let tokenizer = Tokenizer::for_model(&request.model);
+        self.calibrated(&request.model, &tokenizer, tokenizer.count_request(request))
+    }
+
+    /// Estimated tokens of a text
+    pub fn estimate_text_tokens(&self, model: &str, text: &str) -> u32 {
+        let tokenizer = Tokenizer::for_model(model);
+        self.calibrated(model, &tokenizer, tokenizer.count(text))
+    }
+
+    fn calibrated(&self, model: &str, tokenizer: &Tokenizer, tokens: u32) -> u32 {
+        if tokenizer.is_exact() {
+            return tokens;
+        }
+        let ratio = self.token_ratios.read().unwrap().get(model).copied().unwrap_or(1.0);
+        (tokens as f32 * ratio).round() as u32
+    }
+
+    /// Learn how far the heuristic is from the count of the provider
+    fn calibrate_tokens(&self, request: &ChatCompletionParameters, prompt_tokens: u32) {
+        let tokenizer = Tokenizer::for_model(&request.model);
+        let estimated = tokenizer.count_request(request);
+        if tokenizer.is_exact() || estimated == 0 || prompt_tokens == 0 {
+            return;
+        }
+        let observed = (prompt_tokens as f32 / estimated as f32).clamp(0.5, 2.0);
+        let mut ratios = self.token_ratios.write().unwrap();
+        let ratio = ratios.entry(request.model.clone()).or_insert(observed);
+        *ratio = 0.7 * *ratio + 0.3 * observed;
+    }
+
+    /// Estimate the usage the provider did not report
+    fn fill_usage(&self, request: &ChatCompletionParameters, response: &mut ChatCompletionResponse) {
+        let tokenizer = Tokenizer::for_model(&request.model);
+        let completion_tokens: u32 = response.choices.iter()
+            .map(|choice| tokenizer.count_message(&choice.message))
+            .sum();
+        let completion_tokens = self.calibrated(&request.model, &tokenizer, completion_tokens);
+        let prompt_tokens = self.estimate_request_tokens(request);
+
+        let usage = response.usage.get_or_insert_with(|| Usage {
+            input_tokens: None,
+            input_tokens_details: None,
+            output_tokens: None,
+            output_tokens_details: None,
+            prompt_tokens: None,
+            completion_tokens: None,
+            total_tokens: 0,
+            prompt_tokens_details: None,
+            completion_tokens_details: None,
+        });
+        let prompt_tokens = *usage.prompt_tokens.get_or_insert(prompt_tokens);
+        let completion_tokens = *usage.completion_tokens.get_or_insert(completion_tokens);
+        usage.total_tokens = prompt_tokens + completion_tokens;
+    }
+}
+
 /// Higher level chat client
 impl LlmClient {
+    pub fn with_reasoning(mut self, reasoning: ReasoningOptions) -> Self {
+        self.reasoning = reasoning;
+        self
+    }
+
     pub async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
         let request = request
-            .fix_mistral_alternating();
+            .fix_mistral_alternating()
+            .without_reasoning(!self.reasoning.keep_in_trace);
 
-        let response = self.provider
+        let sent = request.clone();
+        let mut response = self.provider
             .chat(request)
             .await?
             .extract_think_content();
 
+        match response.usage.as_ref().and_then(|usage| usage.prompt_tokens) {
+            Some(prompt_tokens) => self.calibrate_tokens(&sent, prompt_tokens),
+            None => self.fill_usage(&sent, &mut response),
+        }
+
+        if self.reasoning.strip {
+            for choice in &mut response.choices {
+                if let ChatMessage::Assistant { reasoning_content, .. } = &mut choice.message {
+                    *reasoning_content = None;
+                }
+            }
+        }
         Ok(response)
     }
 
     pub async fn chat_stream(&self, request: ChatCompletionParameters) -> Result<LlmStream, LlmError> {
         let request = request
-            .fix_mistral_alternating();
+            .fix_mistral_alternating()
+            .without_reasoning(!self.reasoning.keep_in_trace);
 
-        self.provider.chat_stream(request).await
+        let stream = self.provider.chat_stream(request).await?;
+        Ok(split_think_stream(stream, self.reasoning))
     }
 
 
@@ -296,6 +561,24 @@ impl ExtractThinkContent for ChatCompletionResponse {
     }
 }
 
+pub trait WithoutReasoning {
+    /// Remove the reasoning of the assistant messages when `strip` is set
+    fn without_reasoning(self, strip: bool) -> ChatCompletionParameters;
+}
+
+impl WithoutReasoning for ChatCompletionParameters {
+    fn without_reasoning(mut self, strip: bool) -> ChatCompletionParameters {
+        if strip {
+            for message in &mut self.messages {
+                if let ChatMessage::Assistant { reasoning_content, .. } = message {
+                    *reasoning_content = None;
+                }
+            }
+        }
+        self
+    }
+}
+
 pub trait FixMistralAlternating {
     /// Mistral enforces alternating of user/assistant which is problematic in multiturn 
     /// conversation where assistant or toolcall can be cancelled by the user...
@@ -338,4 +621,37 @@ impl FixMistralAlternating for ChatCompletionParameters {
         }
         res
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_failed_listing_is_not_retried_right_away() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(503))
+            // once per model
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let provider = GeminiProvider::new("test-key".to_string()).with_base_url(server.uri());
+        let client = LlmClient::from_provider(Box::new(provider));
+
+        // the bundled table still answers while the provider is down
+        for _ in 0..3 {
+            let info = client.model_info("gemini-2.5-pro").await;
+            assert_eq!(info.context_length, Some(1_048_576));
+        }
+        assert!(client.supports_functions("gemini-2.5-pro").await);
+
+        // what the user and the bundled table do not tell comes from the provider
+        let overridden = client.with_model_info(ModelInfo { supports_structured_output: Some(false), ..ModelInfo::new("custom") });
+        assert!(!overridden.supports_structured_output("custom").await);
+        assert!(overridden.supports_functions("custom").await);
+    }
+}
@@ -3,9 +3,17 @@ pub mod providers;
 pub mod provider;
 pub mod chat;
 pub mod tool;
+pub mod model_info;
+pub mod reasoning;
+pub mod audit;
+pub mod tokens;
 
 // Re-export our client
 pub use client::LlmClient;
+pub use model_info::ModelInfo;
+pub use reasoning::ReasoningOptions;
+pub use audit::AuditConfig;
+pub use tokens::Tokenizer;
 
 pub use tool::{
     ToolDescription, 
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+/// What is known about a model, fields are None when unknown
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub id: String,
+    /// Context window in tokens (prompt and completion)
+    #[serde(default)]
+    pub context_length: Option<u32>,
+    /// Maximum number of tokens of a completion
+    #[serde(default)]
+    pub max_output_tokens: Option<u32>,
+    #[serde(default)]
+    pub supports_tools: Option<bool>,
+    /// Whether the model follows a json schema response format
+    #[serde(default)]
+    pub supports_structured_output: Option<bool>,
+    #[serde(default)]
+    pub supports_vision: Option<bool>,
+    #[serde(default)]
+    pub supports_reasoning: Option<bool>,
+    /// Price in USD of a prompt token
+    #[serde(default)]
+    pub input_price: Option<f64>,
+    /// Price in USD of a completion token
+    #[serde(default)]
+    pub output_price: Option<f64>,
+}
+
+impl ModelInfo {
+    pub fn new(id: &str) -> Self {
+        Self { id: id.to_string(), ..Self::default() }
+    }
+
+    /// Fill the unknown fields from `other`
+    pub fn or(self, other: ModelInfo) -> Self {
+        Self {
+            id: self.id,
+            context_length: self.context_length.or(other.context_length),
+            max_output_tokens: self.max_output_tokens.or(other.max_output_tokens),
+            supports_tools: self.supports_tools.or(other.supports_tools),
+            supports_structured_output: self.supports_structured_output.or(other.supports_structured_output),
+            supports_vision: self.supports_vision.or(other.supports_vision),
+            supports_reasoning: self.supports_reasoning.or(other.supports_reasoning),
+            input_price: self.input_price.or(other.input_price),
+            output_price: self.output_price.or(other.output_price),
+        }
+    }
+
+    /// Cost in USD of a request, None if the prices are unknown
+    pub fn cost(&self, input_tokens: u32, output_tokens: u32) -> Option<f64> {
+        Some(self.input_price? * input_tokens as f64 + self.output_price? * output_tokens as f64)
+    }
+}
+
+/// Bundled knowledge about common models, used when the provider listing does not tell
+struct KnownModel {
+    /// matched against the model id, lowercase and without the vendor prefix (`openai/`)
+    prefix: &'static str,
+    context_length: u32,
+    max_output_tokens: u32,
+    tools: bool,
+    vision: bool,
+    reasoning: bool,
+    /// USD per million tokens, 0 when the model is not sold per token
+    input_price: f64,
+    output_price: f64,
+}
+
+macro_rules! known {
+    ($prefix:expr, $ctx:expr, $out:expr, tools: $tools:expr, vision: $vision:expr, reasoning: $reasoning:expr, price: ($input:expr, $output:expr)) => {
+        KnownModel { prefix: $prefix, context_length: $ctx, max_output_tokens: $out, tools: $tools, vision: $vision, reasoning: $reasoning, input_price: $input, output_price: $output }
+    };
+}
+
+const KNOWN_MODELS: &[KnownModel] = &[
+    // openai
+    known!("gpt-5", 400_000, 128_000, tools: true, vision: true, reasoning: true, price: (1.25, 10.0)),
+    known!("gpt-5-mini", 400_000, 128_000, tools: true, vision: true, reasoning: true, price: (0.25, 2.0)),
+    known!("gpt-5-nano", 400_000, 128_000, tools: true, vision: true, reasoning: true, price: (0.05, 0.4)),
+    known!("gpt-4.1", 1_047_576, 32_768, tools: true, vision: true, reasoning: false, price: (2.0, 8.0)),
+    known!("gpt-4.1-mini", 1_047_576, 32_768, tools: true, vision: true, reasoning: false, price: (0.4, 1.6)),
+    known!("gpt-4.1-nano", 1_047_576, 32_768, tools: true, vision: true, reasoning: false, price: (0.1, 0.4)),
+    known!("gpt-4o", 128_000, 16_384, tools: true, vision: true, reasoning: false, price: (2.5, 10.0)),
+    known!("gpt-4o-mini", 128_000, 16_384, tools: true, vision: true, reasoning: false, price: (0.15, 0.6)),
+    known!("o3", 200_000, 100_000, tools: true, vision: true, reasoning: true, price: (2.0, 8.0)),
+    known!("o4-mini", 200_000, 100_000, tools: true, vision: true, reasoning: true, price: (1.1, 4.4)),
+    known!("gpt-oss", 131_072, 32_768, tools: true, vision: false, reasoning: true, price: (0.0, 0.0)),
+    // anthropic
+    known!("claude-opus-4", 200_000, 32_000, tools: true, vision: true, reasoning: true, price: (15.0, 75.0)),
+    known!("claude-sonnet-4", 200_000, 64_000, tools: true, vision: true, reasoning: true, price: (3.0, 15.0)),
+    known!("claude-haiku-4", 200_000, 64_000, tools: true, vision: true, reasoning: true, price: (1.0, 5.0)),
+    known!("claude-3-7-sonnet", 200_000, 64_000, tools: true, vision: true, reasoning: true, price: (3.0, 15.0)),
+    known!("claude-3-5-sonnet", 200_000, 8_192, tools: true, vision: true, reasoning: false, price: (3.0, 15.0)),
+    known!("claude-3-5-haiku", 200_000, 8_192, tools: true, vision: false, reasoning: false, price: (0.8, 4.0)),
+    known!("claude-3-opus", 200_000, 4_096, tools: true, vision: true, reasoning: false, price: (15.0, 75.0)),
+    known!("claude-3-haiku", 200_000, 4_096, tools: true, vision: true, reasoning: false, price: (0.25, 1.25)),
+    // google
+    known!("gemini-2.5-pro", 1_048_576, 65_536, tools: true, vision: true, reasoning: true, price: (1.25, 10.0)),
+    known!("gemini-2.5-flash", 1_048_576, 65_536, tools: true, vision: true, reasoning: true, price: (0.3, 2.5)),
+    known!("gemini-2.0-flash", 1_048_576, 8_192, tools: true, vision: true, reasoning: false, price: (0.1, 0.4)),
+    // mistral
+    known!("mistral-large", 131_072, 32_768, tools: true, vision: false, reasoning: false, price: (2.0, 6.0)),
+    known!("mistral-medium", 131_072, 32_768, tools: true, vision: true, reasoning: false, price: (0.4, 2.0)),
+    known!("mistral-small", 131_072, 32_768, tools: true, vision: true, reasoning: false, price: (0.1, 0.3)),
+    known!("magistral", 40_000, 40_000, tools: true, vision: false, reasoning: true, price: (0.0, 0.0)),
+    known!("codestral", 256_000, 32_768, tools: true, vision: false, reasoning: false, price: (0.3, 0.9)),
+    known!("devstral", 131_072, 32_768, tools: true, vision: false, reasoning: false, price: (0.1, 0.3)),
+    // open weights
+    known!("qwen3", 32_768, 8_192, tools: true, vision: false, reasoning: true, price: (0.0, 0.0)),
+    known!("qwen3-coder", 262_144, 65_536, tools: true, vision: false, reasoning: false, price: (0.0, 0.0)),
+    known!("qwen2.5-coder", 32_768, 8_192, tools: true, vision: false, reasoning: false, price: (0.0, 0.0)),
+    known!("llama-3.3", 131_072, 8_192, tools: true, vision: false, reasoning: false, price: (0.0, 0.0)),
+    known!("llama3.3", 131_072, 8_192, tools: true, vision: false, reasoning: false, price: (0.0, 0.0)),
+    known!("deepseek-r1", 131_072, 32_768, tools: false, vision: false, reasoning: true, price: (0.0, 0.0)),
+    known!("deepseek-v3", 131_072, 8_192, tools: true, vision: false, reasoning: false, price: (0.0, 0.0)),
+];
+
+/// Bundled info about a model, from the entry with the longest matching prefix
+pub fn known_model_info(model: &str) -> Option<ModelInfo> {
+    let lowered = model.to_lowercase();
+    let name = lowered.rsplit('/').next().unwrap_or(&lowered);
+    let known = KNOWN_MODELS.iter()
+        .filter(|k| name.starts_with(k.prefix))
+        .max_by_key(|k| k.prefix.len())?;
+
+    let price = |per_million: f64| (per_million > 0.0).then(|| per_million / 1_000_000.0);
+    Some(ModelInfo {
+        id: model.to_string(),
+        context_length: Some(known.context_length),
+        max_output_tokens: Some(known.max_output_tokens),
+        supports_tools: Some(known.tools),
+        // the bundled models that call tools are also the ones following a json schema
+        supports_structured_output: Some(known.tools),
+        supports_vision: Some(known.vision),
+        supports_reasoning: Some(known.reasoning),
+        input_price: price(known.input_price),
+        output_price: price(known.output_price),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_model_longest_prefix() {
+        let info = known_model_info("openai/GPT-4o-mini-2024-07-18").unwrap();
+        assert_eq!(info.id, "openai/GPT-4o-mini-2024-07-18");
+        assert_eq!(info.input_price, Some(0.15 / 1_000_000.0));
+
+        let info = known_model_info("gpt-4o-2024-11-20").unwrap();
+        assert_eq!(info.input_price, Some(2.5 / 1_000_000.0));
+
+        // open weights models have no price
+        let info = known_model_info("Qwen3-32B").unwrap();
+        assert_eq!(info.context_length, Some(32_768));
+        assert_eq!(info.input_price, None);
+
+        assert!(known_model_info("some-unknown-model").is_none());
+    }
+
+    #[test]
+    fn test_model_info_or_keeps_known_fields() {
+        let listed = ModelInfo { context_length: Some(64_000), ..ModelInfo::new("mistral-small-latest") };
+        let info = listed.or(known_model_info("mistral-small-latest").unwrap());
+        assert_eq!(info.context_length, Some(64_000));
+        assert_eq!(info.supports_tools, Some(true));
+        assert!((info.cost(1_000_000, 0).unwrap() - 0.1).abs() < 1e-9);
+    }
+}
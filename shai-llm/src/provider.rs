@@ -2,6 +2,7 @@ use std::fmt::Debug;
 use async_trait::async_trait;
 use futures::Stream;
 use std::error::Error;
+use crate::model_info::ModelInfo;
 use openai_dive::v1::endpoints::chat::Chat;
 use openai_dive::v1::resources::{
     chat::{ChatCompletionParameters, ChatCompletionResponse, ChatCompletionChunkResponse},
@@ -55,6 +56,11 @@ pub trait LlmProvider: Send + Sync {
             .ok_or_else(|| "no model available".into())
     }
 
+    /// What the provider listing tells about a model, None if it does not describe it
+    async fn model_info(&self, model: &str) -> Result<Option<ModelInfo>, LlmError> {
+        Ok(None)
+    }
+
     async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError>;
     
     async fn chat_stream(&self, request: ChatCompletionParameters) -> Result<LlmStream, LlmError>;
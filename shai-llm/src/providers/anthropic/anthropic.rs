@@ -1,8 +1,13 @@
 use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use crate::model_info::known_model_info;
+use crate::audit;
+use crate::providers::content_text;
 use super::api::*;
 use async_trait::async_trait;
 use reqwest::Client;
-use serde_json::json;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
 use futures::{StreamExt, stream};
 use openai_dive::v1::resources::{
     chat::{ChatCompletionParameters, ChatCompletionResponse, ChatCompletionChunkResponse, ChatMessage, DeltaChatMessage, ChatMessageContent, ChatCompletionChoice, ChatCompletionChunkChoice, ToolCall, Function},
@@ -10,9 +15,17 @@ use openai_dive::v1::resources::{
     shared::{FinishReason, Usage},
 };
 
+/// max_tokens when the request does not set it
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
 pub struct AnthropicProvider {
     api_key: String,
     client: Client,
+    /// extended thinking budget in tokens, None = only when the request sets a reasoning effort
+    thinking_budget: Option<u32>,
+    /// signed thinking blocks by the id of the first tool call they led to,
+    /// they must be sent back for the model to go on thinking after the tool results
+    thinking_blocks: Mutex<HashMap<String, Vec<Value>>>,
 }
 
 impl AnthropicProvider {
@@ -20,14 +33,26 @@ impl AnthropicProvider {
         Self {
             api_key,
             client: Client::new(),
+            thinking_budget: None,
+            thinking_blocks: Mutex::new(HashMap::new()),
         }
     }
 
+    /// Enable extended thinking with the given budget in tokens
+    pub fn with_thinking(mut self, budget_tokens: u32) -> Self {
+        self.thinking_budget = Some(budget_tokens);
+        self
+    }
+
     /// Create Anthropic provider from environment variables
     /// Returns None if required environment variables are not set
     pub fn from_env() -> Option<Self> {
         std::env::var("ANTHROPIC_API_KEY").ok().map(|api_key| {
-            Self::new(api_key)
+            let provider = Self::new(api_key);
+            match std::env::var("ANTHROPIC_THINKING_BUDGET").ok().and_then(|b| b.parse().ok()) {
+                Some(budget) => provider.with_thinking(budget),
+                None => provider,
+            }
         })
     }
 
@@ -99,10 +124,11 @@ impl AnthropicProvider {
     fn convert_anthropic_event_to_stream_response(event: AnthropicStreamEvent) -> Result<Option<ChatCompletionChunkResponse>, LlmError> {
         match event {
             AnthropicStreamEvent::ContentBlockDelta { delta, .. } => {
-                let content = match delta {
-                    AnthropicDelta::TextDelta { text } => Some(ChatMessageContent::Text(text)),
-                    AnthropicDelta::ThinkingDelta { thinking: _ } => return Ok(None), // Skip thinking content
-                    AnthropicDelta::InputJsonDelta { partial_json } => Some(ChatMessageContent::Text(partial_json)),
+                let (content, reasoning_content) = match delta {
+                    AnthropicDelta::TextDelta { text } => (Some(ChatMessageContent::Text(text)), None),
+                    AnthropicDelta::ThinkingDelta { thinking } => (None, Some(thinking)),
+                    AnthropicDelta::SignatureDelta { .. } => return Ok(None),
+                    AnthropicDelta::InputJsonDelta { partial_json } => (Some(ChatMessageContent::Text(partial_json)), None),
                 };
 
                 Ok(Some(ChatCompletionChunkResponse {
@@ -117,7 +143,7 @@ impl AnthropicProvider {
                         index: Some(0),
                         delta: DeltaChatMessage::Assistant {
                             content,
-                            reasoning_content: None,
+                            reasoning_content,
                             refusal: None,
                             name: None,
                             tool_calls: None,
@@ -186,38 +212,144 @@ impl AnthropicProvider {
     }
 
     pub(crate) fn convert_to_anthropic_format(&self, request: &ChatCompletionParameters) -> serde_json::Value {
+        // read the sampling parameters from the wire format, they map one to one
+        let params = serde_json::to_value(request).unwrap_or_default();
         let (system_messages, messages) = self.convert_messages(&request.messages);
 
         let mut anthropic_request = json!({
             "model": request.model,
-            "max_tokens": request.max_tokens.unwrap_or(1000),
             "messages": messages
         });
 
         if !system_messages.is_empty() {
-            anthropic_request["system"] = json!(system_messages.join("\n\n"));
+            // cache breakpoint: the system prompt is the stable prefix of every request of a session
+            anthropic_request["system"] = json!([{
+                "type": "text",
+                "text": system_messages.join("\n\n"),
+                "cache_control": {"type": "ephemeral"}
+            }]);
         }
 
         if let Some(tools) = &request.tools {
-            anthropic_request["tools"] = json!(self.convert_tools(tools));
+            let mut tools = self.convert_tools(tools);
+            if let Some(last) = tools.last_mut() {
+                last["cache_control"] = json!({"type": "ephemeral"});
+            }
+            anthropic_request["tools"] = json!(tools);
+        }
+
+        let tool_choice = Self::convert_tool_choice(&params["tool_choice"]);
+        let forced_tool = tool_choice.as_ref().is_some_and(|c| matches!(c["type"].as_str(), Some("any" | "tool")));
+        if let Some(tool_choice) = tool_choice {
+            anthropic_request["tool_choice"] = tool_choice;
+        }
+
+        match &params["stop"] {
+            Value::String(stop) => anthropic_request["stop_sequences"] = json!([stop]),
+            Value::Array(stops) if !stops.is_empty() => anthropic_request["stop_sequences"] = json!(stops),
+            _ => {}
+        }
+
+        let mut max_tokens = params["max_completion_tokens"].as_u64()
+            .or(params["max_tokens"].as_u64())
+            .unwrap_or_else(|| {
+                let model_max = known_model_info(&request.model).and_then(|info| info.max_output_tokens);
+                model_max.unwrap_or(DEFAULT_MAX_TOKENS).min(DEFAULT_MAX_TOKENS) as u64
+            });
+
+        // extended thinking cannot be combined with a forced tool, and a tool loop started
+        // with thinking can only go on if its signed thinking block is sent back
+        let budget = self.thinking_budget.or_else(|| Self::effort_budget(params["reasoning_effort"].as_str()));
+        match budget.filter(|_| !forced_tool && self.can_continue_thinking(&request.messages)) {
+            Some(budget) => {
+                if max_tokens <= budget as u64 {
+                    max_tokens = budget as u64 + DEFAULT_MAX_TOKENS as u64;
+                }
+                anthropic_request["thinking"] = json!({"type": "enabled", "budget_tokens": budget});
+            }
+            None => {
+                if let Some(temperature) = params.get("temperature").filter(|t| t.is_number()) {
+                    anthropic_request["temperature"] = temperature.clone();
+                }
+                if let Some(top_p) = params.get("top_p").filter(|t| t.is_number()) {
+                    anthropic_request["top_p"] = top_p.clone();
+                }
+            }
         }
+        anthropic_request["max_tokens"] = json!(max_tokens);
 
         anthropic_request
     }
 
+    /// OpenAI tool_choice ("auto", "required", "none" or a named function) to Anthropic
+    fn convert_tool_choice(tool_choice: &Value) -> Option<Value> {
+        match tool_choice {
+            Value::String(choice) => match choice.as_str() {
+                "auto" => Some(json!({"type": "auto"})),
+                "required" => Some(json!({"type": "any"})),
+                "none" => Some(json!({"type": "none"})),
+                _ => None,
+            },
+            Value::Object(_) => tool_choice["function"]["name"].as_str()
+                .map(|name| json!({"type": "tool", "name": name})),
+            _ => None,
+        }
+    }
+
+    /// Thinking budget for an OpenAI reasoning effort
+    fn effort_budget(effort: Option<&str>) -> Option<u32> {
+        match effort? {
+            "minimal" | "low" => Some(1024),
+            "medium" => Some(4096),
+            "high" => Some(16384),
+            _ => None,
+        }
+    }
+
+    /// False when the conversation continues a tool call whose thinking block is not known
+    fn can_continue_thinking(&self, messages: &[ChatMessage]) -> bool {
+        let Some(last_assistant) = messages.iter().rposition(|m| matches!(m, ChatMessage::Assistant { .. })) else {
+            return true;
+        };
+        let continues_tools = messages[last_assistant + 1..].iter().all(|m| matches!(m, ChatMessage::Tool { .. }));
+        match &messages[last_assistant] {
+            ChatMessage::Assistant { tool_calls: Some(calls), .. } if continues_tools && !calls.is_empty() => {
+                self.thinking_blocks.lock().unwrap().contains_key(&calls[0].id)
+            }
+            _ => true,
+        }
+    }
+
+    /// Forget the thinking of every assistant message but the last one, the api only needs
+    /// the thinking that led to the tool calls being answered
+    fn evict_thinking_blocks(&self, messages: &[ChatMessage]) {
+        let Some(last_assistant) = messages.iter().rposition(|m| matches!(m, ChatMessage::Assistant { .. })) else {
+            return;
+        };
+        let mut thinking_blocks = self.thinking_blocks.lock().unwrap();
+        for message in &messages[..last_assistant] {
+            if let ChatMessage::Assistant { tool_calls: Some(calls), .. } = message {
+                if let Some(call) = calls.first() {
+                    thinking_blocks.remove(&call.id);
+                }
+            }
+        }
+    }
+
     fn convert_messages(&self, messages: &[ChatMessage]) -> (Vec<String>, Vec<serde_json::Value>) {
+        self.evict_thinking_blocks(messages);
         let mut system_messages = Vec::new();
         let mut converted_messages = Vec::new();
 
         for (i, msg) in messages.iter().enumerate() {
             match msg {
                 ChatMessage::System { content, .. } => {
-                    system_messages.push(self.extract_content_text(content));
+                    system_messages.push(content_text(content));
                 }
                 ChatMessage::User { content, .. } => {
                     converted_messages.push(json!({
                         "role": "user",
-                        "content": self.extract_content_text(content)
+                        "content": self.convert_user_content(content)
                     }));
                 }
                 ChatMessage::Assistant { content, tool_calls, .. } => {
@@ -232,7 +364,7 @@ impl AnthropicProvider {
                 ChatMessage::Developer { content, .. } => {
                     converted_messages.push(json!({
                         "role": "user",
-                        "content": self.extract_content_text(content)
+                        "content": self.convert_user_content(content)
                     }));
                 }
                 ChatMessage::Tool { content, tool_call_id, .. } => {
@@ -251,14 +383,55 @@ impl AnthropicProvider {
         (system_messages, converted_messages)
     }
 
+    /// Plain text stays a string, content parts become text and image blocks
+    fn convert_user_content(&self, content: &ChatMessageContent) -> serde_json::Value {
+        let ChatMessageContent::ContentPart(parts) = content else {
+            return json!(content_text(content));
+        };
+
+        let blocks: Vec<Value> = parts.iter()
+            .filter_map(|part| {
+                let part = serde_json::to_value(part).ok()?;
+                match part["type"].as_str()? {
+                    "text" => Some(json!({"type": "text", "text": part["text"]})),
+                    "image_url" => Self::convert_image(part["image_url"]["url"].as_str()?),
+                    _ => None, // audio, files...
+                }
+            })
+            .collect();
+        json!(blocks)
+    }
+
+    /// Image block from a data url (base64) or a plain url
+    fn convert_image(url: &str) -> Option<Value> {
+        match url.strip_prefix("data:") {
+            Some(data_url) => {
+                let (media_type, data) = data_url.split_once(";base64,")?;
+                Some(json!({
+                    "type": "image",
+                    "source": {"type": "base64", "media_type": media_type, "data": data}
+                }))
+            }
+            None => Some(json!({
+                "type": "image",
+                "source": {"type": "url", "url": url}
+            })),
+        }
+    }
+
     fn build_assistant_content(&self, content: &Option<ChatMessageContent>, tool_calls: &Option<Vec<ToolCall>>, is_final: bool) -> Option<serde_json::Value> {
         match tool_calls {
             Some(calls) => {
                 let mut blocks = Vec::new();
+
+                // Signed thinking that led to these calls goes first
+                if let Some(thinking) = calls.first().and_then(|call| self.thinking_blocks.lock().unwrap().get(&call.id).cloned()) {
+                    blocks.extend(thinking);
+                }
                 
                 // Add text content if present
                 if let Some(text_content) = content {
-                    let text = self.extract_content_text(text_content);
+                    let text = content_text(text_content);
                     if !text.is_empty() {
                         blocks.push(json!({"type": "text", "text": text}));
                     }
@@ -278,7 +451,7 @@ impl AnthropicProvider {
                 Some(json!(blocks))
             }
             None => {
-                let text = content.as_ref().map(|c| self.extract_content_text(c)).unwrap_or_default();
+                let text = content.as_ref().map(content_text).unwrap_or_default();
                 
                 // Only allow empty content if this is the final assistant message
                 if text.is_empty() && !is_final {
@@ -300,25 +473,10 @@ impl AnthropicProvider {
         }).collect()
     }
 
-    fn extract_content_text(&self, content: &ChatMessageContent) -> String {
-        match content {
-            ChatMessageContent::Text(text) => text.clone(),
-            ChatMessageContent::ContentPart(parts) => {
-                parts.iter().filter_map(|part| {
-                    match part {
-                        openai_dive::v1::resources::chat::ChatMessageContentPart::Text(text_part) => {
-                            Some(text_part.text.clone())
-                        }
-                        _ => None, // Skip images, audio, etc.
-                    }
-                }).collect::<Vec<_>>().join(" ")
-            }
-            ChatMessageContent::None => String::new(),
-        }
-    }
-
-    fn convert_from_anthropic_format(&self, response: serde_json::Value) -> Result<ChatCompletionResponse, LlmError> {
+    pub(crate) fn convert_from_anthropic_format(&self, response: serde_json::Value) -> Result<ChatCompletionResponse, LlmError> {
         let mut text_content = Vec::new();
+        let mut thinking_content = Vec::new();
+        let mut thinking_blocks = Vec::new();
         let mut tool_calls = Vec::new();
         
         // Parse content array
@@ -330,6 +488,15 @@ impl AnthropicProvider {
                             text_content.push(text.to_string());
                         }
                     }
+                    Some("thinking") => {
+                        if let Some(thinking) = content_block["thinking"].as_str() {
+                            thinking_content.push(thinking.to_string());
+                        }
+                        thinking_blocks.push(content_block.clone());
+                    }
+                    Some("redacted_thinking") => {
+                        thinking_blocks.push(content_block.clone());
+                    }
                     Some("tool_use") => {
                         if let (Some(id), Some(name), Some(input)) = (
                             content_block["id"].as_str(),
@@ -359,8 +526,26 @@ impl AnthropicProvider {
             Some(ChatMessageContent::Text(combined_text))
         };
         
+        let reasoning_content = if thinking_content.is_empty() {
+            None
+        } else {
+            Some(thinking_content.join("\n"))
+        };
+
+        // keep the signed thinking to send it back with the tool results
+        if let (Some(call), false) = (tool_calls.first(), thinking_blocks.is_empty()) {
+            self.thinking_blocks.lock().unwrap().insert(call.id.clone(), thinking_blocks);
+        }
+
         // Convert tool_calls to Option
         let tool_calls_option = if tool_calls.is_empty() { None } else { Some(tool_calls) };
+
+        // cached prompt tokens are counted apart from input_tokens
+        let usage = &response["usage"];
+        let prompt_tokens = ["input_tokens", "cache_read_input_tokens", "cache_creation_input_tokens"].iter()
+            .map(|field| usage[field].as_u64().unwrap_or(0) as u32)
+            .sum::<u32>();
+        let completion_tokens = usage["output_tokens"].as_u64().unwrap_or(0) as u32;
         
         Ok(ChatCompletionResponse {
             id: Some(response["id"].as_str().unwrap_or("").to_string()),
@@ -371,7 +556,7 @@ impl AnthropicProvider {
                 index: 0,
                 message: ChatMessage::Assistant {
                     content,
-                    reasoning_content: None,
+                    reasoning_content,
                     refusal: None,
                     name: None,
                     audio: None,
@@ -385,9 +570,9 @@ impl AnthropicProvider {
                 input_tokens_details: None,
                 output_tokens: None,
                 output_tokens_details: None,
-                prompt_tokens: Some(response["usage"]["input_tokens"].as_u64().unwrap_or(0) as u32),
-                completion_tokens: Some(response["usage"]["output_tokens"].as_u64().unwrap_or(0) as u32),
-                total_tokens: response["usage"]["input_tokens"].as_u64().unwrap_or(0) as u32 + response["usage"]["output_tokens"].as_u64().unwrap_or(0) as u32,
+                prompt_tokens: Some(prompt_tokens),
+                completion_tokens: Some(completion_tokens),
+                total_tokens: prompt_tokens + completion_tokens,
                 prompt_tokens_details: None,
                 completion_tokens_details: None,
             }),
@@ -444,9 +629,14 @@ impl LlmProvider for AnthropicProvider {
 
     async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
         let anthropic_request = self.convert_to_anthropic_format(&request);
+        let url = format!("{}/messages", ANTHROPIC_API_BASE);
+        let audit = audit::call(&url).map(|call| call.with_secret(&self.api_key));
+        if let Some(audit) = &audit {
+            audit.request(&anthropic_request);
+        }
         
         let response = self.client
-            .post(&format!("{}/messages", ANTHROPIC_API_BASE))
+            .post(&url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
@@ -456,10 +646,16 @@ impl LlmProvider for AnthropicProvider {
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
+            if let Some(audit) = &audit {
+                audit.error(&error_text);
+            }
             return Err(format!("Anthropic API error: {}", error_text).into());
         }
 
         let anthropic_response: serde_json::Value = response.json().await?;
+        if let Some(audit) = &audit {
+            audit.response(&anthropic_response);
+        }
         self.convert_from_anthropic_format(anthropic_response)
     }
 
@@ -467,9 +663,14 @@ impl LlmProvider for AnthropicProvider {
         let mut anthropic_request = self.convert_to_anthropic_format(&request);
         // Add streaming parameter
         anthropic_request["stream"] = json!(true);
+        let url = format!("{}/messages", ANTHROPIC_API_BASE);
+        let audit = audit::call(&url).map(|call| call.with_secret(&self.api_key));
+        if let Some(audit) = &audit {
+            audit.request(&anthropic_request);
+        }
         
         let response = self.client
-            .post(&format!("{}/messages", ANTHROPIC_API_BASE))
+            .post(&url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("Content-Type", "application/json")
@@ -479,10 +680,15 @@ impl LlmProvider for AnthropicProvider {
 
         if !response.status().is_success() {
             let error_text = response.text().await?;
+            if let Some(audit) = &audit {
+                audit.error(&error_text);
+            }
             return Err(format!("Anthropic API streaming error: {}", error_text).into());
         }
 
-        Self::parse_anthropic_stream(response).await
+        // the converted chunks are recorded, the raw events are spread over network chunks
+        let stream = Self::parse_anthropic_stream(response).await?;
+        Ok(audit::audit_stream(stream, audit))
     }
 
     fn supports_functions(&self, model: String) -> bool {
@@ -503,6 +709,7 @@ impl LlmProvider for AnthropicProvider {
             display_name: "Anthropic (Claude 3.5 Sonnet, Claude 3 Opus)",
             env_vars: vec![
                 EnvVar::required("ANTHROPIC_API_KEY", "Anthropic API key"),
+                EnvVar::optional("ANTHROPIC_THINKING_BUDGET", "Extended thinking budget in tokens"),
             ],
         }
     }
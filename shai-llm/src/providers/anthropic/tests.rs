@@ -3,12 +3,39 @@ mod tests {
     use super::*;
     use crate::providers::anthropic::AnthropicProvider;
     use crate::provider::LlmProvider;
-    use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent, ChatCompletionParametersBuilder};
+    use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent, ChatCompletionParameters, ChatCompletionParametersBuilder};
     use serde_json::json;
 
     fn setup_provider() -> AnthropicProvider {
-        // Assume ANTHROPIC_API_KEY exists in environment
-        AnthropicProvider::from_env().expect("ANTHROPIC_API_KEY must be set for tests")
+        // request conversion does not call the API
+        AnthropicProvider::new("test-key".to_string())
+    }
+
+    /// Request from its OpenAI wire format
+    fn request(value: serde_json::Value) -> ChatCompletionParameters {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn read_tool() -> serde_json::Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "read",
+                "description": "Read a file",
+                "parameters": {"type": "object", "properties": {"path": {"type": "string"}}}
+            }
+        })
+    }
+
+    fn tool_exchange() -> serde_json::Value {
+        json!([
+            {"role": "user", "content": "read main.py"},
+            {"role": "assistant", "tool_calls": [{
+                "id": "toolu_01", "type": "function",
+                "function": {"name": "read", "arguments": "{\"path\":\"main.py\"}"}
+            }]},
+            {"role": "tool", "tool_call_id": "toolu_01", "content": "print('hello')"}
+        ])
     }
 
     #[tokio::test]
@@ -36,7 +63,8 @@ mod tests {
         
         // Check that system message is extracted to top-level system parameter
         assert!(anthropic_format.get("system").is_some());
-        assert_eq!(anthropic_format["system"].as_str().unwrap(), "You are a helpful assistant.");
+        assert_eq!(anthropic_format["system"][0]["text"].as_str().unwrap(), "You are a helpful assistant.");
+        assert_eq!(anthropic_format["system"][0]["cache_control"], json!({"type": "ephemeral"}));
         
         // Check that messages array only contains non-system messages
         let messages = anthropic_format["messages"].as_array().unwrap();
@@ -74,7 +102,7 @@ mod tests {
         
         // Check that system messages are combined
         assert!(anthropic_format.get("system").is_some());
-        let system_content = anthropic_format["system"].as_str().unwrap();
+        let system_content = anthropic_format["system"][0]["text"].as_str().unwrap();
         assert!(system_content.contains("You are a helpful assistant."));
         assert!(system_content.contains("Always be concise."));
         
@@ -174,4 +202,168 @@ mod tests {
         assert_eq!(tool_result_content[0]["tool_use_id"].as_str().unwrap(), "toolu_018qHepKa8d4rbZ9qskd2vqw");
         assert_eq!(tool_result_content[0]["content"].as_str().unwrap(), "Successfully updated file '/Users/lloiseau/Work/test/main.py' with 22 bytes");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_sampling_parameters() {
+        let provider = setup_provider();
+        let anthropic_format = provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": [{"role": "user", "content": "Hello!"}],
+            "max_completion_tokens": 2000,
+            "temperature": 0.2,
+            "top_p": 0.9,
+            "stop": ["END"]
+        })));
+
+        assert_eq!(anthropic_format["max_tokens"], 2000);
+        assert!((anthropic_format["temperature"].as_f64().unwrap() - 0.2).abs() < 1e-6);
+        assert!((anthropic_format["top_p"].as_f64().unwrap() - 0.9).abs() < 1e-6);
+        assert_eq!(anthropic_format["stop_sequences"], json!(["END"]));
+
+        // without max tokens, the output limit of the model caps the default
+        let anthropic_format = provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-3-haiku-20240307",
+            "messages": [{"role": "user", "content": "Hello!"}],
+            "stop": "END"
+        })));
+        assert_eq!(anthropic_format["max_tokens"], 4096);
+        assert_eq!(anthropic_format["stop_sequences"], json!(["END"]));
+        assert!(anthropic_format.get("temperature").is_none());
+    }
+
+    #[test]
+    fn test_tool_choice_and_tool_caching() {
+        let provider = setup_provider();
+        let convert = |tool_choice: serde_json::Value| provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": [{"role": "user", "content": "read main.py"}],
+            "tools": [read_tool(), read_tool()],
+            "tool_choice": tool_choice
+        })));
+
+        let anthropic_format = convert(json!("required"));
+        assert_eq!(anthropic_format["tool_choice"], json!({"type": "any"}));
+        let tools = anthropic_format["tools"].as_array().unwrap();
+        assert!(tools[0].get("cache_control").is_none());
+        assert_eq!(tools[1]["cache_control"], json!({"type": "ephemeral"}));
+
+        assert_eq!(convert(json!("auto"))["tool_choice"], json!({"type": "auto"}));
+        assert_eq!(convert(json!("none"))["tool_choice"], json!({"type": "none"}));
+        assert_eq!(
+            convert(json!({"type": "function", "function": {"name": "read"}}))["tool_choice"],
+            json!({"type": "tool", "name": "read"})
+        );
+    }
+
+    #[test]
+    fn test_image_content() {
+        let provider = setup_provider();
+        let anthropic_format = provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "what is this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}},
+                {"type": "image_url", "image_url": {"url": "https://example.com/cat.jpg"}}
+            ]}]
+        })));
+
+        let content = anthropic_format["messages"][0]["content"].as_array().unwrap();
+        assert_eq!(content.len(), 3);
+        assert_eq!(content[0], json!({"type": "text", "text": "what is this?"}));
+        assert_eq!(content[1], json!({
+            "type": "image",
+            "source": {"type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo="}
+        }));
+        assert_eq!(content[2], json!({
+            "type": "image",
+            "source": {"type": "url", "url": "https://example.com/cat.jpg"}
+        }));
+    }
+
+    #[test]
+    fn test_thinking() {
+        let provider = setup_provider();
+        let anthropic_format = provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": [{"role": "user", "content": "Hello!"}],
+            "reasoning_effort": "medium",
+            "temperature": 0.2,
+            "max_completion_tokens": 1000
+        })));
+        assert_eq!(anthropic_format["thinking"], json!({"type": "enabled", "budget_tokens": 4096}));
+        assert!(anthropic_format["max_tokens"].as_u64().unwrap() > 4096);
+        assert!(anthropic_format.get("temperature").is_none());
+
+        // thinking cannot be combined with a forced tool
+        let provider = setup_provider().with_thinking(2048);
+        let anthropic_format = provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": [{"role": "user", "content": "read main.py"}],
+            "tools": [read_tool()],
+            "tool_choice": "required"
+        })));
+        assert!(anthropic_format.get("thinking").is_none());
+
+        // nor continue a tool call made without it
+        let anthropic_format = provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": tool_exchange(),
+            "tools": [read_tool()]
+        })));
+        assert!(anthropic_format.get("thinking").is_none());
+    }
+
+    #[test]
+    fn test_thinking_blocks_round_trip() {
+        let provider = setup_provider().with_thinking(2048);
+        let response = provider.convert_from_anthropic_format(json!({
+            "id": "msg_01",
+            "model": "claude-sonnet-4-20250514",
+            "content": [
+                {"type": "thinking", "thinking": "I should read the file", "signature": "sig"},
+                {"type": "tool_use", "id": "toolu_01", "name": "read", "input": {"path": "main.py"}}
+            ],
+            "usage": {"input_tokens": 10, "cache_read_input_tokens": 100, "output_tokens": 20}
+        })).unwrap();
+
+        let ChatMessage::Assistant { reasoning_content, tool_calls, .. } = &response.choices[0].message else {
+            panic!("expected an assistant message");
+        };
+        assert_eq!(reasoning_content.as_deref(), Some("I should read the file"));
+        assert_eq!(tool_calls.as_ref().unwrap()[0].id, "toolu_01");
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, Some(110));
+        assert_eq!(usage.total_tokens, 130);
+
+        // the signed thinking goes back before the tool use, and thinking stays on
+        let anthropic_format = provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": tool_exchange(),
+            "tools": [read_tool()]
+        })));
+        let assistant_content = anthropic_format["messages"][1]["content"].as_array().unwrap();
+        assert_eq!(assistant_content[0]["type"], "thinking");
+        assert_eq!(assistant_content[0]["signature"], "sig");
+        assert_eq!(assistant_content[1]["type"], "tool_use");
+        assert_eq!(anthropic_format["thinking"]["budget_tokens"], 2048);
+
+        // once the model answered, the thinking of the earlier call is dropped
+        let mut messages = tool_exchange();
+        messages.as_array_mut().unwrap().push(json!({"role": "assistant", "content": "It prints hello."}));
+        let anthropic_format = provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": messages,
+            "tools": [read_tool()]
+        })));
+        assert_eq!(anthropic_format["messages"][1]["content"][0]["type"], "tool_use");
+
+        // and is no longer known to continue that call
+        let anthropic_format = provider.convert_to_anthropic_format(&request(json!({
+            "model": "claude-sonnet-4-20250514",
+            "messages": tool_exchange(),
+            "tools": [read_tool()]
+        })));
+        assert_eq!(anthropic_format["messages"][1]["content"][0]["type"], "tool_use");
+        assert!(anthropic_format.get("thinking").is_none());
+    }
+}
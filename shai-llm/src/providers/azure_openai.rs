@@ -0,0 +1,275 @@
+// Azure OpenAI provider, requests are routed to a deployment of the resource
+use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use crate::chat::{ChatClient, NoHooks};
+use async_trait::async_trait;
+use futures::StreamExt;
+use openai_dive::v1::resources::{
+    chat::{ChatCompletionParameters, ChatCompletionResponse},
+    model::{ListModelResponse, Model},
+};
+
+const AZURE_OPENAI_API_VERSION: &str = "2024-10-21";
+
+/// How requests to the resource are authenticated
+#[derive(Debug, Clone)]
+pub enum AzureAuth {
+    /// resource key, sent in the `api-key` header
+    ApiKey(String),
+    /// Microsoft Entra ID access token, sent as a bearer token. It is never refreshed,
+    /// requests fail once it expires (usually after an hour) until the provider is recreated
+    EntraToken(String),
+}
+
+pub struct AzureOpenAIProvider {
+    /// https://<resource>.openai.azure.com
+    endpoint: String,
+    api_version: String,
+    auth: AzureAuth,
+    /// one client per deployment, a request goes to the one named by its model
+    clients: Vec<(String, ChatClient)>,
+}
+
+impl AzureOpenAIProvider {
+    /// Fails when no deployment is given, requests would have nowhere to go
+    pub fn new(endpoint: String, deployments: Vec<String>, auth: AzureAuth) -> Result<Self, LlmError> {
+        if deployments.is_empty() {
+            return Err("AZURE_OPENAI_DEPLOYMENT must name at least one deployment".into());
+        }
+        let provider = Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            api_version: AZURE_OPENAI_API_VERSION.to_string(),
+            auth,
+            clients: vec![],
+        };
+        Ok(provider.with_clients(deployments))
+    }
+
+    pub fn with_api_version(mut self, api_version: String) -> Self {
+        self.api_version = api_version;
+        let deployments = std::mem::take(&mut self.clients).into_iter().map(|(deployment, _)| deployment).collect();
+        self.with_clients(deployments)
+    }
+
+    /// Build the client of each deployment, with the current endpoint, version and auth
+    fn with_clients(mut self, deployments: Vec<String>) -> Self {
+        self.clients = deployments.into_iter()
+            .map(|deployment| {
+                let client = self.client(&deployment);
+                (deployment, client)
+            })
+            .collect();
+        self
+    }
+
+    /// Create Azure OpenAI provider from environment variables
+    /// Returns None if required environment variables are not set
+    pub fn from_env() -> Option<Self> {
+        let env = |name: &str| std::env::var(name).ok();
+        Self::configure(
+            env("AZURE_OPENAI_ENDPOINT")?,
+            env("AZURE_OPENAI_DEPLOYMENT")?,
+            env("AZURE_OPENAI_API_VERSION"),
+            env("AZURE_OPENAI_API_KEY"),
+            env("AZURE_OPENAI_AD_TOKEN"),
+        ).ok()
+    }
+
+    /// Provider from its settings as strings, `deployments` is a comma separated list.
+    /// The api key is used when both it and an Entra ID token are set
+    pub fn configure(endpoint: String, deployments: String, api_version: Option<String>, api_key: Option<String>, ad_token: Option<String>) -> Result<Self, LlmError> {
+        let deployments: Vec<String> = deployments.split(',')
+            .map(|d| d.trim().to_string())
+            .filter(|d| !d.is_empty())
+            .collect();
+
+        let auth = match (api_key.filter(|k| !k.is_empty()), ad_token.filter(|t| !t.is_empty())) {
+            (Some(key), _) => AzureAuth::ApiKey(key),
+            (None, Some(token)) => AzureAuth::EntraToken(token),
+            (None, None) => return Err("either AZURE_OPENAI_API_KEY or AZURE_OPENAI_AD_TOKEN must be set".into()),
+        };
+
+        let provider = Self::new(endpoint, deployments, auth)?;
+        Ok(match api_version.filter(|v| !v.is_empty()) {
+            Some(api_version) => provider.with_api_version(api_version),
+            None => provider,
+        })
+    }
+
+    /// Chat client pointing at a deployment
+    fn client(&self, deployment: &str) -> ChatClient {
+        let base_url = format!("{}/openai/deployments/{}", self.endpoint, deployment);
+        let client = match &self.auth {
+            AzureAuth::ApiKey(key) => ChatClient::new(key.clone(), base_url).with_api_key_header("api-key"),
+            AzureAuth::EntraToken(token) => ChatClient::new(token.clone(), base_url),
+        };
+        client.with_query("api-version", &self.api_version)
+    }
+
+    /// Client of the deployment serving `model`, the model must name one
+    fn client_for(&self, model: &str) -> Result<&ChatClient, LlmError> {
+        self.clients.iter()
+            .find(|(deployment, _)| deployment == model)
+            .map(|(_, client)| client)
+            .ok_or_else(|| {
+                let deployments: Vec<&str> = self.clients.iter().map(|(deployment, _)| deployment.as_str()).collect();
+                format!("no Azure OpenAI deployment named '{}', configured: {}", model, deployments.join(", ")).into()
+            })
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AzureOpenAIProvider {
+    async fn models(&self) -> Result<ListModelResponse, LlmError> {
+        // the data plane does not list deployments, the configured ones are the models
+        Ok(ListModelResponse {
+            object: "list".to_string(),
+            data: self.clients.iter()
+                .map(|(deployment, _)| Model {
+                    id: deployment.clone(),
+                    object: "model".to_string(),
+                    created: None,
+                    owned_by: "azure".to_string(),
+                })
+                .collect(),
+        })
+    }
+
+    async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
+        let response = self.client_for(&request.model)?.chat_completion(&request, &NoHooks).await
+            .map_err(|e| Box::new(e) as LlmError)?;
+        Ok(response)
+    }
+
+    async fn chat_stream(&self, mut request: ChatCompletionParameters) -> Result<LlmStream, LlmError> {
+        // Ensure streaming is enabled
+        request.stream = Some(true);
+
+        let stream = self.client_for(&request.model)?.chat_completion_stream(&request, NoHooks).await
+            .map_err(|e| Box::new(e) as LlmError)?;
+
+        // azure sends the prompt content filter results first, in a chunk without choices
+        let converted_stream = stream
+            .filter(|result| futures::future::ready(!matches!(result, Ok(chunk) if chunk.choices.is_empty())))
+            .map(|result| result.map_err(|e| Box::new(e) as LlmError));
+
+        Ok(Box::new(Box::pin(converted_stream)))
+    }
+
+    fn supports_functions(&self, model: String) -> bool {
+        true
+    }
+
+    fn supports_structured_output(&self, model: String) -> bool {
+        true
+    }
+
+    fn name(&self) -> &'static str {
+        "azure_openai"
+    }
+
+    fn info() -> ProviderInfo {
+        ProviderInfo {
+            name: "azure_openai",
+            display_name: "Azure OpenAI",
+            env_vars: vec![
+                EnvVar::required("AZURE_OPENAI_ENDPOINT", "Resource endpoint (https://<resource>.openai.azure.com)"),
+                EnvVar::required("AZURE_OPENAI_DEPLOYMENT", "Deployment name, or a comma separated list of them"),
+                EnvVar::optional("AZURE_OPENAI_API_KEY", "Resource API key (or set AZURE_OPENAI_AD_TOKEN)"),
+                EnvVar::optional("AZURE_OPENAI_AD_TOKEN", "Microsoft Entra ID access token, used without an API key. Not refreshed, set a new one when it expires"),
+                EnvVar::optional("AZURE_OPENAI_API_VERSION", "API version (defaults to 2024-10-21)"),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn completion() -> serde_json::Value {
+        json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "finish_reason": "stop"
+            }]
+        })
+    }
+
+    fn request(model: &str) -> ChatCompletionParameters {
+        serde_json::from_value(json!({
+            "model": model,
+            "messages": [{"role": "user", "content": "Hello!"}]
+        })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_routes_to_deployment_with_api_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/gpt-4o-mini/chat/completions"))
+            .and(query_param("api-version", "2025-01-01-preview"))
+            .and(header("api-key", "secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/gpt-4o/chat/completions"))
+            .and(query_param("api-version", "2025-01-01-preview"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = AzureOpenAIProvider::configure(
+            format!("{}/", server.uri()),
+            "gpt-4o, gpt-4o-mini".to_string(),
+            Some("2025-01-01-preview".to_string()),
+            Some("secret".to_string()),
+            None,
+        ).unwrap();
+
+        provider.chat(request("gpt-4o-mini")).await.unwrap();
+        let response = provider.chat(request("gpt-4o")).await.unwrap();
+        assert_eq!(response.choices.len(), 1);
+
+        // a model without deployment is an error, not a request to another model
+        let error = provider.chat(request("gpt-5")).await.unwrap_err();
+        assert!(error.to_string().contains("no Azure OpenAI deployment named 'gpt-5'"), "{}", error);
+
+        let models = provider.models().await.unwrap();
+        let ids: Vec<_> = models.data.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["gpt-4o", "gpt-4o-mini"]);
+    }
+
+    #[tokio::test]
+    async fn test_entra_token_auth() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/openai/deployments/gpt-4o/chat/completions"))
+            .and(query_param("api-version", AZURE_OPENAI_API_VERSION))
+            .and(header("authorization", "Bearer entra-token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(completion()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = AzureOpenAIProvider::configure(server.uri(), "gpt-4o".to_string(), None, None, Some("entra-token".to_string())).unwrap();
+        provider.chat(request("gpt-4o")).await.unwrap();
+    }
+
+    #[test]
+    fn test_configure_errors() {
+        assert!(AzureOpenAIProvider::configure("https://x.openai.azure.com".to_string(), " , ".to_string(), None, Some("key".to_string()), None).is_err());
+        assert!(AzureOpenAIProvider::configure("https://x.openai.azure.com".to_string(), "gpt-4o".to_string(), None, None, None).is_err());
+        assert!(AzureOpenAIProvider::new("https://x.openai.azure.com".to_string(), vec![], AzureAuth::ApiKey("key".to_string())).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use serde::{Serialize, Deserialize};
+
+// Gemini generateContent response, the same shape is streamed chunk by chunk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiResponse {
+    #[serde(default)]
+    pub candidates: Vec<GeminiCandidate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_metadata: Option<GeminiUsage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiCandidate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<GeminiContent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub parts: Vec<GeminiPart>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiPart {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// the text is a thought summary, not part of the answer
+    #[serde(default)]
+    pub thought: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thought_signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function_call: Option<GeminiFunctionCall>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GeminiFunctionCall {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub args: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiUsage {
+    #[serde(default)]
+    pub prompt_token_count: u32,
+    #[serde(default)]
+    pub candidates_token_count: u32,
+    #[serde(default)]
+    pub thoughts_token_count: u32,
+    #[serde(default)]
+    pub cached_content_token_count: u32,
+    #[serde(default)]
+    pub total_token_count: u32,
+}
+
+// models listing
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiModelsResponse {
+    #[serde(default)]
+    pub models: Vec<GeminiModel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiModel {
+    /// "models/gemini-2.5-flash"
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_token_limit: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_token_limit: Option<u32>,
+    #[serde(default)]
+    pub supported_generation_methods: Vec<String>,
+    #[serde(default)]
+    pub thinking: bool,
+}
+
+impl GeminiModel {
+    /// Model id without the "models/" prefix, as used in requests
+    pub fn id(&self) -> &str {
+        self.name.strip_prefix("models/").unwrap_or(&self.name)
+    }
+
+    pub fn can_chat(&self) -> bool {
+        self.supported_generation_methods.iter().any(|m| m == "generateContent")
+    }
+}
+
+pub const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
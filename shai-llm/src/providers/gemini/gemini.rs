@@ -0,0 +1,594 @@
+use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use crate::model_info::ModelInfo;
+use crate::audit::{self, AuditCall};
+use super::api::*;
+use crate::providers::{content_text, turn_start};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use futures::StreamExt;
+use openai_dive::v1::resources::{
+    chat::{ChatCompletionParameters, ChatCompletionResponse, ChatCompletionChunkResponse, ChatMessage, DeltaChatMessage, ChatMessageContent, ChatCompletionChoice, ChatCompletionChunkChoice, ChatCompletionTool, ToolCall, Function},
+    model::{ListModelResponse, Model},
+    shared::{FinishReason, Usage},
+};
+
+/// Harm categories the safety threshold applies to
+const HARM_CATEGORIES: [&str; 4] = [
+    "HARM_CATEGORY_HARASSMENT",
+    "HARM_CATEGORY_HATE_SPEECH",
+    "HARM_CATEGORY_SEXUALLY_EXPLICIT",
+    "HARM_CATEGORY_DANGEROUS_CONTENT",
+];
+
+pub struct GeminiProvider {
+    api_key: String,
+    base_url: String,
+    client: Client,
+    /// thinking budget in tokens (-1 lets the model decide), None = only when the request sets a reasoning effort
+    thinking_budget: Option<i32>,
+    /// block threshold for every harm category (BLOCK_NONE, BLOCK_ONLY_HIGH...), None = API defaults
+    safety_threshold: Option<String>,
+    /// thought signatures by tool call id, they are sent back with the calls
+    /// so the model keeps its reasoning across a tool loop
+    signatures: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl GeminiProvider {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            api_key,
+            base_url: GEMINI_API_BASE.to_string(),
+            client: Client::new(),
+            thinking_budget: None,
+            safety_threshold: None,
+            signatures: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Enable thinking with the given budget in tokens, -1 for a dynamic budget
+    pub fn with_thinking(mut self, budget_tokens: i32) -> Self {
+        self.thinking_budget = Some(budget_tokens);
+        self
+    }
+
+    pub fn with_safety_threshold(mut self, threshold: String) -> Self {
+        self.safety_threshold = Some(threshold);
+        self
+    }
+
+    /// Create Gemini provider from environment variables
+    /// Returns None if required environment variables are not set
+    pub fn from_env() -> Option<Self> {
+        let api_key = std::env::var("GEMINI_API_KEY").ok()?;
+        let env = |name: &str| std::env::var(name).ok();
+        Some(Self::configure(api_key, env("GEMINI_BASE_URL"), env("GEMINI_THINKING_BUDGET"), env("GEMINI_SAFETY_THRESHOLD")))
+    }
+
+    /// Provider from its settings as strings, the optional ones are ignored when empty or invalid
+    pub fn configure(api_key: String, base_url: Option<String>, thinking_budget: Option<String>, safety_threshold: Option<String>) -> Self {
+        let mut provider = Self::new(api_key);
+        if let Some(base_url) = base_url.filter(|u| !u.is_empty()) {
+            provider = provider.with_base_url(base_url);
+        }
+        if let Some(budget) = thinking_budget.and_then(|b| b.parse().ok()) {
+            provider = provider.with_thinking(budget);
+        }
+        if let Some(threshold) = safety_threshold.filter(|t| !t.is_empty()) {
+            provider = provider.with_safety_threshold(threshold);
+        }
+        provider
+    }
+
+    pub(crate) fn convert_to_gemini_format(&self, request: &ChatCompletionParameters) -> Value {
+        let params = serde_json::to_value(request).unwrap_or_default();
+        let (system_messages, contents) = self.convert_messages(&request.messages);
+
+        let mut gemini_request = json!({ "contents": contents });
+
+        if !system_messages.is_empty() {
+            gemini_request["systemInstruction"] = json!({
+                "parts": [{"text": system_messages.join("\n\n")}]
+            });
+        }
+
+        if let Some(tools) = &request.tools {
+            gemini_request["tools"] = json!([{ "functionDeclarations": Self::convert_tools(tools) }]);
+        }
+
+        if let Some(config) = Self::convert_tool_choice(&params["tool_choice"]) {
+            gemini_request["toolConfig"] = json!({ "functionCallingConfig": config });
+        }
+
+        let mut generation_config = serde_json::Map::new();
+        for (openai, gemini) in [("temperature", "temperature"), ("top_p", "topP"), ("seed", "seed"),
+                                 ("presence_penalty", "presencePenalty"), ("frequency_penalty", "frequencyPenalty")] {
+            if params[openai].is_number() {
+                generation_config.insert(gemini.to_string(), params[openai].clone());
+            }
+        }
+        if let Some(max_tokens) = params["max_completion_tokens"].as_u64().or(params["max_tokens"].as_u64()) {
+            generation_config.insert("maxOutputTokens".to_string(), json!(max_tokens));
+        }
+        match &params["stop"] {
+            Value::String(stop) => { generation_config.insert("stopSequences".to_string(), json!([stop])); }
+            Value::Array(stops) if !stops.is_empty() => { generation_config.insert("stopSequences".to_string(), json!(stops)); }
+            _ => {}
+        }
+        match params["response_format"]["type"].as_str() {
+            Some("json_object") => {
+                generation_config.insert("responseMimeType".to_string(), json!("application/json"));
+            }
+            Some("json_schema") => {
+                generation_config.insert("responseMimeType".to_string(), json!("application/json"));
+                generation_config.insert("responseJsonSchema".to_string(), params["response_format"]["json_schema"]["schema"].clone());
+            }
+            _ => {}
+        }
+        let budget = self.thinking_budget.or_else(|| Self::effort_budget(params["reasoning_effort"].as_str()));
+        if let Some(budget) = budget {
+            generation_config.insert("thinkingConfig".to_string(), json!({
+                "thinkingBudget": budget,
+                "includeThoughts": budget != 0
+            }));
+        }
+        if !generation_config.is_empty() {
+            gemini_request["generationConfig"] = Value::Object(generation_config);
+        }
+
+        if let Some(threshold) = &self.safety_threshold {
+            gemini_request["safetySettings"] = HARM_CATEGORIES.iter()
+                .map(|category| json!({"category": category, "threshold": threshold}))
+                .collect();
+        }
+
+        gemini_request
+    }
+
+    /// OpenAI tool_choice ("auto", "required", "none" or a named function) to a function calling config
+    fn convert_tool_choice(tool_choice: &Value) -> Option<Value> {
+        match tool_choice {
+            Value::String(choice) => match choice.as_str() {
+                "auto" => Some(json!({"mode": "AUTO"})),
+                "required" => Some(json!({"mode": "ANY"})),
+                "none" => Some(json!({"mode": "NONE"})),
+                _ => None,
+            },
+            Value::Object(_) => tool_choice["function"]["name"].as_str()
+                .map(|name| json!({"mode": "ANY", "allowedFunctionNames": [name]})),
+            _ => None,
+        }
+    }
+
+    /// Thinking budget for an OpenAI reasoning effort
+    fn effort_budget(effort: Option<&str>) -> Option<i32> {
+        match effort? {
+            "minimal" => Some(0),
+            "low" => Some(1024),
+            "medium" => Some(8192),
+            "high" => Some(24576),
+            _ => None,
+        }
+    }
+
+    fn convert_tools(tools: &[ChatCompletionTool]) -> Vec<Value> {
+        tools.iter().map(|tool| {
+            json!({
+                "name": tool.function.name,
+                "description": tool.function.description.as_ref().unwrap_or(&tool.function.name),
+                // full json schema, `parameters` only takes the OpenAPI subset
+                "parametersJsonSchema": tool.function.parameters
+            })
+        }).collect()
+    }
+
+    /// Forget the signatures of the turns before the current one
+    fn evict_signatures(&self, messages: &[ChatMessage]) {
+        let Some(start) = turn_start(messages) else {
+            return;
+        };
+        let mut signatures = self.signatures.lock().unwrap();
+        for message in &messages[..start] {
+            if let ChatMessage::Assistant { tool_calls: Some(calls), .. } = message {
+                for call in calls {
+                    signatures.remove(&call.id);
+                }
+            }
+        }
+    }
+
+    fn convert_messages(&self, messages: &[ChatMessage]) -> (Vec<String>, Vec<Value>) {
+        self.evict_signatures(messages);
+        let mut system_messages = Vec::new();
+        let mut contents: Vec<Value> = Vec::new();
+        // function responses are matched by name, tool messages only carry the call id
+        let mut call_names: HashMap<String, String> = HashMap::new();
+
+        for msg in messages {
+            let (role, parts) = match msg {
+                ChatMessage::System { content, .. } => {
+                    system_messages.push(content_text(content));
+                    continue;
+                }
+                ChatMessage::User { content, .. } | ChatMessage::Developer { content, .. } => {
+                    ("user", Self::convert_user_content(content))
+                }
+                ChatMessage::Assistant { content, tool_calls, .. } => {
+                    let mut parts = Vec::new();
+                    let text = content.as_ref().map(content_text).unwrap_or_default();
+                    if !text.is_empty() {
+                        parts.push(json!({"text": text}));
+                    }
+                    let signatures = self.signatures.lock().unwrap();
+                    for call in tool_calls.iter().flatten() {
+                        call_names.insert(call.id.clone(), call.function.name.clone());
+                        let args: Value = serde_json::from_str(&call.function.arguments).unwrap_or_else(|_| json!({}));
+                        let mut part = json!({"functionCall": {"name": call.function.name, "args": args}});
+                        if let Some(signature) = signatures.get(&call.id) {
+                            part["thoughtSignature"] = json!(signature);
+                        }
+                        parts.push(part);
+                    }
+                    ("model", parts)
+                }
+                ChatMessage::Tool { content, tool_call_id, .. } => {
+                    let name = call_names.get(tool_call_id).cloned().unwrap_or_else(|| tool_call_id.clone());
+                    ("user", vec![json!({
+                        "functionResponse": {
+                            "name": name,
+                            "response": {"content": content_text(content)}
+                        }
+                    })])
+                }
+            };
+
+            if parts.is_empty() {
+                continue;
+            }
+            // consecutive messages of a role make one turn, parallel function responses must be sent together
+            match contents.last_mut() {
+                Some(last) if last["role"] == role => {
+                    last["parts"].as_array_mut().unwrap().extend(parts);
+                }
+                _ => contents.push(json!({"role": role, "parts": parts})),
+            }
+        }
+
+        (system_messages, contents)
+    }
+
+    /// Text and image parts of a user message
+    fn convert_user_content(content: &ChatMessageContent) -> Vec<Value> {
+        let ChatMessageContent::ContentPart(parts) = content else {
+            let text = content_text(content);
+            return if text.is_empty() { vec![] } else { vec![json!({"text": text})] };
+        };
+
+        parts.iter()
+            .filter_map(|part| {
+                let part = serde_json::to_value(part).ok()?;
+                match part["type"].as_str()? {
+                    "text" => Some(json!({"text": part["text"]})),
+                    "image_url" => {
+                        let url = part["image_url"]["url"].as_str()?;
+                        match url.strip_prefix("data:").and_then(|data_url| data_url.split_once(";base64,")) {
+                            Some((mime_type, data)) => Some(json!({"inlineData": {"mimeType": mime_type, "data": data}})),
+                            None => Some(json!({"fileData": {"fileUri": url}})),
+                        }
+                    }
+                    _ => None, // audio, files...
+                }
+            })
+            .collect()
+    }
+
+    /// Split the parts of a candidate into answer, thoughts and tool calls, keeping the thought signatures
+    fn read_parts(parts: &[GeminiPart], signatures: &Mutex<HashMap<String, String>>) -> (String, String, Vec<ToolCall>) {
+        let mut text = String::new();
+        let mut thoughts = String::new();
+        let mut tool_calls = Vec::new();
+
+        for part in parts {
+            if let Some(call) = &part.function_call {
+                let id = call.id.clone().unwrap_or_else(|| format!("call_{}", uuid::Uuid::new_v4().simple()));
+                if let Some(signature) = &part.thought_signature {
+                    signatures.lock().unwrap().insert(id.clone(), signature.clone());
+                }
+                tool_calls.push(ToolCall {
+                    id,
+                    r#type: "function".to_string(),
+                    function: Function {
+                        name: call.name.clone(),
+                        arguments: serde_json::to_string(&call.args).unwrap_or_default(),
+                    }
+                });
+            } else if let Some(part_text) = &part.text {
+                if part.thought { thoughts.push_str(part_text) } else { text.push_str(part_text) }
+            }
+        }
+
+        (text, thoughts, tool_calls)
+    }
+
+    fn convert_finish_reason(reason: Option<&str>, has_tool_calls: bool) -> Option<FinishReason> {
+        let reason = match reason? {
+            "STOP" if has_tool_calls => "tool_calls",
+            "STOP" => "stop",
+            "MAX_TOKENS" => "length",
+            "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => "content_filter",
+            _ => "stop",
+        };
+        serde_json::from_value(json!(reason)).ok()
+    }
+
+    fn convert_usage(usage: &GeminiUsage) -> Usage {
+        // thoughts are billed as output
+        let completion_tokens = usage.candidates_token_count + usage.thoughts_token_count;
+        Usage {
+            input_tokens: None,
+            input_tokens_details: None,
+            output_tokens: None,
+            output_tokens_details: None,
+            prompt_tokens: Some(usage.prompt_token_count),
+            completion_tokens: Some(completion_tokens),
+            total_tokens: usage.prompt_token_count + completion_tokens,
+            prompt_tokens_details: None,
+            completion_tokens_details: None,
+        }
+    }
+
+    pub(crate) fn convert_from_gemini_format(&self, response: GeminiResponse, model: &str) -> Result<ChatCompletionResponse, LlmError> {
+        let candidate = response.candidates.first()
+            .ok_or("Gemini returned no candidate, the prompt may have been blocked")?;
+        let parts = candidate.content.as_ref().map(|c| c.parts.as_slice()).unwrap_or_default();
+        let (text, thoughts, tool_calls) = Self::read_parts(parts, &self.signatures);
+        let finish_reason = Self::convert_finish_reason(candidate.finish_reason.as_deref(), !tool_calls.is_empty());
+
+        Ok(ChatCompletionResponse {
+            id: Some(response.response_id.clone().unwrap_or_else(|| format!("gemini-{}", uuid::Uuid::new_v4()))),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u32,
+            model: response.model_version.clone().unwrap_or_else(|| model.to_string()),
+            choices: vec![ChatCompletionChoice {
+                index: 0,
+                message: ChatMessage::Assistant {
+                    content: (!text.is_empty()).then(|| ChatMessageContent::Text(text)),
+                    reasoning_content: (!thoughts.is_empty()).then_some(thoughts),
+                    refusal: None,
+                    name: None,
+                    audio: None,
+                    tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                },
+                finish_reason,
+                logprobs: None,
+            }],
+            usage: response.usage_metadata.as_ref().map(Self::convert_usage),
+            service_tier: None,
+            system_fingerprint: None,
+        })
+    }
+
+    /// One streamed chunk, `next_tool_index` numbers the tool calls across the stream
+    fn convert_chunk(chunk: GeminiResponse, model: &str, next_tool_index: &mut u32, signatures: &Mutex<HashMap<String, String>>) -> Result<ChatCompletionChunkResponse, LlmError> {
+        let candidate = chunk.candidates.first().cloned().unwrap_or_default();
+        let parts = candidate.content.map(|c| c.parts).unwrap_or_default();
+        let (text, thoughts, tool_calls) = Self::read_parts(&parts, signatures);
+        let finish_reason = Self::convert_finish_reason(candidate.finish_reason.as_deref(), *next_tool_index > 0 || !tool_calls.is_empty());
+
+        // gemini sends each call whole, it becomes a single delta
+        let delta_tool_calls: Vec<Value> = tool_calls.iter()
+            .map(|call| {
+                let delta = json!({
+                    "index": *next_tool_index,
+                    "id": call.id,
+                    "type": "function",
+                    "function": {"name": call.function.name, "arguments": call.function.arguments}
+                });
+                *next_tool_index += 1;
+                delta
+            })
+            .collect();
+
+        Ok(ChatCompletionChunkResponse {
+            id: Some(chunk.response_id.clone().unwrap_or_else(|| format!("gemini-{}", uuid::Uuid::new_v4()))),
+            object: "chat.completion.chunk".to_string(),
+            created: chrono::Utc::now().timestamp() as u32,
+            model: chunk.model_version.clone().unwrap_or_else(|| model.to_string()),
+            choices: vec![ChatCompletionChunkChoice {
+                index: Some(0),
+                delta: DeltaChatMessage::Assistant {
+                    content: (!text.is_empty()).then(|| ChatMessageContent::Text(text)),
+                    reasoning_content: (!thoughts.is_empty()).then_some(thoughts),
+                    refusal: None,
+                    name: None,
+                    tool_calls: if delta_tool_calls.is_empty() { None } else { Some(serde_json::from_value(json!(delta_tool_calls))?) },
+                },
+                finish_reason,
+                logprobs: None,
+            }],
+            usage: chunk.usage_metadata.as_ref().filter(|_| candidate.finish_reason.is_some()).map(Self::convert_usage),
+            system_fingerprint: None,
+        })
+    }
+
+    /// Parse the server sent events of streamGenerateContent, events may be split across network chunks
+    fn parse_gemini_stream(response: reqwest::Response, model: String, signatures: Arc<Mutex<HashMap<String, String>>>) -> LlmStream {
+        let mut bytes = response.bytes_stream();
+        let stream = async_stream::stream! {
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut next_tool_index = 0;
+            while let Some(chunk) = bytes.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        yield Err(Box::new(e) as LlmError);
+                        break;
+                    }
+                };
+                buffer.extend_from_slice(&chunk);
+
+                while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let line = String::from_utf8_lossy(&line);
+                    let Some(data) = line.trim().strip_prefix("data:") else {
+                        continue;
+                    };
+                    match serde_json::from_str::<GeminiResponse>(data.trim()) {
+                        Ok(chunk) => yield Self::convert_chunk(chunk, &model, &mut next_tool_index, &signatures),
+                        Err(e) => yield Err(format!("Failed to parse Gemini event {}: {}", data, e).into()),
+                    }
+                }
+            }
+        };
+        Box::new(Box::pin(stream))
+    }
+
+    async fn post(&self, method: &str, model: &str, body: &Value) -> Result<(reqwest::Response, Option<AuditCall>), LlmError> {
+        let url = format!("{}/models/{}:{}", self.base_url, model, method);
+        let audit = audit::call(&url).map(|call| call.with_secret(&self.api_key));
+        if let Some(audit) = &audit {
+            audit.request(body);
+        }
+
+        let response = self.client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .header("Content-Type", "application/json")
+            .json(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            if let Some(audit) = &audit {
+                audit.error(&error_text);
+            }
+            return Err(format!("Gemini API error: {}", error_text).into());
+        }
+        Ok((response, audit))
+    }
+
+    /// Get Gemini models using their native API format
+    pub async fn gemini_models(&self) -> Result<Vec<GeminiModel>, LlmError> {
+        let mut models = Vec::new();
+        let mut page_token: Option<String> = None;
+        loop {
+            let mut request = self.client
+                .get(format!("{}/models", self.base_url))
+                .header("x-goog-api-key", &self.api_key)
+                .query(&[("pageSize", "1000")]);
+            if let Some(token) = &page_token {
+                request = request.query(&[("pageToken", token)]);
+            }
+
+            let response = request.send().await?;
+            if !response.status().is_success() {
+                let error_text = response.text().await?;
+                return Err(format!("Gemini API error: {}", error_text).into());
+            }
+
+            let page: GeminiModelsResponse = response.json().await?;
+            models.extend(page.models.into_iter().filter(|m| m.can_chat()));
+            match page.next_page_token.filter(|t| !t.is_empty()) {
+                Some(token) => page_token = Some(token),
+                None => return Ok(models),
+            }
+        }
+    }
+}
+
+impl From<&GeminiModel> for ModelInfo {
+    fn from(model: &GeminiModel) -> Self {
+        ModelInfo {
+            id: model.id().to_string(),
+            context_length: model.input_token_limit,
+            max_output_tokens: model.output_token_limit,
+            supports_reasoning: Some(model.thinking),
+            ..ModelInfo::default()
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for GeminiProvider {
+    async fn models(&self) -> Result<ListModelResponse, LlmError> {
+        let models = self.gemini_models().await?;
+        Ok(ListModelResponse {
+            object: "list".to_string(),
+            data: models.iter()
+                .map(|m| Model {
+                    id: m.id().to_string(),
+                    object: "model".to_string(),
+                    created: None,
+                    owned_by: "google".to_string(),
+                })
+                .collect(),
+        })
+    }
+
+    async fn default_model(&self) -> Result<String, LlmError> {
+        let models = self.models().await?;
+        models.data.iter()
+            .find(|m| m.id == "gemini-2.5-flash")
+            .or_else(|| models.data.first())
+            .map(|m| m.id.clone())
+            .ok_or_else(|| "no model available".into())
+    }
+
+    async fn model_info(&self, model: &str) -> Result<Option<ModelInfo>, LlmError> {
+        let models = self.gemini_models().await?;
+        Ok(models.iter()
+            .find(|m| m.id() == model)
+            .map(ModelInfo::from))
+    }
+
+    async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
+        let gemini_request = self.convert_to_gemini_format(&request);
+        let (response, audit) = self.post("generateContent", &request.model, &gemini_request).await?;
+        let gemini_response: Value = response.json().await?;
+        if let Some(audit) = &audit {
+            audit.response(&gemini_response);
+        }
+        let gemini_response: GeminiResponse = serde_json::from_value(gemini_response)?;
+        self.convert_from_gemini_format(gemini_response, &request.model)
+    }
+
+    async fn chat_stream(&self, request: ChatCompletionParameters) -> Result<LlmStream, LlmError> {
+        let gemini_request = self.convert_to_gemini_format(&request);
+        let (response, audit) = self.post("streamGenerateContent?alt=sse", &request.model, &gemini_request).await?;
+        let stream = Self::parse_gemini_stream(response, request.model.clone(), self.signatures.clone());
+        Ok(audit::audit_stream(stream, audit))
+    }
+
+    fn supports_functions(&self, model: String) -> bool {
+        true
+    }
+
+    fn supports_structured_output(&self, model: String) -> bool {
+        true
+    }
+
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn info() -> ProviderInfo {
+        ProviderInfo {
+            name: "gemini",
+            display_name: "Google Gemini (Gemini 2.5 Pro, Gemini 2.5 Flash)",
+            env_vars: vec![
+                EnvVar::required("GEMINI_API_KEY", "Google AI Studio API key"),
+                EnvVar::optional("GEMINI_BASE_URL", "Gemini API base URL (defaults to the public v1beta endpoint)"),
+                EnvVar::optional("GEMINI_THINKING_BUDGET", "Thinking budget in tokens, -1 for dynamic"),
+                EnvVar::optional("GEMINI_SAFETY_THRESHOLD", "Safety block threshold (BLOCK_NONE, BLOCK_ONLY_HIGH...)"),
+            ],
+        }
+    }
+}
@@ -0,0 +1,208 @@
+#[cfg(test)]
+mod tests {
+    use crate::providers::gemini::GeminiProvider;
+    use crate::providers::tests::{chat_error, collect_stream, request, sent_body, tool_exchange};
+    use crate::provider::LlmProvider;
+    use openai_dive::v1::resources::chat::ChatMessage;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn setup_provider(server: &MockServer) -> GeminiProvider {
+        GeminiProvider::new("test-key".to_string()).with_base_url(server.uri())
+    }
+
+    fn read_tool() -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "read",
+                "description": "Read a file",
+                "parameters": {"type": "object", "properties": {"path": {"type": "string"}}}
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn test_chat_with_tool_call() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.5-flash:generateContent"))
+            .and(header("x-goog-api-key", "test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "candidates": [{
+                    "content": {"role": "model", "parts": [
+                        {"text": "I should read it", "thought": true},
+                        {"functionCall": {"name": "read", "args": {"path": "main.py"}}, "thoughtSignature": "sig"}
+                    ]},
+                    "finishReason": "STOP"
+                }],
+                "usageMetadata": {"promptTokenCount": 100, "candidatesTokenCount": 10, "thoughtsTokenCount": 5, "totalTokenCount": 115},
+                "modelVersion": "gemini-2.5-flash",
+                "responseId": "resp-1"
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server);
+        let response = provider.chat(request(json!({
+            "model": "gemini-2.5-flash",
+            "messages": [
+                {"role": "system", "content": "You are a coding assistant."},
+                {"role": "user", "content": "read main.py"}
+            ],
+            "tools": [read_tool()],
+            "tool_choice": "required",
+            "temperature": 0.3,
+            "max_completion_tokens": 500
+        }))).await.unwrap();
+
+        let body = sent_body(&server, "/models/gemini-2.5-flash:generateContent").await;
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "You are a coding assistant.");
+        assert_eq!(body["contents"], json!([{"role": "user", "parts": [{"text": "read main.py"}]}]));
+        assert_eq!(body["tools"][0]["functionDeclarations"][0]["name"], "read");
+        assert_eq!(body["toolConfig"]["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 500);
+        assert!((body["generationConfig"]["temperature"].as_f64().unwrap() - 0.3).abs() < 1e-6);
+
+        let ChatMessage::Assistant { content, reasoning_content, tool_calls, .. } = &response.choices[0].message else {
+            panic!("expected an assistant message");
+        };
+        assert!(content.is_none());
+        assert_eq!(reasoning_content.as_deref(), Some("I should read it"));
+        let tool_calls = tool_calls.as_ref().unwrap();
+        assert_eq!(tool_calls[0].function.name, "read");
+        assert_eq!(serde_json::from_str::<Value>(&tool_calls[0].function.arguments).unwrap(), json!({"path": "main.py"}));
+        let usage = response.usage.as_ref().unwrap();
+        assert_eq!(usage.prompt_tokens, Some(100));
+        assert_eq!(usage.completion_tokens, Some(15));
+        assert_eq!(serde_json::to_value(&response.choices[0].finish_reason).unwrap(), "tool_calls");
+
+        // the tool loop goes on with the call, its signature and the result
+        let call_id = tool_calls[0].id.clone();
+        provider.chat(request(json!({
+            "model": "gemini-2.5-flash",
+            "messages": tool_exchange(&call_id)
+        }))).await.unwrap();
+
+        let body = sent_body(&server, "/models/gemini-2.5-flash:generateContent").await;
+        let contents = body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(contents[1]["parts"][0]["functionCall"], json!({"name": "read", "args": {"path": "main.py"}}));
+        assert_eq!(contents[1]["parts"][0]["thoughtSignature"], "sig");
+        assert_eq!(contents[2]["parts"][0]["functionResponse"], json!({"name": "read", "response": {"content": "print('hello')"}}));
+    }
+
+        // once the user speaks again the signatures of the earlier turn are dropped
+        let mut messages = tool_exchange(&call_id);
+        messages.as_array_mut().unwrap().extend([
+            json!({"role": "assistant", "content": "Done"}),
+            json!({"role": "user", "content": "thanks"}),
+        ]);
+        provider.chat(request(json!({"model": "gemini-2.5-flash", "messages": messages}))).await.unwrap();
+        let body = sent_body(&server, "/models/gemini-2.5-flash:generateContent").await;
+        assert!(body["contents"][1]["parts"][0].get("thoughtSignature").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream() {
+        let server = MockServer::start().await;
+        let events = [
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "Let me think", "thought": true}]}}]}),
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": "Hello"}]}}]}),
+            json!({"candidates": [{"content": {"role": "model", "parts": [{"text": " world"}]}, "finishReason": "STOP"}],
+                   "usageMetadata": {"promptTokenCount": 8, "candidatesTokenCount": 2, "totalTokenCount": 10}}),
+        ];
+        let sse: String = events.iter().map(|e| format!("data: {}\r\n\r\n", e)).collect();
+        Mock::given(method("POST"))
+            .and(path("/models/gemini-2.5-flash:streamGenerateContent"))
+            .and(query_param("alt", "sse"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server);
+        let stream = provider.chat_stream(request(json!({
+            "model": "gemini-2.5-flash",
+            "messages": [{"role": "user", "content": "Hello!"}]
+        }))).await.unwrap();
+
+        let answer = collect_stream(stream).await;
+        assert_eq!(answer.content, "Hello world");
+        assert_eq!(answer.reasoning, "Let me think");
+        assert_eq!(answer.usage.unwrap().total_tokens, 10);
+    }
+
+    #[tokio::test]
+    async fn test_models_and_model_info() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "models": [
+                    {"name": "models/text-embedding-004", "supportedGenerationMethods": ["embedContent"]},
+                    {"name": "models/gemini-2.5-pro", "inputTokenLimit": 1048576, "outputTokenLimit": 65536,
+                     "supportedGenerationMethods": ["generateContent", "countTokens"], "thinking": true},
+                    {"name": "models/gemini-2.5-flash", "inputTokenLimit": 1048576, "outputTokenLimit": 65536,
+                     "supportedGenerationMethods": ["generateContent"], "thinking": true}
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server);
+        let models = provider.models().await.unwrap();
+        let ids: Vec<_> = models.data.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["gemini-2.5-pro", "gemini-2.5-flash"]);
+        assert_eq!(provider.default_model().await.unwrap(), "gemini-2.5-flash");
+
+        let info = provider.model_info("gemini-2.5-pro").await.unwrap().unwrap();
+        assert_eq!(info.context_length, Some(1048576));
+        assert_eq!(info.max_output_tokens, Some(65536));
+        assert_eq!(info.supports_reasoning, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(400).set_body_string("API key not valid"))
+            .mount(&server)
+            .await;
+
+        let error = chat_error(&setup_provider(&server), "gemini-2.5-flash").await;
+        assert!(error.contains("API key not valid"));
+    }
+
+    #[test]
+    fn test_request_conversion() {
+        let provider = GeminiProvider::new("test-key".to_string())
+            .with_thinking(-1)
+            .with_safety_threshold("BLOCK_ONLY_HIGH".to_string());
+        let gemini_format = provider.convert_to_gemini_format(&request(json!({
+            "model": "gemini-2.5-flash",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "what is this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}}
+            ]}],
+            "tools": [read_tool()],
+            "tool_choice": {"type": "function", "function": {"name": "read"}},
+            "stop": "END",
+            "response_format": {"type": "json_schema", "json_schema": {"name": "answer", "schema": {"type": "object"}}}
+        })));
+
+        assert_eq!(gemini_format["contents"][0]["parts"], json!([
+            {"text": "what is this?"},
+            {"inlineData": {"mimeType": "image/png", "data": "iVBORw0KGgo="}}
+        ]));
+        assert_eq!(gemini_format["toolConfig"]["functionCallingConfig"], json!({"mode": "ANY", "allowedFunctionNames": ["read"]}));
+        let config = &gemini_format["generationConfig"];
+        assert_eq!(config["stopSequences"], json!(["END"]));
+        assert_eq!(config["responseMimeType"], "application/json");
+        assert_eq!(config["responseJsonSchema"], json!({"type": "object"}));
+        assert_eq!(config["thinkingConfig"], json!({"thinkingBudget": -1, "includeThoughts": true}));
+        assert_eq!(gemini_format["safetySettings"].as_array().unwrap().len(), 4);
+        assert_eq!(gemini_format["safetySettings"][0]["threshold"], "BLOCK_ONLY_HIGH");
+    }
+}
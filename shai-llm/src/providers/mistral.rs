@@ -1,6 +1,8 @@
 // Mistral provider using flexible chat client with JSON hooks
 use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
 use crate::chat::{ChatClient, JsonHooks};
+use crate::model_info::ModelInfo;
+use crate::reasoning::normalize_response_json;
 use serde_json::Value;
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -56,6 +58,18 @@ impl From<MistralModel> for Model {
     }
 }
 
+impl From<&MistralModel> for ModelInfo {
+    fn from(mistral_model: &MistralModel) -> Self {
+        ModelInfo {
+            id: mistral_model.id.clone(),
+            context_length: u32::try_from(mistral_model.max_context_length).ok(),
+            supports_tools: Some(mistral_model.capabilities.function_calling),
+            supports_vision: Some(mistral_model.capabilities.vision),
+            ..ModelInfo::default()
+        }
+    }
+}
+
 pub struct MistralProvider {
     client: ChatClient,
     hooks: MistralHooks,
@@ -77,6 +91,23 @@ impl MistralProvider {
             .ok()
             .map(|api_key| Self::new(api_key))
     }
+
+    /// Fetch the models with their capabilities
+    async fn mistral_models(&self) -> Result<MistralListModelResponse, LlmError> {
+        let url = format!("{}/models", self.client.base_url);
+        
+        let response = self.client.http_client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", self.client.api_key))
+            .send()
+            .await
+            .map_err(|e| Box::new(e) as LlmError)?;
+            
+        response
+            .json()
+            .await
+            .map_err(|e| Box::new(e) as LlmError)
+    }
 }
 
 
@@ -115,6 +146,8 @@ impl JsonHooks for MistralHooks {
                 }
             }
         }
+        // magistral models send their thinking as content parts
+        normalize_response_json(&mut json);
         Ok(json)
     }
     
@@ -136,6 +169,7 @@ impl JsonHooks for MistralHooks {
                 }
             }
         }
+        normalize_response_json(&mut json);
         Ok(json)
     }
 }
@@ -144,19 +178,7 @@ impl JsonHooks for MistralHooks {
 impl LlmProvider for MistralProvider {
     async fn models(&self) -> Result<ListModelResponse, LlmError> {
         // Fetch models from Mistral API using the existing HTTP client
-        let url = format!("{}/models", self.client.base_url);
-        
-        let response = self.client.http_client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.client.api_key))
-            .send()
-            .await
-            .map_err(|e| Box::new(e) as LlmError)?;
-            
-        let mistral_response: MistralListModelResponse = response
-            .json()
-            .await
-            .map_err(|e| Box::new(e) as LlmError)?;
+        let mistral_response = self.mistral_models().await?;
         
         // Filter models that support function calling and convert to OpenAI format
         let filtered_models: Vec<Model> = mistral_response.data
@@ -175,6 +197,13 @@ impl LlmProvider for MistralProvider {
         Ok("mistral-small-latest".to_string())
     }
 
+    async fn model_info(&self, model: &str) -> Result<Option<ModelInfo>, LlmError> {
+        let mistral_response = self.mistral_models().await?;
+        Ok(mistral_response.data.iter()
+            .find(|m| m.id == model || m.aliases.iter().any(|alias| alias == model))
+            .map(ModelInfo::from))
+    }
+
     async fn chat(&self, mut request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
         // Mistral uses max_tokens instead of max_completion_tokens
         if request.max_completion_tokens.is_some() {
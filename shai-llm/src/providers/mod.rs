@@ -1,11 +1,52 @@
 pub mod openai;
 pub mod openai_compatible;
+pub mod openai_responses;
+pub mod azure_openai;
 pub mod openrouter;
 pub mod ovhcloud;
 pub mod anthropic;
+pub mod gemini;
 pub mod ollama;
 pub mod mistral;
+pub mod replay;
 // pub mod mistral_native; // TODO: Complete implementation
 
+use openai_dive::v1::resources::chat::{ChatMessage, ChatMessageContent, ChatMessageContentPart};
+
+/// Text of a message for the apis taking plain text, images and audio are left out
+pub(crate) fn content_text(content: &ChatMessageContent) -> String {
+    match content {
+        ChatMessageContent::Text(text) => text.clone(),
+        ChatMessageContent::ContentPart(parts) => parts.iter()
+            .filter_map(|part| match part {
+                ChatMessageContentPart::Text(text_part) => Some(text_part.text.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        ChatMessageContent::None => String::new(),
+    }
+}
+
+/// Position of the user message starting the current turn, None before the first one.
+/// User messages following tool calls (agent notes, messages queued while the tools ran)
+/// belong to the tool loop and do not start a turn
+pub(crate) fn turn_start(messages: &[ChatMessage]) -> Option<usize> {
+    (0..messages.len()).rev()
+        .filter(|&i| matches!(messages[i], ChatMessage::User { .. }))
+        .find(|&i| !in_tool_loop(&messages[..i]))
+}
+
+/// Whether the last message from the model or a tool is a tool call or result
+fn in_tool_loop(messages: &[ChatMessage]) -> bool {
+    let last = messages.iter().rev()
+        .find(|m| !matches!(m, ChatMessage::User { .. } | ChatMessage::System { .. } | ChatMessage::Developer { .. }));
+    match last {
+        Some(ChatMessage::Tool { .. }) => true,
+        Some(ChatMessage::Assistant { tool_calls: Some(calls), .. }) => !calls.is_empty(),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests;
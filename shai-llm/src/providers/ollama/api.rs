@@ -0,0 +1,116 @@
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+// /api/chat response, the same shape is streamed line by line
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OllamaChatResponse {
+    #[serde(default)]
+    pub model: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<OllamaMessage>,
+    #[serde(default)]
+    pub done: bool,
+    /// "stop", "length", "load"...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub done_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_eval_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eval_count: Option<u32>,
+    /// set instead of the message when the request failed mid stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OllamaMessage {
+    #[serde(default)]
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<OllamaToolCall>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OllamaToolCall {
+    pub function: OllamaFunction,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OllamaFunction {
+    pub name: String,
+    /// an object, not a json string as with OpenAI
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+// /api/show
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OllamaShowResponse {
+    /// parameters of the modelfile, one "name value" per line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<String>,
+    /// gguf metadata, e.g. "llama.context_length"
+    #[serde(default)]
+    pub model_info: serde_json::Map<String, Value>,
+    /// "completion", "tools", "vision", "thinking"...
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+impl OllamaShowResponse {
+    /// Context length the model was trained for
+    pub fn context_length(&self) -> Option<u32> {
+        self.model_info.iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+            .map(|length| length as u32)
+    }
+
+    /// num_ctx set by the modelfile, the server uses it when the request does not
+    pub fn num_ctx(&self) -> Option<u32> {
+        self.parameters.as_deref()?
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("num_ctx"))
+            .find_map(|value| value.trim().parse().ok())
+    }
+
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+// /api/pull progress, streamed line by line until the status is "success"
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OllamaPullProgress {
+    #[serde(default)]
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl OllamaPullProgress {
+    /// Percentage of the layer being downloaded, if any
+    pub fn percent(&self) -> Option<u64> {
+        match (self.completed, self.total) {
+            (Some(completed), Some(total)) if total > 0 => Some(completed * 100 / total),
+            _ => None,
+        }
+    }
+}
+
+pub const OLLAMA_BASE_URL: &str = "http://127.0.0.1:11434/v1";
+/// num_ctx given to models whose modelfile does not set one, their full
+/// context would take too much memory on most machines
+pub const OLLAMA_DEFAULT_MAX_NUM_CTX: u32 = 32768;
+/// num_ctx the server loads a model with when neither the request nor the modelfile set one
+pub const OLLAMA_SERVER_NUM_CTX: u32 = 4096;
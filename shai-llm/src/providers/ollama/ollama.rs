@@ -0,0 +1,569 @@
+// llm/providers/ollama/ollama.rs
+use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use crate::chat::ChatClient;
+use crate::model_info::ModelInfo;
+use crate::reasoning::ReasoningHooks;
+use crate::audit::{self, AuditCall};
+use super::api::*;
+use crate::providers::content_text;
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::warn;
+use openai_dive::v1::{
+    api::Client,
+    resources::{
+        chat::{ChatCompletionParameters, ChatCompletionResponse, ChatCompletionChunkResponse, ChatMessage, DeltaChatMessage, ChatMessageContent, ChatMessageContentPart, ChatCompletionChoice, ChatCompletionChunkChoice, ToolCall, Function},
+        model::ListModelResponse,
+        shared::{FinishReason, Usage},
+    },
+};
+
+pub type PullStream = Box<dyn Stream<Item = Result<OllamaPullProgress, LlmError>> + Send + Unpin>;
+
+pub struct OllamaProvider {
+    client: Client,
+    /// chat client normalizing the `reasoning` field of thinking models
+    chat_client: ChatClient,
+    http: reqwest::Client,
+    /// server root of the native api, without the /v1 of the OpenAI compatible one
+    native_url: String,
+    /// chat through /api/chat instead of the OpenAI compatible endpoint, which ignores the options below
+    native: bool,
+    /// context size, None = what the modelfile sets or the model context up to OLLAMA_DEFAULT_MAX_NUM_CTX
+    num_ctx: Option<u32>,
+    /// how long the model stays loaded after a request, e.g. "30m" or "-1"
+    keep_alive: Option<String>,
+    /// maximum number of tokens of a completion when the request does not set one
+    num_predict: Option<i32>,
+    /// /api/show answers by model
+    shown: Mutex<HashMap<String, OllamaShowResponse>>,
+}
+
+impl OllamaProvider {
+    pub fn new(base_url: Option<String>) -> Self {
+        let mut client = Client::new(String::new());
+        let url = base_url.unwrap_or_else(|| OLLAMA_BASE_URL.to_string());
+        client.set_base_url(&url);
+        let chat_client = ChatClient::new(String::new(), url.clone());
+        let native_url = url.trim_end_matches('/').trim_end_matches("/v1").to_string();
+        Self {
+            client,
+            chat_client,
+            http: reqwest::Client::new(),
+            native_url,
+            native: false,
+            num_ctx: None,
+            keep_alive: None,
+            num_predict: None,
+            shown: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Chat through the native /api/chat endpoint
+    pub fn with_native(mut self, native: bool) -> Self {
+        self.native = native;
+        self
+    }
+
+    pub fn with_num_ctx(mut self, num_ctx: u32) -> Self {
+        self.num_ctx = Some(num_ctx);
+        self
+    }
+
+    pub fn with_keep_alive(mut self, keep_alive: String) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    pub fn with_num_predict(mut self, num_predict: i32) -> Self {
+        self.num_predict = Some(num_predict);
+        self
+    }
+
+    /// Create Ollama provider from environment variables
+    /// Returns None if required environment variables are not set
+    pub fn from_env() -> Option<Self> {
+        let base_url = std::env::var("OLLAMA_BASE_URL").ok()?;
+        let env = |name: &str| std::env::var(name).ok();
+        Some(Self::configure(Some(base_url), env("OLLAMA_NATIVE"), env("OLLAMA_NUM_CTX"), env("OLLAMA_KEEP_ALIVE"), env("OLLAMA_NUM_PREDICT")))
+    }
+
+    /// Provider from its settings as strings, the optional ones are ignored when empty or invalid
+    pub fn configure(base_url: Option<String>, native: Option<String>, num_ctx: Option<String>, keep_alive: Option<String>, num_predict: Option<String>) -> Self {
+        let mut provider = Self::new(base_url.filter(|u| !u.is_empty()));
+        if let Some(native) = native {
+            provider = provider.with_native(matches!(native.to_lowercase().as_str(), "1" | "true" | "yes" | "on"));
+        }
+        if let Some(num_ctx) = num_ctx.and_then(|n| n.parse().ok()) {
+            provider = provider.with_num_ctx(num_ctx);
+        }
+        if let Some(keep_alive) = keep_alive.filter(|k| !k.is_empty()) {
+            provider = provider.with_keep_alive(keep_alive);
+        }
+        if let Some(num_predict) = num_predict.and_then(|n| n.parse().ok()) {
+            provider = provider.with_num_predict(num_predict);
+        }
+        provider
+    }
+
+    async fn post(&self, endpoint: &str, body: &Value) -> Result<reqwest::Response, LlmError> {
+        let response = self.http
+            .post(format!("{}/api/{}", self.native_url, endpoint))
+            .json(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(format!("Ollama API error: {}", error_text).into());
+        }
+        Ok(response)
+    }
+
+    /// Details of a model installed on the server, fails if it is not installed
+    pub async fn show(&self, model: &str) -> Result<OllamaShowResponse, LlmError> {
+        if let Some(shown) = self.shown.lock().unwrap().get(model) {
+            return Ok(shown.clone());
+        }
+        let response = self.post("show", &json!({"model": model})).await?;
+        let shown: OllamaShowResponse = response.json().await?;
+        self.shown.lock().unwrap().insert(model.to_string(), shown.clone());
+        Ok(shown)
+    }
+
+    /// Download a model from the Ollama library, the stream ends once it is installed
+    pub async fn pull(&self, model: &str) -> Result<PullStream, LlmError> {
+        let response = self.post("pull", &json!({"model": model, "stream": true})).await?;
+        let stream = Self::parse_lines::<OllamaPullProgress>(response).map(|progress| match progress {
+            Ok(OllamaPullProgress { error: Some(error), .. }) => Err(format!("Ollama pull error: {}", error).into()),
+            progress => progress,
+        });
+        Ok(Box::new(Box::pin(stream)))
+    }
+
+    /// Context size the server will use for this model
+    async fn effective_num_ctx(&self, model: &str) -> Option<u32> {
+        if self.num_ctx.is_some() {
+            return self.num_ctx;
+        }
+        let shown = self.show(model).await.ok()?;
+        shown.num_ctx().or_else(|| shown.context_length().map(|length| length.min(OLLAMA_DEFAULT_MAX_NUM_CTX)))
+    }
+
+    /// num_ctx to send, None leaves the one of the modelfile
+    async fn request_num_ctx(&self, model: &str) -> Option<u32> {
+        if self.num_ctx.is_some() {
+            return self.num_ctx;
+        }
+        let shown = match self.show(model).await {
+            Ok(shown) => shown,
+            Err(e) => {
+                warn!(target: "llm::ollama", model, error = %e, "could not read the model settings, the server picks the context size");
+                return None;
+            }
+        };
+        match shown.num_ctx() {
+            Some(_) => None,
+            None => shown.context_length().map(|length| length.min(OLLAMA_DEFAULT_MAX_NUM_CTX)),
+        }
+    }
+
+    pub(crate) async fn convert_to_ollama_format(&self, request: &ChatCompletionParameters, stream: bool) -> Value {
+        let params = serde_json::to_value(request).unwrap_or_default();
+
+        let mut ollama_request = json!({
+            "model": request.model,
+            "messages": Self::convert_messages(&request.messages),
+            "stream": stream,
+        });
+
+        if let Some(tools) = &request.tools {
+            ollama_request["tools"] = serde_json::to_value(tools).unwrap_or_default();
+        }
+
+        let mut options = serde_json::Map::new();
+        for name in ["temperature", "top_p", "seed", "presence_penalty", "frequency_penalty"] {
+            if params[name].is_number() {
+                options.insert(name.to_string(), params[name].clone());
+            }
+        }
+        match &params["stop"] {
+            Value::String(stop) => { options.insert("stop".to_string(), json!([stop])); }
+            Value::Array(stops) if !stops.is_empty() => { options.insert("stop".to_string(), json!(stops)); }
+            _ => {}
+        }
+        let num_predict = params["max_completion_tokens"].as_i64()
+            .or(params["max_tokens"].as_i64())
+            .or(self.num_predict.map(i64::from));
+        if let Some(num_predict) = num_predict {
+            options.insert("num_predict".to_string(), json!(num_predict));
+        }
+        if let Some(num_ctx) = self.request_num_ctx(&request.model).await {
+            options.insert("num_ctx".to_string(), json!(num_ctx));
+        }
+        if !options.is_empty() {
+            ollama_request["options"] = Value::Object(options);
+        }
+
+        match params["response_format"]["type"].as_str() {
+            Some("json_object") => ollama_request["format"] = json!("json"),
+            Some("json_schema") => ollama_request["format"] = params["response_format"]["json_schema"]["schema"].clone(),
+            _ => {}
+        }
+        if params["reasoning_effort"].is_string() {
+            ollama_request["think"] = json!(true);
+        }
+        if let Some(keep_alive) = &self.keep_alive {
+            // a number of seconds or a duration like "30m"
+            ollama_request["keep_alive"] = keep_alive.parse::<i64>().map(|seconds| json!(seconds)).unwrap_or_else(|_| json!(keep_alive));
+        }
+
+        ollama_request
+    }
+
+    fn convert_messages(messages: &[ChatMessage]) -> Vec<Value> {
+        // tool results are matched by name, tool messages only carry the call id
+        let mut call_names: HashMap<String, String> = HashMap::new();
+
+        messages.iter().map(|msg| match msg {
+            ChatMessage::System { content, .. } | ChatMessage::Developer { content, .. } => {
+                json!({"role": "system", "content": content_text(content)})
+            }
+            ChatMessage::User { content, .. } => {
+                let mut message = json!({"role": "user", "content": content_text(content)});
+                let images = Self::extract_images(content);
+                if !images.is_empty() {
+                    message["images"] = json!(images);
+                }
+                message
+            }
+            ChatMessage::Assistant { content, reasoning_content, tool_calls, .. } => {
+                let mut message = json!({
+                    "role": "assistant",
+                    "content": content.as_ref().map(content_text).unwrap_or_default()
+                });
+                if let Some(thinking) = reasoning_content {
+                    message["thinking"] = json!(thinking);
+                }
+                let calls: Vec<Value> = tool_calls.iter().flatten()
+                    .map(|call| {
+                        call_names.insert(call.id.clone(), call.function.name.clone());
+                        let arguments: Value = serde_json::from_str(&call.function.arguments).unwrap_or_else(|_| json!({}));
+                        json!({"function": {"name": call.function.name, "arguments": arguments}})
+                    })
+                    .collect();
+                if !calls.is_empty() {
+                    message["tool_calls"] = json!(calls);
+                }
+                message
+            }
+            ChatMessage::Tool { content, tool_call_id, .. } => {
+                let mut message = json!({"role": "tool", "content": content_text(content)});
+                if let Some(name) = call_names.get(tool_call_id) {
+                    message["tool_name"] = json!(name);
+                }
+                message
+            }
+        }).collect()
+    }
+
+    /// Base64 data of the images of a message, ollama does not fetch urls
+    fn extract_images(content: &ChatMessageContent) -> Vec<String> {
+        let ChatMessageContent::ContentPart(parts) = content else {
+            return vec![];
+        };
+        parts.iter()
+            .filter_map(|part| {
+                let part = serde_json::to_value(part).ok()?;
+                let url = part["image_url"]["url"].as_str()?;
+                url.split_once(";base64,").map(|(_, data)| data.to_string())
+            })
+            .collect()
+    }
+
+    fn convert_tool_calls(calls: &[OllamaToolCall]) -> Vec<ToolCall> {
+        calls.iter()
+            .map(|call| ToolCall {
+                id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                r#type: "function".to_string(),
+                function: Function {
+                    name: call.function.name.clone(),
+                    arguments: serde_json::to_string(&call.function.arguments).unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    fn convert_finish_reason(reason: Option<&str>, has_tool_calls: bool) -> Option<FinishReason> {
+        let reason = match reason {
+            _ if has_tool_calls => "tool_calls",
+            Some("length") => "length",
+            _ => "stop",
+        };
+        serde_json::from_value(json!(reason)).ok()
+    }
+
+    fn convert_usage(response: &OllamaChatResponse) -> Option<Usage> {
+        let prompt_tokens = response.prompt_eval_count?;
+        let completion_tokens = response.eval_count.unwrap_or(0);
+        Some(Usage {
+            input_tokens: None,
+            input_tokens_details: None,
+            output_tokens: None,
+            output_tokens_details: None,
+            prompt_tokens: Some(prompt_tokens),
+            completion_tokens: Some(completion_tokens),
+            total_tokens: prompt_tokens + completion_tokens,
+            prompt_tokens_details: None,
+            completion_tokens_details: None,
+        })
+    }
+
+    pub(crate) fn convert_from_ollama_format(response: OllamaChatResponse, model: &str) -> Result<ChatCompletionResponse, LlmError> {
+        if let Some(error) = &response.error {
+            return Err(format!("Ollama API error: {}", error).into());
+        }
+        let message = response.message.clone().unwrap_or_default();
+        let tool_calls = Self::convert_tool_calls(&message.tool_calls);
+        let thinking = message.thinking.filter(|t| !t.is_empty());
+
+        Ok(ChatCompletionResponse {
+            id: Some(format!("ollama-{}", uuid::Uuid::new_v4())),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u32,
+            model: if response.model.is_empty() { model.to_string() } else { response.model.clone() },
+            choices: vec![ChatCompletionChoice {
+                index: 0,
+                message: ChatMessage::Assistant {
+                    content: (!message.content.is_empty()).then(|| ChatMessageContent::Text(message.content)),
+                    reasoning_content: thinking,
+                    refusal: None,
+                    name: None,
+                    audio: None,
+                    tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                },
+                finish_reason: Self::convert_finish_reason(response.done_reason.as_deref(), !message.tool_calls.is_empty()),
+                logprobs: None,
+            }],
+            usage: Self::convert_usage(&response),
+            service_tier: None,
+            system_fingerprint: None,
+        })
+    }
+
+    /// One streamed line, `next_tool_index` numbers the tool calls across the stream
+    fn convert_chunk(chunk: OllamaChatResponse, model: &str, next_tool_index: &mut u32) -> Result<ChatCompletionChunkResponse, LlmError> {
+        if let Some(error) = &chunk.error {
+            return Err(format!("Ollama API error: {}", error).into());
+        }
+        let message = chunk.message.clone().unwrap_or_default();
+        let tool_calls = Self::convert_tool_calls(&message.tool_calls);
+
+        // ollama sends each call whole, it becomes a single delta
+        let delta_tool_calls: Vec<Value> = tool_calls.iter()
+            .map(|call| {
+                let delta = json!({
+                    "index": *next_tool_index,
+                    "id": call.id,
+                    "type": "function",
+                    "function": {"name": call.function.name, "arguments": call.function.arguments}
+                });
+                *next_tool_index += 1;
+                delta
+            })
+            .collect();
+        let finish_reason = chunk.done
+            .then(|| Self::convert_finish_reason(chunk.done_reason.as_deref(), *next_tool_index > 0))
+            .flatten();
+
+        Ok(ChatCompletionChunkResponse {
+            id: Some(format!("ollama-{}", uuid::Uuid::new_v4())),
+            object: "chat.completion.chunk".to_string(),
+            created: chrono::Utc::now().timestamp() as u32,
+            model: if chunk.model.is_empty() { model.to_string() } else { chunk.model.clone() },
+            choices: vec![ChatCompletionChunkChoice {
+                index: Some(0),
+                delta: DeltaChatMessage::Assistant {
+                    content: (!message.content.is_empty()).then(|| ChatMessageContent::Text(message.content)),
+                    reasoning_content: message.thinking.filter(|t| !t.is_empty()),
+                    refusal: None,
+                    name: None,
+                    tool_calls: if delta_tool_calls.is_empty() { None } else { Some(serde_json::from_value(json!(delta_tool_calls))?) },
+                },
+                finish_reason,
+                logprobs: None,
+            }],
+            usage: if chunk.done { Self::convert_usage(&chunk) } else { None },
+            system_fingerprint: None,
+        })
+    }
+
+    /// Parse a stream of json lines, lines may be split across network chunks
+    fn parse_lines<T: DeserializeOwned + Send + 'static>(response: reqwest::Response) -> impl Stream<Item = Result<T, LlmError>> + Send {
+        let mut bytes = response.bytes_stream();
+        async_stream::stream! {
+            let mut buffer: Vec<u8> = Vec::new();
+            while let Some(chunk) = bytes.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        yield Err(Box::new(e) as LlmError);
+                        break;
+                    }
+                };
+                buffer.extend_from_slice(&chunk);
+
+                while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let line = String::from_utf8_lossy(&line);
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    yield serde_json::from_str::<T>(line.trim())
+                        .map_err(|e| format!("Failed to parse Ollama line {}: {}", line.trim(), e).into());
+                }
+            }
+        }
+    }
+
+    async fn native_post(&self, body: &Value) -> Result<(reqwest::Response, Option<AuditCall>), LlmError> {
+        let url = format!("{}/api/chat", self.native_url);
+        let audit = audit::call(&url);
+        if let Some(audit) = &audit {
+            audit.request(body);
+        }
+
+        let response = self.http.post(url).json(body).send().await?;
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            if let Some(audit) = &audit {
+                audit.error(&error_text);
+            }
+            return Err(format!("Ollama API error: {}", error_text).into());
+        }
+        Ok((response, audit))
+    }
+
+    async fn native_chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
+        let ollama_request = self.convert_to_ollama_format(&request, false).await;
+        let (response, audit) = self.native_post(&ollama_request).await?;
+        let ollama_response: Value = response.json().await?;
+        if let Some(audit) = &audit {
+            audit.response(&ollama_response);
+        }
+        let ollama_response: OllamaChatResponse = serde_json::from_value(ollama_response)?;
+        Self::convert_from_ollama_format(ollama_response, &request.model)
+    }
+
+    async fn native_chat_stream(&self, request: ChatCompletionParameters) -> Result<LlmStream, LlmError> {
+        let ollama_request = self.convert_to_ollama_format(&request, true).await;
+        let (response, audit) = self.native_post(&ollama_request).await?;
+
+        let model = request.model.clone();
+        let mut next_tool_index = 0;
+        let stream = Self::parse_lines::<OllamaChatResponse>(response)
+            .map(move |line| line.and_then(|chunk| Self::convert_chunk(chunk, &model, &mut next_tool_index)));
+        Ok(audit::audit_stream(Box::new(Box::pin(stream)), audit))
+    }
+}
+
+impl From<&OllamaShowResponse> for ModelInfo {
+    fn from(shown: &OllamaShowResponse) -> Self {
+        ModelInfo {
+            context_length: shown.context_length(),
+            supports_tools: Some(shown.has_capability("tools")),
+            supports_vision: Some(shown.has_capability("vision")),
+            supports_reasoning: Some(shown.has_capability("thinking")),
+            ..ModelInfo::default()
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OllamaProvider {
+    async fn models(&self) -> Result<ListModelResponse, LlmError> {
+        let response = self.client.models().list().await
+            .map_err(|e| Box::new(e) as LlmError)?;
+        Ok(response)
+    }
+
+    async fn default_model(&self) -> Result<String, LlmError> {
+        let models = self.models().await?; // Get the models
+
+        models.data.iter()
+            .find(|m| m.id.to_lowercase().contains("smol"))
+            .or_else(|| models.data.first())
+            .map(|m| m.id.clone())
+            .ok_or_else(|| "no model available".into())
+    }
+
+    async fn model_info(&self, model: &str) -> Result<Option<ModelInfo>, LlmError> {
+        let shown = self.show(model).await?;
+        let mut info = ModelInfo { id: model.to_string(), ..ModelInfo::from(&shown) };
+        // the model only gets the context it was loaded with, which the OpenAI compatible
+        // api cannot set: it is the one of the modelfile or the server default
+        info.context_length = if self.native {
+            self.effective_num_ctx(model).await.or(info.context_length)
+        } else {
+            let num_ctx = shown.num_ctx().unwrap_or(OLLAMA_SERVER_NUM_CTX);
+            Some(info.context_length.map_or(num_ctx, |length| length.min(num_ctx)))
+        };
+        Ok(Some(info))
+    }
+
+    async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
+        if self.native {
+            return self.native_chat(request).await;
+        }
+        let response = self.chat_client.chat_completion(&request, &ReasoningHooks).await
+            .map_err(|e| Box::new(e) as LlmError)?;
+        Ok(response)
+    }
+
+    async fn chat_stream(&self, mut request: ChatCompletionParameters) -> Result<LlmStream, LlmError> {
+        if self.native {
+            return self.native_chat_stream(request).await;
+        }
+        request.stream = Some(true);
+
+        let stream = self.chat_client.chat_completion_stream(&request, ReasoningHooks).await
+            .map_err(|e| Box::new(e) as LlmError)?;
+
+        let converted_stream = stream.map(|result| {
+            result.map_err(|e| Box::new(e) as LlmError)
+        });
+
+        Ok(Box::new(Box::pin(converted_stream)))
+    }
+
+    fn supports_functions(&self, model: String) -> bool {
+        true
+    }
+
+    fn supports_structured_output(&self, model: String) -> bool {
+        true
+    }
+
+    fn name(&self) -> &'static str {
+        "ollama"
+    }
+
+    fn info() -> ProviderInfo {
+        ProviderInfo {
+            name: "ollama",
+            display_name: "Ollama",
+            env_vars: vec![
+                EnvVar::optional("OLLAMA_BASE_URL", "ollama base open ai compat url"),
+                EnvVar::optional("OLLAMA_NATIVE", "Use the native /api/chat endpoint, needed for the options below (true/false)"),
+                EnvVar::optional("OLLAMA_NUM_CTX", "Context size in tokens (defaults to the model context, up to 32768)"),
+                EnvVar::optional("OLLAMA_KEEP_ALIVE", "How long the model stays loaded, e.g. 30m or -1"),
+                EnvVar::optional("OLLAMA_NUM_PREDICT", "Maximum tokens of a completion"),
+            ],
+        }
+    }
+
+}
@@ -0,0 +1,213 @@
+#[cfg(test)]
+mod tests {
+    use crate::providers::ollama::OllamaProvider;
+    use crate::providers::tests::{collect_stream, request, sent_body, tool_exchange};
+    use crate::provider::LlmProvider;
+    use futures::StreamExt;
+    use openai_dive::v1::resources::chat::ChatMessage;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Native provider pointed at the mock server, given as the OpenAI compatible url
+    fn setup_provider(server: &MockServer) -> OllamaProvider {
+        OllamaProvider::new(Some(format!("{}/v1", server.uri()))).with_native(true)
+    }
+
+    async fn mount_show(server: &MockServer, show: Value) {
+        Mock::given(method("POST"))
+            .and(path("/api/show"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(show))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_native_chat_with_tool_call() {
+        let server = MockServer::start().await;
+        mount_show(&server, json!({
+            "model_info": {"general.architecture": "qwen3", "qwen3.context_length": 131072},
+            "capabilities": ["completion", "tools", "thinking"]
+        })).await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "qwen3:8b",
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "thinking": "I should read it",
+                    "tool_calls": [{"function": {"name": "read", "arguments": {"path": "main.py"}}}]
+                },
+                "done": true,
+                "done_reason": "stop",
+                "prompt_eval_count": 120,
+                "eval_count": 15
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server).with_keep_alive("30m".to_string());
+        let response = provider.chat(request(json!({
+            "model": "qwen3:8b",
+            "messages": [
+                {"role": "system", "content": "You are a coding assistant."},
+                {"role": "user", "content": "read main.py"}
+            ],
+            "tools": [{"type": "function", "function": {"name": "read", "parameters": {"type": "object"}}}],
+            "temperature": 0.3,
+            "max_tokens": 500
+        }))).await.unwrap();
+
+        let body = sent_body(&server, "/api/chat").await;
+        assert_eq!(body["messages"][0], json!({"role": "system", "content": "You are a coding assistant."}));
+        assert_eq!(body["tools"][0]["function"]["name"], "read");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["keep_alive"], "30m");
+        assert_eq!(body["options"]["num_predict"], 500);
+        // the context of the model, capped
+        assert_eq!(body["options"]["num_ctx"], 32768);
+        assert!((body["options"]["temperature"].as_f64().unwrap() - 0.3).abs() < 1e-6);
+
+        let ChatMessage::Assistant { content, reasoning_content, tool_calls, .. } = &response.choices[0].message else {
+            panic!("expected an assistant message");
+        };
+        assert!(content.is_none());
+        assert_eq!(reasoning_content.as_deref(), Some("I should read it"));
+        let tool_calls = tool_calls.as_ref().unwrap();
+        assert_eq!(tool_calls[0].function.name, "read");
+        assert_eq!(serde_json::from_str::<Value>(&tool_calls[0].function.arguments).unwrap(), json!({"path": "main.py"}));
+        assert_eq!(serde_json::to_value(&response.choices[0].finish_reason).unwrap(), "tool_calls");
+        let usage = response.usage.as_ref().unwrap();
+        assert_eq!(usage.prompt_tokens, Some(120));
+        assert_eq!(usage.total_tokens, 135);
+
+        // the tool loop goes on with the call as an object and the result named after it
+        let call_id = tool_calls[0].id.clone();
+        provider.chat(request(json!({
+            "model": "qwen3:8b",
+            "messages": tool_exchange(&call_id)
+        }))).await.unwrap();
+
+        let body = sent_body(&server, "/api/chat").await;
+        assert_eq!(body["messages"][1]["tool_calls"], json!([{"function": {"name": "read", "arguments": {"path": "main.py"}}}]));
+        assert_eq!(body["messages"][2], json!({"role": "tool", "content": "print('hello')", "tool_name": "read"}));
+    }
+
+    #[tokio::test]
+    async fn test_native_chat_stream() {
+        let server = MockServer::start().await;
+        mount_show(&server, json!({"parameters": "num_ctx 8192\nstop \"<|end|>\"", "capabilities": ["completion"]})).await;
+        let lines = [
+            json!({"model": "llama3.2", "message": {"role": "assistant", "content": "Hello"}, "done": false}),
+            json!({"model": "llama3.2", "message": {"role": "assistant", "content": " world"}, "done": false}),
+            json!({"model": "llama3.2", "message": {"role": "assistant", "content": ""}, "done": true, "done_reason": "stop",
+                   "prompt_eval_count": 8, "eval_count": 2}),
+        ];
+        let ndjson: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .and(body_partial_json(json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(ndjson, "application/x-ndjson"))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server);
+        let stream = provider.chat_stream(request(json!({
+            "model": "llama3.2",
+            "messages": [{"role": "user", "content": "Hello!"}]
+        }))).await.unwrap();
+
+        let answer = collect_stream(stream).await;
+        assert_eq!(answer.content, "Hello world");
+        assert_eq!(serde_json::to_value(&answer.finish_reason).unwrap(), "stop");
+        assert_eq!(answer.usage.unwrap().total_tokens, 10);
+
+        // the modelfile sets num_ctx, the request leaves it alone
+        let body = sent_body(&server, "/api/chat").await;
+        assert!(body["options"]["num_ctx"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_model_info_from_show() {
+        let server = MockServer::start().await;
+        mount_show(&server, json!({
+            "model_info": {"general.architecture": "gemma3", "gemma3.context_length": 131072},
+            "capabilities": ["completion", "vision"]
+        })).await;
+
+        let info = setup_provider(&server).with_num_ctx(16384).model_info("gemma3").await.unwrap().unwrap();
+        assert_eq!(info.id, "gemma3");
+        // the model only gets the context it is loaded with
+        assert_eq!(info.context_length, Some(16384));
+        assert_eq!(info.supports_tools, Some(false));
+        assert_eq!(info.supports_vision, Some(true));
+
+        // the OpenAI compatible api cannot raise it above the server default
+        let compatible = OllamaProvider::new(Some(format!("{}/v1", server.uri())));
+        let info = compatible.model_info("gemma3").await.unwrap().unwrap();
+        assert_eq!(info.context_length, Some(4096));
+    }
+
+    #[tokio::test]
+    async fn test_pull() {
+        let server = MockServer::start().await;
+        let lines = [
+            json!({"status": "pulling manifest"}),
+            json!({"status": "pulling a2af6cc3eb7f", "digest": "sha256:a2af6cc3eb7f", "total": 1000, "completed": 500}),
+            json!({"status": "success"}),
+        ];
+        let ndjson: String = lines.iter().map(|l| format!("{}\n", l)).collect();
+        Mock::given(method("POST"))
+            .and(path("/api/pull"))
+            .and(body_partial_json(json!({"model": "smollm2:135m"})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(ndjson, "application/x-ndjson"))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server);
+        let progress: Vec<_> = provider.pull("smollm2:135m").await.unwrap()
+            .map(|p| p.unwrap())
+            .collect()
+            .await;
+        assert_eq!(progress.len(), 3);
+        assert_eq!(progress[1].percent(), Some(50));
+        assert_eq!(progress.last().unwrap().status, "success");
+    }
+
+    #[tokio::test]
+    async fn test_pull_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/pull"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("{\"error\":\"pull model manifest: file does not exist\"}\n", "application/x-ndjson"))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server);
+        let mut stream = provider.pull("does-not-exist").await.unwrap();
+        let error = stream.next().await.unwrap().unwrap_err();
+        assert!(error.to_string().contains("file does not exist"));
+    }
+
+    #[test]
+    fn test_request_conversion() {
+        let provider = OllamaProvider::configure(None, Some("true".to_string()), Some("4096".to_string()), Some("-1".to_string()), Some("256".to_string()));
+        let ollama_format = futures::executor::block_on(provider.convert_to_ollama_format(&request(json!({
+            "model": "llava",
+            "messages": [{"role": "user", "content": [
+                {"type": "text", "text": "what is this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}}
+            ]}],
+            "stop": "END",
+            "reasoning_effort": "high",
+            "response_format": {"type": "json_schema", "json_schema": {"name": "answer", "schema": {"type": "object"}}}
+        })), false));
+
+        assert_eq!(ollama_format["messages"][0], json!({"role": "user", "content": "what is this?", "images": ["iVBORw0KGgo="]}));
+        assert_eq!(ollama_format["options"], json!({"stop": ["END"], "num_predict": 256, "num_ctx": 4096}));
+        assert_eq!(ollama_format["format"], json!({"type": "object"}));
+        assert_eq!(ollama_format["think"], true);
+        assert_eq!(ollama_format["keep_alive"], -1);
+    }
+}
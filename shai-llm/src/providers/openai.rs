@@ -1,5 +1,6 @@
 // llm/providers/openai.rs
 use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use crate::chat::{ChatClient, NoHooks};
 use async_trait::async_trait;
 use futures::StreamExt;
 use openai_dive::v1::{
@@ -12,13 +13,15 @@ use openai_dive::v1::{
 
 pub struct OpenAIProvider {
     client: Client,
+    chat_client: ChatClient,
 }
 
 impl OpenAIProvider {
     pub fn new(api_key: String) -> Self {
-        let mut client = Client::new(api_key);
+        let mut client = Client::new(api_key.clone());
         client.set_base_url("https://api.openai.com/v1");
-        Self { client }
+        let chat_client = ChatClient::new(api_key, "https://api.openai.com/v1".to_string());
+        Self { client, chat_client }
     }
 
     /// Create OpenAI provider from environment variables
@@ -49,7 +52,7 @@ impl LlmProvider for OpenAIProvider {
     }
 
     async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
-        let response = self.client.chat().create(request).await
+        let response = self.chat_client.chat_completion(&request, &NoHooks).await
             .map_err(|e| Box::new(e) as LlmError)?;
         Ok(response)
     }
@@ -58,7 +61,7 @@ impl LlmProvider for OpenAIProvider {
         // Ensure streaming is enabled
         request.stream = Some(true);
         
-        let stream = self.client.chat().create_stream(request).await
+        let stream = self.chat_client.chat_completion_stream(&request, NoHooks).await
             .map_err(|e| Box::new(e) as LlmError)?;
 
         let converted_stream = stream.map(|result| {
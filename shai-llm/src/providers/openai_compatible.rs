@@ -1,5 +1,6 @@
 // llm/providers/openai_compatible.rs
 use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use crate::chat::{ChatClient, NoHooks};
 use async_trait::async_trait;
 use futures::StreamExt;
 use openai_dive::v1::{
@@ -14,13 +15,15 @@ use serde_json::Value;
 
 pub struct OpenAICompatibleProvider {
     client: Client,
+    chat_client: ChatClient,
 }
 
 impl OpenAICompatibleProvider {
     pub fn new(api_key: String, base_url: String) -> Self {
-        let mut client = Client::new(api_key);
+        let mut client = Client::new(api_key.clone());
         client.set_base_url(&base_url);
-        Self { client }
+        let chat_client = ChatClient::new(api_key, base_url);
+        Self { client, chat_client }
     }
 
     /// Create OpenAI Compatible provider from environment variables
@@ -44,7 +47,7 @@ impl LlmProvider for OpenAICompatibleProvider {
     }
 
     async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
-        let mut response = self.client.chat().create(request).await
+        let mut response = self.chat_client.chat_completion(&request, &NoHooks).await
             .map_err(|e| Box::new(e) as LlmError)?;
 
         Ok(response)
@@ -54,7 +57,7 @@ impl LlmProvider for OpenAICompatibleProvider {
         // Ensure streaming is enabled
         request.stream = Some(true);
         
-        let stream = self.client.chat().create_stream(request).await
+        let stream = self.chat_client.chat_completion_stream(&request, NoHooks).await
             .map_err(|e| Box::new(e) as LlmError)?;
 
         let converted_stream = stream.map(|result| {
@@ -0,0 +1,70 @@
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+// /v1/responses response, also carried whole by the response.completed stream event
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponsesResponse {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub model: String,
+    /// "completed", "incomplete", "failed"...
+    #[serde(default)]
+    pub status: String,
+    /// message, reasoning and function_call items, kept as json since the
+    /// reasoning items are sent back as they came
+    #[serde(default)]
+    pub output: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResponsesUsage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub incomplete_details: Option<IncompleteDetails>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ResponsesError>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponsesUsage {
+    #[serde(default)]
+    pub input_tokens: u32,
+    /// reasoning tokens included
+    #[serde(default)]
+    pub output_tokens: u32,
+    #[serde(default)]
+    pub total_tokens: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IncompleteDetails {
+    /// "max_output_tokens" or "content_filter"
+    #[serde(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponsesError {
+    #[serde(default)]
+    pub code: Option<String>,
+    #[serde(default)]
+    pub message: String,
+}
+
+// server sent event of a streamed response, only the fields we read
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponsesEvent {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_index: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<ResponsesResponse>,
+    /// set by "error" events
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+pub const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
@@ -0,0 +1,5 @@
+pub mod api;
+pub mod openai_responses;
+pub mod tests;
+
+pub use openai_responses::OpenAIResponsesProvider;
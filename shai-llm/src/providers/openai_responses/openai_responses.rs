@@ -0,0 +1,562 @@
+// llm/providers/openai_responses/openai_responses.rs
+use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use crate::audit::{self, AuditCall};
+use super::api::*;
+use crate::providers::{content_text, turn_start};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use openai_dive::v1::{
+    api::Client,
+    resources::{
+        chat::{ChatCompletionParameters, ChatCompletionResponse, ChatCompletionChunkResponse, ChatMessage, DeltaChatMessage, ChatMessageContent, ChatMessageContentPart, ChatCompletionChoice, ChatCompletionChunkChoice, ToolCall, Function},
+        model::ListModelResponse,
+        shared::{FinishReason, Usage},
+    },
+};
+
+/// Reasoning items by the id of the first tool call they led to, with their last use
+type ReasoningItems = Arc<Mutex<HashMap<String, (Instant, Vec<Value>)>>>;
+
+/// Reasoning unused for this long is dropped, the turn it belongs to was given up
+const REASONING_TTL: Duration = Duration::from_secs(3600);
+
+/// OpenAI models through the Responses API (/v1/responses) instead of chat completions.
+/// Requests are stateless: the encrypted reasoning items of the responses calling tools are
+/// kept here and sent back with their calls until the user starts a new turn, the model drops
+/// the reasoning of the previous turns anyway
+pub struct OpenAIResponsesProvider {
+    api_key: String,
+    base_url: String,
+    client: Client,
+    http: reqwest::Client,
+    /// reasoning summary requested from the model ("auto", "concise", "detailed"), None = no summary
+    reasoning_summary: Option<String>,
+    reasoning: ReasoningItems,
+}
+
+impl OpenAIResponsesProvider {
+    pub fn new(api_key: String) -> Self {
+        let mut client = Client::new(api_key.clone());
+        client.set_base_url(OPENAI_API_BASE);
+        Self {
+            api_key,
+            base_url: OPENAI_API_BASE.to_string(),
+            client,
+            http: reqwest::Client::new(),
+            reasoning_summary: None,
+            reasoning: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self.client.set_base_url(&self.base_url);
+        self
+    }
+
+    pub fn with_reasoning_summary(mut self, summary: String) -> Self {
+        self.reasoning_summary = Some(summary);
+        self
+    }
+
+    /// Create OpenAI Responses provider from environment variables
+    /// Returns None if required environment variables are not set
+    pub fn from_env() -> Option<Self> {
+        let api_key = std::env::var("OPENAI_API_KEY").ok()?;
+        let env = |name: &str| std::env::var(name).ok();
+        Some(Self::configure(api_key, env("OPENAI_RESPONSES_BASE_URL"), env("OPENAI_REASONING_SUMMARY")))
+    }
+
+    /// Provider from its settings as strings, the optional ones are ignored when empty
+    pub fn configure(api_key: String, base_url: Option<String>, reasoning_summary: Option<String>) -> Self {
+        let mut provider = Self::new(api_key);
+        if let Some(base_url) = base_url.filter(|u| !u.is_empty()) {
+            provider = provider.with_base_url(base_url);
+        }
+        if let Some(summary) = reasoning_summary.filter(|s| !s.is_empty()) {
+            provider = provider.with_reasoning_summary(summary);
+        }
+        provider
+    }
+
+    pub(crate) fn convert_to_responses_format(&self, request: &ChatCompletionParameters, stream: bool) -> Value {
+        let params = serde_json::to_value(request).unwrap_or_default();
+        let (instructions, input) = self.convert_messages(&request.messages);
+
+        let mut responses_request = json!({
+            "model": request.model,
+            "input": input,
+            "stream": stream,
+            // stateless, the reasoning comes back encrypted and is sent again by us
+            "store": false,
+            "include": ["reasoning.encrypted_content"],
+        });
+
+        if !instructions.is_empty() {
+            responses_request["instructions"] = json!(instructions.join("\n\n"));
+        }
+
+        if let Some(tools) = params["tools"].as_array() {
+            responses_request["tools"] = tools.iter()
+                .map(|tool| {
+                    let function = &tool["function"];
+                    let mut converted = json!({
+                        "type": "function",
+                        "name": function["name"],
+                        "parameters": function["parameters"],
+                    });
+                    for field in ["description", "strict"] {
+                        if !function[field].is_null() {
+                            converted[field] = function[field].clone();
+                        }
+                    }
+                    converted
+                })
+                .collect();
+        }
+
+        match &params["tool_choice"] {
+            Value::String(choice) => responses_request["tool_choice"] = json!(choice),
+            Value::Object(_) => {
+                if let Some(name) = params["tool_choice"]["function"]["name"].as_str() {
+                    responses_request["tool_choice"] = json!({"type": "function", "name": name});
+                }
+            }
+            _ => {}
+        }
+
+        for name in ["temperature", "top_p", "parallel_tool_calls"] {
+            if !params[name].is_null() {
+                responses_request[name] = params[name].clone();
+            }
+        }
+        if let Some(max_tokens) = params["max_completion_tokens"].as_u64().or(params["max_tokens"].as_u64()) {
+            responses_request["max_output_tokens"] = json!(max_tokens);
+        }
+
+        let mut reasoning = serde_json::Map::new();
+        if let Some(effort) = params["reasoning_effort"].as_str() {
+            reasoning.insert("effort".to_string(), json!(effort));
+        }
+        if let Some(summary) = &self.reasoning_summary {
+            reasoning.insert("summary".to_string(), json!(summary));
+        }
+        if !reasoning.is_empty() {
+            responses_request["reasoning"] = Value::Object(reasoning);
+        }
+
+        match params["response_format"]["type"].as_str() {
+            Some("json_object") => responses_request["text"] = json!({"format": {"type": "json_object"}}),
+            Some("json_schema") => {
+                let schema = &params["response_format"]["json_schema"];
+                let mut format = json!({"type": "json_schema", "name": schema["name"], "schema": schema["schema"]});
+                if schema["strict"].is_boolean() {
+                    format["strict"] = schema["strict"].clone();
+                }
+                responses_request["text"] = json!({"format": format});
+            }
+            _ => {}
+        }
+
+        responses_request
+    }
+
+    /// Instructions from the system messages and input items from the others
+    fn convert_messages(&self, messages: &[ChatMessage]) -> (Vec<String>, Vec<Value>) {
+        let mut instructions = Vec::new();
+        let mut input = Vec::new();
+        self.evict_reasoning(messages);
+        let mut reasoning = self.reasoning.lock().unwrap();
+
+        for msg in messages {
+            match msg {
+                ChatMessage::System { content, .. } => {
+                    instructions.push(content_text(content));
+                }
+                ChatMessage::Developer { content, .. } => {
+                    input.push(json!({"type": "message", "role": "developer", "content": content_text(content)}));
+                }
+                ChatMessage::User { content, .. } => {
+                    input.push(json!({"type": "message", "role": "user", "content": Self::convert_user_content(content)}));
+                }
+                ChatMessage::Assistant { content, tool_calls, .. } => {
+                    let text = content.as_ref().map(content_text).unwrap_or_default();
+                    let calls = tool_calls.clone().unwrap_or_default();
+                    // the reasoning goes first, as the model produced it
+                    if let Some((used, items)) = calls.first().and_then(|call| reasoning.get_mut(&call.id)) {
+                        *used = Instant::now();
+                        input.extend(items.iter().cloned());
+                    }
+                    if !text.is_empty() {
+                        input.push(json!({
+                            "type": "message",
+                            "role": "assistant",
+                            "content": [{"type": "output_text", "text": text}]
+                        }));
+                    }
+                    for call in calls {
+                        input.push(json!({
+                            "type": "function_call",
+                            "call_id": call.id,
+                            "name": call.function.name,
+                            "arguments": call.function.arguments
+                        }));
+                    }
+                }
+                ChatMessage::Tool { content, tool_call_id, .. } => {
+                    input.push(json!({
+                        "type": "function_call_output",
+                        "call_id": tool_call_id,
+                        "output": content_text(content)
+                    }));
+                }
+            }
+        }
+
+        (instructions, input)
+    }
+
+    /// Text and image parts of a user message
+    fn convert_user_content(content: &ChatMessageContent) -> Value {
+        let ChatMessageContent::ContentPart(parts) = content else {
+            return json!(content_text(content));
+        };
+
+        parts.iter()
+            .filter_map(|part| {
+                let part = serde_json::to_value(part).ok()?;
+                match part["type"].as_str()? {
+                    "text" => Some(json!({"type": "input_text", "text": part["text"]})),
+                    "image_url" => Some(json!({"type": "input_image", "image_url": part["image_url"]["url"]})),
+                    _ => None, // audio, files...
+                }
+            })
+            .collect()
+    }
+
+    /// Forget the reasoning of the turns before the current one
+    fn evict_reasoning(&self, messages: &[ChatMessage]) {
+        let Some(start) = turn_start(messages) else {
+            return;
+        };
+        let mut reasoning = self.reasoning.lock().unwrap();
+        for message in &messages[..start] {
+            if let ChatMessage::Assistant { tool_calls: Some(calls), .. } = message {
+                if let Some(call) = calls.first() {
+                    reasoning.remove(&call.id);
+                }
+            }
+        }
+    }
+
+    /// Split output items into answer, reasoning summary, tool calls and the reasoning items to send back
+    fn read_output(output: &[Value]) -> (String, String, Vec<ToolCall>, Vec<Value>) {
+        let mut text = String::new();
+        let mut summaries = Vec::new();
+        let mut tool_calls = Vec::new();
+        let mut reasoning_items = Vec::new();
+
+        for item in output {
+            match item["type"].as_str() {
+                Some("reasoning") => {
+                    summaries.extend(item["summary"].as_array().into_iter().flatten()
+                        .filter_map(|summary| summary["text"].as_str().map(str::to_string)));
+                    reasoning_items.push(item.clone());
+                }
+                Some("message") => {
+                    for part in item["content"].as_array().into_iter().flatten() {
+                        if part["type"] == "output_text" {
+                            text.push_str(part["text"].as_str().unwrap_or(""));
+                        }
+                    }
+                }
+                Some("function_call") => tool_calls.push(ToolCall {
+                    id: item["call_id"].as_str().unwrap_or_default().to_string(),
+                    r#type: "function".to_string(),
+                    function: Function {
+                        name: item["name"].as_str().unwrap_or_default().to_string(),
+                        arguments: item["arguments"].as_str().unwrap_or("{}").to_string(),
+                    }
+                }),
+                _ => {}
+            }
+        }
+
+        (text, summaries.join("\n\n"), tool_calls, reasoning_items)
+    }
+
+    /// Keep the reasoning items of a response calling tools for the next steps of the turn,
+    /// the ones of sessions that ended mid-turn are dropped after a while
+    fn remember_reasoning(reasoning: &ReasoningItems, tool_calls: &[ToolCall], items: Vec<Value>) {
+        if let (Some(call), false) = (tool_calls.first(), items.is_empty()) {
+            let mut reasoning = reasoning.lock().unwrap();
+            reasoning.retain(|_, (used, _)| used.elapsed() < REASONING_TTL);
+            reasoning.insert(call.id.clone(), (Instant::now(), items));
+        }
+    }
+
+    fn convert_finish_reason(response: &ResponsesResponse, has_tool_calls: bool) -> Option<FinishReason> {
+        let reason = match response.incomplete_details.as_ref().map(|d| d.reason.as_str()) {
+            Some("max_output_tokens") => "length",
+            Some("content_filter") => "content_filter",
+            _ if has_tool_calls => "tool_calls",
+            _ => "stop",
+        };
+        serde_json::from_value(json!(reason)).ok()
+    }
+
+    fn convert_usage(usage: &ResponsesUsage) -> Usage {
+        Usage {
+            input_tokens: None,
+            input_tokens_details: None,
+            output_tokens: None,
+            output_tokens_details: None,
+            prompt_tokens: Some(usage.input_tokens),
+            completion_tokens: Some(usage.output_tokens),
+            total_tokens: usage.input_tokens + usage.output_tokens,
+            prompt_tokens_details: None,
+            completion_tokens_details: None,
+        }
+    }
+
+    fn check_status(response: &ResponsesResponse) -> Result<(), LlmError> {
+        match (&response.error, response.status.as_str()) {
+            (Some(error), _) => Err(format!("OpenAI Responses API error: {}", error.message).into()),
+            (None, "failed") => Err("OpenAI Responses API error: the response failed".into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn convert_from_responses_format(&self, response: ResponsesResponse, model: &str) -> Result<ChatCompletionResponse, LlmError> {
+        Self::check_status(&response)?;
+        let (text, summary, tool_calls, reasoning_items) = Self::read_output(&response.output);
+        Self::remember_reasoning(&self.reasoning, &tool_calls, reasoning_items);
+        let finish_reason = Self::convert_finish_reason(&response, !tool_calls.is_empty());
+
+        Ok(ChatCompletionResponse {
+            id: Some(response.id.clone()),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u32,
+            model: if response.model.is_empty() { model.to_string() } else { response.model.clone() },
+            choices: vec![ChatCompletionChoice {
+                index: 0,
+                message: ChatMessage::Assistant {
+                    content: (!text.is_empty()).then(|| ChatMessageContent::Text(text)),
+                    reasoning_content: (!summary.is_empty()).then_some(summary),
+                    refusal: None,
+                    name: None,
+                    audio: None,
+                    tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                },
+                finish_reason,
+                logprobs: None,
+            }],
+            usage: response.usage.as_ref().map(Self::convert_usage),
+            service_tier: None,
+            system_fingerprint: None,
+        })
+    }
+
+    fn chunk(id: &str, model: &str, content: Option<String>, reasoning: Option<String>, tool_calls: Vec<Value>, finish_reason: Option<FinishReason>, usage: Option<Usage>) -> Result<ChatCompletionChunkResponse, LlmError> {
+        Ok(ChatCompletionChunkResponse {
+            id: Some(id.to_string()),
+            object: "chat.completion.chunk".to_string(),
+            created: chrono::Utc::now().timestamp() as u32,
+            model: model.to_string(),
+            choices: vec![ChatCompletionChunkChoice {
+                index: Some(0),
+                delta: DeltaChatMessage::Assistant {
+                    content: content.map(ChatMessageContent::Text),
+                    reasoning_content: reasoning,
+                    refusal: None,
+                    name: None,
+                    tool_calls: if tool_calls.is_empty() { None } else { Some(serde_json::from_value(json!(tool_calls))?) },
+                },
+                finish_reason,
+                logprobs: None,
+            }],
+            usage,
+            system_fingerprint: None,
+        })
+    }
+
+    /// Parse the server sent events of a streamed response into chat completion chunks,
+    /// events may be split across network chunks
+    fn parse_responses_stream(response: reqwest::Response, model: String, reasoning: ReasoningItems) -> LlmStream {
+        let mut bytes = response.bytes_stream();
+        let stream = async_stream::stream! {
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut id = String::new();
+            let mut model = model;
+            // tool call index by output index
+            let mut tool_indices: HashMap<u32, u32> = HashMap::new();
+            while let Some(chunk) = bytes.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        yield Err(Box::new(e) as LlmError);
+                        break;
+                    }
+                };
+                buffer.extend_from_slice(&chunk);
+
+                while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let line = String::from_utf8_lossy(&line);
+                    let Some(data) = line.trim().strip_prefix("data:") else {
+                        continue;
+                    };
+                    let event = match serde_json::from_str::<ResponsesEvent>(data.trim()) {
+                        Ok(event) => event,
+                        Err(e) => {
+                            yield Err(format!("Failed to parse Responses event {}: {}", data, e).into());
+                            continue;
+                        }
+                    };
+
+                    match event.kind.as_str() {
+                        "response.created" => {
+                            if let Some(response) = &event.response {
+                                id = response.id.clone();
+                                if !response.model.is_empty() {
+                                    model = response.model.clone();
+                                }
+                            }
+                        }
+                        "response.output_text.delta" => {
+                            yield Self::chunk(&id, &model, event.delta, None, vec![], None, None);
+                        }
+                        "response.reasoning_summary_text.delta" => {
+                            yield Self::chunk(&id, &model, None, event.delta, vec![], None, None);
+                        }
+                        "response.output_item.added" => {
+                            let item = event.item.unwrap_or_default();
+                            if item["type"] == "function_call" {
+                                let index = tool_indices.len() as u32;
+                                tool_indices.insert(event.output_index.unwrap_or(index), index);
+                                yield Self::chunk(&id, &model, None, None, vec![json!({
+                                    "index": index,
+                                    "id": item["call_id"],
+                                    "type": "function",
+                                    "function": {"name": item["name"], "arguments": item["arguments"].as_str().unwrap_or("")}
+                                })], None, None);
+                            }
+                        }
+                        "response.function_call_arguments.delta" => {
+                            let index = event.output_index.and_then(|i| tool_indices.get(&i).copied()).unwrap_or(0);
+                            yield Self::chunk(&id, &model, None, None, vec![json!({
+                                "index": index,
+                                "function": {"arguments": event.delta.unwrap_or_default()}
+                            })], None, None);
+                        }
+                        "response.completed" | "response.incomplete" | "response.failed" => {
+                            let response = event.response.unwrap_or_default();
+                            if let Err(e) = Self::check_status(&response) {
+                                yield Err(e);
+                                break;
+                            }
+                            let (_, _, tool_calls, reasoning_items) = Self::read_output(&response.output);
+                            Self::remember_reasoning(&reasoning, &tool_calls, reasoning_items);
+                            let finish_reason = Self::convert_finish_reason(&response, !tool_indices.is_empty());
+                            yield Self::chunk(&id, &model, None, None, vec![], finish_reason, response.usage.as_ref().map(Self::convert_usage));
+                        }
+                        "error" => {
+                            yield Err(format!("OpenAI Responses API error: {}", event.message.unwrap_or_default()).into());
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        };
+        Box::new(Box::pin(stream))
+    }
+
+    async fn post(&self, body: &Value) -> Result<(reqwest::Response, Option<AuditCall>), LlmError> {
+        let url = format!("{}/responses", self.base_url);
+        let audit = audit::call(&url).map(|call| call.with_secret(&self.api_key));
+        if let Some(audit) = &audit {
+            audit.request(body);
+        }
+
+        let response = self.http
+            .post(url)
+            .bearer_auth(&self.api_key)
+            .json(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            if let Some(audit) = &audit {
+                audit.error(&error_text);
+            }
+            return Err(format!("OpenAI Responses API error: {}", error_text).into());
+        }
+        Ok((response, audit))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAIResponsesProvider {
+    async fn models(&self) -> Result<ListModelResponse, LlmError> {
+        let response = self.client.models().list().await
+            .map_err(|e| Box::new(e) as LlmError)?;
+        Ok(response)
+    }
+
+    async fn default_model(&self) -> Result<String, LlmError> {
+        let models = self.models().await?;
+        models.data.iter()
+            .find(|m| m.id == "gpt-5")
+            .or_else(|| models.data.first())
+            .map(|m| m.id.clone())
+            .ok_or_else(|| "no model available".into())
+    }
+
+    async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
+        let responses_request = self.convert_to_responses_format(&request, false);
+        let (response, audit) = self.post(&responses_request).await?;
+        let responses_response: Value = response.json().await?;
+        if let Some(audit) = &audit {
+            audit.response(&responses_response);
+        }
+        let responses_response: ResponsesResponse = serde_json::from_value(responses_response)?;
+        self.convert_from_responses_format(responses_response, &request.model)
+    }
+
+    async fn chat_stream(&self, request: ChatCompletionParameters) -> Result<LlmStream, LlmError> {
+        let responses_request = self.convert_to_responses_format(&request, true);
+        let (response, audit) = self.post(&responses_request).await?;
+        let stream = Self::parse_responses_stream(response, request.model.clone(), self.reasoning.clone());
+        Ok(audit::audit_stream(stream, audit))
+    }
+
+    fn supports_functions(&self, model: String) -> bool {
+        true
+    }
+
+    fn supports_structured_output(&self, model: String) -> bool {
+        true
+    }
+
+    fn name(&self) -> &'static str {
+        "openai_responses"
+    }
+
+    fn info() -> ProviderInfo {
+        ProviderInfo {
+            name: "openai_responses",
+            display_name: "OpenAI Responses API (GPT-5, o-series reasoning)",
+            env_vars: vec![
+                EnvVar::required("OPENAI_API_KEY", "OpenAI API key"),
+                EnvVar::optional("OPENAI_RESPONSES_BASE_URL", "API base URL (defaults to https://api.openai.com/v1)"),
+                EnvVar::optional("OPENAI_REASONING_SUMMARY", "Reasoning summary shown as thoughts (auto, concise, detailed)"),
+            ],
+        }
+    }
+}
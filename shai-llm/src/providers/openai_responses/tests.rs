@@ -0,0 +1,221 @@
+#[cfg(test)]
+mod tests {
+    use crate::providers::openai_responses::OpenAIResponsesProvider;
+    use crate::providers::tests::{chat_error, collect_stream, request, sent_body, tool_exchange};
+    use crate::provider::LlmProvider;
+    use openai_dive::v1::resources::chat::ChatMessage;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn setup_provider(server: &MockServer) -> OpenAIResponsesProvider {
+        OpenAIResponsesProvider::new("test-key".to_string()).with_base_url(format!("{}/v1", server.uri()))
+    }
+
+    #[tokio::test]
+    async fn test_chat_with_tool_call_keeps_reasoning() {
+        let server = MockServer::start().await;
+        let reasoning = json!({
+            "type": "reasoning",
+            "id": "rs_1",
+            "summary": [{"type": "summary_text", "text": "I should read the file"}],
+            "encrypted_content": "gAAAAB..."
+        });
+        Mock::given(method("POST"))
+            .and(path("/v1/responses"))
+            .and(header("authorization", "Bearer test-key"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "resp_1",
+                "model": "gpt-5",
+                "status": "completed",
+                "output": [
+                    reasoning.clone(),
+                    {"type": "function_call", "id": "fc_1", "call_id": "call_1", "name": "read", "arguments": "{\"path\":\"main.py\"}"}
+                ],
+                "usage": {"input_tokens": 100, "output_tokens": 40, "total_tokens": 140}
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server).with_reasoning_summary("auto".to_string());
+        let response = provider.chat(request(json!({
+            "model": "gpt-5",
+            "messages": [
+                {"role": "system", "content": "You are a coding assistant."},
+                {"role": "user", "content": "read main.py"}
+            ],
+            "tools": [{"type": "function", "function": {"name": "read", "description": "Read a file", "parameters": {"type": "object"}}}],
+            "reasoning_effort": "high"
+        }))).await.unwrap();
+
+        let body = sent_body(&server, "/v1/responses").await;
+        assert_eq!(body["instructions"], "You are a coding assistant.");
+        assert_eq!(body["input"], json!([{"type": "message", "role": "user", "content": "read main.py"}]));
+        assert_eq!(body["tools"], json!([{"type": "function", "name": "read", "description": "Read a file", "parameters": {"type": "object"}}]));
+        assert_eq!(body["reasoning"], json!({"effort": "high", "summary": "auto"}));
+        assert_eq!(body["store"], false);
+
+        let ChatMessage::Assistant { content, reasoning_content, tool_calls, .. } = &response.choices[0].message else {
+            panic!("expected an assistant message");
+        };
+        assert!(content.is_none());
+        assert_eq!(reasoning_content.as_deref(), Some("I should read the file"));
+        let tool_calls = tool_calls.as_ref().unwrap();
+        assert_eq!(tool_calls[0].id, "call_1");
+        assert_eq!(tool_calls[0].function.name, "read");
+        assert_eq!(serde_json::to_value(&response.choices[0].finish_reason).unwrap(), "tool_calls");
+        let usage = response.usage.as_ref().unwrap();
+        assert_eq!(usage.prompt_tokens, Some(100));
+        assert_eq!(usage.total_tokens, 140);
+
+        // the next step sends the reasoning back ahead of the call it led to
+        provider.chat(request(json!({
+            "model": "gpt-5",
+            "messages": tool_exchange("call_1")
+        }))).await.unwrap();
+
+        let body = sent_body(&server, "/v1/responses").await;
+        assert_eq!(body["input"][1], reasoning);
+        assert_eq!(body["input"][2], json!({"type": "function_call", "call_id": "call_1", "name": "read", "arguments": "{\"path\":\"main.py\"}"}));
+        assert_eq!(body["input"][3], json!({"type": "function_call_output", "call_id": "call_1", "output": "print('hello')"}));
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_sent_back_within_the_turn_only() {
+        let server = MockServer::start().await;
+        let reasoning = |id: &str| json!({"type": "reasoning", "id": id, "summary": [], "encrypted_content": "gAAAAB..."});
+        Mock::given(method("POST"))
+            .and(path("/v1/responses"))
+            .and(body_partial_json(json!({"input": [{"type": "message", "role": "user", "content": "read main.py"}]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "resp_1", "model": "gpt-5", "status": "completed",
+                "output": [
+                    reasoning("rs_1"),
+                    {"type": "function_call", "id": "fc_1", "call_id": "call_1", "name": "read", "arguments": "{}"}
+                ]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/responses"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "resp_2", "model": "gpt-5", "status": "completed",
+                "output": [
+                    reasoning("rs_2"),
+                    {"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "OK"}]}
+                ]
+            })))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server);
+        provider.chat(request(json!({"model": "gpt-5", "messages": [{"role": "user", "content": "read main.py"}]}))).await.unwrap();
+        provider.chat(request(json!({"model": "gpt-5", "messages": tool_exchange("call_1")}))).await.unwrap();
+        assert_eq!(sent_body(&server, "/v1/responses").await["input"][1], reasoning("rs_1"));
+
+        // a note or a message queued while the tools ran continues the turn
+        let mut messages = tool_exchange("call_1");
+        messages.as_array_mut().unwrap().push(json!({"role": "user", "content": "Note: main.py is long"}));
+        provider.chat(request(json!({"model": "gpt-5", "messages": messages}))).await.unwrap();
+        assert_eq!(sent_body(&server, "/v1/responses").await["input"][1], reasoning("rs_1"));
+
+        // identical answers carry no reasoning, and the earlier turn no longer sends its own
+        let mut messages = tool_exchange("call_1");
+        messages.as_array_mut().unwrap().extend([
+            json!({"role": "assistant", "content": "OK"}),
+            json!({"role": "user", "content": "again"}),
+            json!({"role": "assistant", "content": "OK"}),
+            json!({"role": "user", "content": "once more"}),
+        ]);
+        provider.chat(request(json!({"model": "gpt-5", "messages": messages}))).await.unwrap();
+        let body = sent_body(&server, "/v1/responses").await;
+        let items = body["input"].as_array().unwrap();
+        assert!(!items.iter().any(|item| item["type"] == "reasoning"), "{:?}", items);
+        assert_eq!(items.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream() {
+        let server = MockServer::start().await;
+        let events = [
+            json!({"type": "response.created", "response": {"id": "resp_2", "model": "gpt-5", "status": "in_progress", "output": []}}),
+            json!({"type": "response.reasoning_summary_text.delta", "output_index": 0, "delta": "Thinking"}),
+            json!({"type": "response.output_text.delta", "output_index": 1, "delta": "Let me "}),
+            json!({"type": "response.output_text.delta", "output_index": 1, "delta": "check."}),
+            json!({"type": "response.output_item.added", "output_index": 2,
+                   "item": {"type": "function_call", "call_id": "call_9", "name": "ls", "arguments": ""}}),
+            json!({"type": "response.function_call_arguments.delta", "output_index": 2, "delta": "{\"path\":"}),
+            json!({"type": "response.function_call_arguments.delta", "output_index": 2, "delta": "\".\"}"}),
+            json!({"type": "response.completed", "response": {"id": "resp_2", "model": "gpt-5", "status": "completed", "output": [],
+                   "usage": {"input_tokens": 20, "output_tokens": 10, "total_tokens": 30}}}),
+        ];
+        let sse: String = events.iter().map(|e| format!("event: {}\ndata: {}\n\n", e["type"].as_str().unwrap(), e)).collect();
+        Mock::given(method("POST"))
+            .and(path("/v1/responses"))
+            .and(body_partial_json(json!({"stream": true})))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(sse, "text/event-stream"))
+            .mount(&server)
+            .await;
+
+        let provider = setup_provider(&server);
+        let stream = provider.chat_stream(request(json!({
+            "model": "gpt-5",
+            "messages": [{"role": "user", "content": "list the files"}]
+        }))).await.unwrap();
+
+        let answer = collect_stream(stream).await;
+        assert_eq!(answer.content, "Let me check.");
+        assert_eq!(answer.reasoning, "Thinking");
+        assert_eq!(answer.tool_calls, vec![("call_9".to_string(), "{\"path\":\".\"}".to_string())]);
+        assert_eq!(serde_json::to_value(&answer.finish_reason).unwrap(), "tool_calls");
+        assert_eq!(answer.usage.unwrap().total_tokens, 30);
+    }
+
+    #[tokio::test]
+    async fn test_api_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/responses"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": {"message": "Unsupported parameter: 'temperature'", "type": "invalid_request_error"}
+            })))
+            .mount(&server)
+            .await;
+
+        let error = chat_error(&setup_provider(&server), "o3").await;
+        assert!(error.contains("Unsupported parameter"));
+    }
+
+    #[test]
+    fn test_request_conversion() {
+        let provider = OpenAIResponsesProvider::configure("key".to_string(), None, Some("".to_string()));
+        let responses_format = provider.convert_to_responses_format(&request(json!({
+            "model": "gpt-4.1",
+            "messages": [
+                {"role": "developer", "content": "Answer in json"},
+                {"role": "user", "content": [
+                    {"type": "text", "text": "what is this?"},
+                    {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}}
+                ]},
+                {"role": "assistant", "content": "A logo."}
+            ],
+            "tool_choice": {"type": "function", "function": {"name": "read"}},
+            "max_completion_tokens": 256,
+            "response_format": {"type": "json_schema", "json_schema": {"name": "answer", "schema": {"type": "object"}, "strict": true}}
+        })), false);
+
+        assert!(responses_format["instructions"].is_null());
+        assert_eq!(responses_format["input"][0], json!({"type": "message", "role": "developer", "content": "Answer in json"}));
+        assert_eq!(responses_format["input"][1]["content"], json!([
+            {"type": "input_text", "text": "what is this?"},
+            {"type": "input_image", "image_url": "data:image/png;base64,iVBORw0KGgo="}
+        ]));
+        assert_eq!(responses_format["input"][2], json!({"type": "message", "role": "assistant", "content": [{"type": "output_text", "text": "A logo."}]}));
+        assert_eq!(responses_format["tool_choice"], json!({"type": "function", "name": "read"}));
+        assert_eq!(responses_format["max_output_tokens"], 256);
+        assert_eq!(responses_format["text"], json!({"format": {"type": "json_schema", "name": "answer", "schema": {"type": "object"}, "strict": true}}));
+        // no effort nor summary asked
+        assert!(responses_format["reasoning"].is_null());
+        assert_eq!(responses_format["stream"], false);
+    }
+}
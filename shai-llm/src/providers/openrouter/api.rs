@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use crate::model_info::ModelInfo;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OpenRouterModelsResponse {
@@ -33,6 +34,10 @@ pub struct OpenRouterArchitecture {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct OpenRouterTopProvider {
     pub is_moderated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_completion_tokens: Option<i64>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -65,6 +70,25 @@ impl OpenRouterModel {
     }
 }
 
+impl OpenRouterModel {
+    /// Capabilities and prices (USD per token) of the model
+    pub fn to_model_info(&self) -> ModelInfo {
+        let supports = |parameter: &str| self.supported_parameters.as_ref()
+            .map(|parameters| parameters.iter().any(|p| p == parameter));
+        ModelInfo {
+            id: self.id.clone(),
+            context_length: u32::try_from(self.context_length).ok().filter(|c| *c > 0),
+            max_output_tokens: self.top_provider.max_completion_tokens.and_then(|m| u32::try_from(m).ok()),
+            supports_tools: supports("tools"),
+            supports_structured_output: supports("structured_outputs"),
+            supports_vision: Some(self.architecture.input_modalities.iter().any(|m| m == "image")),
+            supports_reasoning: supports("reasoning"),
+            input_price: self.pricing.prompt.parse().ok(),
+            output_price: self.pricing.completion.parse().ok(),
+        }
+    }
+}
+
 impl OpenRouterModelsResponse {
     /// Convert OpenRouter models response to openai_dive ListModelResponse format
     pub fn to_openai_models_response(&self) -> openai_dive::v1::resources::model::ListModelResponse {
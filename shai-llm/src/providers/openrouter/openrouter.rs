@@ -1,4 +1,7 @@
 use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use crate::model_info::ModelInfo;
+use crate::chat::ChatClient;
+use crate::reasoning::ReasoningHooks;
 use super::api::OpenRouterModelsResponse;
 use async_trait::async_trait;
 use futures::StreamExt;
@@ -14,7 +17,8 @@ use openai_dive::v1::{
 const OPENROUTER_API_BASE: &str = "https://openrouter.ai/api/v1";
 
 pub struct OpenRouterProvider {
-    client: Client,
+    /// chat client normalizing the `reasoning` field of the responses
+    client: ChatClient,
     api_key: String,
     base_url: String,
     http_client: reqwest::Client,
@@ -22,8 +26,7 @@ pub struct OpenRouterProvider {
 
 impl OpenRouterProvider {
     pub fn new(api_key: String) -> Self {
-        let mut client = Client::new(api_key.clone());
-        client.set_base_url(OPENROUTER_API_BASE);
+        let client = ChatClient::new(api_key.clone(), OPENROUTER_API_BASE.to_string());
         Self { 
             client,
             api_key,
@@ -78,6 +81,13 @@ impl LlmProvider for OpenRouterProvider {
     }
 
 
+    async fn model_info(&self, model: &str) -> Result<Option<ModelInfo>, LlmError> {
+        let openrouter_response = self.openrouter_models().await?;
+        Ok(openrouter_response.data.iter()
+            .find(|m| m.id == model)
+            .map(|m| m.to_model_info()))
+    }
+
     async fn default_model(&self) -> Result<String, LlmError> {
         let models = self.models().await?; 
     
@@ -90,7 +100,7 @@ impl LlmProvider for OpenRouterProvider {
     }
 
     async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
-        let response = self.client.chat().create(request).await
+        let response = self.client.chat_completion(&request, &ReasoningHooks).await
             .map_err(|e| Box::new(e) as LlmError)?;
         Ok(response)
     }
@@ -99,7 +109,7 @@ impl LlmProvider for OpenRouterProvider {
         // Ensure streaming is enabled
         request.stream = Some(true);
         
-        let stream = self.client.chat().create_stream(request).await
+        let stream = self.client.chat_completion_stream(&request, ReasoningHooks).await
             .map_err(|e| Box::new(e) as LlmError)?;
 
         let converted_stream = stream.map(|result| {
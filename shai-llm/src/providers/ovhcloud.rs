@@ -1,5 +1,6 @@
 // llm/providers/ovhcloud.rs
 use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use crate::chat::{ChatClient, NoHooks};
 use async_trait::async_trait;
 use futures::StreamExt;
 use openai_dive::v1::{
@@ -17,14 +18,16 @@ const OVH_API_BASE: &str = "https://oai.endpoints.kepler.ai.cloud.ovh.net/v1";
 
 pub struct OvhCloudProvider {
     client: Client,
+    chat_client: ChatClient,
 }
 
 impl OvhCloudProvider {
     pub fn new(api_key: String, base_url: Option<String>) -> Self {
-        let mut client = Client::new(api_key);
+        let mut client = Client::new(api_key.clone());
         let url = base_url.unwrap_or_else(|| OVH_API_BASE.to_string());
         client.set_base_url(&url);
-        Self { client }
+        let chat_client = ChatClient::new(api_key, url);
+        Self { client, chat_client }
     }
 
     /// Create OVH Cloud provider from environment variables
@@ -67,7 +70,7 @@ impl LlmProvider for OvhCloudProvider {
 
     async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
         let sanitized_request = self.sanitize_request(request);
-        let mut response = self.client.chat().create(sanitized_request).await
+        let mut response = self.chat_client.chat_completion(&sanitized_request, &NoHooks).await
             .map_err(|e| Box::new(e) as LlmError)?;
 
         Ok(response)
@@ -77,7 +80,7 @@ impl LlmProvider for OvhCloudProvider {
         request.stream = Some(true);
         let sanitized_request = self.sanitize_request(request);
         
-        let stream = self.client.chat().create_stream(sanitized_request).await
+        let stream = self.chat_client.chat_completion_stream(&sanitized_request, NoHooks).await
             .map_err(|e| Box::new(e) as LlmError)?;
 
         let converted_stream = stream.map(|result| {
@@ -0,0 +1,527 @@
+// Replay provider: records the exchanges of a real provider to a cassette file and serves
+// them back, or answers from a script, so tests run without network
+use crate::provider::{LlmProvider, LlmError, LlmStream, ProviderInfo, EnvVar};
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use openai_dive::v1::resources::{
+    chat::{ChatCompletionParameters, ChatCompletionResponse, ChatCompletionChunkResponse, ChatMessage, ChatMessageContent, ChatCompletionChoice, ToolCall, Function},
+    model::{ListModelResponse, Model},
+    shared::Usage,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A request and what the provider answered, streamed or not
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// the request as sent, without the streaming flags
+    pub request: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<ChatCompletionResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<Vec<ChatCompletionChunkResponse>>,
+}
+
+/// The interactions recorded in a file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// What a scripted provider answers next
+#[derive(Debug, Clone)]
+pub enum ScriptedResponse {
+    Response(ChatCompletionResponse),
+    Error(String),
+}
+
+enum Mode {
+    /// forward to the provider and write every interaction to the cassette
+    Record { inner: Box<dyn LlmProvider>, path: PathBuf },
+    /// serve the interactions of the cassette
+    Replay,
+    /// serve the queued responses in order
+    Scripted,
+}
+
+#[derive(Default)]
+struct ReplayState {
+    cassette: Cassette,
+    /// interactions already served, each is served once
+    used: Vec<bool>,
+    script: VecDeque<ScriptedResponse>,
+    requests: Vec<ChatCompletionParameters>,
+}
+
+/// Provider for tests, clones share their cassette, script and requests
+#[derive(Clone)]
+pub struct ReplayProvider {
+    mode: Arc<Mode>,
+    state: Arc<Mutex<ReplayState>>,
+}
+
+impl ReplayProvider {
+    fn with_mode(mode: Mode, mut cassette: Cassette) -> Self {
+        // recorded requests go through the same normalization as the live ones, so that
+        // cassettes written by hand or before a normalization change still match
+        for interaction in &mut cassette.interactions {
+            if let Ok(request) = serde_json::from_value::<ChatCompletionParameters>(interaction.request.clone()) {
+                interaction.request = normalize_request(&request);
+            }
+        }
+        let used = vec![false; cassette.interactions.len()];
+        Self {
+            mode: Arc::new(mode),
+            state: Arc::new(Mutex::new(ReplayState { cassette, used, ..ReplayState::default() })),
+        }
+    }
+
+    /// Forward to `inner` and record to `path`, replacing what it held
+    pub fn record(inner: Box<dyn LlmProvider>, path: impl Into<PathBuf>) -> Self {
+        Self::with_mode(Mode::Record { inner, path: path.into() }, Cassette::default())
+    }
+
+    /// Serve the interactions recorded in `path`
+    pub fn replay(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::with_mode(Mode::Replay, Cassette::load(path.as_ref())?))
+    }
+
+    /// Serve the given interactions
+    pub fn from_cassette(cassette: Cassette) -> Self {
+        Self::with_mode(Mode::Replay, cassette)
+    }
+
+    /// Answer with the queued responses, see `then_text` and `then_tool_call`
+    pub fn scripted() -> Self {
+        Self::with_mode(Mode::Scripted, Cassette::default())
+    }
+
+    /// Queue a response
+    pub fn push(&self, response: ScriptedResponse) {
+        self.state.lock().unwrap().script.push_back(response);
+    }
+
+    pub fn then_response(self, response: ChatCompletionResponse) -> Self {
+        self.push(ScriptedResponse::Response(response));
+        self
+    }
+
+    pub fn then_text(self, text: &str) -> Self {
+        self.then_response(text_response(text))
+    }
+
+    pub fn then_tool_call(self, name: &str, arguments: Value) -> Self {
+        self.then_tool_calls(vec![(name, arguments)])
+    }
+
+    /// Queue an answer calling several tools at once
+    pub fn then_tool_calls(self, calls: Vec<(&str, Value)>) -> Self {
+        let step = self.state.lock().unwrap().script.len();
+        self.then_response(tool_calls_response(step, calls))
+    }
+
+    pub fn then_error(self, error: &str) -> Self {
+        self.push(ScriptedResponse::Error(error.to_string()));
+        self
+    }
+
+    /// Requests received so far
+    pub fn requests(&self) -> Vec<ChatCompletionParameters> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// The recorded or loaded interactions
+    pub fn cassette(&self) -> Cassette {
+        self.state.lock().unwrap().cassette.clone()
+    }
+
+    /// First interaction not served yet whose request matches, those answering
+    /// the same way (streamed or not) come first
+    fn take_interaction(&self, request: &Value, streamed: bool) -> Result<Interaction, LlmError> {
+        let mut state = self.state.lock().unwrap();
+        let candidates: Vec<usize> = (0..state.cassette.interactions.len())
+            .filter(|i| !state.used[*i] && state.cassette.interactions[*i].request == *request)
+            .collect();
+        let index = candidates.iter()
+            .find(|i| state.cassette.interactions[**i].chunks.is_some() == streamed)
+            .or(candidates.first())
+            .copied()
+            .ok_or_else(|| format!("no recorded interaction matches the request: {}", request))?;
+        state.used[index] = true;
+        Ok(state.cassette.interactions[index].clone())
+    }
+
+    fn next_scripted(&self) -> Result<ChatCompletionResponse, LlmError> {
+        match self.state.lock().unwrap().script.pop_front() {
+            Some(ScriptedResponse::Response(response)) => Ok(response),
+            Some(ScriptedResponse::Error(error)) => Err(error.into()),
+            None => Err("the script has no response left".into()),
+        }
+    }
+
+    fn save_interaction(state: &Mutex<ReplayState>, path: &Path, interaction: Interaction) -> std::io::Result<()> {
+        let mut state = state.lock().unwrap();
+        state.cassette.interactions.push(interaction);
+        state.used.push(true);
+        state.cassette.save(path)
+    }
+}
+
+/// Request as matched against the cassette, the streaming flags do not change the answer
+/// and the environment block of system prompts (date, platform, working directory) is blanked
+pub fn normalize_request(request: &ChatCompletionParameters) -> Value {
+    let mut json = serde_json::to_value(request).unwrap_or_default();
+    if let Some(object) = json.as_object_mut() {
+        object.remove("stream");
+        object.remove("stream_options");
+    }
+
+    let system_messages = json["messages"].as_array_mut().into_iter().flatten()
+        .filter(|message| matches!(message["role"].as_str(), Some("system" | "developer")));
+    for message in system_messages {
+        match &mut message["content"] {
+            Value::String(text) => *text = blank_environment(text),
+            Value::Array(parts) => {
+                for part in parts {
+                    if let Some(Value::String(text)) = part.get_mut("text") {
+                        *text = blank_environment(text);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    json
+}
+
+/// Empty the `<env>...</env>` block of a prompt, it changes with the day and the machine
+fn blank_environment(text: &str) -> String {
+    match (text.find("<env>"), text.find("</env>")) {
+        (Some(start), Some(end)) if start < end => format!("{}<env></env>{}", &text[..start], &text[end + "</env>".len()..]),
+        _ => text.to_string(),
+    }
+}
+
+fn response(message: ChatMessage, finish_reason: &str) -> ChatCompletionResponse {
+    ChatCompletionResponse {
+        id: Some("replay".to_string()),
+        object: "chat.completion".to_string(),
+        created: 0,
+        model: "replay".to_string(),
+        choices: vec![ChatCompletionChoice {
+            index: 0,
+            message,
+            finish_reason: serde_json::from_value(json!(finish_reason)).ok(),
+            logprobs: None,
+        }],
+        usage: Some(Usage {
+            input_tokens: None,
+            input_tokens_details: None,
+            output_tokens: None,
+            output_tokens_details: None,
+            prompt_tokens: Some(0),
+            completion_tokens: Some(0),
+            total_tokens: 0,
+            prompt_tokens_details: None,
+            completion_tokens_details: None,
+        }),
+        service_tier: None,
+        system_fingerprint: None,
+    }
+}
+
+/// Assistant answer with some text
+pub fn text_response(text: &str) -> ChatCompletionResponse {
+    response(ChatMessage::Assistant {
+        content: Some(ChatMessageContent::Text(text.to_string())),
+        reasoning_content: None,
+        refusal: None,
+        name: None,
+        audio: None,
+        tool_calls: None,
+    }, "stop")
+}
+
+/// Assistant answer calling tools, the call ids are `call_<step>_<n>`
+pub fn tool_calls_response(step: usize, calls: Vec<(&str, Value)>) -> ChatCompletionResponse {
+    let tool_calls = calls.into_iter()
+        .enumerate()
+        .map(|(n, (name, arguments))| ToolCall {
+            id: format!("call_{}_{}", step, n),
+            r#type: "function".to_string(),
+            function: Function {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        })
+        .collect();
+    response(ChatMessage::Assistant {
+        content: None,
+        reasoning_content: None,
+        refusal: None,
+        name: None,
+        audio: None,
+        tool_calls: Some(tool_calls),
+    }, "tool_calls")
+}
+
+/// The chunks a stream of this response would be made of, a single one
+pub fn response_to_chunks(response: &ChatCompletionResponse) -> Result<Vec<ChatCompletionChunkResponse>, LlmError> {
+    let mut json = serde_json::to_value(response)?;
+    json["object"] = json!("chat.completion.chunk");
+    for choice in json["choices"].as_array_mut().into_iter().flatten() {
+        let mut delta = choice.as_object_mut().and_then(|c| c.remove("message")).unwrap_or_default();
+        for (index, call) in delta["tool_calls"].as_array_mut().into_iter().flatten().enumerate() {
+            call["index"] = json!(index);
+        }
+        choice["delta"] = delta;
+    }
+    Ok(vec![serde_json::from_value(json)?])
+}
+
+fn chunks_stream(chunks: Vec<ChatCompletionChunkResponse>) -> LlmStream {
+    Box::new(stream::iter(chunks.into_iter().map(Ok)))
+}
+
+#[async_trait]
+impl LlmProvider for ReplayProvider {
+    async fn models(&self) -> Result<ListModelResponse, LlmError> {
+        if let Mode::Record { inner, .. } = &*self.mode {
+            return inner.models().await;
+        }
+
+        let state = self.state.lock().unwrap();
+        let mut ids: Vec<String> = Vec::new();
+        let recorded = state.cassette.interactions.iter().filter_map(|i| i.request["model"].as_str().map(str::to_string));
+        for id in recorded.chain(std::iter::once("replay".to_string())) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        Ok(ListModelResponse {
+            object: "list".to_string(),
+            data: ids.into_iter()
+                .map(|id| Model { id, object: "model".to_string(), created: None, owned_by: "replay".to_string() })
+                .collect(),
+        })
+    }
+
+    async fn chat(&self, request: ChatCompletionParameters) -> Result<ChatCompletionResponse, LlmError> {
+        let normalized = normalize_request(&request);
+        self.state.lock().unwrap().requests.push(request.clone());
+
+        match &*self.mode {
+            Mode::Record { inner, path } => {
+                let response = inner.chat(request).await?;
+                Self::save_interaction(&self.state, path, Interaction {
+                    request: normalized,
+                    response: Some(response.clone()),
+                    chunks: None,
+                })?;
+                Ok(response)
+            }
+            Mode::Replay => {
+                let interaction = self.take_interaction(&normalized, false)?;
+                interaction.response.ok_or_else(|| "the matching interaction was recorded as a stream".into())
+            }
+            Mode::Scripted => self.next_scripted(),
+        }
+    }
+
+    async fn chat_stream(&self, request: ChatCompletionParameters) -> Result<LlmStream, LlmError> {
+        let normalized = normalize_request(&request);
+        self.state.lock().unwrap().requests.push(request.clone());
+
+        match &*self.mode {
+            Mode::Record { inner, path } => {
+                let mut inner_stream = inner.chat_stream(request).await?;
+                let (state, path) = (self.state.clone(), path.clone());
+                let recording = async_stream::stream! {
+                    let mut chunks = Vec::new();
+                    let mut failed = false;
+                    while let Some(chunk) = inner_stream.next().await {
+                        match &chunk {
+                            Ok(chunk) => chunks.push(chunk.clone()),
+                            Err(_) => failed = true,
+                        }
+                        yield chunk;
+                    }
+                    // a broken stream is not worth replaying
+                    if !failed {
+                        let interaction = Interaction { request: normalized, response: None, chunks: Some(chunks) };
+                        if let Err(e) = Self::save_interaction(&state, &path, interaction) {
+                            yield Err(Box::new(e) as LlmError);
+                        }
+                    }
+                };
+                Ok(Box::new(Box::pin(recording)))
+            }
+            Mode::Replay => {
+                let interaction = self.take_interaction(&normalized, true)?;
+                let chunks = match (interaction.chunks, interaction.response) {
+                    (Some(chunks), _) => chunks,
+                    (None, Some(response)) => response_to_chunks(&response)?,
+                    (None, None) => vec![],
+                };
+                Ok(chunks_stream(chunks))
+            }
+            Mode::Scripted => Ok(chunks_stream(response_to_chunks(&self.next_scripted()?)?)),
+        }
+    }
+
+    fn supports_functions(&self, model: String) -> bool {
+        match &*self.mode {
+            Mode::Record { inner, .. } => inner.supports_functions(model),
+            _ => true,
+        }
+    }
+
+    fn supports_structured_output(&self, model: String) -> bool {
+        match &*self.mode {
+            Mode::Record { inner, .. } => inner.supports_structured_output(model),
+            _ => true,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "replay"
+    }
+
+    fn info() -> ProviderInfo {
+        ProviderInfo {
+            name: "replay",
+            display_name: "Replay (recorded responses, for tests)",
+            env_vars: vec![
+                EnvVar::required("SHAI_REPLAY_CASSETTE", "Cassette file to replay or record"),
+                EnvVar::optional("SHAI_REPLAY_PROVIDER", "Provider to record from, replays when not set"),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openai_dive::v1::resources::chat::DeltaChatMessage;
+
+    fn request(text: &str) -> ChatCompletionParameters {
+        serde_json::from_value(json!({
+            "model": "replay",
+            "messages": [{"role": "user", "content": text}]
+        })).unwrap()
+    }
+
+    fn cassette_path() -> PathBuf {
+        std::env::temp_dir().join(format!("shai-cassette-{}.json", uuid::Uuid::new_v4()))
+    }
+
+    fn text_of(response: &ChatCompletionResponse) -> Option<String> {
+        match &response.choices[0].message {
+            ChatMessage::Assistant { content: Some(ChatMessageContent::Text(text)), .. } => Some(text.clone()),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scripted_responses() {
+        let provider = ReplayProvider::scripted()
+            .then_tool_call("ls", json!({"path": "."}))
+            .then_error("rate limited")
+            .then_text("done");
+
+        let response = provider.chat(request("list the files")).await.unwrap();
+        let ChatMessage::Assistant { tool_calls: Some(calls), .. } = &response.choices[0].message else {
+            panic!("expected tool calls");
+        };
+        assert_eq!(calls[0].id, "call_0_0");
+        assert_eq!(calls[0].function.name, "ls");
+
+        assert!(provider.chat(request("again")).await.is_err());
+
+        let mut stream = provider.chat_stream(request("and now")).await.unwrap();
+        let chunk = stream.next().await.unwrap().unwrap();
+        assert!(matches!(
+            &chunk.choices[0].delta,
+            DeltaChatMessage::Assistant { content: Some(ChatMessageContent::Text(text)), .. } if text == "done"
+        ));
+
+        assert!(provider.chat(request("more")).await.is_err(), "the script is exhausted");
+        assert_eq!(provider.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let path = cassette_path();
+        let live = ReplayProvider::scripted().then_text("first").then_text("second");
+        let recorder = ReplayProvider::record(Box::new(live), &path);
+
+        assert_eq!(text_of(&recorder.chat(request("hello")).await.unwrap()).as_deref(), Some("first"));
+        let chunks: Vec<_> = recorder.chat_stream(request("stream please")).await.unwrap().collect().await;
+        assert_eq!(chunks.len(), 1);
+
+        let replayer = ReplayProvider::replay(&path).unwrap();
+        assert_eq!(replayer.cassette().interactions.len(), 2);
+
+        // streamed and non streamed requests match the same recording
+        let chunks: Vec<_> = replayer.chat_stream(request("hello")).await.unwrap().collect().await;
+        assert_eq!(chunks.len(), 1);
+        let chunks: Vec<_> = replayer.chat_stream(request("stream please")).await.unwrap().collect().await;
+        assert!(matches!(
+            &chunks[0].as_ref().unwrap().choices[0].delta,
+            DeltaChatMessage::Assistant { content: Some(ChatMessageContent::Text(text)), .. } if text == "second"
+        ));
+
+        // each interaction is served once, unknown requests fail
+        assert!(replayer.chat(request("hello")).await.is_err());
+        assert!(replayer.chat(request("something else")).await.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_environment_is_not_matched() {
+        let prompt = |date: &str, dir: &str| -> ChatCompletionParameters {
+            serde_json::from_value(json!({
+                "model": "replay",
+                "messages": [
+                    {"role": "system", "content": format!("Be helpful.\n<env>\n  Today's date: {}\n  Working directory: {}\n</env>\n", date, dir)},
+                    {"role": "user", "content": "hello"}
+                ]
+            })).unwrap()
+        };
+
+        let live = ReplayProvider::scripted().then_text("hi");
+        let path = cassette_path();
+        let recorder = ReplayProvider::record(Box::new(live), &path);
+        recorder.chat(prompt("2026-01-01", "/home/alice/project")).await.unwrap();
+
+        // recorded on another day on another machine
+        let replayer = ReplayProvider::replay(&path).unwrap();
+        let response = replayer.chat(prompt("2026-10-18", "/tmp/ci/project")).await.unwrap();
+        assert_eq!(text_of(&response).as_deref(), Some("hi"));
+
+        // the other messages still count
+        let mut other = prompt("2026-10-18", "/tmp/ci/project");
+        other.messages.pop();
+        assert!(ReplayProvider::replay(&path).unwrap().chat(other).await.is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
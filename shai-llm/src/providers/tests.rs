@@ -1,10 +1,12 @@
-use crate::provider::{LlmProvider, LlmError};
+use crate::provider::{LlmProvider, LlmError, LlmStream};
 use openai_dive::v1::resources::{
-    chat::{ChatCompletionFunction, ChatCompletionParametersBuilder, ChatCompletionTool, ChatCompletionToolChoice, ChatCompletionToolType, ChatMessage, ChatMessageContent, DeltaChatMessage, ChatCompletionResponseFormat, JsonSchemaBuilder},
+    chat::{ChatCompletionFunction, ChatCompletionParameters, ChatCompletionParametersBuilder, ChatCompletionTool, ChatCompletionToolChoice, ChatCompletionToolType, ChatMessage, ChatMessageContent, DeltaChatMessage, ChatCompletionResponseFormat, JsonSchemaBuilder},
     model::ListModelResponse,
+    shared::{FinishReason, Usage},
 };
 use futures::StreamExt;
-use serde_json::json;
+use serde_json::{json, Value};
+use wiremock::MockServer;
 
 /// Test function calling with boolean parameters to detect model-specific JSON issues
 pub async fn test_provider_function_calling_boolean_params(provider: Box<dyn LlmProvider>) {
@@ -264,16 +266,89 @@ pub async fn test_provider_chat_stream(provider: Box<dyn LlmProvider>) {
 pub fn create_provider_from_env(provider_name: &str) -> Option<Box<dyn LlmProvider>> {
     match provider_name {
         "openai" => crate::providers::openai::OpenAIProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
+        "openai_responses" => crate::providers::openai_responses::OpenAIResponsesProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
         "anthropic" => crate::providers::anthropic::AnthropicProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
+        "gemini" => crate::providers::gemini::GeminiProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
         "ollama" => crate::providers::ollama::OllamaProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
         "openrouter" => crate::providers::openrouter::OpenRouterProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
         "openai_compatible" => crate::providers::openai_compatible::OpenAICompatibleProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
+        "azure_openai" => crate::providers::azure_openai::AzureOpenAIProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
         "ovhcloud" => crate::providers::ovhcloud::OvhCloudProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
         "mistral" => crate::providers::mistral::MistralProvider::from_env().map(|p| Box::new(p) as Box<dyn LlmProvider>),
         _ => None,
     }
 }
 
+/// Request from its OpenAI wire format
+pub fn request(value: Value) -> ChatCompletionParameters {
+    serde_json::from_value(value).unwrap()
+}
+
+/// Body of the last request the mock server received on `path`
+pub async fn sent_body(server: &MockServer, path: &str) -> Value {
+    let requests = server.received_requests().await.unwrap();
+    requests.iter().rev().find(|r| r.url.path() == path).unwrap().body_json().unwrap()
+}
+
+/// Messages of a conversation going on after the model read main.py through the call `call_id`
+pub fn tool_exchange(call_id: &str) -> Value {
+    json!([
+        {"role": "user", "content": "read main.py"},
+        {"role": "assistant", "tool_calls": [{
+            "id": call_id, "type": "function",
+            "function": {"name": "read", "arguments": "{\"path\":\"main.py\"}"}
+        }]},
+        {"role": "tool", "tool_call_id": call_id, "content": "print('hello')"}
+    ])
+}
+
+/// Error of a chat request to a provider answering with an error
+pub async fn chat_error(provider: &dyn LlmProvider, model: &str) -> String {
+    provider.chat(request(json!({
+        "model": model,
+        "messages": [{"role": "user", "content": "Hello!"}]
+    }))).await.unwrap_err().to_string()
+}
+
+/// A streamed answer put back together
+#[derive(Debug, Default)]
+pub struct StreamedAnswer {
+    pub content: String,
+    pub reasoning: String,
+    /// id and arguments of each tool call, by index
+    pub tool_calls: Vec<(String, String)>,
+    pub finish_reason: Option<FinishReason>,
+    pub usage: Option<Usage>,
+}
+
+pub async fn collect_stream(mut stream: LlmStream) -> StreamedAnswer {
+    let mut answer = StreamedAnswer::default();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.unwrap();
+        if let DeltaChatMessage::Assistant { content, reasoning_content, tool_calls, .. } = &chunk.choices[0].delta {
+            if let Some(ChatMessageContent::Text(text)) = content {
+                answer.content.push_str(text);
+            }
+            answer.reasoning.push_str(reasoning_content.as_deref().unwrap_or(""));
+            for call in tool_calls.iter().flatten() {
+                let call = serde_json::to_value(call).unwrap();
+                let index = call["index"].as_u64().unwrap() as usize;
+                if answer.tool_calls.len() <= index {
+                    answer.tool_calls.resize(index + 1, Default::default());
+                }
+                let (id, arguments) = &mut answer.tool_calls[index];
+                if let Some(call_id) = call["id"].as_str() {
+                    *id = call_id.to_string();
+                }
+                arguments.push_str(call["function"]["arguments"].as_str().unwrap_or(""));
+            }
+        }
+        answer.finish_reason = answer.finish_reason.or(chunk.choices[0].finish_reason.clone());
+        answer.usage = answer.usage.or(chunk.usage);
+    }
+    answer
+}
+
 /// Macro to generate tests for all providers
 macro_rules! register_providers_for_testing {
     ($($provider_name:ident),*) => {
@@ -331,10 +406,13 @@ macro_rules! register_providers_for_testing {
 // Register all providers for testing
 register_providers_for_testing!(
     openai,
+    openai_responses,
     anthropic,
+    gemini,
     ollama,
     openrouter,
     openai_compatible,
+    azure_openai,
     ovhcloud,
     mistral
 );
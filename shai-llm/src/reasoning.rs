@@ -0,0 +1,339 @@
+// Reasoning normalization: every provider ends up with the reasoning in reasoning_content
+use crate::chat::JsonHooks;
+use crate::provider::{LlmError, LlmStream};
+use async_trait::async_trait;
+use futures::StreamExt;
+use openai_dive::v1::error::APIError;
+use openai_dive::v1::resources::chat::ChatCompletionChunkResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+const THINK_OPEN: &str = "<think>";
+const THINK_CLOSE: &str = "</think>";
+
+/// What the client does with the reasoning of the model
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReasoningOptions {
+    /// drop the reasoning from responses and streams
+    pub strip: bool,
+    /// send the reasoning of earlier assistant messages back to the model,
+    /// some APIs reject it in requests
+    pub keep_in_trace: bool,
+}
+
+impl Default for ReasoningOptions {
+    fn default() -> Self {
+        Self { strip: false, keep_in_trace: true }
+    }
+}
+
+/// Splits streamed text into content and the reasoning found between <think> tags,
+/// a tag may be cut anywhere by the chunk boundaries
+#[derive(Debug, Default)]
+pub struct ThinkSplitter {
+    in_think: bool,
+    /// a think block just closed, the blank lines following it are dropped
+    after_think: bool,
+    /// end of the text seen so far that may be the beginning of a tag
+    pending: String,
+}
+
+impl ThinkSplitter {
+    /// Split the next piece of text, returns (content, reasoning)
+    pub fn push(&mut self, text: &str) -> (String, String) {
+        let mut input = std::mem::take(&mut self.pending);
+        input.push_str(text);
+
+        let (mut content, mut reasoning) = (String::new(), String::new());
+        let mut rest = input.as_str();
+        loop {
+            let tag = if self.in_think { THINK_CLOSE } else { THINK_OPEN };
+            let (piece, found) = match rest.find(tag) {
+                Some(pos) => (&rest[..pos], true),
+                None => {
+                    let held = partial_tag_len(rest, tag);
+                    self.pending = rest[rest.len() - held..].to_string();
+                    (&rest[..rest.len() - held], false)
+                }
+            };
+
+            if self.in_think {
+                reasoning.push_str(piece);
+            } else {
+                self.push_content(&mut content, piece);
+            }
+            if !found {
+                break;
+            }
+            rest = &rest[piece.len() + tag.len()..];
+            self.in_think = !self.in_think;
+            self.after_think |= !self.in_think;
+        }
+        (content, reasoning)
+    }
+
+    /// Text held back at the end of the stream, returns (content, reasoning)
+    pub fn finish(&mut self) -> (String, String) {
+        let pending = std::mem::take(&mut self.pending);
+        if self.in_think {
+            (String::new(), pending)
+        } else {
+            let mut content = String::new();
+            self.push_content(&mut content, &pending);
+            (content, String::new())
+        }
+    }
+
+    fn push_content(&mut self, content: &mut String, piece: &str) {
+        let piece = if self.after_think { piece.trim_start() } else { piece };
+        if !piece.is_empty() {
+            self.after_think = false;
+            content.push_str(piece);
+        }
+    }
+}
+
+/// Length of the longest end of `text` that starts `tag`
+fn partial_tag_len(text: &str, tag: &str) -> usize {
+    (1..tag.len()).rev()
+        .find(|n| text.len() >= *n && text.is_char_boundary(text.len() - n) && tag.starts_with(&text[text.len() - n..]))
+        .unwrap_or(0)
+}
+
+/// Move the provider specific reasoning of a message or delta to `reasoning_content`:
+/// `reasoning` (OpenRouter, Ollama) and thinking content parts (Mistral)
+pub fn normalize_message_json(message: &mut Value) {
+    let Some(message) = message.as_object_mut() else {
+        return;
+    };
+    let mut reasoning = String::new();
+
+    if let Some(Value::String(text)) = message.remove("reasoning") {
+        reasoning.push_str(&text);
+    }
+
+    let content = match message.get("content") {
+        Some(Value::Array(parts)) if parts.iter().any(|part| part["type"] == "thinking") => {
+            let mut text = String::new();
+            for part in parts {
+                match part["type"].as_str() {
+                    Some("thinking") => reasoning.push_str(&thinking_text(&part["thinking"])),
+                    Some("text") => text.push_str(part["text"].as_str().unwrap_or_default()),
+                    _ => {}
+                }
+            }
+            Some(text)
+        }
+        _ => None,
+    };
+    if let Some(text) = content {
+        message.insert("content".to_string(), if text.is_empty() { Value::Null } else { Value::String(text) });
+    }
+
+    if reasoning.is_empty() {
+        return;
+    }
+    match message.get_mut("reasoning_content") {
+        Some(Value::String(existing)) => existing.push_str(&reasoning),
+        _ => {
+            message.insert("reasoning_content".to_string(), Value::String(reasoning));
+        }
+    }
+}
+
+/// Text of a Mistral thinking part, a string or a list of text chunks
+fn thinking_text(thinking: &Value) -> String {
+    match thinking {
+        Value::String(text) => text.clone(),
+        Value::Array(chunks) => chunks.iter().filter_map(|c| c["text"].as_str()).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Normalize the messages and deltas of a chat completion response or chunk
+pub fn normalize_response_json(json: &mut Value) {
+    if let Some(choices) = json.get_mut("choices").and_then(|c| c.as_array_mut()) {
+        for choice in choices {
+            if let Some(message) = choice.get_mut("message") {
+                normalize_message_json(message);
+            }
+            if let Some(delta) = choice.get_mut("delta") {
+                normalize_message_json(delta);
+            }
+        }
+    }
+}
+
+/// Hooks for OpenAI compatible APIs that return their reasoning in their own fields
+pub struct ReasoningHooks;
+
+#[async_trait]
+impl JsonHooks for ReasoningHooks {
+    async fn after_receive(&self, mut json: Value) -> Result<Value, APIError> {
+        normalize_response_json(&mut json);
+        Ok(json)
+    }
+}
+
+/// Split the <think> blocks of a stream into reasoning_content deltas, and strip
+/// the reasoning if asked
+pub fn split_think_stream(stream: LlmStream, options: ReasoningOptions) -> LlmStream {
+    let stream = async_stream::stream! {
+        let mut stream = stream;
+        let mut splitters: HashMap<u64, ThinkSplitter> = HashMap::new();
+        let mut last: Option<ChatCompletionChunkResponse> = None;
+
+        while let Some(chunk) = stream.next().await {
+            match chunk.and_then(|chunk| split_chunk(chunk, &mut splitters, false, options)) {
+                Ok(chunk) => {
+                    last = Some(chunk.clone());
+                    yield Ok(chunk);
+                }
+                Err(e) => yield Err(e),
+            }
+        }
+
+        // text held back by a stream that ended without a finish reason
+        if let Some(mut chunk) = last.filter(|_| splitters.values().any(|s| !s.pending.is_empty())) {
+            chunk.usage = None;
+            for choice in &mut chunk.choices {
+                choice.finish_reason = None;
+            }
+            let mut json = serde_json::to_value(&chunk).unwrap_or_default();
+            for choice in json["choices"].as_array_mut().into_iter().flatten() {
+                choice["delta"] = serde_json::json!({"role": "assistant"});
+            }
+            let chunk = serde_json::from_value(json).map_err(|e| Box::new(e) as LlmError);
+            yield chunk.and_then(|chunk| split_chunk(chunk, &mut splitters, true, options));
+        }
+    };
+    Box::new(Box::pin(stream))
+}
+
+fn split_chunk(chunk: ChatCompletionChunkResponse, splitters: &mut HashMap<u64, ThinkSplitter>, flush: bool, options: ReasoningOptions) -> Result<ChatCompletionChunkResponse, LlmError> {
+    let mut json = serde_json::to_value(&chunk)?;
+    normalize_response_json(&mut json);
+
+    for choice in json["choices"].as_array_mut().into_iter().flatten() {
+        let splitter = splitters.entry(choice["index"].as_u64().unwrap_or(0)).or_default();
+        let finished = flush || !choice["finish_reason"].is_null();
+        let delta = &mut choice["delta"];
+
+        let text = delta["content"].as_str().map(str::to_string);
+        let (mut content, mut reasoning) = text.as_deref().map(|t| splitter.push(t)).unwrap_or_default();
+        if finished {
+            let (rest_content, rest_reasoning) = splitter.finish();
+            content.push_str(&rest_content);
+            reasoning.push_str(&rest_reasoning);
+        }
+        if text.is_some() || !content.is_empty() {
+            delta["content"] = if content.is_empty() { Value::Null } else { Value::String(content) };
+        }
+
+        let reasoning = format!("{}{}", delta["reasoning_content"].as_str().unwrap_or_default(), reasoning);
+        if options.strip || reasoning.is_empty() {
+            if let Some(delta) = delta.as_object_mut() {
+                delta.remove("reasoning_content");
+            }
+        } else {
+            delta["reasoning_content"] = Value::String(reasoning);
+            delta["role"] = Value::String("assistant".to_string());
+        }
+    }
+
+    Ok(serde_json::from_value(json)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use openai_dive::v1::resources::chat::{ChatMessageContent, DeltaChatMessage};
+    use serde_json::json;
+
+    fn split_all(pieces: &[&str]) -> (String, String) {
+        let mut splitter = ThinkSplitter::default();
+        let (mut content, mut reasoning) = (String::new(), String::new());
+        for piece in pieces {
+            let (c, r) = splitter.push(piece);
+            content.push_str(&c);
+            reasoning.push_str(&r);
+        }
+        let (c, r) = splitter.finish();
+        (content + &c, reasoning + &r)
+    }
+
+    #[test]
+    fn test_think_tags_across_chunks() {
+        let expected = ("Hello!".to_string(), "the user greets me".to_string());
+        assert_eq!(split_all(&["<think>the user greets me</think>\n\nHello!"]), expected);
+        assert_eq!(split_all(&["<thi", "nk>the user ", "greets me</", "think", ">\n", "\nHel", "lo!"]), expected);
+        assert_eq!(split_all(&["<", "t", "h", "i", "n", "k", ">", "the user greets me", "<", "/think>", "Hello!"]), expected);
+
+        // a lone "<" is content, an unclosed block is reasoning
+        assert_eq!(split_all(&["a <", " b"]), ("a < b".to_string(), String::new()));
+        assert_eq!(split_all(&["<think>still thinking <"]), (String::new(), "still thinking <".to_string()));
+    }
+
+    #[test]
+    fn test_normalize_provider_fields() {
+        // OpenRouter and Ollama
+        let mut response = json!({"choices": [{"index": 0, "message": {"role": "assistant", "content": "4", "reasoning": "2+2"}}]});
+        normalize_response_json(&mut response);
+        assert_eq!(response["choices"][0]["message"], json!({"role": "assistant", "content": "4", "reasoning_content": "2+2"}));
+
+        // Mistral thinking parts
+        let mut chunk = json!({"choices": [{"index": 0, "delta": {"content": [
+            {"type": "thinking", "thinking": [{"type": "text", "text": "2+2"}]},
+            {"type": "text", "text": "4"}
+        ]}}]});
+        normalize_response_json(&mut chunk);
+        assert_eq!(chunk["choices"][0]["delta"], json!({"content": "4", "reasoning_content": "2+2"}));
+    }
+
+    fn chunk(content: &str, finish_reason: Option<&str>) -> Result<ChatCompletionChunkResponse, LlmError> {
+        Ok(serde_json::from_value(json!({
+            "id": "chunk",
+            "object": "chat.completion.chunk",
+            "created": 0,
+            "model": "qwen3",
+            "choices": [{"index": 0, "delta": {"role": "assistant", "content": content}, "finish_reason": finish_reason}]
+        })).unwrap())
+    }
+
+    async fn collect(stream: LlmStream) -> (String, String) {
+        let chunks: Vec<_> = stream.collect().await;
+        let (mut content, mut reasoning) = (String::new(), String::new());
+        for chunk in chunks {
+            if let DeltaChatMessage::Assistant { content: c, reasoning_content: r, .. } = &chunk.unwrap().choices[0].delta {
+                if let Some(ChatMessageContent::Text(text)) = c {
+                    content.push_str(text);
+                }
+                reasoning.push_str(r.as_deref().unwrap_or_default());
+            }
+        }
+        (content, reasoning)
+    }
+
+    #[tokio::test]
+    async fn test_split_think_stream() {
+        let chunks = || vec![chunk("<think>let me", None), chunk(" see</thi", None), chunk("nk>It is 4", None), chunk(".<", Some("stop"))];
+
+        let stream: LlmStream = Box::new(stream::iter(chunks()));
+        let (content, reasoning) = collect(split_think_stream(stream, ReasoningOptions::default())).await;
+        assert_eq!(content, "It is 4.<");
+        assert_eq!(reasoning, "let me see");
+
+        let stream: LlmStream = Box::new(stream::iter(chunks()));
+        let (content, reasoning) = collect(split_think_stream(stream, ReasoningOptions { strip: true, ..Default::default() })).await;
+        assert_eq!(content, "It is 4.<");
+        assert_eq!(reasoning, "");
+
+        // held back text is flushed when the stream ends without a finish reason
+        let stream: LlmStream = Box::new(stream::iter(vec![chunk("a <", None)]));
+        let (content, _) = collect(split_think_stream(stream, ReasoningOptions::default())).await;
+        assert_eq!(content, "a <");
+    }
+}
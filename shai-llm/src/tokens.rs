@@ -0,0 +1,238 @@
+// Local token estimation, exact with the OpenAI tokenizers and a per family
+// chars per token ratio for the other models
+use crate::tool::ToolBox;
+use openai_dive::v1::resources::chat::{ChatCompletionParameters, ChatMessage};
+use serde_json::Value;
+use std::sync::OnceLock;
+use tiktoken_rs::CoreBPE;
+
+/// Tokens framing every message (role, separators)
+const MESSAGE_OVERHEAD: u32 = 3;
+/// Tokens priming the assistant reply
+const REPLY_OVERHEAD: u32 = 3;
+/// Tokens framing every tool definition
+const TOOL_OVERHEAD: u32 = 8;
+/// A high detail 1024x1024 image, the size of the others is not known from the request
+const IMAGE_TOKENS: u32 = 765;
+
+/// How the tokens of a model are counted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tokenizer {
+    /// gpt-4o, gpt-4.1, gpt-5, o-series and gpt-oss
+    O200k,
+    /// gpt-4 and gpt-3.5
+    Cl100k,
+    /// no tokenizer available, characters per token
+    Heuristic(f32),
+}
+
+fn o200k() -> Option<&'static CoreBPE> {
+    static BPE: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    BPE.get_or_init(|| tiktoken_rs::o200k_base().ok()).as_ref()
+}
+
+fn cl100k() -> Option<&'static CoreBPE> {
+    static BPE: OnceLock<Option<CoreBPE>> = OnceLock::new();
+    BPE.get_or_init(|| tiktoken_rs::cl100k_base().ok()).as_ref()
+}
+
+impl Tokenizer {
+    /// Tokenizer of a model, by the family in its name
+    pub fn for_model(model: &str) -> Self {
+        let model = model.to_lowercase();
+        let name = model.rsplit('/').next().unwrap_or(&model);
+        if ["gpt-4o", "gpt-4.1", "gpt-4.5", "gpt-5", "gpt-oss", "chatgpt", "o1", "o3", "o4"].iter().any(|p| name.starts_with(p)) {
+            Tokenizer::O200k
+        } else if name.starts_with("gpt-4") || name.starts_with("gpt-3.5") {
+            Tokenizer::Cl100k
+        } else if name.contains("claude") {
+            Tokenizer::Heuristic(3.5)
+        } else if ["mistral", "codestral", "devstral", "magistral", "ministral", "pixtral"].iter().any(|p| name.contains(p)) {
+            Tokenizer::Heuristic(3.6)
+        } else if name.contains("gemini") || name.contains("gemma") {
+            Tokenizer::Heuristic(4.0)
+        } else {
+            Tokenizer::Heuristic(3.8)
+        }
+    }
+
+    /// Whether the count is the one of the provider, not an approximation
+    pub fn is_exact(&self) -> bool {
+        !matches!(self, Tokenizer::Heuristic(_))
+    }
+
+    pub fn count(&self, text: &str) -> u32 {
+        if text.is_empty() {
+            return 0;
+        }
+        let bpe = match self {
+            Tokenizer::O200k => o200k(),
+            Tokenizer::Cl100k => cl100k(),
+            Tokenizer::Heuristic(_) => None,
+        };
+        match (bpe, self) {
+            (Some(bpe), _) => bpe.encode_ordinary(text).len() as u32,
+            (None, Tokenizer::Heuristic(chars_per_token)) => (text.chars().count() as f32 / chars_per_token).ceil() as u32,
+            // the tokenizer data failed to load
+            (None, _) => (text.chars().count() as f32 / 4.0).ceil() as u32,
+        }
+    }
+
+    /// Tokens of a message content, a string or an array of parts
+    fn count_content(&self, content: &Value) -> u32 {
+        match content {
+            Value::String(text) => self.count(text),
+            Value::Array(parts) => parts.iter()
+                .map(|part| match part["type"].as_str() {
+                    Some("image_url") | Some("image") => IMAGE_TOKENS,
+                    _ => self.count(part["text"].as_str().unwrap_or("")),
+                })
+                .sum(),
+            _ => 0,
+        }
+    }
+
+    pub fn count_message(&self, message: &ChatMessage) -> u32 {
+        let json = serde_json::to_value(message).unwrap_or_default();
+        let mut tokens = MESSAGE_OVERHEAD + self.count_content(&json["content"]);
+        if let Some(reasoning) = json["reasoning_content"].as_str() {
+            tokens += self.count(reasoning);
+        }
+        if json["name"].is_string() {
+            tokens += 1;
+        }
+        for call in json["tool_calls"].as_array().into_iter().flatten() {
+            tokens += MESSAGE_OVERHEAD
+                + self.count(call["function"]["name"].as_str().unwrap_or(""))
+                + self.count(call["function"]["arguments"].as_str().unwrap_or(""));
+        }
+        tokens
+    }
+
+    pub fn count_messages(&self, messages: &[ChatMessage]) -> u32 {
+        messages.iter().map(|m| self.count_message(m)).sum::<u32>() + REPLY_OVERHEAD
+    }
+
+    pub fn count_tool(&self, name: &str, description: &str, parameters: &Value) -> u32 {
+        TOOL_OVERHEAD + self.count(name) + self.count(description) + self.count(&parameters.to_string())
+    }
+
+    pub fn count_tools(&self, tools: &ToolBox) -> u32 {
+        tools.iter()
+            .map(|tool| self.count_tool(&tool.name(), &tool.description(), &tool.parameters_schema()))
+            .sum()
+    }
+
+    /// Tokens of the prompt of a request, its messages and tool definitions
+    pub fn count_request(&self, request: &ChatCompletionParameters) -> u32 {
+        let tools = serde_json::to_value(&request.tools).unwrap_or_default();
+        let tools: u32 = tools.as_array().into_iter().flatten()
+            .map(|tool| {
+                let function = &tool["function"];
+                self.count_tool(
+                    function["name"].as_str().unwrap_or(""),
+                    function["description"].as_str().unwrap_or(""),
+                    &function["parameters"],
+                )
+            })
+            .sum();
+        self.count_messages(&request.messages) + tools
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openai_dive::v1::resources::chat::ChatMessageContent;
+    use serde_json::json;
+
+    fn user(text: &str) -> ChatMessage {
+        ChatMessage::User { content: ChatMessageContent::Text(text.to_string()), name: None }
+    }
+
+    #[test]
+    fn test_tokenizer_for_model() {
+        assert_eq!(Tokenizer::for_model("gpt-4o-mini"), Tokenizer::O200k);
+        assert_eq!(Tokenizer::for_model("openai/gpt-5"), Tokenizer::O200k);
+        assert_eq!(Tokenizer::for_model("gpt-4-turbo"), Tokenizer::Cl100k);
+        assert_eq!(Tokenizer::for_model("claude-sonnet-4-5"), Tokenizer::Heuristic(3.5));
+        assert!(!Tokenizer::for_model("Qwen3-32B").is_exact());
+    }
+
+    #[test]
+    fn test_exact_count() {
+        // "hello world" is two tokens with both OpenAI tokenizers
+        assert_eq!(Tokenizer::O200k.count("hello world"), 2);
+        assert_eq!(Tokenizer::Cl100k.count("hello world"), 2);
+        assert_eq!(Tokenizer::Heuristic(4.0).count("hello world!"), 3);
+        assert_eq!(Tokenizer::Heuristic(4.0).count(""), 0);
+    }
+
+    #[test]
+    fn test_count_messages_and_tools() {
+        let tokenizer = Tokenizer::Heuristic(4.0);
+        let messages = vec![user("12345678"), user("1234")];
+        assert_eq!(tokenizer.count_messages(&messages), 2 * MESSAGE_OVERHEAD + 2 + 1 + REPLY_OVERHEAD);
+
+        let request: ChatCompletionParameters = serde_json::from_value(json!({
+            "model": "m",
+            "messages": [{"role": "user", "content": "12345678"}],
+            "tools": [{"type": "function", "function": {"name": "ls", "description": "list", "parameters": {}}}]
+        })).unwrap();
+        let tools = TOOL_OVERHEAD + 1 + 1 + 1;
+        assert_eq!(tokenizer.count_request(&request), MESSAGE_OVERHEAD + 2 + REPLY_OVERHEAD + tools);
+    }
+
+    #[tokio::test]
+    async fn test_client_fills_missing_usage() {
+        use crate::providers::replay::{text_response, ReplayProvider};
+        use crate::LlmClient;
+
+        let mut response = text_response("hello world");
+        response.usage = None;
+        let client = LlmClient::from_provider(Box::new(ReplayProvider::scripted().then_response(response)));
+        let request: ChatCompletionParameters = serde_json::from_value(json!({
+            "model": "gpt-4o",
+            "messages": [{"role": "user", "content": "hello world"}]
+        })).unwrap();
+
+        let usage = client.chat(request).await.unwrap().usage.unwrap();
+        assert_eq!(usage.prompt_tokens, Some(MESSAGE_OVERHEAD + 2 + REPLY_OVERHEAD));
+        assert_eq!(usage.completion_tokens, Some(MESSAGE_OVERHEAD + 2));
+        assert_eq!(usage.total_tokens, 2 * MESSAGE_OVERHEAD + 4 + REPLY_OVERHEAD);
+    }
+
+    #[tokio::test]
+    async fn test_client_calibrates_heuristic() {
+        use crate::providers::replay::{text_response, ReplayProvider};
+        use crate::LlmClient;
+
+        let mut response = text_response("ok");
+        response.usage.as_mut().unwrap().prompt_tokens = Some(16);
+        let client = LlmClient::from_provider(Box::new(ReplayProvider::scripted().then_response(response)));
+        let request: ChatCompletionParameters = serde_json::from_value(json!({
+            "model": "claude-sonnet-4-5",
+            "messages": [{"role": "user", "content": "1234567"}]
+        })).unwrap();
+
+        assert_eq!(client.estimate_request_tokens(&request), 8);
+        client.chat(request.clone()).await.unwrap();
+        assert_eq!(client.estimate_request_tokens(&request), 16);
+    }
+
+    #[test]
+    fn test_count_tool_calls_and_images() {
+        let tokenizer = Tokenizer::Heuristic(4.0);
+        let message: ChatMessage = serde_json::from_value(json!({
+            "role": "assistant",
+            "tool_calls": [{"id": "1", "type": "function", "function": {"name": "read", "arguments": "{\"path\":\"a\"}"}}]
+        })).unwrap();
+        assert_eq!(tokenizer.count_message(&message), MESSAGE_OVERHEAD + MESSAGE_OVERHEAD + 1 + 3);
+
+        let image: ChatMessage = serde_json::from_value(json!({
+            "role": "user",
+            "content": [{"type": "text", "text": "look"}, {"type": "image_url", "image_url": {"url": "data:image/png;base64,AAAA"}}]
+        })).unwrap();
+        assert_eq!(tokenizer.count_message(&image), MESSAGE_OVERHEAD + 1 + IMAGE_TOKENS);
+    }
+}
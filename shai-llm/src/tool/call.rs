@@ -60,6 +60,11 @@ impl ToolCallAuto for LlmClient {
         request: ChatCompletionParameters,
         tools: &ToolBox
     ) -> Result<ChatCompletionResponse, LlmError> {
+        // no need to try function calling on a model known not to support it
+        if self.model_info(&request.model).await.supports_tools == Some(false) {
+            return self.chat_with_tools_so(request, tools).await;
+        }
+
         if let Ok(result) = self.chat_with_tools_fc_auto(request.clone(), tools).await {
             return Ok(result);
         }
@@ -81,6 +81,7 @@ fn tool_impl(args: String, input: ItemImpl) -> syn::Result<TokenStream2> {
     // Find the execute method and extract parameter type
     let mut execute_method = None;
     let mut execute_preview_method = None;
+    let mut shutdown_method = None;
     let mut param_type = None;
     let mut has_cancel_token = false;
 
@@ -111,6 +112,8 @@ fn tool_impl(args: String, input: ItemImpl) -> syn::Result<TokenStream2> {
                 }
             } else if method.sig.ident == "execute_preview" {
                 execute_preview_method = Some(method);
+            } else if method.sig.ident == "shutdown" {
+                shutdown_method = Some(method);
             }
         }
     }
@@ -161,6 +164,17 @@ fn tool_impl(args: String, input: ItemImpl) -> syn::Result<TokenStream2> {
         quote! {}
     };
 
+    // Generate shutdown method if user provided one
+    let shutdown_impl = if shutdown_method.is_some() {
+        quote! {
+            async fn shutdown(&self) {
+                <Self>::shutdown(self).await
+            }
+        }
+    } else {
+        quote! {}
+    };
+
     // Generate the execute implementation based on whether user method has cancel_token
     let execute_impl = if has_cancel_token {
         quote! {
@@ -272,6 +286,8 @@ fn tool_impl(args: String, input: ItemImpl) -> syn::Result<TokenStream2> {
             #execute_impl
 
             #execute_preview_impl
+
+            #shutdown_impl
         }
 
     };